@@ -38,6 +38,21 @@ fn main() {
     println!("cargo:rustc-link-lib=lapack");
     println!("cargo:rustc-link-lib=cblas");
 
+    #[cfg(feature = "cuda")]
+    {
+        // dlib has to have been built with DLIB_USE_CUDA for this to do anything,
+        // which the wrapper checks when it's compiled
+        let cuda_path = std::env::var("CUDA_PATH").unwrap_or_else(|_| "/usr/local/cuda".into());
+        println!("cargo:rerun-if-env-changed=CUDA_PATH");
+        println!("cargo:rustc-link-search=native={}/lib64", cuda_path);
+
+        for lib in &["cudart", "cublas", "cudnn", "curand", "cusolver"] {
+            println!("cargo:rustc-link-lib={}", lib);
+        }
+
+        config.define("DLIB_FACE_RECOGNITION_CUDA", None);
+    }
+
     config.build("src/lib.rs");
 
     #[cfg(feature = "embed-any")]
@@ -73,5 +88,10 @@ fn main() {
             &client,
             "http://dlib.net/files/shape_predictor_68_face_landmarks.dat.bz2",
         );
+        #[cfg(feature = "embed-lp5")]
+        download_and_unzip(
+            &client,
+            "http://dlib.net/files/shape_predictor_5_face_landmarks.dat.bz2",
+        );
     }
 }
@@ -3,8 +3,8 @@ extern crate cpp;
 
 mod wrapper;
 
-use dlib_face_recognition::ImageMatrix;
-use opencv::prelude::Mat;
+use dlib_face_recognition::{ImageMatrix, ImageMatrixView};
+use opencv::prelude::*;
 
 /// Copy a matrix from an opencv mat
 pub fn matrix_to_opencv_mat(mat: &Mat) -> ImageMatrix {
@@ -20,3 +20,124 @@ pub fn matrix_to_opencv_mat(mat: &Mat) -> ImageMatrix {
         })
     }
 }
+
+// The layout of an 8-bit, 2D mat's pixels.
+struct Layout<'a> {
+    rows: usize,
+    columns: usize,
+    channels: usize,
+    step: usize,
+    bytes: &'a [u8],
+}
+
+fn layout(mat: &Mat) -> Result<Layout<'_>, String> {
+    let raw = mat.as_raw_Mat();
+
+    let mut rows = 0usize;
+    let mut columns = 0usize;
+    let mut channels = 0usize;
+    let mut step = 0usize;
+    let mut data: *const u8 = std::ptr::null();
+
+    let supported = unsafe {
+        let rows = &mut rows;
+        let columns = &mut columns;
+        let channels = &mut channels;
+        let step = &mut step;
+        let data = &mut data;
+
+        cpp!([raw as "const cv::Mat*", rows as "size_t*", columns as "size_t*", channels as "size_t*", step as "size_t*", data as "const uint8_t**"] -> bool as "bool" {
+            if (raw->dims != 2 || raw->depth() != CV_8U) {
+                return false;
+            }
+
+            *rows = raw->rows;
+            *columns = raw->cols;
+            *channels = raw->channels();
+            *step = raw->step[0];
+            *data = raw->data;
+            return true;
+        })
+    };
+
+    if !supported {
+        return Err("Only 2D mats of 8-bit values are supported".into());
+    }
+
+    let bytes = if rows == 0 || columns == 0 {
+        &[]
+    } else {
+        // the pixels live as long as the mat
+        let len = step * (rows - 1) + columns * channels;
+        unsafe { std::slice::from_raw_parts(data, len) }
+    };
+
+    Ok(Layout {
+        rows,
+        columns,
+        channels,
+        step,
+        bytes,
+    })
+}
+
+/// View an 8-bit, 3 channel mat as an image without copying it, for the detectors and predictors that take an
+/// [`ImageMatrixView`].
+///
+/// dlib's images are in rgb order while opencv's are usually in bgr order, so this is only zero-copy for mats
+/// that have already been converted to rgb, e.g. with `imgproc::cvt_color` and `COLOR_BGR2RGB`. Use
+/// [`mat_to_matrix`] to copy other mats.
+pub fn mat_as_view(mat: &Mat) -> Result<ImageMatrixView<'_>, String> {
+    let layout = layout(mat)?;
+    if layout.channels != 3 {
+        return Err(format!(
+            "Only 3 channel mats can be viewed, got {} channels",
+            layout.channels
+        ));
+    }
+
+    ImageMatrixView::new(layout.columns, layout.rows, layout.step, layout.bytes)
+}
+
+/// Copy a matrix from an 8-bit opencv mat with 1 (grayscale), 3 (bgr) or 4 (bgra) channels.
+pub fn mat_to_matrix(mat: &Mat) -> Result<ImageMatrix, String> {
+    let layout = layout(mat)?;
+    let (width, height, step) = (layout.columns, layout.rows, layout.step);
+
+    match layout.channels {
+        1 => ImageMatrix::from_gray(width, height, step, layout.bytes),
+        3 => ImageMatrix::from_bgr(width, height, step, layout.bytes),
+        4 => ImageMatrix::from_bgra(width, height, step, layout.bytes),
+        channels => Err(format!("Mats with {} channels aren't supported", channels)),
+    }
+}
+
+/// Copy a matrix into a new 3 channel opencv mat, in opencv's usual bgr order.
+pub fn matrix_to_mat(matrix: &ImageMatrix) -> Result<Mat, String> {
+    let mut mat = Mat::new_rows_cols_with_default(
+        matrix.height() as i32,
+        matrix.width() as i32,
+        opencv::core::CV_8UC3,
+        opencv::core::Scalar::all(0.0),
+    )
+    .map_err(|error| error.to_string())?;
+
+    let out = mat.as_raw_mut_Mat();
+
+    unsafe {
+        cpp!([matrix as "const dlib::matrix<dlib::rgb_pixel>*", out as "cv::Mat*"] {
+            for (long y = 0; y < matrix->nr(); y++) {
+                uint8_t* row = out->ptr<uint8_t>(y);
+
+                for (long x = 0; x < matrix->nc(); x++) {
+                    const dlib::rgb_pixel& pixel = (*matrix)(y, x);
+                    row[3 * x] = pixel.blue;
+                    row[3 * x + 1] = pixel.green;
+                    row[3 * x + 2] = pixel.red;
+                }
+            }
+        })
+    }
+
+    Ok(mat)
+}
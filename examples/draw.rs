@@ -5,12 +5,12 @@ use dlib_face_recognition::*;
 fn draw_rectangle(image: &mut RgbImage, rect: &Rectangle, colour: Rgb<u8>) {
     for x in rect.left..rect.right {
         image.put_pixel(x as u32, rect.top as u32, colour);
-        image.put_pixel(x as u32, rect.bottom as u32, colour);
+        image.put_pixel(x as u32, rect.bottom as u32 - 1, colour);
     }
 
     for y in rect.top..rect.bottom {
         image.put_pixel(rect.left as u32, y as u32, colour);
-        image.put_pixel(rect.right as u32, y as u32, colour);
+        image.put_pixel(rect.right as u32 - 1, y as u32, colour);
     }
 }
 
@@ -0,0 +1,59 @@
+#[cfg(feature = "embed-all")]
+fn tick<R>(name: &str, runs: u32, f: impl Fn() -> R) -> R {
+    let now = std::time::Instant::now();
+    let mut result = f();
+    for _ in 1..runs {
+        result = f();
+    }
+    println!(
+        "[{}] mean elapsed time: {:.2}ms",
+        name,
+        now.elapsed().as_secs_f64() * 1000.0 / runs as f64
+    );
+    result
+}
+
+#[cfg(feature = "embed-all")]
+fn main() {
+    use dlib_face_recognition::*;
+
+    let input = std::env::args().nth(1).unwrap();
+    let runs = 20;
+
+    let matrix = ImageMatrix::load(input).unwrap();
+
+    let detector = FaceDetector::default();
+    let predictor = LandmarkPredictor::default();
+    let model = FaceEncoderNetwork::default();
+
+    let landmarks: Vec<FaceLandmarks> = detector
+        .face_locations(&matrix)
+        .iter()
+        .map(|rect| predictor.face_landmarks(&matrix, rect))
+        .collect();
+    println!("Encoding {} faces, {} times each", landmarks.len(), runs);
+
+    model.warm_up();
+
+    let doubles = tick("f64", runs, || {
+        model.get_face_encodings(&matrix, &landmarks, 0)
+    });
+    let floats = tick("f32", runs, || {
+        model.get_face_encodings_f32(&matrix, &landmarks)
+    });
+
+    let largest_difference = doubles
+        .iter()
+        .zip(&floats)
+        .map(|(double, float)| double.distance(&float.to_f64()))
+        .fold(0.0, f64::max);
+    println!(
+        "Largest distance between f64 and f32 encodings: {:e}",
+        largest_difference
+    );
+}
+
+#[cfg(not(feature = "embed-all"))]
+fn main() {
+    panic!("You need to run this example with '--features embed-all'.");
+}
@@ -0,0 +1,225 @@
+use crate::geometry::Rectangle;
+use crate::landmark_prediction::FaceLandmarks;
+use crate::matrix::ImageMatrix;
+
+/// How faces are hidden by [`anonymize`] and [`anonymize_landmarks`].
+///
+/// Both methods scale with the size of each face, so small and large faces are hidden equally well.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Anonymization {
+    /// A gaussian blur with a standard deviation of `sigma` times the size of the face.
+    Blur { sigma: f64 },
+    /// Replace the face with `blocks` squares across of its average colour.
+    Pixelate { blocks: usize },
+}
+
+impl Default for Anonymization {
+    fn default() -> Self {
+        Anonymization::Blur { sigma: 0.1 }
+    }
+}
+
+fn anonymize_polygons(
+    image: &ImageMatrix,
+    polygons: &[Vec<[f64; 2]>],
+    method: Anonymization,
+) -> ImageMatrix {
+    let (method, parameter) = match method {
+        Anonymization::Blur { sigma } => (0, sigma),
+        Anonymization::Pixelate { blocks } => (1, blocks as f64),
+    };
+
+    let mut out = image.clone();
+
+    for polygon in polygons {
+        let out = &mut out;
+        let num_points = polygon.len();
+        let points = polygon.as_ptr() as *const f64;
+
+        unsafe {
+            cpp!([out as "dlib::matrix<dlib::rgb_pixel>*", points as "const double*", num_points as "size_t", method as "int", parameter as "double"] {
+                anonymize_polygon(*out, points, num_points, method, parameter);
+            })
+        }
+    }
+
+    out
+}
+
+/// Hide the faces inside each of the rectangles, e.g. before publishing camera footage.
+pub fn anonymize(image: &ImageMatrix, faces: &[Rectangle], method: Anonymization) -> ImageMatrix {
+    let polygons: Vec<Vec<[f64; 2]>> = faces
+        .iter()
+        .map(|rect| {
+            let (left, top) = (rect.left as f64, rect.top as f64);
+            let (right, bottom) = (rect.right as f64, rect.bottom as f64);
+            vec![[left, top], [right, top], [right, bottom], [left, bottom]]
+        })
+        .collect();
+
+    anonymize_polygons(image, &polygons, method)
+}
+
+/// Hide faces using their landmarks, leaving more of the background untouched than [`anonymize`].
+///
+/// Each face is hidden inside the convex hull of its landmarks, grown by 10% to cover their edges.
+pub fn anonymize_landmarks(
+    image: &ImageMatrix,
+    faces: &[FaceLandmarks],
+    method: Anonymization,
+) -> ImageMatrix {
+    let polygons: Vec<Vec<[f64; 2]>> = faces
+        .iter()
+        .map(|landmarks| {
+            let points: Vec<[f64; 2]> = landmarks
+                .iter()
+                .map(|point| [point.x() as f64, point.y() as f64])
+                .collect();
+
+            grown(&convex_hull(&points), 1.1)
+        })
+        .collect();
+
+    anonymize_polygons(image, &polygons, method)
+}
+
+// Andrew's monotone chain algorithm, returning the hull in counter-clockwise order.
+fn convex_hull(points: &[[f64; 2]]) -> Vec<[f64; 2]> {
+    let mut points = points.to_vec();
+    points.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    points.dedup();
+
+    if points.len() < 3 {
+        return points;
+    }
+
+    let cross = |o: [f64; 2], a: [f64; 2], b: [f64; 2]| {
+        (a[0] - o[0]) * (b[1] - o[1]) - (a[1] - o[1]) * (b[0] - o[0])
+    };
+
+    let mut hull: Vec<[f64; 2]> = Vec::with_capacity(points.len() + 1);
+
+    // the lower half of the hull
+    for &point in &points {
+        while hull.len() >= 2 && cross(hull[hull.len() - 2], hull[hull.len() - 1], point) <= 0.0 {
+            hull.pop();
+        }
+        hull.push(point);
+    }
+
+    // the upper half, which mustn't remove any of the lower half
+    let lower = hull.len() + 1;
+    for &point in points.iter().rev().skip(1) {
+        while hull.len() >= lower && cross(hull[hull.len() - 2], hull[hull.len() - 1], point) <= 0.0
+        {
+            hull.pop();
+        }
+        hull.push(point);
+    }
+
+    // the last point is the same as the first
+    hull.pop();
+    hull
+}
+
+// Scale a polygon about its centroid.
+fn grown(polygon: &[[f64; 2]], factor: f64) -> Vec<[f64; 2]> {
+    if polygon.is_empty() {
+        return Vec::new();
+    }
+
+    let n = polygon.len() as f64;
+    let cx = polygon.iter().map(|p| p[0]).sum::<f64>() / n;
+    let cy = polygon.iter().map(|p| p[1]).sum::<f64>() / n;
+
+    polygon
+        .iter()
+        .map(|p| [cx + (p[0] - cx) * factor, cy + (p[1] - cy) * factor])
+        .collect()
+}
+
+#[test]
+fn test_convex_hull() {
+    let points = [
+        [0.0, 0.0],
+        [10.0, 0.0],
+        [5.0, 5.0],
+        [10.0, 10.0],
+        [0.0, 10.0],
+        [5.0, 0.0],
+        [0.0, 0.0],
+    ];
+
+    assert_eq!(
+        convex_hull(&points),
+        vec![[0.0, 0.0], [10.0, 0.0], [10.0, 10.0], [0.0, 10.0]]
+    );
+    assert_eq!(convex_hull(&points[..2]), vec![[0.0, 0.0], [10.0, 0.0]]);
+
+    assert_eq!(
+        grown(&[[0.0, 0.0], [2.0, 0.0], [2.0, 2.0], [0.0, 2.0]], 2.0),
+        vec![[-1.0, -1.0], [3.0, -1.0], [3.0, 3.0], [-1.0, 3.0]]
+    );
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn test_anonymize() {
+    let image = image::open("assets/obama_1.jpg").unwrap().to_rgb();
+    let matrix = ImageMatrix::from_image(&image);
+
+    let landmarks = crate::landmark_prediction::obama_five_points();
+    let face = landmarks.rect();
+    let outside = Rectangle {
+        left: 10000,
+        top: 10000,
+        right: 10100,
+        bottom: 10100,
+    };
+
+    for &method in &[
+        Anonymization::default(),
+        Anonymization::Pixelate { blocks: 8 },
+    ] {
+        let anonymized = anonymize(&matrix, &[face, outside], method);
+        assert_eq!(anonymized.width(), matrix.width());
+        assert_eq!(anonymized.height(), matrix.height());
+
+        // most of the face is changed, and nothing more than a pixel away from it is
+        let (inside, border) = (face.padded(-1), face.padded(1));
+        let (mut changed, mut total) = (0, 0);
+        for y in 0..matrix.height() {
+            for x in 0..matrix.width() {
+                let (left, top) = (x as i64, y as i64);
+                let same = anonymized.get_pixel(x, y) == matrix.get_pixel(x, y);
+
+                if left >= inside.left
+                    && left < inside.right
+                    && top >= inside.top
+                    && top < inside.bottom
+                {
+                    total += 1;
+                    if !same {
+                        changed += 1;
+                    }
+                } else if left < border.left
+                    || left >= border.right
+                    || top < border.top
+                    || top >= border.bottom
+                {
+                    assert!(same, "{:?} changed at ({}, {})", method, x, y);
+                }
+            }
+        }
+        assert!(
+            changed * 2 > total,
+            "{:?} changed {} of {}",
+            method,
+            changed,
+            total
+        );
+    }
+
+    let anonymized = anonymize_landmarks(&matrix, &[landmarks], Anonymization::default());
+    assert_eq!(anonymized.width(), matrix.width());
+}
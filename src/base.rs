@@ -1,11 +1,48 @@
 use std::ffi::*;
+use std::ops::Deref;
 use std::path::*;
+use std::slice;
 
 pub fn path_as_cstring(path: &Path) -> Result<CString, String> {
     if !path.exists() {
         Err(format!("File not found: '{}'", path.display()))
     } else {
-        let string = path.to_str().unwrap();
-        Ok(CString::new(string).unwrap())
+        path_to_cstring(path)
+    }
+}
+
+/// Like [`path_as_cstring`], but for files that are about to be written and so needn't exist yet.
+pub fn path_to_cstring(path: &Path) -> Result<CString, String> {
+    path.to_str()
+        .and_then(|string| CString::new(string).ok())
+        .ok_or_else(|| format!("Invalid path: '{}'", path.display()))
+}
+
+cpp_class!(
+    /// A wrapper around a `std::string`, used to pass byte buffers out of C++.
+    pub(crate) unsafe struct CppBytes as "std::string"
+);
+
+impl Deref for CppBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        let len = unsafe {
+            cpp!([self as "const std::string*"] -> usize as "size_t" {
+                return self->size();
+            })
+        };
+
+        if len == 0 {
+            &[]
+        } else {
+            unsafe {
+                let pointer = cpp!([self as "const std::string*"] -> *const u8 as "const char*" {
+                    return self->data();
+                });
+
+                slice::from_raw_parts(pointer, len)
+            }
+        }
     }
 }
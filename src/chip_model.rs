@@ -0,0 +1,75 @@
+use crate::face_alignment::{face_chips, ChipOptions};
+use crate::landmark_prediction::FaceLandmarks;
+use crate::matrix::ImageMatrix;
+
+/// A network that runs on aligned face chips and outputs a vector of values for each of them.
+///
+/// This is the extension point for auxiliary models such as age or gender estimators: implement
+/// [`ChipModel::run_chips`] and [`ChipModel::chip_options`] for the model, and it can run on the faces found
+/// by the rest of the pipeline with [`ChipModel::run_faces`]. The outputs are the raw values of the network,
+/// such as class scores, and interpreting them is up to the caller.
+///
+/// dlib can only deserialize a network into the exact C++ type it was trained as, so every architecture is
+/// compiled in: [`AgePredictorNetwork`] loads dlib's community age predictor, and [`FaceEncoderNetwork`] loads
+/// models with the face encoder's architecture, such as ones fine-tuned with [`FaceEncoderNetwork::fine_tune`].
+/// Other networks need a wrapper compiled with their type implementing this trait, and models exported to ONNX
+/// can be run with `OnnxChipModel` behind the `onnx` feature.
+///
+/// [`AgePredictorNetwork`]: crate::face_analysis::AgePredictorNetwork
+/// [`FaceEncoderNetwork`]: crate::FaceEncoderNetwork
+/// [`FaceEncoderNetwork::fine_tune`]: crate::FaceEncoderNetwork::fine_tune
+pub trait ChipModel {
+    /// How face chips should be extracted for this model.
+    fn chip_options(&self) -> ChipOptions;
+
+    /// Run the model on already extracted chips, returning the outputs for each chip in turn.
+    fn run_chips(&self, chips: &[ImageMatrix]) -> Result<Vec<Vec<f32>>, String>;
+
+    /// Extract a chip for each face and run the model on them.
+    ///
+    /// Fails if any of the landmarks don't have either 68 or 5 points, or the model's chip options aren't valid.
+    fn run_faces(
+        &self,
+        image: &ImageMatrix,
+        landmarks: &[FaceLandmarks],
+    ) -> Result<Vec<Vec<f32>>, String> {
+        let chips = face_chips(image, landmarks, &self.chip_options())?;
+        self.run_chips(&chips)
+    }
+}
+
+#[test]
+fn test_run_faces() {
+    struct ChipSize;
+
+    impl ChipModel for ChipSize {
+        fn chip_options(&self) -> ChipOptions {
+            ChipOptions {
+                size: 32,
+                ..Default::default()
+            }
+        }
+
+        fn run_chips(&self, chips: &[ImageMatrix]) -> Result<Vec<Vec<f32>>, String> {
+            Ok(chips
+                .iter()
+                .map(|chip| vec![chip.width() as f32, chip.height() as f32])
+                .collect())
+        }
+    }
+
+    let landmarks = crate::landmark_prediction::obama_five_points();
+
+    let outputs = ChipSize
+        .run_faces(
+            &ImageMatrix::blank(100, 100),
+            &[landmarks.clone(), landmarks],
+        )
+        .unwrap();
+    assert_eq!(outputs, vec![vec![32.0, 32.0]; 2]);
+
+    let unsupported = FaceLandmarks::new(&Default::default(), &[]);
+    assert!(ChipSize
+        .run_faces(&ImageMatrix::blank(100, 100), &[unsupported])
+        .is_err());
+}
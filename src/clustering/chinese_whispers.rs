@@ -0,0 +1,59 @@
+use crate::face_encoding::FaceEncoding;
+
+/// Group encodings into identities using dlib's chinese whispers graph clustering.
+///
+/// A graph is built with an edge between every pair of encodings closer than `threshold`
+/// (0.6 is appropriate for the dlib encodings), and then clustered.
+/// Returns one cluster label per encoding, numbered from zero.
+pub fn chinese_whispers(encodings: &[FaceEncoding], threshold: f64) -> Vec<usize> {
+    let len = encodings.len();
+    let mut labels = vec![0usize; len];
+
+    if len == 0 {
+        return labels;
+    }
+
+    unsafe {
+        let encodings = encodings.as_ptr();
+        let out = labels.as_mut_ptr();
+
+        cpp!([encodings as "const dlib::matrix<double,0,1>*", len as "size_t", threshold as "double", out as "size_t*"] {
+            std::vector<dlib::sample_pair> edges;
+
+            // Include an edge from each encoding to itself so that every encoding gets a label.
+            for (size_t i = 0; i < len; ++i) {
+                for (size_t j = i; j < len; ++j) {
+                    if (dlib::length(encodings[i] - encodings[j]) < threshold) {
+                        edges.push_back(dlib::sample_pair(i, j));
+                    }
+                }
+            }
+
+            std::vector<unsigned long> labels;
+            dlib::chinese_whispers(edges, labels);
+
+            for (size_t i = 0; i < len; ++i) {
+                out[i] = labels[i];
+            }
+        });
+    }
+
+    labels
+}
+
+#[test]
+fn test_chinese_whispers() {
+    let encodings = vec![
+        FaceEncoding::new_from_scalar(0.0),
+        FaceEncoding::new_from_scalar(1.0),
+        FaceEncoding::new_from_scalar(0.01),
+        FaceEncoding::new_from_scalar(1.01),
+    ];
+
+    let labels = chinese_whispers(&encodings, 0.6);
+
+    assert_eq!(labels.len(), 4);
+    assert_eq!(labels[0], labels[2]);
+    assert_eq!(labels[1], labels[3]);
+    assert_ne!(labels[0], labels[1]);
+}
@@ -0,0 +1,90 @@
+use crate::face_encoding::FaceEncoding;
+
+#[derive(Copy, Clone, PartialEq)]
+enum State {
+    Unvisited,
+    Noise,
+    Cluster(usize),
+}
+
+fn neighbours(encodings: &[FaceEncoding], index: usize, eps: f64) -> Vec<usize> {
+    encodings[index]
+        .distances(encodings)
+        .into_iter()
+        .enumerate()
+        .filter(|(_, distance)| *distance <= eps)
+        .map(|(index, _)| index)
+        .collect()
+}
+
+/// Group encodings into identities using DBSCAN.
+///
+/// An encoding with at least `min_points` encodings (including itself) within `eps` is a core point,
+/// and clusters grow outwards from core points. Encodings that are not reachable from any core point
+/// are treated as noise, which makes this well suited to footage containing many unknown faces.
+///
+/// Returns one label per encoding: `Some(cluster)` numbered from zero, or `None` for noise.
+pub fn dbscan(encodings: &[FaceEncoding], eps: f64, min_points: usize) -> Vec<Option<usize>> {
+    let mut states = vec![State::Unvisited; encodings.len()];
+    let mut clusters = 0;
+
+    for index in 0..encodings.len() {
+        if states[index] != State::Unvisited {
+            continue;
+        }
+
+        let mut seeds = neighbours(encodings, index, eps);
+        if seeds.len() < min_points {
+            states[index] = State::Noise;
+            continue;
+        }
+
+        let cluster = clusters;
+        clusters += 1;
+        states[index] = State::Cluster(cluster);
+
+        while let Some(seed) = seeds.pop() {
+            match states[seed] {
+                State::Noise => states[seed] = State::Cluster(cluster),
+                State::Unvisited => {
+                    states[seed] = State::Cluster(cluster);
+
+                    let expansion = neighbours(encodings, seed, eps);
+                    if expansion.len() >= min_points {
+                        seeds.extend(expansion);
+                    }
+                }
+                State::Cluster(_) => {}
+            }
+        }
+    }
+
+    states
+        .into_iter()
+        .map(|state| match state {
+            State::Cluster(cluster) => Some(cluster),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn test_dbscan() {
+    let encodings = vec![
+        FaceEncoding::new_from_scalar(0.0),
+        FaceEncoding::new_from_scalar(0.01),
+        FaceEncoding::new_from_scalar(0.02),
+        FaceEncoding::new_from_scalar(5.0),
+        FaceEncoding::new_from_scalar(1.0),
+        FaceEncoding::new_from_scalar(1.01),
+    ];
+
+    let labels = dbscan(&encodings, 0.6, 2);
+
+    assert_eq!(labels[0], Some(0));
+    assert_eq!(labels[1], Some(0));
+    assert_eq!(labels[2], Some(0));
+    assert_eq!(labels[3], None);
+    assert_eq!(labels[4], Some(1));
+    assert_eq!(labels[5], Some(1));
+}
@@ -0,0 +1,7 @@
+//! Unsupervised clustering of face encodings into identities.
+
+mod chinese_whispers;
+mod dbscan;
+
+pub use self::chinese_whispers::chinese_whispers;
+pub use self::dbscan::dbscan;
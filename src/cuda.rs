@@ -0,0 +1,68 @@
+//! Selecting the GPU that dlib's networks run on.
+//!
+//! The CNN face detector and the face encoding network run on the GPU when dlib is built with CUDA, and
+//! the `cuda` feature flag links against it. Otherwise they run on the CPU, and these functions report no devices.
+
+/// Whether dlib was built with CUDA and a CUDA device is available, so the networks run on the GPU.
+pub fn cuda_available() -> bool {
+    cuda_device_count() > 0
+}
+
+/// The number of CUDA devices, or 0 if dlib was built without CUDA.
+pub fn cuda_device_count() -> usize {
+    let count = unsafe {
+        cpp!([] -> i32 as "int" {
+            return cuda_device_count();
+        })
+    };
+
+    count.max(0) as usize
+}
+
+/// The CUDA device networks run on in the current thread, if any.
+pub fn cuda_device() -> Option<usize> {
+    let device = unsafe {
+        cpp!([] -> i32 as "int" {
+            return cuda_device();
+        })
+    };
+
+    if device < 0 {
+        None
+    } else {
+        Some(device as usize)
+    }
+}
+
+/// Run networks on the given CUDA device.
+///
+/// Like CUDA itself, the device is selected per thread, so this has to be called on every thread that runs
+/// a network. Networks should be loaded after selecting the device they'll run on.
+pub fn set_cuda_device(device: usize) -> Result<(), String> {
+    let count = cuda_device_count();
+    if device >= count {
+        return Err(format!(
+            "Can't use CUDA device {}, there are only {} devices",
+            device, count
+        ));
+    }
+
+    let device = device as i32;
+    let set = unsafe {
+        cpp!([device as "int"] -> bool as "bool" {
+            return set_cuda_device(device);
+        })
+    };
+
+    if set {
+        Ok(())
+    } else {
+        Err(format!("Failed to select CUDA device {}", device))
+    }
+}
+
+#[test]
+fn test_invalid_cuda_device() {
+    assert!(set_cuda_device(cuda_device_count()).is_err());
+    assert_eq!(cuda_available(), cuda_device_count() > 0);
+}
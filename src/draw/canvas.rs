@@ -0,0 +1,125 @@
+#[cfg(feature = "image")]
+use std::ops::{Deref, DerefMut};
+
+#[cfg(feature = "image")]
+use image::{ImageBuffer, Rgb};
+
+use crate::matrix::ImageMatrix;
+
+/// Anything that can be drawn on.
+pub trait Canvas {
+    fn width(&self) -> usize;
+
+    fn height(&self) -> usize;
+
+    /// Set the colour of a pixel, which is always inside the canvas.
+    fn put_pixel(&mut self, x: usize, y: usize, color: [u8; 3]);
+
+    /// Set the colour of a pixel, ignoring pixels outside of the canvas.
+    fn plot(&mut self, x: i64, y: i64, color: [u8; 3]) {
+        if x >= 0 && y >= 0 && (x as usize) < self.width() && (y as usize) < self.height() {
+            self.put_pixel(x as usize, y as usize, color);
+        }
+    }
+}
+
+#[cfg(feature = "image")]
+impl<C: Deref<Target = [u8]> + DerefMut> Canvas for ImageBuffer<Rgb<u8>, C> {
+    fn width(&self) -> usize {
+        ImageBuffer::width(self) as usize
+    }
+
+    fn height(&self) -> usize {
+        ImageBuffer::height(self) as usize
+    }
+
+    fn put_pixel(&mut self, x: usize, y: usize, color: [u8; 3]) {
+        ImageBuffer::put_pixel(self, x as u32, y as u32, Rgb(color));
+    }
+}
+
+impl Canvas for ImageMatrix {
+    fn width(&self) -> usize {
+        ImageMatrix::width(self)
+    }
+
+    fn height(&self) -> usize {
+        ImageMatrix::height(self)
+    }
+
+    fn put_pixel(&mut self, x: usize, y: usize, color: [u8; 3]) {
+        let [red, green, blue] = color;
+
+        unsafe {
+            cpp!([self as "dlib::matrix<dlib::rgb_pixel>*", x as "size_t", y as "size_t", red as "uint8_t", green as "uint8_t", blue as "uint8_t"] {
+                (*self)(y, x) = dlib::rgb_pixel(red, green, blue);
+            })
+        }
+    }
+}
+
+/// A canvas over a raw buffer of rgb channel values (r, g, b, r, g, b), one row after another.
+pub struct RgbBuffer<'a> {
+    data: &'a mut [u8],
+    width: usize,
+    height: usize,
+}
+
+impl<'a> RgbBuffer<'a> {
+    /// # Panics
+    ///
+    /// Panics if the buffer is smaller than `width * height * 3`.
+    pub fn new(data: &'a mut [u8], width: usize, height: usize) -> Self {
+        assert!(
+            data.len() >= width * height * 3,
+            "A {}x{} rgb buffer needs at least {} bytes, but got {}",
+            width,
+            height,
+            width * height * 3,
+            data.len()
+        );
+
+        Self {
+            data,
+            width,
+            height,
+        }
+    }
+}
+
+impl Canvas for RgbBuffer<'_> {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn put_pixel(&mut self, x: usize, y: usize, color: [u8; 3]) {
+        let offset = (y * self.width + x) * 3;
+        self.data[offset..offset + 3].copy_from_slice(&color);
+    }
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn test_canvases() {
+    let mut image = image::RgbImage::new(4, 3);
+    image.plot(1, 2, [1, 2, 3]);
+    image.plot(-1, 0, [1, 2, 3]);
+    image.plot(4, 0, [1, 2, 3]);
+    assert_eq!(image.get_pixel(1, 2), &Rgb([1, 2, 3]));
+    assert_eq!(image.pixels().filter(|p| p.0 != [0, 0, 0]).count(), 1);
+
+    let mut data = vec![0u8; 4 * 3 * 3];
+    let mut buffer = RgbBuffer::new(&mut data, 4, 3);
+    buffer.plot(1, 2, [1, 2, 3]);
+    buffer.plot(0, 3, [1, 2, 3]);
+    assert_eq!(&data[27..30], &[1, 2, 3]);
+    assert_eq!(data.iter().filter(|&&v| v != 0).count(), 3);
+
+    let mut matrix = ImageMatrix::blank(4, 3);
+    matrix.plot(1, 2, [1, 2, 3]);
+    matrix.plot(10, 10, [1, 2, 3]);
+}
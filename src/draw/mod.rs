@@ -0,0 +1,11 @@
+//! Drawing rectangles, landmarks and labels onto images, for annotated debug output.
+
+mod canvas;
+mod font;
+mod shapes;
+
+pub use self::canvas::{Canvas, RgbBuffer};
+pub use self::font::{draw_text, text_size};
+pub use self::shapes::{
+    draw_label, draw_landmarks, draw_line, draw_point, draw_points, draw_rectangle, draw_track_id,
+};
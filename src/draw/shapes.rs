@@ -0,0 +1,226 @@
+use super::canvas::Canvas;
+use super::font::{draw_text, text_size};
+use crate::geometry::{Point, Rectangle};
+use crate::landmark_prediction::FaceLandmarks;
+
+fn fill_square<C: Canvas + ?Sized>(canvas: &mut C, x: i64, y: i64, size: i64, color: [u8; 3]) {
+    let start = -(size - 1) / 2;
+    for dy in start..start + size {
+        for dx in start..start + size {
+            canvas.plot(x + dx, y + dy, color);
+        }
+    }
+}
+
+/// Draw a straight line between two points, `thickness` pixels wide.
+pub fn draw_line<C: Canvas + ?Sized>(
+    canvas: &mut C,
+    from: Point,
+    to: Point,
+    color: [u8; 3],
+    thickness: u32,
+) {
+    let thickness = thickness.max(1) as i64;
+
+    // bresenham's line algorithm
+    let (mut x, mut y) = (from.x(), from.y());
+    let dx = (to.x() - x).abs();
+    let dy = -(to.y() - y).abs();
+    let sx = if x < to.x() { 1 } else { -1 };
+    let sy = if y < to.y() { 1 } else { -1 };
+    let mut error = dx + dy;
+
+    loop {
+        fill_square(canvas, x, y, thickness, color);
+
+        if x == to.x() && y == to.y() {
+            break;
+        }
+
+        let doubled = 2 * error;
+        if doubled >= dy {
+            error += dy;
+            x += sx;
+        }
+        if doubled <= dx {
+            error += dx;
+            y += sy;
+        }
+    }
+}
+
+/// Draw the outline of a rectangle, `thickness` pixels wide, along its outermost pixels.
+pub fn draw_rectangle<C: Canvas + ?Sized>(
+    canvas: &mut C,
+    rect: &Rectangle,
+    color: [u8; 3],
+    thickness: u32,
+) {
+    let (right, bottom) = (rect.right - 1, rect.bottom - 1);
+    let corners = [
+        Point::new(rect.left, rect.top),
+        Point::new(right, rect.top),
+        Point::new(right, bottom),
+        Point::new(rect.left, bottom),
+    ];
+
+    for i in 0..4 {
+        draw_line(canvas, corners[i], corners[(i + 1) % 4], color, thickness);
+    }
+}
+
+/// Draw a filled circle.
+pub fn draw_point<C: Canvas + ?Sized>(canvas: &mut C, point: Point, color: [u8; 3], radius: u32) {
+    let radius = radius as i64;
+
+    for dy in -radius..=radius {
+        for dx in -radius..=radius {
+            if dx * dx + dy * dy <= radius * radius {
+                canvas.plot(point.x() + dx, point.y() + dy, color);
+            }
+        }
+    }
+}
+
+/// Draw a filled circle at each of the points.
+pub fn draw_points<C: Canvas + ?Sized>(
+    canvas: &mut C,
+    points: &[Point],
+    color: [u8; 3],
+    radius: u32,
+) {
+    for &point in points {
+        draw_point(canvas, point, color, radius);
+    }
+}
+
+/// Draw each of the landmarks of a face as a filled circle.
+pub fn draw_landmarks<C: Canvas + ?Sized>(
+    canvas: &mut C,
+    landmarks: &FaceLandmarks,
+    color: [u8; 3],
+    radius: u32,
+) {
+    draw_points(canvas, landmarks, color, radius);
+}
+
+/// Draw a text label on a filled background just above a rectangle, or just inside it if there is no room above.
+///
+/// The text is black or white, whichever stands out more against `color`, and scales with the width of the rectangle.
+pub fn draw_label<C: Canvas + ?Sized>(
+    canvas: &mut C,
+    rect: &Rectangle,
+    text: &str,
+    color: [u8; 3],
+) {
+    let scale = (rect.width() / 100).clamp(1, 4) as u32;
+    let (width, height) = text_size(text, scale);
+    let padding = scale as i64;
+
+    let (width, height) = (width as i64 + 2 * padding, height as i64 + 2 * padding);
+    let top = if rect.top - height >= 0 {
+        rect.top - height
+    } else {
+        rect.top
+    };
+
+    for y in top..top + height {
+        for x in rect.left..rect.left + width {
+            canvas.plot(x, y, color);
+        }
+    }
+
+    let luma = 0.299 * color[0] as f64 + 0.587 * color[1] as f64 + 0.114 * color[2] as f64;
+    let text_color = if luma > 128.0 {
+        [0, 0, 0]
+    } else {
+        [255, 255, 255]
+    };
+
+    draw_text(
+        canvas,
+        rect.left + padding,
+        top + padding,
+        text,
+        text_color,
+        scale,
+    );
+}
+
+/// Draw a face rectangle labelled with its track id.
+pub fn draw_track_id<C: Canvas + ?Sized>(
+    canvas: &mut C,
+    rect: &Rectangle,
+    track_id: u64,
+    color: [u8; 3],
+) {
+    draw_rectangle(canvas, rect, color, 2);
+    draw_label(canvas, rect, &format!("#{}", track_id), color);
+}
+
+#[cfg(all(test, feature = "image"))]
+fn count_color(image: &image::RgbImage, color: [u8; 3]) -> usize {
+    image.pixels().filter(|p| p.0 == color).count()
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn test_draw_shapes() {
+    let red = [255, 0, 0];
+
+    let mut image = image::RgbImage::new(20, 20);
+    draw_line(&mut image, Point::new(0, 0), Point::new(9, 0), red, 1);
+    assert_eq!(count_color(&image, red), 10);
+
+    let mut image = image::RgbImage::new(20, 20);
+    draw_line(&mut image, Point::new(0, 0), Point::new(9, 9), red, 1);
+    assert_eq!(count_color(&image, red), 10);
+    assert_eq!(image.get_pixel(5, 5).0, red);
+
+    let mut image = image::RgbImage::new(20, 20);
+    let rect = Rectangle {
+        left: 2,
+        top: 2,
+        right: 12,
+        bottom: 7,
+    };
+    draw_rectangle(&mut image, &rect, red, 1);
+    assert_eq!(count_color(&image, red), 2 * 10 + 2 * 3);
+    assert_eq!(image.get_pixel(11, 6).0, red);
+    assert_eq!(image.get_pixel(12, 7).0, [0, 0, 0]);
+    assert_eq!(image.get_pixel(5, 5).0, [0, 0, 0]);
+
+    // shapes partly outside the canvas are clipped
+    let mut image = image::RgbImage::new(20, 20);
+    draw_point(&mut image, Point::new(0, 0), red, 1);
+    assert_eq!(count_color(&image, red), 3);
+
+    let mut image = image::RgbImage::new(20, 20);
+    draw_points(&mut image, &[Point::new(5, 5), Point::new(10, 10)], red, 0);
+    assert_eq!(count_color(&image, red), 2);
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn test_draw_label() {
+    let white = [255, 255, 255];
+    let rect = Rectangle {
+        left: 5,
+        top: 30,
+        right: 50,
+        bottom: 60,
+    };
+
+    let mut image = image::RgbImage::new(80, 80);
+    draw_track_id(&mut image, &rect, 7, white);
+
+    // the background sits above the rectangle, with black text on it
+    assert_eq!(image.get_pixel(5, 21).0, white);
+    assert!(count_color(&image, [0, 0, 0]) > 0);
+
+    // and moves inside the rectangle at the top of the image
+    let mut image = image::RgbImage::new(80, 80);
+    let top = Rectangle { top: 0, ..rect };
+    draw_label(&mut image, &top, "A", white);
+    assert_eq!(image.get_pixel(5, 0).0, white);
+}
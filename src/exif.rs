@@ -0,0 +1,153 @@
+//! Just enough EXIF parsing to find the orientation of a JPEG.
+
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+// The EXIF block has to fit in a single JPEG segment, so is always within the first 64KiB or so.
+const HEADER_SIZE: u64 = 128 * 1024;
+
+const ORIENTATION_TAG: u16 = 0x0112;
+
+/// Read the EXIF orientation of a JPEG file, from 1 (upright) to 8.
+///
+/// Returns `None` for files that aren't JPEGs, or that don't have an orientation.
+pub(crate) fn read_orientation(filename: &Path) -> Option<u16> {
+    let mut header = Vec::new();
+    File::open(filename)
+        .ok()?
+        .take(HEADER_SIZE)
+        .read_to_end(&mut header)
+        .ok()?;
+
+    orientation(&header)
+}
+
+pub(crate) fn orientation(jpeg: &[u8]) -> Option<u16> {
+    if !jpeg.starts_with(&[0xff, 0xd8]) {
+        return None;
+    }
+
+    let mut offset = 2;
+    while offset + 4 <= jpeg.len() {
+        if jpeg[offset] != 0xff {
+            return None;
+        }
+
+        let marker = jpeg[offset + 1];
+        // the image data starts after the start of scan marker, so there's no EXIF after it
+        if marker == 0xda {
+            return None;
+        }
+
+        let length = u16::from_be_bytes([jpeg[offset + 2], jpeg[offset + 3]]) as usize;
+        let segment = jpeg.get(offset + 4..offset + 2 + length)?;
+
+        if marker == 0xe1 && segment.starts_with(b"Exif\0\0") {
+            return tiff_orientation(&segment[6..]);
+        }
+
+        offset += 2 + length;
+    }
+
+    None
+}
+
+fn tiff_orientation(tiff: &[u8]) -> Option<u16> {
+    let big_endian = match tiff.get(0..2)? {
+        b"II" => false,
+        b"MM" => true,
+        _ => return None,
+    };
+
+    let read_u16 = |offset: usize| -> Option<u16> {
+        let bytes = [*tiff.get(offset)?, *tiff.get(offset + 1)?];
+        Some(if big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        })
+    };
+    let read_u32 = |offset: usize| -> Option<u32> {
+        let bytes = [
+            *tiff.get(offset)?,
+            *tiff.get(offset + 1)?,
+            *tiff.get(offset + 2)?,
+            *tiff.get(offset + 3)?,
+        ];
+        Some(if big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        })
+    };
+
+    if read_u16(2)? != 42 {
+        return None;
+    }
+
+    let ifd = read_u32(4)? as usize;
+    let entries = read_u16(ifd)? as usize;
+
+    (0..entries)
+        .map(|index| ifd + 2 + index * 12)
+        .find(|&entry| read_u16(entry) == Some(ORIENTATION_TAG))
+        .and_then(|entry| read_u16(entry + 8))
+        .filter(|orientation| (1..=8).contains(orientation))
+}
+
+#[cfg(test)]
+fn jpeg_with_orientation(orientation: u16, big_endian: bool) -> Vec<u8> {
+    let u16_bytes = |value: u16| {
+        if big_endian {
+            value.to_be_bytes()
+        } else {
+            value.to_le_bytes()
+        }
+    };
+    let u32_bytes = |value: u32| {
+        if big_endian {
+            value.to_be_bytes()
+        } else {
+            value.to_le_bytes()
+        }
+    };
+
+    let mut tiff = Vec::new();
+    tiff.extend_from_slice(if big_endian { b"MM" } else { b"II" });
+    tiff.extend_from_slice(&u16_bytes(42));
+    tiff.extend_from_slice(&u32_bytes(8));
+    // two entries, with the orientation second
+    tiff.extend_from_slice(&u16_bytes(2));
+    tiff.extend_from_slice(&u16_bytes(0x010f));
+    tiff.extend_from_slice(&[0; 10]);
+    tiff.extend_from_slice(&u16_bytes(ORIENTATION_TAG));
+    tiff.extend_from_slice(&u16_bytes(3));
+    tiff.extend_from_slice(&u32_bytes(1));
+    tiff.extend_from_slice(&u16_bytes(orientation));
+    tiff.extend_from_slice(&[0, 0]);
+
+    let mut jpeg = vec![0xff, 0xd8];
+    // an unrelated segment before the EXIF one
+    jpeg.extend_from_slice(&[0xff, 0xe0, 0, 4, 1, 2]);
+    jpeg.extend_from_slice(&[0xff, 0xe1]);
+    jpeg.extend_from_slice(&((tiff.len() + 8) as u16).to_be_bytes());
+    jpeg.extend_from_slice(b"Exif\0\0");
+    jpeg.extend_from_slice(&tiff);
+    jpeg.extend_from_slice(&[0xff, 0xda, 0, 2]);
+    jpeg
+}
+
+#[test]
+fn test_orientation() {
+    assert_eq!(orientation(&jpeg_with_orientation(6, false)), Some(6));
+    assert_eq!(orientation(&jpeg_with_orientation(3, true)), Some(3));
+    assert_eq!(orientation(&jpeg_with_orientation(9, true)), None);
+
+    let mut truncated = jpeg_with_orientation(6, false);
+    truncated.truncate(30);
+    assert_eq!(orientation(&truncated), None);
+
+    assert_eq!(orientation(&[0xff, 0xd8, 0xff, 0xda, 0, 2]), None);
+    assert_eq!(orientation(b"\x89PNG"), None);
+}
@@ -0,0 +1,169 @@
+use crate::landmark_prediction::FaceLandmarks;
+use crate::matrix::{ImageMatrix, ImageMatrixView};
+
+/// Options for extracting aligned face chips.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ChipOptions {
+    /// The width and height of the chip in pixels.
+    pub size: u64,
+    /// How much of a border to leave around the face, as a fraction of the size of the face.
+    pub padding: f64,
+}
+
+impl Default for ChipOptions {
+    /// The layout used by the face encoding network, a 150x150 chip with 0.25 padding.
+    fn default() -> Self {
+        Self {
+            size: 150,
+            padding: 0.25,
+        }
+    }
+}
+
+impl ChipOptions {
+    /// Check the options can be used to extract a chip, which needs a size of at least 1 and a finite,
+    /// non-negative padding.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.size == 0 {
+            return Err("Chips must be at least 1x1".into());
+        }
+
+        if !self.padding.is_finite() || self.padding < 0.0 {
+            return Err(format!(
+                "Chip padding must not be negative, but got {}",
+                self.padding
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+// dlib asserts on anything else inside the FFI call, so both are checked first.
+pub(super) fn check_alignment(
+    landmarks: &FaceLandmarks,
+    options: &ChipOptions,
+) -> Result<(), String> {
+    if landmarks.num_parts() != 68 && landmarks.num_parts() != 5 {
+        return Err(format!(
+            "Face alignment requires 68 or 5 landmarks, but got {}",
+            landmarks.num_parts()
+        ));
+    }
+
+    options.validate()
+}
+
+/// Extract a square crop of a face, rotated and scaled so that the eyes and nose are in canonical positions.
+///
+/// Fails if the landmarks don't have either 68 or 5 points, or the options aren't
+/// [valid](ChipOptions::validate).
+pub fn face_chip(
+    image: &ImageMatrix,
+    landmarks: &FaceLandmarks,
+    options: &ChipOptions,
+) -> Result<ImageMatrix, String> {
+    check_alignment(landmarks, options)?;
+
+    let size = options.size;
+    let padding = options.padding;
+
+    Ok(unsafe {
+        cpp!([image as "const dlib::matrix<dlib::rgb_pixel>*", landmarks as "const dlib::full_object_detection*", size as "uint64_t", padding as "double"] -> ImageMatrix as "dlib::matrix<dlib::rgb_pixel>" {
+            dlib::matrix<dlib::rgb_pixel> chip;
+            dlib::extract_image_chip(*image, dlib::get_face_chip_details(*landmarks, size, padding), chip);
+            return chip;
+        })
+    })
+}
+
+/// Extract aligned crops of several faces from the same image.
+///
+/// Fails if any of the landmarks don't have either 68 or 5 points, or the options aren't
+/// [valid](ChipOptions::validate).
+pub fn face_chips(
+    image: &ImageMatrix,
+    landmarks: &[FaceLandmarks],
+    options: &ChipOptions,
+) -> Result<Vec<ImageMatrix>, String> {
+    landmarks
+        .iter()
+        .map(|landmarks| face_chip(image, landmarks, options))
+        .collect()
+}
+
+/// Extract aligned crops of several faces from a view over a borrowed rgb buffer.
+///
+/// Fails if any of the landmarks don't have either 68 or 5 points, or the options aren't
+/// [valid](ChipOptions::validate).
+pub fn face_chips_view(
+    image: &ImageMatrixView,
+    landmarks: &[FaceLandmarks],
+    options: &ChipOptions,
+) -> Result<Vec<ImageMatrix>, String> {
+    let size = options.size;
+    let padding = options.padding;
+
+    landmarks
+        .iter()
+        .map(|landmarks| {
+            check_alignment(landmarks, options)?;
+
+            Ok(unsafe {
+                cpp!([image as "const rgb_image_view*", landmarks as "const dlib::full_object_detection*", size as "uint64_t", padding as "double"] -> ImageMatrix as "dlib::matrix<dlib::rgb_pixel>" {
+                    dlib::matrix<dlib::rgb_pixel> chip;
+                    dlib::extract_image_chip(*image, dlib::get_face_chip_details(*landmarks, size, padding), chip);
+                    return chip;
+                })
+            })
+        })
+        .collect()
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn test_face_chip() {
+    let image = image::open("assets/obama_1.jpg").unwrap().to_rgb();
+    let matrix = ImageMatrix::from_image(&image);
+    let landmarks = crate::landmark_prediction::obama_five_points();
+
+    let chip = face_chip(&matrix, &landmarks, &ChipOptions::default()).unwrap();
+    assert_eq!((chip.width(), chip.height()), (150, 150));
+
+    let chips = face_chips(
+        &matrix,
+        &[landmarks.clone(), landmarks.clone()],
+        &ChipOptions {
+            size: 80,
+            padding: 0.5,
+        },
+    )
+    .unwrap();
+    assert_eq!(chips.len(), 2);
+    assert_eq!((chips[1].width(), chips[1].height()), (80, 80));
+
+    let view = ImageMatrixView::from_image(&image);
+    let viewed = face_chips_view(&view, &[landmarks.clone()], &ChipOptions::default()).unwrap();
+    assert_eq!((viewed[0].width(), viewed[0].height()), (150, 150));
+
+    for options in &[
+        ChipOptions {
+            size: 0,
+            ..Default::default()
+        },
+        ChipOptions {
+            padding: -0.5,
+            ..Default::default()
+        },
+        ChipOptions {
+            padding: f64::NAN,
+            ..Default::default()
+        },
+    ] {
+        assert!(face_chip(&matrix, &landmarks, options).is_err());
+        assert!(face_chips_view(&view, &[landmarks.clone()], options).is_err());
+    }
+
+    let unsupported = FaceLandmarks::new(&Default::default(), &[]);
+    assert!(face_chip(&matrix, &unsupported, &ChipOptions::default()).is_err());
+}
@@ -0,0 +1,180 @@
+use crate::matrix::{GrayImageMatrix, ImageMatrix};
+
+/// Parameters of the Tan–Triggs illumination normalization.
+///
+/// The defaults are the values recommended by Tan and Triggs in "Enhanced Local Texture Feature Sets for
+/// Face Recognition Under Difficult Lighting Conditions".
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TanTriggsOptions {
+    /// The exponent of the gamma correction that compresses bright regions, or 0 to take the logarithm.
+    pub gamma: f64,
+    /// The standard deviation in pixels of the inner gaussian of the difference of gaussians filter, which
+    /// removes noise and aliasing.
+    pub inner_sigma: f64,
+    /// The standard deviation in pixels of the outer gaussian of the difference of gaussians filter, which
+    /// removes shading.
+    pub outer_sigma: f64,
+    /// The exponent used when equalizing the contrast, which reduces the influence of large values.
+    pub alpha: f64,
+    /// The value large values are compressed towards when equalizing the contrast.
+    pub tau: f64,
+}
+
+impl Default for TanTriggsOptions {
+    fn default() -> Self {
+        Self {
+            gamma: 0.2,
+            inner_sigma: 1.0,
+            outer_sigma: 2.0,
+            alpha: 0.1,
+            tau: 10.0,
+        }
+    }
+}
+
+/// Normalize the lighting of a face chip with the Tan–Triggs preprocessing chain of gamma correction, a
+/// difference of gaussians filter and contrast equalization.
+///
+/// This removes shadows and differences in exposure that otherwise dominate the distance between encodings
+/// of the same face under strong directional light, e.g. from outdoor cameras. The result is a grayscale
+/// image, with 128 for regions without detail. Apply it to chips from [`face_chips`] before passing them to
+/// [`FaceEncoderNetwork::encode_chips`], and to every chip that will be compared to them, since normalized
+/// and unnormalized chips don't encode alike.
+///
+/// [`face_chips`]: crate::face_alignment::face_chips
+/// [`FaceEncoderNetwork::encode_chips`]: crate::FaceEncoderNetwork::encode_chips
+pub fn normalize_illumination(chip: &ImageMatrix, options: &TanTriggsOptions) -> ImageMatrix {
+    let gray = GrayImageMatrix::from_rgb(chip);
+    let (width, height) = (gray.width(), gray.height());
+
+    let values: Vec<f64> = gray.to_raw().iter().map(|&value| value as f64).collect();
+    let normalized = tan_triggs(&values, width, height, options);
+
+    let tau = options.tau;
+    let bytes: Vec<u8> = normalized
+        .iter()
+        .map(|&value| (128.0 + value / tau * 127.0).round() as u8)
+        .collect();
+
+    ImageMatrix::from_gray(width, height, width, &bytes).unwrap()
+}
+
+// The Tan–Triggs chain on a single channel, returning values in `-tau..=tau`.
+fn tan_triggs(values: &[f64], width: usize, height: usize, options: &TanTriggsOptions) -> Vec<f64> {
+    let corrected: Vec<f64> = values
+        .iter()
+        .map(|&value| {
+            if options.gamma > 0.0 {
+                value.powf(options.gamma)
+            } else {
+                value.ln_1p()
+            }
+        })
+        .collect();
+
+    let inner = gaussian_blur(&corrected, width, height, options.inner_sigma);
+    let outer = gaussian_blur(&corrected, width, height, options.outer_sigma);
+    let mut filtered: Vec<f64> = inner.iter().zip(outer.iter()).map(|(a, b)| a - b).collect();
+
+    let (alpha, tau) = (options.alpha, options.tau);
+    equalize_contrast(&mut filtered, |value| value.abs().powf(alpha), alpha);
+    equalize_contrast(
+        &mut filtered,
+        |value| value.abs().min(tau).powf(alpha),
+        alpha,
+    );
+
+    for value in filtered.iter_mut() {
+        *value = tau * (*value / tau).tanh();
+    }
+
+    filtered
+}
+
+// Below this the values are only rounding errors, e.g. from filtering a featureless chip, and are left as is
+// rather than amplified.
+const MIN_CONTRAST: f64 = 1e-9;
+
+// Divide the values by the `alpha` power mean of `measure` over them.
+fn equalize_contrast(values: &mut [f64], measure: impl Fn(f64) -> f64, alpha: f64) {
+    if values.is_empty() {
+        return;
+    }
+
+    let mean = values.iter().map(|&value| measure(value)).sum::<f64>() / values.len() as f64;
+    let scale = mean.powf(1.0 / alpha);
+
+    if scale > MIN_CONTRAST {
+        for value in values.iter_mut() {
+            *value /= scale;
+        }
+    }
+}
+
+// A separable gaussian blur that repeats the pixels at the border, or a copy for `sigma` of 0.
+fn gaussian_blur(values: &[f64], width: usize, height: usize, sigma: f64) -> Vec<f64> {
+    if sigma <= 0.0 || values.is_empty() {
+        return values.to_vec();
+    }
+
+    let radius = (3.0 * sigma).ceil() as i64;
+    let kernel: Vec<f64> = (-radius..=radius)
+        .map(|offset| (-((offset * offset) as f64) / (2.0 * sigma * sigma)).exp())
+        .collect();
+    let total: f64 = kernel.iter().sum();
+
+    let convolve = |input: &[f64], horizontal: bool| -> Vec<f64> {
+        let mut output = vec![0f64; input.len()];
+        for y in 0..height as i64 {
+            for x in 0..width as i64 {
+                let sum: f64 = kernel
+                    .iter()
+                    .zip(-radius..=radius)
+                    .map(|(weight, offset)| {
+                        let (u, v) = if horizontal {
+                            ((x + offset).clamp(0, width as i64 - 1), y)
+                        } else {
+                            (x, (y + offset).clamp(0, height as i64 - 1))
+                        };
+                        weight * input[(v * width as i64 + u) as usize]
+                    })
+                    .sum();
+                output[(y * width as i64 + x) as usize] = sum / total;
+            }
+        }
+        output
+    };
+
+    convolve(&convolve(values, true), false)
+}
+
+#[test]
+fn test_tan_triggs() {
+    let options = TanTriggsOptions::default();
+    let (width, height) = (24, 16);
+
+    let pattern: Vec<f64> = (0..width * height)
+        .map(|i| 60.0 + 30.0 * ((i % width) as f64 * 0.7).sin() + 20.0 * ((i / width) as f64).cos())
+        .collect();
+    let normalized = tan_triggs(&pattern, width, height, &options);
+    assert!(normalized.iter().all(|value| value.abs() <= options.tau));
+
+    // the same pattern under three times the light normalizes to the same values
+    let brighter: Vec<f64> = pattern.iter().map(|value| value * 3.0).collect();
+    let relit = tan_triggs(&brighter, width, height, &options);
+    for (a, b) in normalized.iter().zip(relit.iter()) {
+        assert!((a - b).abs() < 1e-9);
+    }
+
+    let flat = tan_triggs(&vec![100.0; width * height], width, height, &options);
+    assert!(flat.iter().all(|value| value.abs() < 1e-9));
+}
+
+#[test]
+fn test_normalize_illumination() {
+    let chip = ImageMatrix::blank(32, 32).adjust_brightness(90);
+    let normalized = normalize_illumination(&chip, &TanTriggsOptions::default());
+
+    assert_eq!((normalized.width(), normalized.height()), (32, 32));
+    assert!(normalized.to_raw_rgb().iter().all(|&value| value == 128));
+}
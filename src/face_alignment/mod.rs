@@ -0,0 +1,13 @@
+//! Aligning, triangulating and warping faces using their landmarks, and normalizing the lighting of face chips.
+
+mod chip;
+mod illumination;
+mod morph;
+mod transform;
+mod triangulation;
+
+pub use self::chip::{face_chip, face_chips, face_chips_view, ChipOptions};
+pub use self::illumination::{normalize_illumination, TanTriggsOptions};
+pub use self::morph::{warp_face, Blending};
+pub use self::transform::{alignment_transform, landmarks_in_chip, similarity_transform, warp};
+pub use self::triangulation::{delaunay_triangulation, landmark_triangulation};
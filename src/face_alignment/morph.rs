@@ -0,0 +1,115 @@
+use crate::landmark_prediction::FaceLandmarks;
+use crate::matrix::ImageMatrix;
+
+use super::triangulation::delaunay_triangulation;
+
+/// How a warped face is blended into the destination image.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Blending {
+    /// Paste the face over the destination without any blending.
+    Hard,
+    /// Fade the edge of the face into the destination over roughly `radius` pixels.
+    Feathered { radius: f64 },
+    /// Poisson blending, which keeps the detail of the face but matches the colour and lighting of the
+    /// destination around its edge. More iterations give smoother results but take longer.
+    Seamless { iterations: usize },
+}
+
+impl Default for Blending {
+    fn default() -> Self {
+        Blending::Feathered { radius: 5.0 }
+    }
+}
+
+fn coordinates(landmarks: &FaceLandmarks) -> Vec<[f64; 2]> {
+    landmarks
+        .iter()
+        .map(|point| [point.x() as f64, point.y() as f64])
+        .collect()
+}
+
+/// Warp the face in `src_image` so that its landmarks line up with `dst_landmarks`, and blend it into `dst_image`.
+///
+/// The face is split into triangles between its landmarks, and each triangle is warped separately, so the
+/// expression and shape of the destination face are kept. Passing the same image as both source and destination
+/// transfers an expression, while different images swap faces.
+///
+/// # Panics
+///
+/// Panics if the two sets of landmarks have a different number of points.
+pub fn warp_face(
+    src_image: &ImageMatrix,
+    src_landmarks: &FaceLandmarks,
+    dst_image: &ImageMatrix,
+    dst_landmarks: &FaceLandmarks,
+    blending: Blending,
+) -> ImageMatrix {
+    assert_eq!(
+        src_landmarks.num_parts(),
+        dst_landmarks.num_parts(),
+        "Both faces must have the same number of landmarks"
+    );
+
+    let src_points = coordinates(src_landmarks);
+    let dst_points = coordinates(dst_landmarks);
+    let triangles = delaunay_triangulation(&dst_points);
+
+    let (mode, parameter) = match blending {
+        Blending::Hard => (0, 0.0),
+        Blending::Feathered { radius } => (1, radius),
+        Blending::Seamless { iterations } => (2, iterations as f64),
+    };
+
+    unsafe {
+        let src_points = src_points.as_ptr() as *const f64;
+        let dst_points = dst_points.as_ptr() as *const f64;
+        let num_triangles = triangles.len();
+        let triangles = triangles.as_ptr() as *const usize;
+
+        cpp!([src_image as "const dlib::matrix<dlib::rgb_pixel>*", dst_image as "const dlib::matrix<dlib::rgb_pixel>*", src_points as "const double*", dst_points as "const double*", triangles as "const size_t*", num_triangles as "size_t", mode as "int", parameter as "double"] -> ImageMatrix as "dlib::matrix<dlib::rgb_pixel>" {
+            return warp_face_triangles(*src_image, *dst_image, src_points, dst_points, triangles, num_triangles, mode, parameter);
+        })
+    }
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn test_warp_face() {
+    use crate::geometry::{Point, Rectangle};
+
+    let image = image::open("assets/obama_1.jpg").unwrap().to_rgb();
+    let src = ImageMatrix::from_image(&image);
+    let dst = ImageMatrix::blank(200, 150);
+
+    let src_landmarks = crate::landmark_prediction::obama_five_points();
+    let dst_landmarks = FaceLandmarks::new(
+        &Rectangle::default(),
+        &[
+            Point::new(140, 40),
+            Point::new(120, 40),
+            Point::new(60, 40),
+            Point::new(80, 40),
+            Point::new(100, 90),
+        ],
+    );
+
+    for &blending in &[
+        Blending::Hard,
+        Blending::default(),
+        Blending::Seamless { iterations: 10 },
+    ] {
+        let warped = warp_face(&src, &src_landmarks, &dst, &dst_landmarks, blending);
+        assert_eq!((warped.width(), warped.height()), (200, 150));
+    }
+
+    // without blending, each landmark's pixel is copied from the same landmark in the source
+    let warped = warp_face(&src, &src_landmarks, &dst, &dst_landmarks, Blending::Hard);
+    for (from, to) in src_landmarks.iter().zip(dst_landmarks.iter()) {
+        let expected = src.get_pixel(from.x() as usize, from.y() as usize).unwrap();
+        let actual = warped.get_pixel(to.x() as usize, to.y() as usize).unwrap();
+        for (expected, actual) in expected.iter().zip(actual.iter()) {
+            assert!((*expected as i32 - *actual as i32).abs() <= 1);
+        }
+    }
+    assert_eq!(warped.get_pixel(0, 0), Some([0, 0, 0]));
+}
@@ -0,0 +1,217 @@
+use crate::geometry::AffineTransform;
+use crate::landmark_prediction::FaceLandmarks;
+use crate::matrix::ImageMatrix;
+
+use super::chip::{check_alignment, ChipOptions};
+
+/// The rotation, uniform scale and translation that best maps the `from` points onto the `to` points,
+/// in the least squares sense.
+///
+/// This can be used to align faces to a custom template, e.g. by mapping a set of landmarks onto the
+/// positions they should have in the output image.
+///
+/// # Panics
+///
+/// Panics if the two sets of points have different lengths, or there are fewer than 2 of them.
+pub fn similarity_transform(from: &[[f64; 2]], to: &[[f64; 2]]) -> AffineTransform {
+    assert_eq!(
+        from.len(),
+        to.len(),
+        "Both sets of points must have the same length"
+    );
+    assert!(
+        from.len() >= 2,
+        "At least 2 points are needed to find a similarity transform"
+    );
+
+    let mean = |points: &[[f64; 2]]| {
+        let n = points.len() as f64;
+        let (x, y) = points
+            .iter()
+            .fold((0.0, 0.0), |(x, y), p| (x + p[0], y + p[1]));
+        [x / n, y / n]
+    };
+
+    let from_mean = mean(from);
+    let to_mean = mean(to);
+
+    let mut dot = 0.0;
+    let mut cross = 0.0;
+    let mut norm = 0.0;
+
+    for (p, q) in from.iter().zip(to) {
+        let a = [p[0] - from_mean[0], p[1] - from_mean[1]];
+        let b = [q[0] - to_mean[0], q[1] - to_mean[1]];
+
+        dot += a[0] * b[0] + a[1] * b[1];
+        cross += a[0] * b[1] - a[1] * b[0];
+        norm += a[0] * a[0] + a[1] * a[1];
+    }
+
+    let (cos, sin) = if norm > 0.0 {
+        (dot / norm, cross / norm)
+    } else {
+        (1.0, 0.0)
+    };
+
+    let matrix = [[cos, -sin], [sin, cos]];
+    let offset = [
+        to_mean[0] - (matrix[0][0] * from_mean[0] + matrix[0][1] * from_mean[1]),
+        to_mean[1] - (matrix[1][0] * from_mean[0] + matrix[1][1] * from_mean[1]),
+    ];
+
+    AffineTransform { matrix, offset }
+}
+
+/// The transform that `face_chip` uses to map points in the image to points in the chip.
+///
+/// Fails if the landmarks don't have either 68 or 5 points, or the options aren't
+/// [valid](ChipOptions::validate).
+pub fn alignment_transform(
+    landmarks: &FaceLandmarks,
+    options: &ChipOptions,
+) -> Result<AffineTransform, String> {
+    check_alignment(landmarks, options)?;
+
+    let size = options.size;
+    let padding = options.padding;
+    let mut coefficients = [0f64; 6];
+
+    unsafe {
+        let coefficients = coefficients.as_mut_ptr();
+
+        cpp!([landmarks as "const dlib::full_object_detection*", size as "uint64_t", padding as "double", coefficients as "double*"] {
+            dlib::point_transform_affine transform = dlib::get_mapping_to_chip(dlib::get_face_chip_details(*landmarks, size, padding));
+
+            coefficients[0] = transform.get_m()(0, 0);
+            coefficients[1] = transform.get_m()(0, 1);
+            coefficients[2] = transform.get_m()(1, 0);
+            coefficients[3] = transform.get_m()(1, 1);
+            coefficients[4] = transform.get_b()(0);
+            coefficients[5] = transform.get_b()(1);
+        })
+    }
+
+    Ok(AffineTransform {
+        matrix: [
+            [coefficients[0], coefficients[1]],
+            [coefficients[2], coefficients[3]],
+        ],
+        offset: [coefficients[4], coefficients[5]],
+    })
+}
+
+/// The positions of the landmarks in the chip extracted by `face_chip` with the same options.
+///
+/// Fails for the same reasons as [`alignment_transform`].
+pub fn landmarks_in_chip(
+    landmarks: &FaceLandmarks,
+    options: &ChipOptions,
+) -> Result<Vec<[f64; 2]>, String> {
+    let transform = alignment_transform(landmarks, options)?;
+
+    Ok(landmarks
+        .iter()
+        .map(|point| transform.apply([point.x() as f64, point.y() as f64]))
+        .collect())
+}
+
+/// Warp an image into a new `width` x `height` image, where `transform` maps points in the source image
+/// to points in the output.
+///
+/// Output pixels that map to outside of the source image are black.
+///
+/// # Panics
+///
+/// Panics if the transform is not invertible.
+pub fn warp(
+    image: &ImageMatrix,
+    transform: &AffineTransform,
+    width: usize,
+    height: usize,
+) -> ImageMatrix {
+    // dlib maps each output pixel back to the source image
+    let inverse = transform
+        .inverse()
+        .expect("The warp transform must be invertible");
+
+    let coefficients = [
+        inverse.matrix[0][0],
+        inverse.matrix[0][1],
+        inverse.matrix[1][0],
+        inverse.matrix[1][1],
+        inverse.offset[0],
+        inverse.offset[1],
+    ];
+
+    unsafe {
+        let coefficients = coefficients.as_ptr();
+
+        cpp!([image as "const dlib::matrix<dlib::rgb_pixel>*", coefficients as "const double*", width as "size_t", height as "size_t"] -> ImageMatrix as "dlib::matrix<dlib::rgb_pixel>" {
+            dlib::matrix<double,2,2> m;
+            m = coefficients[0], coefficients[1],
+                coefficients[2], coefficients[3];
+            dlib::dpoint b(coefficients[4], coefficients[5]);
+
+            dlib::matrix<dlib::rgb_pixel> out(height, width);
+            out = dlib::rgb_pixel(0, 0, 0);
+            dlib::transform_image(*image, out, dlib::interpolate_bilinear(), dlib::point_transform_affine(m, b));
+            return out;
+        })
+    }
+}
+
+#[test]
+fn test_similarity_transform() {
+    let from = [[0.0, 0.0], [10.0, 0.0], [0.0, 10.0], [10.0, 10.0]];
+
+    // rotate by 90 degrees, scale by 2 and shift
+    let to: Vec<[f64; 2]> = from
+        .iter()
+        .map(|p| [5.0 - 2.0 * p[1], 3.0 + 2.0 * p[0]])
+        .collect();
+
+    let transform = similarity_transform(&from, &to);
+    let close = |a: f64, b: f64| (a - b).abs() < 1e-9;
+
+    assert!(close(transform.matrix[0][0], 0.0));
+    assert!(close(transform.matrix[0][1], -2.0));
+    assert!(close(transform.matrix[1][0], 2.0));
+    assert!(close(transform.matrix[1][1], 0.0));
+    assert!(close(transform.offset[0], 5.0));
+    assert!(close(transform.offset[1], 3.0));
+
+    for (p, q) in from.iter().zip(&to) {
+        let mapped = transform.apply(*p);
+        assert!(close(mapped[0], q[0]) && close(mapped[1], q[1]));
+    }
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn test_warp() {
+    let image = image::open("assets/obama_1.jpg").unwrap().to_rgb();
+    let matrix = ImageMatrix::from_image(&image);
+    let landmarks = crate::landmark_prediction::obama_five_points();
+
+    let options = ChipOptions::default();
+    let transform = alignment_transform(&landmarks, &options).unwrap();
+
+    // the nose tip should land inside the chip
+    let nose = transform.apply([410.0, 260.0]);
+    assert!(nose[0] > 0.0 && nose[0] < 150.0);
+    assert!(nose[1] > 0.0 && nose[1] < 150.0);
+
+    let in_chip = landmarks_in_chip(&landmarks, &options).unwrap();
+    assert_eq!(in_chip.len(), 5);
+    assert_eq!(in_chip[4], nose);
+
+    let warped = warp(&matrix, &transform, 150, 150);
+    assert_eq!((warped.width(), warped.height()), (150, 150));
+
+    let empty = ChipOptions {
+        size: 0,
+        ..Default::default()
+    };
+    assert!(alignment_transform(&landmarks, &empty).is_err());
+}
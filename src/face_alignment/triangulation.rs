@@ -0,0 +1,219 @@
+use crate::geometry::Point;
+use crate::landmark_prediction::FaceLandmarks;
+
+#[derive(Copy, Clone)]
+struct Triangle {
+    vertices: [usize; 3],
+    center: [f64; 2],
+    radius_squared: f64,
+}
+
+impl Triangle {
+    fn new(points: &[[f64; 2]], vertices: [usize; 3]) -> Self {
+        let [a, b, c] = [
+            points[vertices[0]],
+            points[vertices[1]],
+            points[vertices[2]],
+        ];
+
+        let d = 2.0 * (a[0] * (b[1] - c[1]) + b[0] * (c[1] - a[1]) + c[0] * (a[1] - b[1]));
+        let (center, radius_squared) = if d.abs() < 1e-12 {
+            // degenerate triangles never contain anything, so they get replaced as soon as possible
+            ([0.0, 0.0], f64::INFINITY)
+        } else {
+            let a2 = a[0] * a[0] + a[1] * a[1];
+            let b2 = b[0] * b[0] + b[1] * b[1];
+            let c2 = c[0] * c[0] + c[1] * c[1];
+
+            let center = [
+                (a2 * (b[1] - c[1]) + b2 * (c[1] - a[1]) + c2 * (a[1] - b[1])) / d,
+                (a2 * (c[0] - b[0]) + b2 * (a[0] - c[0]) + c2 * (b[0] - a[0])) / d,
+            ];
+            let dx = a[0] - center[0];
+            let dy = a[1] - center[1];
+
+            (center, dx * dx + dy * dy)
+        };
+
+        Self {
+            vertices,
+            center,
+            radius_squared,
+        }
+    }
+
+    fn circumcircle_contains(&self, point: [f64; 2]) -> bool {
+        let dx = point[0] - self.center[0];
+        let dy = point[1] - self.center[1];
+        dx * dx + dy * dy < self.radius_squared
+    }
+
+    fn edges(&self) -> [[usize; 2]; 3] {
+        let [a, b, c] = self.vertices;
+        [[a, b], [b, c], [c, a]]
+    }
+}
+
+fn same_edge(a: [usize; 2], b: [usize; 2]) -> bool {
+    (a[0] == b[0] && a[1] == b[1]) || (a[0] == b[1] && a[1] == b[0])
+}
+
+/// The Delaunay triangulation of a set of points, as triples of indices into `points`.
+///
+/// Duplicate points are only included once, so some indices may not appear in any triangle.
+pub fn delaunay_triangulation(points: &[[f64; 2]]) -> Vec<[usize; 3]> {
+    if points.len() < 3 {
+        return Vec::new();
+    }
+
+    // start with a triangle that is much larger than all of the points
+    let (mut min_x, mut min_y) = (f64::INFINITY, f64::INFINITY);
+    let (mut max_x, mut max_y) = (f64::NEG_INFINITY, f64::NEG_INFINITY);
+    for point in points {
+        min_x = min_x.min(point[0]);
+        min_y = min_y.min(point[1]);
+        max_x = max_x.max(point[0]);
+        max_y = max_y.max(point[1]);
+    }
+
+    let size = (max_x - min_x).max(max_y - min_y).max(1.0) * 20.0;
+    let mid = [(min_x + max_x) / 2.0, (min_y + max_y) / 2.0];
+
+    let mut vertices = points.to_vec();
+    let first_super = vertices.len();
+    vertices.push([mid[0] - size, mid[1] - size]);
+    vertices.push([mid[0], mid[1] + size]);
+    vertices.push([mid[0] + size, mid[1] - size]);
+
+    let mut triangles = vec![Triangle::new(
+        &vertices,
+        [first_super, first_super + 1, first_super + 2],
+    )];
+
+    for (index, &point) in points.iter().enumerate() {
+        if points[..index].contains(&point) {
+            continue;
+        }
+
+        let (bad, good): (Vec<Triangle>, Vec<Triangle>) = triangles
+            .into_iter()
+            .partition(|triangle| triangle.circumcircle_contains(point));
+
+        // the boundary of the hole left by the bad triangles is made of the edges they don't share
+        let edges: Vec<[usize; 2]> = bad.iter().flat_map(|triangle| triangle.edges()).collect();
+        let boundary = edges.iter().enumerate().filter(|&(i, &edge)| {
+            !edges
+                .iter()
+                .enumerate()
+                .any(|(j, &other)| i != j && same_edge(edge, other))
+        });
+
+        triangles = good;
+        for (_, edge) in boundary {
+            triangles.push(Triangle::new(&vertices, [edge[0], edge[1], index]));
+        }
+    }
+
+    triangles
+        .into_iter()
+        .map(|triangle| triangle.vertices)
+        .filter(|vertices| vertices.iter().all(|&vertex| vertex < first_super))
+        .collect()
+}
+
+/// The Delaunay triangulation of a set of landmarks along with the four corners of a `width` x `height` image.
+///
+/// Returns the triangulated points, the landmarks followed by the top left, top right, bottom right and bottom
+/// left corners, along with the triangles as triples of indices into them.
+pub fn landmark_triangulation(
+    landmarks: &FaceLandmarks,
+    width: usize,
+    height: usize,
+) -> (Vec<Point>, Vec<[usize; 3]>) {
+    let right = width.saturating_sub(1) as i64;
+    let bottom = height.saturating_sub(1) as i64;
+
+    let mut points: Vec<Point> = landmarks.to_vec();
+    points.extend_from_slice(&[
+        Point::new(0, 0),
+        Point::new(right, 0),
+        Point::new(right, bottom),
+        Point::new(0, bottom),
+    ]);
+
+    let coordinates: Vec<[f64; 2]> = points
+        .iter()
+        .map(|point| [point.x() as f64, point.y() as f64])
+        .collect();
+
+    let triangles = delaunay_triangulation(&coordinates);
+    (points, triangles)
+}
+
+#[cfg(test)]
+fn triangle_area(points: &[[f64; 2]], triangle: &[usize; 3]) -> f64 {
+    let [a, b, c] = [
+        points[triangle[0]],
+        points[triangle[1]],
+        points[triangle[2]],
+    ];
+    ((b[0] - a[0]) * (c[1] - a[1]) - (c[0] - a[0]) * (b[1] - a[1])).abs() / 2.0
+}
+
+#[test]
+fn test_delaunay_triangulation() {
+    assert!(delaunay_triangulation(&[[0.0, 0.0], [1.0, 0.0]]).is_empty());
+
+    let square = [
+        [0.0, 0.0],
+        [10.0, 0.0],
+        [10.0, 10.0],
+        [0.0, 10.0],
+        [5.0, 4.0],
+    ];
+    let triangles = delaunay_triangulation(&square);
+
+    // the centre point splits the square into 4 triangles covering its whole area
+    assert_eq!(triangles.len(), 4);
+    let area: f64 = triangles.iter().map(|t| triangle_area(&square, t)).sum();
+    assert!((area - 100.0).abs() < 1e-9);
+    assert!(triangles.iter().all(|t| t.contains(&4)));
+
+    // no point lies inside another triangle's circumcircle
+    for triangle in &triangles {
+        let circle = Triangle::new(&square, *triangle);
+        for (i, point) in square.iter().enumerate() {
+            if !triangle.contains(&i) {
+                assert!(!circle.circumcircle_contains(*point));
+            }
+        }
+    }
+
+    // duplicates are ignored
+    let duplicated = [[0.0, 0.0], [10.0, 0.0], [0.0, 10.0], [10.0, 0.0]];
+    assert_eq!(delaunay_triangulation(&duplicated).len(), 1);
+}
+
+#[test]
+fn test_landmark_triangulation() {
+    use crate::geometry::Rectangle;
+
+    let landmarks = FaceLandmarks::new(
+        &Rectangle::default(),
+        &[Point::new(30, 30), Point::new(60, 30), Point::new(45, 60)],
+    );
+    let (points, triangles) = landmark_triangulation(&landmarks, 100, 100);
+
+    assert_eq!(points.len(), 7);
+    assert_eq!(points[5], Point::new(99, 99));
+
+    let coordinates: Vec<[f64; 2]> = points
+        .iter()
+        .map(|point| [point.x() as f64, point.y() as f64])
+        .collect();
+    let area: f64 = triangles
+        .iter()
+        .map(|t| triangle_area(&coordinates, t))
+        .sum();
+    assert!((area - 99.0 * 99.0).abs() < 1e-9);
+}
@@ -0,0 +1,177 @@
+use std::path::Path;
+use std::sync::Mutex;
+
+use crate::base::path_as_cstring;
+use crate::chip_model::ChipModel;
+use crate::face_alignment::ChipOptions;
+use crate::landmark_prediction::FaceLandmarks;
+use crate::matrix::ImageMatrix;
+
+// The network classifies faces into one year old bins from 0 to 80.
+const NUM_AGES: usize = 81;
+
+/// dlib's community age predictor, `dnn_age_predictor_v1.dat` from
+/// [dlib-models](https://github.com/davisking/dlib-models).
+///
+/// The network outputs the probability of each age from 0 to 80, which [`AgePredictorNetwork::predict_ages`]
+/// reduces to the expected age. Prediction holds a lock on the network, so threads that need to predict at
+/// the same time should each have a clone.
+pub struct AgePredictorNetwork {
+    inner: Mutex<AgePredictorNetworkInner>,
+    chip_options: ChipOptions,
+}
+
+impl Clone for AgePredictorNetwork {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Mutex::new(self.inner.lock().unwrap().clone()),
+            chip_options: self.chip_options,
+        }
+    }
+}
+
+cpp_class!(unsafe struct AgePredictorNetworkInner as "age_predictor_softmax");
+
+impl AgePredictorNetwork {
+    /// Deserialize the age predictor network from a file path.
+    pub fn new<P: AsRef<Path>>(filename: P) -> Result<Self, String> {
+        let string = path_as_cstring(filename.as_ref())?;
+
+        let inner = AgePredictorNetworkInner::default();
+
+        let deserialized = unsafe {
+            let filename = string.as_ptr();
+            let network = &inner;
+
+            cpp!([filename as "char*", network as "age_predictor_softmax*"] -> bool as "bool" {
+                try {
+                    age_predictor_nn loaded;
+                    dlib::deserialize(filename) >> loaded;
+                    network->subnet() = loaded.subnet();
+                    return true;
+                } catch (const std::exception& exception) {
+                    return false;
+                }
+            })
+        };
+
+        if !deserialized {
+            Err(format!(
+                "Failed to deserialize '{}'",
+                filename.as_ref().display()
+            ))
+        } else {
+            Ok(Self::from_inner(inner))
+        }
+    }
+
+    /// Deserialize the age predictor network from a model file that has already been read into memory.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let inner = AgePredictorNetworkInner::default();
+
+        let deserialized = unsafe {
+            let data = bytes.as_ptr();
+            let len = bytes.len();
+            let network = &inner;
+
+            cpp!([data as "const char*", len as "size_t", network as "age_predictor_softmax*"] -> bool as "bool" {
+                try {
+                    std::istringstream stream(std::string(data, len));
+                    age_predictor_nn loaded;
+                    dlib::deserialize(loaded, stream);
+                    network->subnet() = loaded.subnet();
+                    return true;
+                } catch (const std::exception& exception) {
+                    return false;
+                }
+            })
+        };
+
+        if !deserialized {
+            Err("Failed to deserialize age predictor network".into())
+        } else {
+            Ok(Self::from_inner(inner))
+        }
+    }
+
+    fn from_inner(inner: AgePredictorNetworkInner) -> Self {
+        Self {
+            inner: Mutex::new(inner),
+            chip_options: ChipOptions {
+                size: 64,
+                padding: 0.25,
+            },
+        }
+    }
+
+    /// Set how face chips are extracted for the network, which defaults to a 64x64 chip with 0.25 padding.
+    pub fn set_chip_options(&mut self, options: ChipOptions) -> Result<(), String> {
+        options.validate()?;
+        self.chip_options = options;
+        Ok(())
+    }
+
+    /// Estimate the age in years of each face, as the expected value of the network's probabilities.
+    pub fn predict_ages(
+        &self,
+        image: &ImageMatrix,
+        landmarks: &[FaceLandmarks],
+    ) -> Result<Vec<f64>, String> {
+        Ok(self
+            .run_faces(image, landmarks)?
+            .iter()
+            .map(|probabilities| expected_age(probabilities))
+            .collect())
+    }
+}
+
+impl ChipModel for AgePredictorNetwork {
+    fn chip_options(&self) -> ChipOptions {
+        self.chip_options
+    }
+
+    /// Outputs the probability of each age from 0 to 80 for every chip.
+    fn run_chips(&self, chips: &[ImageMatrix]) -> Result<Vec<Vec<f32>>, String> {
+        let mut inner = self.inner.lock().unwrap();
+        let network = &mut *inner;
+
+        Ok(chips
+            .iter()
+            .map(|chip| {
+                let mut probabilities = vec![0f32; NUM_AGES];
+
+                unsafe {
+                    let out = probabilities.as_mut_ptr();
+                    let len = probabilities.len();
+
+                    cpp!([network as "age_predictor_softmax*", chip as "const dlib::matrix<dlib::rgb_pixel>*", out as "float*", len as "size_t"] {
+                        const dlib::tensor& output = (*network)(*chip);
+                        std::copy(output.host(), output.host() + std::min<size_t>(output.size(), len), out);
+                    });
+                }
+
+                probabilities
+            })
+            .collect())
+    }
+}
+
+// The mean of the ages weighted by their probabilities.
+fn expected_age(probabilities: &[f32]) -> f64 {
+    probabilities
+        .iter()
+        .enumerate()
+        .map(|(age, &probability)| age as f64 * probability as f64)
+        .sum()
+}
+
+#[test]
+fn test_age_predictor() {
+    assert!(AgePredictorNetwork::new("assets/obama_1.jpg").is_err());
+    assert!(AgePredictorNetwork::from_bytes(b"not a network").is_err());
+
+    let mut probabilities = vec![0.0; NUM_AGES];
+    probabilities[20] = 0.5;
+    probabilities[40] = 0.5;
+    assert_eq!(expected_age(&probabilities), 30.0);
+}
@@ -0,0 +1,184 @@
+use crate::geometry::Point;
+use crate::landmark_prediction::FaceLandmarks;
+
+use super::geometry::distance;
+
+// https://vision.fe.uni-lj.si/cvww2016/proceedings/papers/05.pdf
+fn single_eye_aspect_ratio(eye: &[Point]) -> f64 {
+    let width = distance(eye[0], eye[3]);
+    if width == 0.0 {
+        return 0.0;
+    }
+
+    (distance(eye[1], eye[5]) + distance(eye[2], eye[4])) / (2.0 * width)
+}
+
+/// The eye aspect ratio (EAR) of a face, averaged over both eyes.
+///
+/// This is the height of the eyes relative to their width, which is roughly constant while they are
+/// open and drops towards zero as they close.
+///
+/// Returns `None` if these aren't 68-point landmarks.
+pub fn eye_aspect_ratio(landmarks: &FaceLandmarks) -> Option<f64> {
+    let left = single_eye_aspect_ratio(landmarks.left_eye()?);
+    let right = single_eye_aspect_ratio(landmarks.right_eye()?);
+
+    Some((left + right) / 2.0)
+}
+
+/// A single blink, from the frame the eyes closed up to the frame they opened again.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Blink {
+    /// The first frame in which the eyes were closed.
+    pub start_frame: u64,
+    /// How many frames the eyes were closed for.
+    pub duration: u64,
+}
+
+/// Detects blinks by tracking the eye aspect ratio of a face over consecutive frames.
+#[derive(Clone, Debug)]
+pub struct BlinkDetector {
+    threshold: f64,
+    min_frames: u64,
+    frame: u64,
+    closed_since: Option<u64>,
+    blinks: u64,
+}
+
+impl BlinkDetector {
+    /// Create a detector that considers the eyes closed while the eye aspect ratio is below `threshold`,
+    /// and only counts closures that last at least `min_frames` frames as blinks.
+    pub fn new(threshold: f64, min_frames: u64) -> Self {
+        Self {
+            threshold,
+            min_frames,
+            frame: 0,
+            closed_since: None,
+            blinks: 0,
+        }
+    }
+
+    /// Feed the landmarks of the next frame, returning a blink if the eyes just opened again.
+    ///
+    /// Frames without 68-point landmarks are skipped without advancing the frame count.
+    pub fn update(&mut self, landmarks: &FaceLandmarks) -> Option<Blink> {
+        let ratio = eye_aspect_ratio(landmarks)?;
+        self.update_ratio(ratio)
+    }
+
+    /// Feed the eye aspect ratio of the next frame, returning a blink if the eyes just opened again.
+    pub fn update_ratio(&mut self, ratio: f64) -> Option<Blink> {
+        let frame = self.frame;
+        self.frame += 1;
+
+        if ratio < self.threshold {
+            if self.closed_since.is_none() {
+                self.closed_since = Some(frame);
+            }
+            return None;
+        }
+
+        let start_frame = self.closed_since.take()?;
+        let duration = frame - start_frame;
+
+        if duration < self.min_frames {
+            return None;
+        }
+
+        self.blinks += 1;
+        Some(Blink {
+            start_frame,
+            duration,
+        })
+    }
+
+    /// Whether the eyes were closed in the last frame.
+    pub fn is_closed(&self) -> bool {
+        self.closed_since.is_some()
+    }
+
+    /// How many frames the eyes have currently been closed for, useful for detecting drowsiness.
+    pub fn closed_frames(&self) -> u64 {
+        self.closed_since
+            .map(|start| self.frame - start)
+            .unwrap_or(0)
+    }
+
+    /// The number of blinks detected so far.
+    pub fn blink_count(&self) -> u64 {
+        self.blinks
+    }
+
+    /// The threshold below which the eyes are considered closed.
+    pub fn threshold(&self) -> f64 {
+        self.threshold
+    }
+
+    /// Forget all previous frames, e.g. when tracking a different face.
+    pub fn reset(&mut self) {
+        self.frame = 0;
+        self.closed_since = None;
+        self.blinks = 0;
+    }
+}
+
+impl Default for BlinkDetector {
+    /// A threshold of 0.2 over at least 2 frames, which works well for most faces at video frame rates.
+    fn default() -> Self {
+        Self::new(0.2, 2)
+    }
+}
+
+#[test]
+fn test_eye_aspect_ratio() {
+    use crate::geometry::Rectangle;
+
+    let mut parts = vec![Point::new(0, 0); 68];
+
+    // a 6x2 left eye and a 6x4 right eye
+    let left_eye = [(0, 0), (2, -1), (4, -1), (6, 0), (4, 1), (2, 1)];
+    let right_eye = [(10, 0), (12, -2), (14, -2), (16, 0), (14, 2), (12, 2)];
+
+    for (i, &(x, y)) in left_eye.iter().enumerate() {
+        parts[36 + i] = Point::new(x, y);
+    }
+    for (i, &(x, y)) in right_eye.iter().enumerate() {
+        parts[42 + i] = Point::new(x, y);
+    }
+
+    let landmarks = FaceLandmarks::new(&Rectangle::default(), &parts);
+    let ratio = eye_aspect_ratio(&landmarks).unwrap();
+    assert!((ratio - 0.5).abs() < 1e-9);
+
+    assert!(eye_aspect_ratio(&FaceLandmarks::new(&Rectangle::default(), &parts[..5])).is_none());
+}
+
+#[test]
+fn test_blink_detector() {
+    let mut detector = BlinkDetector::default();
+
+    assert_eq!(detector.update_ratio(0.3), None);
+
+    // a single frame closure is too short to be a blink
+    assert_eq!(detector.update_ratio(0.1), None);
+    assert_eq!(detector.update_ratio(0.3), None);
+    assert_eq!(detector.blink_count(), 0);
+
+    assert_eq!(detector.update_ratio(0.1), None);
+    assert_eq!(detector.update_ratio(0.15), None);
+    assert!(detector.is_closed());
+    assert_eq!(detector.closed_frames(), 2);
+    assert_eq!(
+        detector.update_ratio(0.3),
+        Some(Blink {
+            start_frame: 3,
+            duration: 2,
+        })
+    );
+    assert!(!detector.is_closed());
+    assert_eq!(detector.blink_count(), 1);
+
+    detector.reset();
+    assert_eq!(detector.blink_count(), 0);
+    assert_eq!(detector.closed_frames(), 0);
+}
@@ -0,0 +1,7 @@
+use crate::geometry::Point;
+
+pub(super) fn distance(a: Point, b: Point) -> f64 {
+    let dx = (a.x() - b.x()) as f64;
+    let dy = (a.y() - b.y()) as f64;
+    (dx * dx + dy * dy).sqrt()
+}
@@ -0,0 +1,14 @@
+//! Measurements of facial expressions and head pose from 68-point landmarks, and age estimates from face chips.
+
+mod age;
+mod blink;
+mod geometry;
+mod mouth;
+mod pose;
+mod quality;
+
+pub use self::age::AgePredictorNetwork;
+pub use self::blink::{eye_aspect_ratio, Blink, BlinkDetector};
+pub use self::mouth::{mouth_aspect_ratio, MouthOpenClassifier};
+pub use self::pose::{CameraIntrinsics, HeadPose};
+pub use self::quality::GeometryQuality;
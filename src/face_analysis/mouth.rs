@@ -0,0 +1,101 @@
+use crate::landmark_prediction::FaceLandmarks;
+
+use super::geometry::distance;
+
+/// The mouth aspect ratio (MAR) of a face.
+///
+/// This is the average height of the inside of the lips relative to their width, which is close to zero
+/// while the mouth is closed and grows as it opens.
+///
+/// Returns `None` if these aren't 68-point landmarks.
+pub fn mouth_aspect_ratio(landmarks: &FaceLandmarks) -> Option<f64> {
+    let lips = landmarks.inner_lips()?;
+
+    let width = distance(lips[0], lips[4]);
+    if width == 0.0 {
+        return Some(0.0);
+    }
+
+    let height =
+        distance(lips[1], lips[7]) + distance(lips[2], lips[6]) + distance(lips[3], lips[5]);
+
+    Some(height / (3.0 * width))
+}
+
+/// Classifies whether a mouth is open from its mouth aspect ratio.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MouthOpenClassifier {
+    threshold: f64,
+}
+
+impl MouthOpenClassifier {
+    /// Create a classifier that considers the mouth open once the mouth aspect ratio reaches `threshold`.
+    ///
+    /// Higher thresholds only count wide openings, such as yawns.
+    pub fn new(threshold: f64) -> Self {
+        Self { threshold }
+    }
+
+    /// Whether the mouth is open.
+    ///
+    /// Returns `None` if these aren't 68-point landmarks.
+    pub fn is_open(&self, landmarks: &FaceLandmarks) -> Option<bool> {
+        mouth_aspect_ratio(landmarks).map(|ratio| self.is_open_ratio(ratio))
+    }
+
+    /// Whether a mouth with the given mouth aspect ratio is open.
+    pub fn is_open_ratio(&self, ratio: f64) -> bool {
+        ratio >= self.threshold
+    }
+
+    /// The ratio at which the mouth is considered open.
+    pub fn threshold(&self) -> f64 {
+        self.threshold
+    }
+}
+
+impl Default for MouthOpenClassifier {
+    /// A threshold of 0.3, which picks up speech as well as yawns.
+    fn default() -> Self {
+        Self::new(0.3)
+    }
+}
+
+#[test]
+fn test_mouth_aspect_ratio() {
+    use crate::geometry::{Point, Rectangle};
+
+    let mut parts = vec![Point::new(0, 0); 68];
+
+    // inner lips 12 wide and 6 tall
+    let lips = [
+        (0, 0),
+        (3, -3),
+        (6, -3),
+        (9, -3),
+        (12, 0),
+        (9, 3),
+        (6, 3),
+        (3, 3),
+    ];
+    for (i, &(x, y)) in lips.iter().enumerate() {
+        parts[60 + i] = Point::new(x, y);
+    }
+
+    let landmarks = FaceLandmarks::new(&Rectangle::default(), &parts);
+    let ratio = mouth_aspect_ratio(&landmarks).unwrap();
+    assert!((ratio - 0.5).abs() < 1e-9);
+
+    assert_eq!(
+        MouthOpenClassifier::default().is_open(&landmarks),
+        Some(true)
+    );
+    assert_eq!(
+        MouthOpenClassifier::new(0.6).is_open(&landmarks),
+        Some(false)
+    );
+    assert!(!MouthOpenClassifier::default().is_open_ratio(0.1));
+
+    let five_point = FaceLandmarks::new(&Rectangle::default(), &parts[..5]);
+    assert_eq!(mouth_aspect_ratio(&five_point), None);
+}
@@ -0,0 +1,391 @@
+use crate::landmark_prediction::FaceLandmarks;
+
+// A generic 3D face model from
+// https://learnopencv.com/head-pose-estimation-using-opencv-and-dlib/
+// converted to camera axes (x to the right of the image, y down and z away from the camera) so that a
+// face looking straight at the camera has no rotation.
+const MODEL_POINTS: [[f64; 3]; 6] = [
+    // nose tip
+    [0.0, 0.0, 0.0],
+    // chin
+    [0.0, 330.0, 65.0],
+    // outer corner of the eye on the left of the image
+    [-225.0, -170.0, 135.0],
+    // outer corner of the eye on the right of the image
+    [225.0, -170.0, 135.0],
+    // corner of the mouth on the left of the image
+    [-150.0, 150.0, 125.0],
+    // corner of the mouth on the right of the image
+    [150.0, 150.0, 125.0],
+];
+
+// The 68-point landmarks corresponding to each of the model points.
+const LANDMARK_INDICES: [usize; 6] = [30, 8, 36, 45, 48, 54];
+
+/// The pinhole camera parameters used to project the face model into the image.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CameraIntrinsics {
+    /// The focal length in pixels along the x and y axes.
+    pub focal_length: [f64; 2],
+    /// The pixel the optical axis passes through.
+    pub principal_point: [f64; 2],
+}
+
+impl CameraIntrinsics {
+    /// Approximate intrinsics for an uncalibrated camera, with the focal length set to the image width and
+    /// the principal point in the middle of the image.
+    pub fn from_image_size(width: usize, height: usize) -> Self {
+        Self {
+            focal_length: [width as f64, width as f64],
+            principal_point: [width as f64 / 2.0, height as f64 / 2.0],
+        }
+    }
+
+    fn project(&self, point: [f64; 3]) -> [f64; 2] {
+        [
+            self.focal_length[0] * point[0] / point[2] + self.principal_point[0],
+            self.focal_length[1] * point[1] / point[2] + self.principal_point[1],
+        ]
+    }
+}
+
+/// The orientation of a head relative to the camera.
+///
+/// All angles are in degrees and are zero when the face is looking straight at the camera.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HeadPose {
+    /// Rotation about the vertical axis, positive when the face turns towards the left of the image.
+    pub yaw: f64,
+    /// Rotation about the horizontal axis, positive when the face tilts down.
+    pub pitch: f64,
+    /// Rotation about the optical axis, positive when the face tilts clockwise in the image.
+    pub roll: f64,
+    /// The rotation from the face model to camera axes, equal to `Rz(roll) * Ry(yaw) * Rx(pitch)`.
+    pub rotation: [[f64; 3]; 3],
+    /// The position of the nose tip relative to the camera, in the arbitrary units of the face model.
+    pub translation: [f64; 3],
+    /// The root mean square distance in pixels between the landmarks and the projected face model.
+    ///
+    /// Large errors mean the landmarks don't fit the generic model well, and the pose is unreliable.
+    pub reprojection_error: f64,
+}
+
+impl HeadPose {
+    /// Estimate the head pose from 68-point landmarks by fitting a generic 3D face model to them.
+    ///
+    /// Returns `None` if these aren't 68-point landmarks, or no sensible pose could be found.
+    pub fn estimate(landmarks: &FaceLandmarks, camera: &CameraIntrinsics) -> Option<Self> {
+        if landmarks.num_parts() != 68 {
+            return None;
+        }
+
+        let mut points = [[0f64; 2]; 6];
+        for (point, &index) in points.iter_mut().zip(LANDMARK_INDICES.iter()) {
+            let part = landmarks[index];
+            *point = [part.x() as f64, part.y() as f64];
+        }
+
+        Self::estimate_from_points(&points, camera)
+    }
+
+    fn estimate_from_points(points: &[[f64; 2]; 6], camera: &CameraIntrinsics) -> Option<Self> {
+        // start facing the camera, at the depth that gives the right distance between the eyes
+        let eye_distance =
+            ((points[3][0] - points[2][0]).powi(2) + (points[3][1] - points[2][1]).powi(2)).sqrt();
+        if eye_distance == 0.0 {
+            return None;
+        }
+
+        let depth =
+            camera.focal_length[0] * (MODEL_POINTS[3][0] - MODEL_POINTS[2][0]) / eye_distance;
+        let initial = [
+            0.0,
+            0.0,
+            0.0,
+            (points[0][0] - camera.principal_point[0]) * depth / camera.focal_length[0],
+            (points[0][1] - camera.principal_point[1]) * depth / camera.focal_length[1],
+            depth,
+        ];
+
+        let params = levenberg_marquardt(initial, points, camera)?;
+        let rotation = rodrigues([params[0], params[1], params[2]]);
+        let translation = [params[3], params[4], params[5]];
+
+        let residuals = residuals(&params, points, camera)?;
+        let reprojection_error =
+            (residuals.iter().map(|r| r * r).sum::<f64>() / MODEL_POINTS.len() as f64).sqrt();
+
+        Some(Self {
+            yaw: (-rotation[2][0]).clamp(-1.0, 1.0).asin().to_degrees(),
+            pitch: rotation[2][1].atan2(rotation[2][2]).to_degrees(),
+            roll: rotation[1][0].atan2(rotation[0][0]).to_degrees(),
+            rotation,
+            translation,
+            reprojection_error,
+        })
+    }
+
+    /// Whether the face is within `max_angle` degrees of looking straight at the camera in both yaw and pitch.
+    pub fn is_frontal(&self, max_angle: f64) -> bool {
+        self.yaw.abs() <= max_angle && self.pitch.abs() <= max_angle
+    }
+}
+
+// The rotation matrix for a rotation vector.
+fn rodrigues(r: [f64; 3]) -> [[f64; 3]; 3] {
+    let theta = (r[0] * r[0] + r[1] * r[1] + r[2] * r[2]).sqrt();
+    if theta < 1e-12 {
+        return [[1.0, -r[2], r[1]], [r[2], 1.0, -r[0]], [-r[1], r[0], 1.0]];
+    }
+
+    let k = [r[0] / theta, r[1] / theta, r[2] / theta];
+    let (sin, cos) = theta.sin_cos();
+    let c = 1.0 - cos;
+
+    [
+        [
+            cos + k[0] * k[0] * c,
+            k[0] * k[1] * c - k[2] * sin,
+            k[0] * k[2] * c + k[1] * sin,
+        ],
+        [
+            k[1] * k[0] * c + k[2] * sin,
+            cos + k[1] * k[1] * c,
+            k[1] * k[2] * c - k[0] * sin,
+        ],
+        [
+            k[2] * k[0] * c - k[1] * sin,
+            k[2] * k[1] * c + k[0] * sin,
+            cos + k[2] * k[2] * c,
+        ],
+    ]
+}
+
+// The difference between the projected model and the landmarks, or `None` if any model point ends up
+// behind the camera.
+fn residuals(
+    params: &[f64; 6],
+    points: &[[f64; 2]; 6],
+    camera: &CameraIntrinsics,
+) -> Option<[f64; 12]> {
+    let rotation = rodrigues([params[0], params[1], params[2]]);
+    let mut residuals = [0f64; 12];
+
+    for (i, (model, point)) in MODEL_POINTS.iter().zip(points.iter()).enumerate() {
+        let mut transformed = [params[3], params[4], params[5]];
+        for (row, value) in transformed.iter_mut().enumerate() {
+            *value += rotation[row][0] * model[0]
+                + rotation[row][1] * model[1]
+                + rotation[row][2] * model[2];
+        }
+
+        if transformed[2] <= 0.0 {
+            return None;
+        }
+
+        let projected = camera.project(transformed);
+        residuals[2 * i] = projected[0] - point[0];
+        residuals[2 * i + 1] = projected[1] - point[1];
+    }
+
+    Some(residuals)
+}
+
+fn cost(residuals: &[f64; 12]) -> f64 {
+    residuals.iter().map(|r| r * r).sum()
+}
+
+fn levenberg_marquardt(
+    mut params: [f64; 6],
+    points: &[[f64; 2]; 6],
+    camera: &CameraIntrinsics,
+) -> Option<[f64; 6]> {
+    let mut current = residuals(&params, points, camera)?;
+    let mut lambda = 1e-3;
+
+    for _ in 0..100 {
+        // numerical jacobian of the residuals
+        let mut jacobian = [[0f64; 6]; 12];
+        for j in 0..6 {
+            let step = 1e-6 * params[j].abs().max(1.0);
+            let mut shifted = params;
+            shifted[j] += step;
+
+            let shifted = residuals(&shifted, points, camera)?;
+            for i in 0..12 {
+                jacobian[i][j] = (shifted[i] - current[i]) / step;
+            }
+        }
+
+        let mut jtj = [[0f64; 6]; 6];
+        let mut jtr = [0f64; 6];
+        for i in 0..12 {
+            for a in 0..6 {
+                jtr[a] += jacobian[i][a] * current[i];
+                for b in 0..6 {
+                    jtj[a][b] += jacobian[i][a] * jacobian[i][b];
+                }
+            }
+        }
+
+        let current_cost = cost(&current);
+        let mut improved = false;
+
+        while lambda < 1e10 {
+            let mut damped = jtj;
+            for (a, row) in damped.iter_mut().enumerate() {
+                row[a] += lambda * (jtj[a][a] + 1e-9);
+            }
+
+            let mut rhs = jtr;
+            for value in rhs.iter_mut() {
+                *value = -*value;
+            }
+
+            if let Some(delta) = solve(damped, rhs) {
+                let mut candidate = params;
+                for (value, delta) in candidate.iter_mut().zip(delta.iter()) {
+                    *value += delta;
+                }
+
+                if let Some(candidate_residuals) = residuals(&candidate, points, camera) {
+                    let candidate_cost = cost(&candidate_residuals);
+                    if candidate_cost < current_cost {
+                        params = candidate;
+                        current = candidate_residuals;
+                        lambda = (lambda / 10.0).max(1e-12);
+                        improved = current_cost - candidate_cost > 1e-12 * current_cost.max(1.0);
+                        break;
+                    }
+                }
+            }
+
+            lambda *= 10.0;
+        }
+
+        if !improved {
+            break;
+        }
+    }
+
+    Some(params)
+}
+
+// Solve a small linear system with gaussian elimination and partial pivoting.
+#[allow(clippy::needless_range_loop)]
+fn solve(mut a: [[f64; 6]; 6], mut b: [f64; 6]) -> Option<[f64; 6]> {
+    for column in 0..6 {
+        let pivot =
+            (column..6).max_by(|&x, &y| a[x][column].abs().total_cmp(&a[y][column].abs()))?;
+
+        if a[pivot][column].is_nan() || a[pivot][column].abs() < 1e-300 {
+            return None;
+        }
+
+        a.swap(column, pivot);
+        b.swap(column, pivot);
+
+        for row in column + 1..6 {
+            let factor = a[row][column] / a[column][column];
+            for k in column..6 {
+                a[row][k] -= factor * a[column][k];
+            }
+            b[row] -= factor * b[column];
+        }
+    }
+
+    let mut x = [0f64; 6];
+    for row in (0..6).rev() {
+        let mut sum = b[row];
+        for k in row + 1..6 {
+            sum -= a[row][k] * x[k];
+        }
+        x[row] = sum / a[row][row];
+    }
+
+    Some(x)
+}
+
+#[cfg(test)]
+fn project_model(
+    yaw: f64,
+    pitch: f64,
+    roll: f64,
+    translation: [f64; 3],
+    camera: &CameraIntrinsics,
+) -> [[f64; 2]; 6] {
+    let multiply = |a: [[f64; 3]; 3], b: [[f64; 3]; 3]| {
+        let mut out = [[0f64; 3]; 3];
+        for i in 0..3 {
+            for j in 0..3 {
+                out[i][j] = (0..3).map(|k| a[i][k] * b[k][j]).sum();
+            }
+        }
+        out
+    };
+
+    let (sy, cy) = yaw.to_radians().sin_cos();
+    let (sp, cp) = pitch.to_radians().sin_cos();
+    let (sr, cr) = roll.to_radians().sin_cos();
+
+    let rz = [[cr, -sr, 0.0], [sr, cr, 0.0], [0.0, 0.0, 1.0]];
+    let ry = [[cy, 0.0, sy], [0.0, 1.0, 0.0], [-sy, 0.0, cy]];
+    let rx = [[1.0, 0.0, 0.0], [0.0, cp, -sp], [0.0, sp, cp]];
+    let rotation = multiply(rz, multiply(ry, rx));
+
+    let mut points = [[0f64; 2]; 6];
+    for (point, model) in points.iter_mut().zip(MODEL_POINTS.iter()) {
+        let mut transformed = translation;
+        for (row, value) in transformed.iter_mut().enumerate() {
+            *value += (0..3).map(|k| rotation[row][k] * model[k]).sum::<f64>();
+        }
+        *point = camera.project(transformed);
+    }
+
+    points
+}
+
+#[test]
+fn test_head_pose_from_points() {
+    let camera = CameraIntrinsics::from_image_size(640, 480);
+
+    for &(yaw, pitch, roll) in &[(0.0, 0.0, 0.0), (20.0, -10.0, 5.0), (-35.0, 15.0, -12.0)] {
+        let points = project_model(yaw, pitch, roll, [50.0, -30.0, 3000.0], &camera);
+        let pose = HeadPose::estimate_from_points(&points, &camera).unwrap();
+
+        assert!((pose.yaw - yaw).abs() < 0.1, "{:?}", pose);
+        assert!((pose.pitch - pitch).abs() < 0.1, "{:?}", pose);
+        assert!((pose.roll - roll).abs() < 0.1, "{:?}", pose);
+        assert!(pose.reprojection_error < 1e-3);
+        assert!((pose.translation[2] - 3000.0).abs() < 1.0);
+    }
+
+    assert!(HeadPose::estimate_from_points(&[[0.0, 0.0]; 6], &camera).is_none());
+}
+
+#[test]
+fn test_head_pose_from_landmarks() {
+    use crate::geometry::{Point, Rectangle};
+
+    let camera = CameraIntrinsics::from_image_size(640, 480);
+    let points = project_model(25.0, 0.0, 0.0, [0.0, 0.0, 1500.0], &camera);
+
+    let mut parts = vec![Point::new(320, 240); 68];
+    for (point, &index) in points.iter().zip(LANDMARK_INDICES.iter()) {
+        parts[index] = Point::new(point[0].round() as i64, point[1].round() as i64);
+    }
+
+    let landmarks = FaceLandmarks::new(&Rectangle::default(), &parts);
+    let pose = HeadPose::estimate(&landmarks, &camera).unwrap();
+
+    assert!((pose.yaw - 25.0).abs() < 2.0, "{:?}", pose);
+    assert!(pose.pitch.abs() < 2.0, "{:?}", pose);
+    assert!(!pose.is_frontal(15.0));
+
+    assert!(HeadPose::estimate(
+        &FaceLandmarks::new(&Rectangle::default(), &parts[..5]),
+        &camera
+    )
+    .is_none());
+}
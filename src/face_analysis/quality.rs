@@ -0,0 +1,164 @@
+use crate::landmark_prediction::FaceLandmarks;
+
+use super::blink::eye_aspect_ratio;
+use super::geometry::distance;
+
+// Pairs of 68-point landmarks that mirror each other across the face, around the jaw, eyebrows, nose, eyes and lips.
+const MIRRORED: [(usize, usize); 20] = [
+    (0, 16),
+    (2, 14),
+    (4, 12),
+    (6, 10),
+    (17, 26),
+    (19, 24),
+    (21, 22),
+    (31, 35),
+    (36, 45),
+    (37, 44),
+    (38, 43),
+    (39, 42),
+    (40, 47),
+    (41, 46),
+    (48, 54),
+    (49, 53),
+    (50, 52),
+    (55, 59),
+    (56, 58),
+    (60, 64),
+];
+
+const NOSE_TIP: usize = 30;
+
+// The eye aspect ratio of comfortably open eyes.
+const OPEN_EYE_ASPECT_RATIO: f64 = 0.25;
+
+/// How well the geometry of a face suits recognition, from its 68-point landmarks.
+///
+/// Every measure is between `0.0` and `1.0`, where `1.0` is a frontal face with open eyes. Profile, heavily
+/// rotated or occluded faces, and faces with badly fitted landmarks, score poorly, and are best left out of an
+/// enrollment gallery.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GeometryQuality {
+    /// How similar the distances from the nose tip to matching points on either side of the face are.
+    pub symmetry: f64,
+    /// How open the eyes are.
+    pub eye_openness: f64,
+    /// How close the nose tip is to halfway between the outer corners of the eyes.
+    pub nose_centering: f64,
+    /// The lowest of the other measures.
+    pub score: f64,
+}
+
+impl GeometryQuality {
+    /// Measure the geometry of a face.
+    ///
+    /// Returns `None` if these aren't 68-point landmarks.
+    pub fn from_landmarks(landmarks: &FaceLandmarks) -> Option<Self> {
+        if landmarks.num_parts() != 68 {
+            return None;
+        }
+
+        let nose = landmarks[NOSE_TIP];
+
+        let symmetry = MIRRORED
+            .iter()
+            .map(|&(left, right)| {
+                let left = distance(nose, landmarks[left]);
+                let right = distance(nose, landmarks[right]);
+                let longest = left.max(right);
+
+                if longest == 0.0 {
+                    1.0
+                } else {
+                    left.min(right) / longest
+                }
+            })
+            .sum::<f64>()
+            / MIRRORED.len() as f64;
+
+        let eye_openness = (eye_aspect_ratio(landmarks)? / OPEN_EYE_ASPECT_RATIO).min(1.0);
+
+        // project the nose onto the line between the outer corners of the eyes
+        let (left, right) = (landmarks[36], landmarks[45]);
+        let axis = [(right.x() - left.x()) as f64, (right.y() - left.y()) as f64];
+        let length_squared = axis[0] * axis[0] + axis[1] * axis[1];
+
+        let nose_centering = if length_squared == 0.0 {
+            0.0
+        } else {
+            let offset = [(nose.x() - left.x()) as f64, (nose.y() - left.y()) as f64];
+            let t = (offset[0] * axis[0] + offset[1] * axis[1]) / length_squared;
+            (1.0 - 2.0 * (t - 0.5).abs()).max(0.0)
+        };
+
+        let score = symmetry.min(eye_openness).min(nose_centering);
+
+        Some(Self {
+            symmetry,
+            eye_openness,
+            nose_centering,
+            score,
+        })
+    }
+
+    /// Whether the face scores at least `min_score` on every measure.
+    pub fn is_acceptable(&self, min_score: f64) -> bool {
+        self.score >= min_score
+    }
+}
+
+#[cfg(test)]
+fn symmetric_face() -> Vec<crate::geometry::Point> {
+    use crate::geometry::Point;
+
+    let mut parts = vec![Point::new(50, 50); 68];
+
+    // an arbitrary but mirrored outline
+    for (k, &(left, right)) in MIRRORED.iter().enumerate() {
+        let (dx, y) = (10 + k as i64, 10 + 3 * k as i64);
+        parts[left] = Point::new(50 - dx, y);
+        parts[right] = Point::new(50 + dx, y);
+    }
+
+    // eyes with an aspect ratio of 0.3
+    let eye = [(20, 30), (26, 27), (34, 27), (40, 30), (34, 33), (26, 33)];
+    let mirrored_eye = [45, 44, 43, 42, 47, 46];
+    for (i, &(x, y)) in eye.iter().enumerate() {
+        parts[36 + i] = Point::new(x, y);
+        parts[mirrored_eye[i]] = Point::new(100 - x, y);
+    }
+
+    parts
+}
+
+#[test]
+fn test_geometry_quality() {
+    use crate::geometry::{Point, Rectangle};
+
+    let rect = Rectangle::default();
+    let mut parts = symmetric_face();
+
+    let quality = GeometryQuality::from_landmarks(&FaceLandmarks::new(&rect, &parts)).unwrap();
+    assert_eq!(quality.symmetry, 1.0);
+    assert_eq!(quality.eye_openness, 1.0);
+    assert_eq!(quality.nose_centering, 1.0);
+    assert!(quality.is_acceptable(0.9));
+
+    // turning the head moves the nose towards one side
+    parts[NOSE_TIP] = Point::new(60, 50);
+    let turned = GeometryQuality::from_landmarks(&FaceLandmarks::new(&rect, &parts)).unwrap();
+    assert!(turned.symmetry < 0.9);
+    assert!((turned.nose_centering - 2.0 / 3.0).abs() < 1e-9);
+    assert!(!turned.is_acceptable(0.9));
+
+    // closed eyes
+    let mut parts = symmetric_face();
+    for &i in &[37, 38, 40, 41, 43, 44, 46, 47] {
+        parts[i] = Point::new(parts[i].x(), 30);
+    }
+    let closed = GeometryQuality::from_landmarks(&FaceLandmarks::new(&rect, &parts)).unwrap();
+    assert_eq!(closed.eye_openness, 0.0);
+    assert_eq!(closed.score, 0.0);
+
+    assert!(GeometryQuality::from_landmarks(&FaceLandmarks::new(&rect, &parts[..5])).is_none());
+}
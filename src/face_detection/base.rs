@@ -1,7 +1,159 @@
+use super::detection::{FaceDetection, FaceDetections};
 use super::location::FaceLocations;
+use crate::geometry::Rectangle;
 use crate::matrix::ImageMatrix;
 
+/// Detectors only need a shared reference, so a single detector can be shared between threads.
 pub trait FaceDetectorTrait {
     /// Detect face rectangles from an image.
-    fn face_locations(&mut self, image: &ImageMatrix) -> FaceLocations;
+    fn face_locations(&self, image: &ImageMatrix) -> FaceLocations;
+
+    /// Detect faces from an image, along with the detector's confidence in each of them
+    /// and the index of the sub-detector that fired.
+    ///
+    /// Detectors that don't produce scores get this default, which reports every face from
+    /// `face_locations` with a confidence of `0.0` and a detector index of `0`.
+    fn face_locations_with_confidence(&self, image: &ImageMatrix) -> FaceDetections {
+        let detections: Vec<FaceDetection> = self
+            .face_locations(image)
+            .iter()
+            .map(|&rect| FaceDetection {
+                rect,
+                confidence: 0.0,
+                detector_index: 0,
+            })
+            .collect();
+
+        FaceDetections::from_detections(&detections)
+    }
+
+    /// Detect face rectangles from an image after upsampling it `num_upsamples` times.
+    ///
+    /// Each upsample doubles the size of the image, which allows smaller faces to be found at the cost
+    /// of speed. The rectangles are returned in the coordinates of the original image.
+    fn face_locations_upsampled(&self, image: &ImageMatrix, num_upsamples: u32) -> FaceLocations {
+        if num_upsamples == 0 {
+            return self.face_locations(image);
+        }
+
+        let rectangles: Vec<Rectangle> = self
+            .face_locations(&upsample(image, num_upsamples))
+            .iter()
+            .map(|rect| downsample(rect, num_upsamples))
+            .collect();
+
+        FaceLocations::from_rectangles(&rectangles)
+    }
+
+    /// Like [`FaceDetectorTrait::face_locations_upsampled`], but along with the detector's confidence in
+    /// each face and the index of the sub-detector that fired.
+    fn face_locations_upsampled_with_confidence(
+        &self,
+        image: &ImageMatrix,
+        num_upsamples: u32,
+    ) -> FaceDetections {
+        if num_upsamples == 0 {
+            return self.face_locations_with_confidence(image);
+        }
+
+        let detections: Vec<FaceDetection> = self
+            .face_locations_with_confidence(&upsample(image, num_upsamples))
+            .iter()
+            .map(|detection| FaceDetection {
+                rect: downsample(&detection.rect, num_upsamples),
+                ..*detection
+            })
+            .collect();
+
+        FaceDetections::from_detections(&detections)
+    }
+
+    /// Detect face rectangles from only the part of an image inside `roi`.
+    ///
+    /// The rectangles are returned in the coordinates of the full image.
+    fn face_locations_in_region(&self, image: &ImageMatrix, roi: Rectangle) -> FaceLocations {
+        let region = image.crop(&roi);
+        let left = roi.left.max(0);
+        let top = roi.top.max(0);
+
+        let rectangles: Vec<Rectangle> = self
+            .face_locations(&region)
+            .iter()
+            .map(|rect| Rectangle {
+                left: rect.left + left,
+                top: rect.top + top,
+                right: rect.right + left,
+                bottom: rect.bottom + top,
+            })
+            .collect();
+
+        FaceLocations::from_rectangles(&rectangles)
+    }
+
+    /// Run the detector on a blank image, so that any lazy initialization happens now rather than on the first real image.
+    fn warm_up(&self) {
+        self.face_locations(&ImageMatrix::blank(320, 240));
+    }
+}
+
+// Double the size of an image `num_upsamples` times.
+fn upsample(image: &ImageMatrix, num_upsamples: u32) -> ImageMatrix {
+    let mut upsampled = image.pyramid_up();
+    for _ in 1..num_upsamples {
+        upsampled = upsampled.pyramid_up();
+    }
+    upsampled
+}
+
+// Map a rectangle in an image upsampled `num_upsamples` times back to the original image.
+fn downsample(rect: &Rectangle, num_upsamples: u32) -> Rectangle {
+    let scale = (1u64 << num_upsamples) as f64;
+    Rectangle {
+        left: (rect.left as f64 / scale).round() as i64,
+        top: (rect.top as f64 / scale).round() as i64,
+        right: (rect.right as f64 / scale).round() as i64,
+        bottom: (rect.bottom as f64 / scale).round() as i64,
+    }
+}
+
+// Finds a single face in the top left corner of every image.
+#[cfg(test)]
+struct CornerDetector;
+
+#[cfg(test)]
+impl FaceDetectorTrait for CornerDetector {
+    fn face_locations(&self, _image: &ImageMatrix) -> FaceLocations {
+        FaceLocations::from_rectangles(&[Rectangle {
+            left: 0,
+            top: 0,
+            right: 9,
+            bottom: 9,
+        }])
+    }
+}
+
+#[test]
+fn test_default_face_locations_with_confidence() {
+    let detections = CornerDetector.face_locations_with_confidence(&ImageMatrix::blank(20, 20));
+
+    assert_eq!(detections.len(), 1);
+    assert_eq!(
+        detections[0].rect,
+        CornerDetector.face_locations(&ImageMatrix::blank(20, 20))[0]
+    );
+    assert_eq!(detections[0].confidence, 0.0);
+    assert_eq!(detections[0].detector_index, 0);
+}
+
+#[test]
+fn test_face_locations_upsampled_with_confidence() {
+    let image = ImageMatrix::blank(20, 20);
+    let detections = CornerDetector.face_locations_upsampled_with_confidence(&image, 1);
+
+    assert_eq!(detections.len(), 1);
+    assert_eq!(
+        detections[0].rect,
+        CornerDetector.face_locations_upsampled(&image, 1)[0]
+    );
+    assert_eq!(detections[0].rect.right, 5);
 }
@@ -1,6 +1,8 @@
 use std::path::Path;
+use std::sync::Mutex;
 
 use super::base::FaceDetectorTrait;
+use super::detection::FaceDetections;
 use super::location::FaceLocations;
 use crate::base::path_as_cstring;
 use crate::matrix::ImageMatrix;
@@ -8,9 +10,22 @@ use crate::matrix::ImageMatrix;
 /// A face detector that uses a Convulsive Neural Network (CNN).
 ///
 /// This is much slower than the regular face detector (depending on the gpu), but is also much more accurate.
-#[derive(Clone)]
+///
+/// The network stores each forward pass in its layers, so a shared detector only processes one image or
+/// batch at a time, however many threads call it. Each clone copies the network's weights and can run
+/// alongside the others, e.g. one per worker of a [`DetectorPool`](super::DetectorPool).
 pub struct FaceDetectorCnn {
-    inner: FaceDetectorCnnInner,
+    inner: Mutex<FaceDetectorCnnInner>,
+    adjust_threshold: f64,
+}
+
+impl Clone for FaceDetectorCnn {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Mutex::new(self.inner.lock().unwrap().clone()),
+            adjust_threshold: self.adjust_threshold,
+        }
+    }
 }
 
 cpp_class!(unsafe struct FaceDetectorCnnInner as "face_detection_cnn");
@@ -41,8 +56,107 @@ impl FaceDetectorCnn {
                 filename.as_ref().display()
             ))
         } else {
-            Ok(Self { inner })
+            Ok(Self::from_inner(inner))
+        }
+    }
+
+    /// Deserialize the face detector from a model file that has already been read into memory, e.g. with `include_bytes!`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let inner = FaceDetectorCnnInner::default();
+
+        let deserialized = unsafe {
+            let data = bytes.as_ptr();
+            let len = bytes.len();
+            let network = &inner;
+
+            cpp!([data as "const char*", len as "size_t", network as "face_detection_cnn*"] -> bool as "bool" {
+                try {
+                    std::istringstream stream(std::string(data, len));
+                    dlib::deserialize(*network, stream);
+                    return true;
+                } catch (const dlib::error& exception) {
+                    return false;
+                }
+            })
+        };
+
+        if !deserialized {
+            Err("Failed to deserialize CNN face detector".into())
+        } else {
+            Ok(Self::from_inner(inner))
+        }
+    }
+
+    fn from_inner(inner: FaceDetectorCnnInner) -> Self {
+        Self {
+            inner: Mutex::new(inner),
+            adjust_threshold: 0.0,
+        }
+    }
+
+    /// Set the amount added to the detection score threshold.
+    ///
+    /// Negative values find more faces at the cost of more false positives,
+    /// positive values find fewer faces but with fewer false positives. Defaults to `0.0`.
+    pub fn set_adjust_threshold(&mut self, adjust_threshold: f64) {
+        self.adjust_threshold = adjust_threshold;
+    }
+
+    pub fn adjust_threshold(&self) -> f64 {
+        self.adjust_threshold
+    }
+
+    /// Detect face rectangles from several images in a single forward pass of the network.
+    ///
+    /// This is much faster than detecting one image at a time when running on a gpu. All of the
+    /// images must be the same size, and memory use grows with the number of images, so long videos
+    /// should be split into batches by the caller.
+    pub fn face_locations_batch(
+        &self,
+        images: &[ImageMatrix],
+    ) -> Result<Vec<FaceLocations>, String> {
+        if let Some(first) = images.first() {
+            let (width, height) = (first.width(), first.height());
+
+            if images
+                .iter()
+                .any(|image| image.width() != width || image.height() != height)
+            {
+                return Err("All images in a batch must be the same size".into());
+            }
+        }
+
+        let mut locations: Vec<FaceLocations> = (0..images.len())
+            .map(|_| FaceLocations::default())
+            .collect();
+
+        if images.is_empty() {
+            return Ok(locations);
+        }
+
+        let mut inner = self.inner.lock().unwrap();
+        let detector = &mut *inner;
+        let adjust_threshold = self.adjust_threshold;
+        let len = images.len();
+        let images = images.as_ptr();
+        let out = locations.as_mut_ptr();
+
+        unsafe {
+            cpp!([detector as "face_detection_cnn*", images as "const dlib::matrix<dlib::rgb_pixel>*", len as "size_t", adjust_threshold as "double", out as "std::vector<dlib::rectangle>*"] {
+                const std::vector<dlib::matrix<dlib::rgb_pixel>> batch(images, images + len);
+                std::vector<std::vector<dlib::mmod_rect>> detections = detector->process_batch(batch, len, adjust_threshold);
+
+                for (size_t i = 0; i < len; i++) {
+                    out[i].reserve(detections[i].size());
+
+                    for (auto &detection: detections[i]) {
+                        out[i].push_back(from_dlib_rect(detection.rect));
+                    }
+                }
+            })
         }
+
+        Ok(locations)
     }
 }
 
@@ -54,12 +168,14 @@ impl Default for FaceDetectorCnn {
 }
 
 impl FaceDetectorTrait for FaceDetectorCnn {
-    fn face_locations(&mut self, image: &ImageMatrix) -> FaceLocations {
-        let detector = &mut self.inner;
+    fn face_locations(&self, image: &ImageMatrix) -> FaceLocations {
+        let mut inner = self.inner.lock().unwrap();
+        let detector = &mut *inner;
+        let adjust_threshold = self.adjust_threshold;
 
         unsafe {
-            cpp!([detector as "face_detection_cnn*", image as "dlib::matrix<dlib::rgb_pixel>*"] -> FaceLocations as "std::vector<dlib::rectangle>" {
-                std::vector<dlib::mmod_rect> detections = (*detector)(*image);
+            cpp!([detector as "face_detection_cnn*", image as "dlib::matrix<dlib::rgb_pixel>*", adjust_threshold as "double"] -> FaceLocations as "std::vector<dlib::rectangle>" {
+                std::vector<dlib::mmod_rect> detections = detector->process(*image, adjust_threshold);
                 // Convert from mmod rectangles
                 // see: https://github.com/davisking/dlib/blob/master/dlib/image_processing/full_object_detection.h#L132
                 // to regular rectangles
@@ -68,11 +184,54 @@ impl FaceDetectorTrait for FaceDetectorCnn {
                 rects.reserve(detections.size());
 
                 for (auto &detection: detections) {
-                    rects.push_back(detection.rect);
+                    rects.push_back(from_dlib_rect(detection.rect));
                 }
 
                 return rects;
             })
         }
     }
+
+    /// The network has a single detector, so the detector index is always zero.
+    fn face_locations_with_confidence(&self, image: &ImageMatrix) -> FaceDetections {
+        let mut inner = self.inner.lock().unwrap();
+        let detector = &mut *inner;
+        let adjust_threshold = self.adjust_threshold;
+
+        unsafe {
+            cpp!([detector as "face_detection_cnn*", image as "dlib::matrix<dlib::rgb_pixel>*", adjust_threshold as "double"] -> FaceDetections as "std::vector<face_detection>" {
+                std::vector<dlib::mmod_rect> detections = detector->process(*image, adjust_threshold);
+
+                std::vector<face_detection> results;
+                results.reserve(detections.size());
+
+                for (auto &detection: detections) {
+                    results.push_back(face_detection{from_dlib_rect(detection.rect), detection.detection_confidence, 0});
+                }
+
+                return results;
+            })
+        }
+    }
+}
+
+#[cfg(all(feature = "image", feature = "embed-fd-nn"))]
+#[test]
+fn test_face_locations_batch() {
+    let first = ImageMatrix::from_image(&image::open("assets/obama_1.jpg").unwrap().to_rgb());
+    let second = first.flip_horizontal();
+    let detector = FaceDetectorCnn::default();
+
+    let batch = detector
+        .face_locations_batch(&[first.clone(), second.clone()])
+        .unwrap();
+
+    assert_eq!(batch.len(), 2);
+    assert_eq!(*batch[0], *detector.face_locations(&first));
+    assert_eq!(*batch[1], *detector.face_locations(&second));
+    assert!(!batch[0].is_empty());
+
+    assert!(detector
+        .face_locations_batch(&[first, ImageMatrix::blank(10, 10)])
+        .is_err());
 }
@@ -0,0 +1,98 @@
+use std::fmt;
+use std::ops::Deref;
+use std::slice;
+
+use crate::geometry::Rectangle;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C)]
+/// A detected face along with the detector's confidence in it.
+pub struct FaceDetection {
+    pub rect: Rectangle,
+    /// The detection score. Higher values are more confident, and the scale depends on the detector.
+    pub confidence: f64,
+    /// The index of the sub-detector that fired.
+    pub detector_index: usize,
+}
+
+impl From<FaceDetection> for (Rectangle, f64, usize) {
+    fn from(detection: FaceDetection) -> Self {
+        (
+            detection.rect,
+            detection.confidence,
+            detection.detector_index,
+        )
+    }
+}
+
+cpp_class!(
+    /// A rust wrapper around a `std::vector<face_detection>`.
+    pub unsafe struct FaceDetections as "std::vector<face_detection>"
+);
+
+impl FaceDetections {
+    pub(crate) fn from_detections(detections: &[FaceDetection]) -> Self {
+        let len = detections.len();
+        let pointer = detections.as_ptr();
+
+        unsafe {
+            cpp!([pointer as "const face_detection*", len as "size_t"] -> FaceDetections as "std::vector<face_detection>" {
+                return std::vector<face_detection>(pointer, pointer + len);
+            })
+        }
+    }
+}
+
+impl Deref for FaceDetections {
+    type Target = [FaceDetection];
+
+    fn deref(&self) -> &Self::Target {
+        let len = unsafe {
+            cpp!([self as "std::vector<face_detection>*"] -> usize as "size_t" {
+                return self->size();
+            })
+        };
+
+        if len == 0 {
+            &[]
+        } else {
+            unsafe {
+                let pointer = cpp!([self as "std::vector<face_detection>*"] -> *const FaceDetection as "face_detection*" {
+                    return &(*self)[0];
+                });
+
+                slice::from_raw_parts(pointer, len)
+            }
+        }
+    }
+}
+
+impl fmt::Debug for FaceDetections {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.deref().fmt(f)
+    }
+}
+
+#[test]
+fn test_face_detection_layout() {
+    let detections = unsafe {
+        cpp!([] -> FaceDetections as "std::vector<face_detection>" {
+            return {face_detection{dlib::rectangle(1, 2, 3, 4), 0.5, 3}};
+        })
+    };
+
+    assert_eq!(
+        &*detections,
+        &[FaceDetection {
+            rect: Rectangle {
+                left: 1,
+                top: 2,
+                right: 3,
+                bottom: 4
+            },
+            confidence: 0.5,
+            detector_index: 3
+        }]
+    );
+}
@@ -0,0 +1,144 @@
+use super::base::FaceDetectorTrait;
+use super::detection::{FaceDetection, FaceDetections};
+use super::location::FaceLocations;
+use super::nms::{non_max_suppression, NmsOptions};
+use crate::geometry::Rectangle;
+use crate::matrix::ImageMatrix;
+
+/// How an [`EnsembleDetector`] runs its detectors.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EnsembleMode {
+    /// Run every detector and merge all of their detections.
+    All,
+    /// Run the detectors in order, stopping at the first one that finds any faces.
+    ///
+    /// This is useful for running a fast detector first, with a slower but more accurate one as a fallback.
+    FirstNonEmpty,
+}
+
+struct Member {
+    detector: Box<dyn FaceDetectorTrait + Send + Sync>,
+    weight: f64,
+    min_confidence: f64,
+}
+
+/// A detector that combines the results of several other detectors.
+///
+/// Each detection's confidence is multiplied by the weight of the detector that found it, which allows
+/// detectors with differently scaled scores to be compared, and detections of the same face are merged
+/// with non-max suppression. The `detector_index` of each detection is the index of the detector in the
+/// ensemble that found it.
+pub struct EnsembleDetector {
+    members: Vec<Member>,
+    mode: EnsembleMode,
+    nms: NmsOptions,
+}
+
+impl EnsembleDetector {
+    pub fn new(mode: EnsembleMode) -> Self {
+        Self {
+            members: Vec::new(),
+            mode,
+            nms: NmsOptions::default(),
+        }
+    }
+
+    /// Add a detector to the ensemble.
+    ///
+    /// Detections with a confidence below `min_confidence` are discarded before being weighted.
+    pub fn push<D: FaceDetectorTrait + Send + Sync + 'static>(
+        &mut self,
+        detector: D,
+        weight: f64,
+        min_confidence: f64,
+    ) {
+        self.members.push(Member {
+            detector: Box::new(detector),
+            weight,
+            min_confidence,
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        self.members.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.members.is_empty()
+    }
+
+    pub fn mode(&self) -> EnsembleMode {
+        self.mode
+    }
+
+    /// Set how detections of the same face from different detectors are merged.
+    pub fn set_nms_options(&mut self, nms: NmsOptions) {
+        self.nms = nms;
+    }
+
+    pub fn nms_options(&self) -> NmsOptions {
+        self.nms
+    }
+}
+
+impl FaceDetectorTrait for EnsembleDetector {
+    fn face_locations(&self, image: &ImageMatrix) -> FaceLocations {
+        let rectangles: Vec<Rectangle> = self
+            .face_locations_with_confidence(image)
+            .iter()
+            .map(|detection| detection.rect)
+            .collect();
+
+        FaceLocations::from_rectangles(&rectangles)
+    }
+
+    fn face_locations_with_confidence(&self, image: &ImageMatrix) -> FaceDetections {
+        let mut detections = Vec::new();
+
+        for (index, member) in self.members.iter().enumerate() {
+            detections.extend(
+                member
+                    .detector
+                    .face_locations_with_confidence(image)
+                    .iter()
+                    .filter(|detection| detection.confidence >= member.min_confidence)
+                    .map(|detection| FaceDetection {
+                        rect: detection.rect,
+                        confidence: detection.confidence * member.weight,
+                        detector_index: index,
+                    }),
+            );
+
+            if self.mode == EnsembleMode::FirstNonEmpty && !detections.is_empty() {
+                break;
+            }
+        }
+
+        FaceDetections::from_detections(&non_max_suppression(&detections, &self.nms))
+    }
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn test_ensemble_detection() {
+    use super::hog::FaceDetector;
+
+    let image = image::open("assets/obama_1.jpg").unwrap().to_rgb();
+    let matrix = ImageMatrix::from_image(&image);
+
+    let mut ensemble = EnsembleDetector::new(EnsembleMode::All);
+    ensemble.push(FaceDetector::new(), 1.0, 0.0);
+    ensemble.push(FaceDetector::new(), 2.0, 0.0);
+
+    let detections = ensemble.face_locations_with_confidence(&matrix);
+    assert_eq!(detections.len(), 1);
+    assert_eq!(detections[0].detector_index, 1);
+
+    let mut chained = EnsembleDetector::new(EnsembleMode::FirstNonEmpty);
+    chained.push(FaceDetector::new(), 1.0, f64::INFINITY);
+    chained.push(FaceDetector::new(), 1.0, 0.0);
+
+    let detections = chained.face_locations_with_confidence(&matrix);
+    assert_eq!(detections.len(), 1);
+    assert_eq!(detections[0].detector_index, 1);
+}
@@ -1,14 +1,30 @@
+use std::sync::Mutex;
+
 use super::base::FaceDetectorTrait;
+use super::detection::FaceDetections;
 use super::location::FaceLocations;
-use crate::matrix::ImageMatrix;
+use crate::matrix::{GrayImageMatrix, ImageMatrix, ImageMatrixView};
 
-#[derive(Clone)]
 /// A Face detector that uses a HOG feature descriptor.
 ///
 /// Pretty fast (~100ms for test images on my machine), but not as accurate (misses more faces)
 /// as the neural network face detector.
+///
+/// dlib's detector reuses its image pyramid buffers between calls, so they are kept behind a lock and
+/// calls to a shared detector from several threads run one at a time. To detect in parallel, give each
+/// thread its own clone or use a [`DetectorPool`](super::DetectorPool).
 pub struct FaceDetector {
-    inner: FaceDetectorInner,
+    inner: Mutex<FaceDetectorInner>,
+    adjust_threshold: f64,
+}
+
+impl Clone for FaceDetector {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Mutex::new(self.inner.lock().unwrap().clone()),
+            adjust_threshold: self.adjust_threshold,
+        }
+    }
 }
 
 cpp_class!(unsafe struct FaceDetectorInner as "dlib::frontal_face_detector");
@@ -24,7 +40,72 @@ impl FaceDetector {
             })
         };
 
-        Self { inner }
+        Self {
+            inner: Mutex::new(inner),
+            adjust_threshold: 0.0,
+        }
+    }
+
+    /// Set the amount added to the detection score threshold.
+    ///
+    /// Negative values find more faces at the cost of more false positives,
+    /// positive values find fewer faces but with fewer false positives. Defaults to `0.0`.
+    pub fn set_adjust_threshold(&mut self, adjust_threshold: f64) {
+        self.adjust_threshold = adjust_threshold;
+    }
+
+    pub fn adjust_threshold(&self) -> f64 {
+        self.adjust_threshold
+    }
+
+    /// Detect face rectangles from a view over a borrowed rgb buffer, without copying it.
+    pub fn face_locations_view(&self, image: &ImageMatrixView) -> FaceLocations {
+        let mut inner = self.inner.lock().unwrap();
+        let detector = &mut *inner;
+        let adjust_threshold = self.adjust_threshold;
+
+        unsafe {
+            cpp!([detector as "dlib::frontal_face_detector*", image as "const rgb_image_view*", adjust_threshold as "double"] -> FaceLocations as "std::vector<dlib::rectangle>" {
+                return from_dlib_rects((*detector)(*image, adjust_threshold));
+            })
+        }
+    }
+
+    /// Detect face rectangles from a grayscale image.
+    pub fn face_locations_gray(&self, image: &GrayImageMatrix) -> FaceLocations {
+        let mut inner = self.inner.lock().unwrap();
+        let detector = &mut *inner;
+        let adjust_threshold = self.adjust_threshold;
+
+        unsafe {
+            cpp!([detector as "dlib::frontal_face_detector*", image as "const dlib::matrix<unsigned char>*", adjust_threshold as "double"] -> FaceLocations as "std::vector<dlib::rectangle>" {
+                return from_dlib_rects((*detector)(*image, adjust_threshold));
+            })
+        }
+    }
+
+    /// Detect faces from a grayscale image, along with the detector's confidence in each of them
+    /// and the index of the sub-detector that fired.
+    pub fn face_locations_with_confidence_gray(&self, image: &GrayImageMatrix) -> FaceDetections {
+        let mut inner = self.inner.lock().unwrap();
+        let detector = &mut *inner;
+        let adjust_threshold = self.adjust_threshold;
+
+        unsafe {
+            cpp!([detector as "dlib::frontal_face_detector*", image as "const dlib::matrix<unsigned char>*", adjust_threshold as "double"] -> FaceDetections as "std::vector<face_detection>" {
+                std::vector<dlib::rect_detection> detections;
+                (*detector)(*image, detections, adjust_threshold);
+
+                std::vector<face_detection> results;
+                results.reserve(detections.size());
+
+                for (auto &detection: detections) {
+                    results.push_back(face_detection{from_dlib_rect(detection.rect), detection.detection_confidence, detection.weight_index});
+                }
+
+                return results;
+            })
+        }
     }
 }
 
@@ -35,17 +116,42 @@ impl Default for FaceDetector {
 }
 
 impl FaceDetectorTrait for FaceDetector {
-    fn face_locations(&mut self, image: &ImageMatrix) -> FaceLocations {
-        let detector = &mut self.inner;
+    fn face_locations(&self, image: &ImageMatrix) -> FaceLocations {
+        let mut inner = self.inner.lock().unwrap();
+        let detector = &mut *inner;
+        let adjust_threshold = self.adjust_threshold;
 
         unsafe {
-            cpp!([detector as "dlib::frontal_face_detector*", image as "dlib::matrix<dlib::rgb_pixel>*"] -> FaceLocations as "std::vector<dlib::rectangle>"  {
-                return (*detector)(*image);
+            cpp!([detector as "dlib::frontal_face_detector*", image as "dlib::matrix<dlib::rgb_pixel>*", adjust_threshold as "double"] -> FaceLocations as "std::vector<dlib::rectangle>"  {
+                return from_dlib_rects((*detector)(*image, adjust_threshold));
+            })
+        }
+    }
+
+    fn face_locations_with_confidence(&self, image: &ImageMatrix) -> FaceDetections {
+        let mut inner = self.inner.lock().unwrap();
+        let detector = &mut *inner;
+        let adjust_threshold = self.adjust_threshold;
+
+        unsafe {
+            cpp!([detector as "dlib::frontal_face_detector*", image as "dlib::matrix<dlib::rgb_pixel>*", adjust_threshold as "double"] -> FaceDetections as "std::vector<face_detection>" {
+                std::vector<dlib::rect_detection> detections;
+                (*detector)(*image, detections, adjust_threshold);
+
+                std::vector<face_detection> results;
+                results.reserve(detections.size());
+
+                for (auto &detection: detections) {
+                    results.push_back(face_detection{from_dlib_rect(detection.rect), detection.detection_confidence, detection.weight_index});
+                }
+
+                return results;
             })
         }
     }
 }
 
+#[cfg(feature = "image")]
 #[test]
 fn test_face_detection() {
     use crate::geometry::Rectangle;
@@ -54,6 +160,7 @@ fn test_face_detection() {
     let matrix = ImageMatrix::from_image(&image);
     let mut detector = FaceDetector::new();
 
+    detector.warm_up();
     let locations = detector.face_locations(&matrix);
 
     assert_eq!(locations.len(), 1);
@@ -62,8 +169,62 @@ fn test_face_detection() {
         Rectangle {
             left: 305,
             top: 113,
-            right: 520,
-            bottom: 328
+            right: 521,
+            bottom: 329
         }
     );
+
+    let detections = detector.face_locations_with_confidence(&matrix);
+
+    assert_eq!(detections.len(), 1);
+    assert_eq!(detections[0].rect, locations[0]);
+    assert!(detections[0].confidence > 0.0);
+
+    let upsampled = detector.face_locations_upsampled(&matrix, 1);
+
+    assert_eq!(upsampled.len(), 1);
+    assert!((upsampled[0].center_x() - locations[0].center_x()).abs() < 20.0);
+    assert!((upsampled[0].center_y() - locations[0].center_y()).abs() < 20.0);
+
+    let in_region = detector.face_locations_in_region(
+        &matrix,
+        Rectangle {
+            left: 200,
+            top: 50,
+            right: 650,
+            bottom: 450,
+        },
+    );
+
+    assert_eq!(in_region.len(), 1);
+    assert!((in_region[0].center_x() - locations[0].center_x()).abs() < 20.0);
+    assert!((in_region[0].center_y() - locations[0].center_y()).abs() < 20.0);
+
+    let outside_region = detector.face_locations_in_region(
+        &matrix,
+        Rectangle {
+            left: -100,
+            top: -100,
+            right: 200,
+            bottom: 100,
+        },
+    );
+
+    assert!(outside_region.is_empty());
+
+    let gray = GrayImageMatrix::from_image(&image::open("assets/obama_1.jpg").unwrap().to_luma());
+    let gray_locations = detector.face_locations_gray(&gray);
+
+    assert_eq!(gray_locations.len(), 1);
+    assert!(gray_locations[0].iou(&locations[0]) > 0.8);
+    assert_eq!(
+        detector
+            .face_locations_gray(&GrayImageMatrix::from_rgb(&matrix))
+            .len(),
+        1
+    );
+    assert_eq!(detector.face_locations_with_confidence_gray(&gray).len(), 1);
+
+    detector.set_adjust_threshold(detections[0].confidence + 1.0);
+    assert!(detector.face_locations(&matrix).is_empty());
 }
@@ -1,4 +1,5 @@
 use std::fmt;
+use std::iter::FromIterator;
 use std::ops::Deref;
 use std::slice;
 
@@ -9,6 +10,19 @@ cpp_class!(
     pub unsafe struct FaceLocations as "std::vector<dlib::rectangle>"
 );
 
+impl FaceLocations {
+    pub(crate) fn from_rectangles(rectangles: &[Rectangle]) -> Self {
+        let len = rectangles.len();
+        let pointer = rectangles.as_ptr();
+
+        unsafe {
+            cpp!([pointer as "const dlib::rectangle*", len as "size_t"] -> FaceLocations as "std::vector<dlib::rectangle>" {
+                return std::vector<dlib::rectangle>(pointer, pointer + len);
+            })
+        }
+    }
+}
+
 impl Deref for FaceLocations {
     type Target = [Rectangle];
 
@@ -38,3 +52,91 @@ impl fmt::Debug for FaceLocations {
         self.deref().fmt(f)
     }
 }
+
+impl PartialEq for FaceLocations {
+    fn eq(&self, other: &Self) -> bool {
+        self.deref().eq(other.deref())
+    }
+}
+
+impl From<&[Rectangle]> for FaceLocations {
+    fn from(rectangles: &[Rectangle]) -> Self {
+        Self::from_rectangles(rectangles)
+    }
+}
+
+impl From<Vec<Rectangle>> for FaceLocations {
+    fn from(rectangles: Vec<Rectangle>) -> Self {
+        Self::from_rectangles(&rectangles)
+    }
+}
+
+impl From<FaceLocations> for Vec<Rectangle> {
+    fn from(locations: FaceLocations) -> Self {
+        locations.to_vec()
+    }
+}
+
+impl FromIterator<Rectangle> for FaceLocations {
+    fn from_iter<I: IntoIterator<Item = Rectangle>>(iter: I) -> Self {
+        Self::from_rectangles(&iter.into_iter().collect::<Vec<_>>())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for FaceLocations {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for FaceLocations {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Vec::<Rectangle>::deserialize(deserializer).map(Self::from)
+    }
+}
+
+#[test]
+fn test_vec_conversions() {
+    let rectangles = vec![
+        Rectangle {
+            left: 1,
+            top: 2,
+            right: 3,
+            bottom: 4,
+        },
+        Rectangle {
+            left: 5,
+            top: 6,
+            right: 7,
+            bottom: 8,
+        },
+    ];
+
+    let locations = FaceLocations::from(rectangles.clone());
+    assert_eq!(locations.len(), 2);
+    assert_eq!(locations[1], rectangles[1]);
+    assert_eq!(
+        rectangles.iter().cloned().collect::<FaceLocations>(),
+        locations
+    );
+    assert_eq!(Vec::from(locations), rectangles);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn can_serialize_and_deserialize() {
+    let locations = FaceLocations::from(vec![Rectangle {
+        left: 1,
+        top: 2,
+        right: 3,
+        bottom: 4,
+    }]);
+
+    let json = serde_json::to_string(&locations).unwrap();
+    assert_eq!(json, r#"[{"left":1,"top":2,"right":3,"bottom":4}]"#);
+
+    let decoded: FaceLocations = serde_json::from_str(&json).unwrap();
+    assert_eq!(decoded, locations);
+}
@@ -2,10 +2,28 @@
 
 mod base;
 mod cnn;
+mod detection;
+mod ensemble;
 mod hog;
 mod location;
+mod nms;
+mod object;
+mod pool;
+mod proposals;
+mod rotating;
+mod size_filter;
+mod tiled;
 
 pub use self::base::FaceDetectorTrait;
 pub use self::cnn::FaceDetectorCnn;
+pub use self::detection::{FaceDetection, FaceDetections};
+pub use self::ensemble::{EnsembleDetector, EnsembleMode};
 pub use self::hog::FaceDetector;
 pub use self::location::FaceLocations;
+pub use self::nms::{non_max_suppression, BoxMerge, NmsOptions};
+pub use self::object::{ObjectDetector, ObjectDetectorTrainingOptions};
+pub use self::pool::DetectorPool;
+pub use self::proposals::{find_candidate_object_locations, CandidateOptions};
+pub use self::rotating::RotatingDetector;
+pub use self::size_filter::SizeFilter;
+pub use self::tiled::TiledDetector;
@@ -0,0 +1,145 @@
+use super::detection::FaceDetection;
+use crate::geometry::Rectangle;
+
+/// How overlapping detections are combined by [`non_max_suppression`].
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum BoxMerge {
+    /// Keep only the most confident detection.
+    #[default]
+    KeepBest,
+    /// Replace the most confident detection's box with the confidence-weighted average of every box it suppressed.
+    ///
+    /// Detections with a negative confidence are given no weight.
+    WeightedAverage,
+}
+
+/// Options for [`non_max_suppression`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct NmsOptions {
+    /// Detections overlapping a more confident one by more than this intersection over union are suppressed.
+    pub iou_threshold: f64,
+    pub merge: BoxMerge,
+}
+
+impl Default for NmsOptions {
+    fn default() -> Self {
+        Self {
+            iou_threshold: 0.3,
+            merge: BoxMerge::default(),
+        }
+    }
+}
+
+fn edges(rect: &Rectangle) -> [f64; 4] {
+    [
+        rect.left as f64,
+        rect.top as f64,
+        rect.right as f64,
+        rect.bottom as f64,
+    ]
+}
+
+/// Remove duplicate detections of the same face, e.g. from multiple detectors or overlapping tiles.
+///
+/// Returns the remaining detections sorted from most to least confident.
+pub fn non_max_suppression(
+    detections: &[FaceDetection],
+    options: &NmsOptions,
+) -> Vec<FaceDetection> {
+    let mut sorted = detections.to_vec();
+    sorted.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+
+    let mut suppressed = vec![false; sorted.len()];
+    let mut kept = Vec::new();
+
+    for i in 0..sorted.len() {
+        if suppressed[i] {
+            continue;
+        }
+
+        let best = sorted[i];
+        let mut weights = best.confidence.max(0.0);
+        let mut sums = [0f64; 4];
+        for (sum, value) in sums.iter_mut().zip(edges(&best.rect).iter()) {
+            *sum += value * weights;
+        }
+
+        for j in i + 1..sorted.len() {
+            if suppressed[j] || sorted[j].rect.iou(&best.rect) <= options.iou_threshold {
+                continue;
+            }
+
+            suppressed[j] = true;
+
+            let weight = sorted[j].confidence.max(0.0);
+            for (sum, value) in sums.iter_mut().zip(edges(&sorted[j].rect).iter()) {
+                *sum += value * weight;
+            }
+            weights += weight;
+        }
+
+        let rect = match options.merge {
+            BoxMerge::WeightedAverage if weights > 0.0 => Rectangle {
+                left: (sums[0] / weights).round() as i64,
+                top: (sums[1] / weights).round() as i64,
+                right: (sums[2] / weights).round() as i64,
+                bottom: (sums[3] / weights).round() as i64,
+            },
+            _ => best.rect,
+        };
+
+        kept.push(FaceDetection { rect, ..best });
+    }
+
+    kept
+}
+
+#[test]
+fn test_non_max_suppression() {
+    let detection = |left, confidence| FaceDetection {
+        rect: Rectangle {
+            left,
+            top: 0,
+            right: left + 100,
+            bottom: 100,
+        },
+        confidence,
+        detector_index: 0,
+    };
+
+    let detections = [detection(0, 1.0), detection(10, 3.0), detection(500, 0.5)];
+
+    let kept = non_max_suppression(&detections, &NmsOptions::default());
+    assert_eq!(kept, vec![detection(10, 3.0), detection(500, 0.5)]);
+
+    let merged = non_max_suppression(
+        &detections,
+        &NmsOptions {
+            merge: BoxMerge::WeightedAverage,
+            ..Default::default()
+        },
+    );
+    assert_eq!(merged.len(), 2);
+    assert_eq!(merged[0].rect.left, 8);
+    assert_eq!(merged[0].confidence, 3.0);
+
+    let strict = non_max_suppression(
+        &detections,
+        &NmsOptions {
+            iou_threshold: 0.9,
+            ..Default::default()
+        },
+    );
+    assert_eq!(strict.len(), 3);
+
+    // a NaN confidence sorts ahead of the rest rather than panicking
+    let with_nan = [
+        detection(0, f64::NAN),
+        detection(10, 3.0),
+        detection(500, 0.5),
+    ];
+    assert_eq!(
+        non_max_suppression(&with_nan, &NmsOptions::default()).len(),
+        2
+    );
+}
@@ -0,0 +1,387 @@
+use std::path::Path;
+use std::sync::Mutex;
+
+use super::base::FaceDetectorTrait;
+use super::detection::FaceDetections;
+use super::location::FaceLocations;
+use crate::base::{path_as_cstring, path_to_cstring, CppBytes};
+use crate::geometry::Rectangle;
+use crate::matrix::{GrayImageMatrix, ImageMatrix};
+
+/// Options for training an [`ObjectDetector`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[repr(C)]
+pub struct ObjectDetectorTrainingOptions {
+    /// The SVM regularization parameter. Larger values fit the training data more closely,
+    /// at the risk of overfitting.
+    pub c: f64,
+    /// The training stops once the solution is within this distance of optimal.
+    pub epsilon: f64,
+    /// Train on left-right mirrored copies of the images as well, for objects that are symmetric.
+    pub symmetric: bool,
+    /// The area in pixels of the sliding window. Its aspect ratio matches the average of the training boxes.
+    pub detection_window_size: usize,
+    pub num_threads: usize,
+}
+
+impl Default for ObjectDetectorTrainingOptions {
+    fn default() -> Self {
+        Self {
+            c: 1.0,
+            epsilon: 0.01,
+            symmetric: false,
+            detection_window_size: 80 * 80,
+            num_threads: 4,
+        }
+    }
+}
+
+/// A custom FHOG object detector, as trained by dlib's `train_simple_object_detector` or `imglab`.
+///
+/// This loads `.svm` files containing a `dlib::object_detector<dlib::scan_fhog_pyramid<dlib::pyramid_down<6>>>`,
+/// so detectors trained for profile faces or other kinds of objects can be used in place of the face detectors.
+///
+/// As with [`FaceDetector`](super::FaceDetector), detection locks the scanner's buffers, so threads sharing
+/// one detector take turns. Clone it per thread to detect in parallel.
+pub struct ObjectDetector {
+    inner: Mutex<ObjectDetectorInner>,
+    adjust_threshold: f64,
+}
+
+impl Clone for ObjectDetector {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Mutex::new(self.inner.lock().unwrap().clone()),
+            adjust_threshold: self.adjust_threshold,
+        }
+    }
+}
+
+cpp_class!(unsafe struct ObjectDetectorInner as "object_detector_fhog");
+
+impl ObjectDetector {
+    /// Load an object detector from a filename.
+    pub fn new<P: AsRef<Path>>(filename: P) -> Result<Self, String> {
+        let string = path_as_cstring(filename.as_ref())?;
+        let inner = ObjectDetectorInner::default();
+
+        let deserialized = unsafe {
+            let filename = string.as_ptr();
+            let detector = &inner;
+
+            cpp!([filename as "char*", detector as "object_detector_fhog*"] -> bool as "bool" {
+                try {
+                    dlib::deserialize(filename) >> *detector;
+                    return true;
+                } catch (const dlib::error& exception) {
+                    return false;
+                }
+            })
+        };
+
+        if !deserialized {
+            Err(format!(
+                "Failed to deserialize '{}'",
+                filename.as_ref().display()
+            ))
+        } else {
+            Ok(Self {
+                inner: Mutex::new(inner),
+                adjust_threshold: 0.0,
+            })
+        }
+    }
+
+    /// Train a new object detector from images and the boxes around the objects in each of them.
+    ///
+    /// Every object in the images must be labelled, as any unlabelled ones are treated as negative examples.
+    pub fn train(
+        samples: &[(ImageMatrix, Vec<Rectangle>)],
+        options: &ObjectDetectorTrainingOptions,
+    ) -> Result<Self, String> {
+        let images: Vec<*const ImageMatrix> = samples.iter().map(|(image, _)| image as _).collect();
+        let counts: Vec<usize> = samples.iter().map(|(_, boxes)| boxes.len()).collect();
+        let boxes: Vec<Rectangle> = samples
+            .iter()
+            .flat_map(|(_, boxes)| boxes.iter().cloned())
+            .collect();
+
+        let inner = ObjectDetectorInner::default();
+        let error = CppBytes::default();
+
+        let trained = unsafe {
+            let len = samples.len();
+            let images = images.as_ptr();
+            let counts = counts.as_ptr();
+            let boxes = boxes.as_ptr();
+            let detector = &inner;
+            let error = &error;
+
+            cpp!([images as "const dlib::matrix<dlib::rgb_pixel>* const*", counts as "const size_t*", boxes as "const dlib::rectangle*", len as "size_t", options as "const object_detector_training_options*", detector as "object_detector_fhog*", error as "std::string*"] -> bool as "bool" {
+                std::vector<dlib::matrix<dlib::rgb_pixel>> image_copies;
+                std::vector<std::vector<dlib::rectangle>> image_boxes;
+
+                for (size_t i = 0; i < len; i++) {
+                    image_copies.push_back(*images[i]);
+                    image_boxes.emplace_back();
+                    for (size_t j = 0; j < counts[i]; j++) {
+                        image_boxes.back().push_back(to_dlib_rect(boxes[j]));
+                    }
+                    boxes += counts[i];
+                }
+
+                try {
+                    *detector = train_object_detector(image_copies, image_boxes, *options);
+                    return true;
+                } catch (const std::exception& exception) {
+                    *error = exception.what();
+                    return false;
+                }
+            })
+        };
+
+        if !trained {
+            Err(format!(
+                "Failed to train object detector: {}",
+                String::from_utf8_lossy(&error)
+            ))
+        } else {
+            Ok(Self {
+                inner: Mutex::new(inner),
+                adjust_threshold: 0.0,
+            })
+        }
+    }
+
+    /// Train a new object detector from an `imglab` XML dataset file.
+    pub fn train_from_xml<P: AsRef<Path>>(
+        filename: P,
+        options: &ObjectDetectorTrainingOptions,
+    ) -> Result<Self, String> {
+        let string = path_as_cstring(filename.as_ref())?;
+        let inner = ObjectDetectorInner::default();
+        let error = CppBytes::default();
+
+        let trained = unsafe {
+            let filename = string.as_ptr();
+            let detector = &inner;
+            let error = &error;
+
+            cpp!([filename as "char*", options as "const object_detector_training_options*", detector as "object_detector_fhog*", error as "std::string*"] -> bool as "bool" {
+                std::vector<dlib::matrix<dlib::rgb_pixel>> images;
+                std::vector<std::vector<dlib::rectangle>> boxes;
+
+                try {
+                    dlib::load_image_dataset(images, boxes, filename);
+                    *detector = train_object_detector(images, boxes, *options);
+                    return true;
+                } catch (const std::exception& exception) {
+                    *error = exception.what();
+                    return false;
+                }
+            })
+        };
+
+        if !trained {
+            Err(format!(
+                "Failed to train object detector from '{}': {}",
+                filename.as_ref().display(),
+                String::from_utf8_lossy(&error)
+            ))
+        } else {
+            Ok(Self {
+                inner: Mutex::new(inner),
+                adjust_threshold: 0.0,
+            })
+        }
+    }
+
+    /// Save the object detector to a file so it can be loaded later with [`ObjectDetector::new`].
+    pub fn save<P: AsRef<Path>>(&self, filename: P) -> Result<(), String> {
+        let string = path_to_cstring(filename.as_ref())?;
+
+        let serialized = unsafe {
+            let filename = string.as_ptr();
+            let inner = self.inner.lock().unwrap();
+            let detector = &*inner;
+
+            cpp!([filename as "char*", detector as "const object_detector_fhog*"] -> bool as "bool" {
+                try {
+                    dlib::serialize(filename) << *detector;
+                    return true;
+                } catch (const dlib::error& exception) {
+                    return false;
+                }
+            })
+        };
+
+        if !serialized {
+            Err(format!(
+                "Failed to serialize '{}'",
+                filename.as_ref().display()
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// The number of sub-detectors, e.g. separate detectors for different face poses.
+    pub fn num_detectors(&self) -> usize {
+        let inner = self.inner.lock().unwrap();
+        let detector = &*inner;
+
+        unsafe {
+            cpp!([detector as "const object_detector_fhog*"] -> usize as "size_t" {
+                return detector->num_detectors();
+            })
+        }
+    }
+
+    /// Set the amount added to the detection score threshold.
+    ///
+    /// Negative values find more objects at the cost of more false positives,
+    /// positive values find fewer objects but with fewer false positives. Defaults to `0.0`.
+    pub fn set_adjust_threshold(&mut self, adjust_threshold: f64) {
+        self.adjust_threshold = adjust_threshold;
+    }
+
+    pub fn adjust_threshold(&self) -> f64 {
+        self.adjust_threshold
+    }
+
+    /// Detect face rectangles from a grayscale image.
+    pub fn face_locations_gray(&self, image: &GrayImageMatrix) -> FaceLocations {
+        let mut inner = self.inner.lock().unwrap();
+        let detector = &mut *inner;
+        let adjust_threshold = self.adjust_threshold;
+
+        unsafe {
+            cpp!([detector as "object_detector_fhog*", image as "const dlib::matrix<unsigned char>*", adjust_threshold as "double"] -> FaceLocations as "std::vector<dlib::rectangle>" {
+                return from_dlib_rects((*detector)(*image, adjust_threshold));
+            })
+        }
+    }
+
+    /// Detect faces from a grayscale image, along with the detector's confidence in each of them
+    /// and the index of the sub-detector that fired.
+    pub fn face_locations_with_confidence_gray(&self, image: &GrayImageMatrix) -> FaceDetections {
+        let mut inner = self.inner.lock().unwrap();
+        let detector = &mut *inner;
+        let adjust_threshold = self.adjust_threshold;
+
+        unsafe {
+            cpp!([detector as "object_detector_fhog*", image as "const dlib::matrix<unsigned char>*", adjust_threshold as "double"] -> FaceDetections as "std::vector<face_detection>" {
+                std::vector<dlib::rect_detection> detections;
+                (*detector)(*image, detections, adjust_threshold);
+
+                std::vector<face_detection> results;
+                results.reserve(detections.size());
+
+                for (auto &detection: detections) {
+                    results.push_back(face_detection{from_dlib_rect(detection.rect), detection.detection_confidence, detection.weight_index});
+                }
+
+                return results;
+            })
+        }
+    }
+}
+
+impl FaceDetectorTrait for ObjectDetector {
+    fn face_locations(&self, image: &ImageMatrix) -> FaceLocations {
+        let mut inner = self.inner.lock().unwrap();
+        let detector = &mut *inner;
+        let adjust_threshold = self.adjust_threshold;
+
+        unsafe {
+            cpp!([detector as "object_detector_fhog*", image as "dlib::matrix<dlib::rgb_pixel>*", adjust_threshold as "double"] -> FaceLocations as "std::vector<dlib::rectangle>" {
+                return from_dlib_rects((*detector)(*image, adjust_threshold));
+            })
+        }
+    }
+
+    fn face_locations_with_confidence(&self, image: &ImageMatrix) -> FaceDetections {
+        let mut inner = self.inner.lock().unwrap();
+        let detector = &mut *inner;
+        let adjust_threshold = self.adjust_threshold;
+
+        unsafe {
+            cpp!([detector as "object_detector_fhog*", image as "dlib::matrix<dlib::rgb_pixel>*", adjust_threshold as "double"] -> FaceDetections as "std::vector<face_detection>" {
+                std::vector<dlib::rect_detection> detections;
+                (*detector)(*image, detections, adjust_threshold);
+
+                std::vector<face_detection> results;
+                results.reserve(detections.size());
+
+                for (auto &detection: detections) {
+                    results.push_back(face_detection{from_dlib_rect(detection.rect), detection.detection_confidence, detection.weight_index});
+                }
+
+                return results;
+            })
+        }
+    }
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn test_load_object_detector() {
+    use super::hog::FaceDetector;
+
+    // The frontal face detector is itself an fhog object detector, so save it out and load it back in.
+    let path = std::env::temp_dir().join("dlib-face-recognition-frontal-face-detector.svm");
+    let filename = path_to_cstring(&path).unwrap();
+
+    unsafe {
+        let filename = filename.as_ptr();
+
+        cpp!([filename as "char*"] {
+            dlib::serialize(filename) << dlib::get_frontal_face_detector();
+        })
+    };
+
+    let image = image::open("assets/obama_1.jpg").unwrap().to_rgb();
+    let matrix = ImageMatrix::from_image(&image);
+
+    let detector = ObjectDetector::new(&path).unwrap();
+    assert_eq!(detector.num_detectors(), 5);
+    assert_eq!(
+        &*detector.face_locations(&matrix),
+        &*FaceDetector::new().face_locations(&matrix)
+    );
+
+    assert!(ObjectDetector::new("does-not-exist.svm").is_err());
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn test_train_object_detector() {
+    let image = image::open("assets/obama_1.jpg").unwrap().to_rgb();
+    let matrix = ImageMatrix::from_image(&image);
+    let face = Rectangle {
+        left: 305,
+        top: 113,
+        right: 521,
+        bottom: 329,
+    };
+
+    let options = ObjectDetectorTrainingOptions {
+        symmetric: true,
+        ..Default::default()
+    };
+    let detector = ObjectDetector::train(&[(matrix.clone(), vec![face])], &options).unwrap();
+
+    let locations = detector.face_locations(&matrix);
+    assert_eq!(locations.len(), 1);
+    assert!(locations[0].iou(&face) > 0.5);
+
+    let path = std::env::temp_dir().join("dlib-face-recognition-trained-detector.svm");
+    detector.save(&path).unwrap();
+    assert_eq!(
+        &*ObjectDetector::new(&path).unwrap().face_locations(&matrix),
+        &*locations
+    );
+    assert!(detector.save("nul\0byte.svm").is_err());
+
+    assert!(ObjectDetector::train(&[(matrix, vec![])], &options).is_err());
+}
@@ -0,0 +1,209 @@
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use super::base::FaceDetectorTrait;
+use super::detection::FaceDetections;
+use crate::matrix::ImageMatrix;
+
+/// A pool of detectors running on background threads.
+///
+/// Images are submitted to the pool and detected by whichever worker is free, and the results are
+/// collected from the pool as they finish. Each worker creates its own detector on its own thread,
+/// so the detector doesn't need to be `Send`. Results may arrive in a different order to the images,
+/// so each one is tagged with the id returned by [`DetectorPool::submit`]. If a detector panics on
+/// an image, that image's result is an `Err` carrying the panic message, and the worker moves on
+/// to the next image.
+pub struct DetectorPool {
+    jobs: Option<Sender<(u64, ImageMatrix)>>,
+    results: Receiver<(u64, Result<FaceDetections, String>)>,
+    workers: Vec<JoinHandle<()>>,
+    next_id: u64,
+    pending: usize,
+}
+
+impl DetectorPool {
+    /// Start `num_workers` threads, each with a detector created by `make_detector`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `num_workers` is zero.
+    pub fn new<D, F>(num_workers: usize, make_detector: F) -> Self
+    where
+        D: FaceDetectorTrait,
+        F: Fn() -> D + Send + Sync + 'static,
+    {
+        assert!(num_workers > 0, "A detector pool needs at least one worker");
+
+        let (jobs, job_receiver) = mpsc::channel::<(u64, ImageMatrix)>();
+        let (result_sender, results) = mpsc::channel();
+
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+        let make_detector = Arc::new(make_detector);
+
+        let workers = (0..num_workers)
+            .map(|_| {
+                let job_receiver = job_receiver.clone();
+                let result_sender = result_sender.clone();
+                let make_detector = make_detector.clone();
+
+                thread::spawn(move || {
+                    let detector = make_detector();
+
+                    loop {
+                        let job = job_receiver.lock().unwrap().recv();
+                        let (id, image) = match job {
+                            Ok(job) => job,
+                            Err(_) => break,
+                        };
+
+                        let detections = panic::catch_unwind(AssertUnwindSafe(|| {
+                            detector.face_locations_with_confidence(&image)
+                        }))
+                        .map_err(|payload| panic_message(&*payload));
+                        if result_sender.send((id, detections)).is_err() {
+                            break;
+                        }
+                    }
+                })
+            })
+            .collect();
+
+        Self {
+            jobs: Some(jobs),
+            results,
+            workers,
+            next_id: 0,
+            pending: 0,
+        }
+    }
+
+    /// Queue an image for detection, returning the id its result will be tagged with.
+    pub fn submit(&mut self, image: ImageMatrix) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        self.jobs
+            .as_ref()
+            .unwrap()
+            .send((id, image))
+            .expect("Detector pool workers have stopped");
+        self.pending += 1;
+
+        id
+    }
+
+    /// The number of submitted images whose results haven't been received yet.
+    pub fn pending(&self) -> usize {
+        self.pending
+    }
+
+    /// Get a finished result if there is one, without blocking.
+    pub fn try_recv(&mut self) -> Option<(u64, Result<FaceDetections, String>)> {
+        let result = self.results.try_recv().ok();
+        if result.is_some() {
+            self.pending -= 1;
+        }
+        result
+    }
+
+    /// Wait for the next result.
+    ///
+    /// Returns `None` if there are no pending images.
+    pub fn recv(&mut self) -> Option<(u64, Result<FaceDetections, String>)> {
+        if self.pending == 0 {
+            return None;
+        }
+
+        let result = self.results.recv().ok();
+        if result.is_some() {
+            self.pending -= 1;
+        }
+        result
+    }
+}
+
+// Describe a worker's panic, which is usually a `&str` or a `String`.
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    let message = if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "unknown panic"
+    };
+
+    format!("Face detector panicked: {}", message)
+}
+
+impl Drop for DetectorPool {
+    fn drop(&mut self) {
+        // Closing the job channel stops the workers once they finish their current image.
+        self.jobs.take();
+
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn test_detector_pool() {
+    use super::hog::FaceDetector;
+
+    let image = image::open("assets/obama_1.jpg").unwrap().to_rgb();
+    let matrix = ImageMatrix::from_image(&image);
+
+    let mut pool = DetectorPool::new(2, FaceDetector::new);
+    let first = pool.submit(matrix.clone());
+    let second = pool.submit(matrix);
+    assert_eq!(pool.pending(), 2);
+
+    let mut ids = Vec::new();
+    while let Some((id, detections)) = pool.recv() {
+        assert_eq!(detections.unwrap().len(), 1);
+        ids.push(id);
+    }
+
+    ids.sort();
+    assert_eq!(ids, vec![first, second]);
+    assert!(pool.try_recv().is_none());
+}
+
+#[cfg(test)]
+struct PanickingDetector;
+
+#[cfg(test)]
+impl FaceDetectorTrait for PanickingDetector {
+    fn face_locations(&self, image: &ImageMatrix) -> super::location::FaceLocations {
+        if image.width() < 10 {
+            panic!("image too small");
+        }
+        super::location::FaceLocations::from_rectangles(&[])
+    }
+}
+
+#[test]
+fn test_detector_pool_panic() {
+    let mut pool = DetectorPool::new(2, || PanickingDetector);
+    let small = pool.submit(ImageMatrix::blank(5, 5));
+    let large = pool.submit(ImageMatrix::blank(20, 20));
+
+    let mut results = Vec::new();
+    while let Some(result) = pool.recv() {
+        results.push(result);
+    }
+    results.sort_by_key(|&(id, _)| id);
+
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].0, small);
+    assert_eq!(
+        results[0].1.as_ref().err().unwrap(),
+        "Face detector panicked: image too small"
+    );
+    assert_eq!(results[1].0, large);
+    assert_eq!(results[1].1.as_ref().unwrap().len(), 0);
+}
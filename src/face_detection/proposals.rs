@@ -0,0 +1,75 @@
+use super::location::FaceLocations;
+use crate::matrix::ImageMatrix;
+
+/// Options for [`find_candidate_object_locations`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct CandidateOptions {
+    /// The segmentation scales to run. Larger values produce larger initial segments.
+    pub kvals: Vec<f64>,
+    /// The minimum size in pixels of the initial segments.
+    pub min_size: u64,
+    /// The maximum number of times neighbouring segments are merged at each scale.
+    pub max_merging_iterations: u64,
+}
+
+impl Default for CandidateOptions {
+    fn default() -> Self {
+        Self {
+            kvals: vec![50.0, 125.0, 200.0],
+            min_size: 20,
+            max_merging_iterations: 50,
+        }
+    }
+}
+
+/// Find rectangles likely to contain an object of some kind using dlib's selective search.
+///
+/// This is useful as a source of candidate regions for classifiers built on top of this crate.
+/// The proposals are not specific to faces, and a typical image produces thousands of them.
+pub fn find_candidate_object_locations(
+    image: &ImageMatrix,
+    options: &CandidateOptions,
+) -> FaceLocations {
+    let kvals = options.kvals.as_ptr();
+    let num_kvals = options.kvals.len();
+    let min_size = options.min_size;
+    let max_merging_iterations = options.max_merging_iterations;
+
+    unsafe {
+        cpp!([
+                image as "const dlib::matrix<dlib::rgb_pixel>*",
+                kvals as "const double*",
+                num_kvals as "size_t",
+                min_size as "uint64_t",
+                max_merging_iterations as "uint64_t"
+            ] -> FaceLocations as "std::vector<dlib::rectangle>" {
+            std::vector<dlib::rectangle> rects;
+
+            if (num_kvals > 0) {
+                dlib::matrix<double,0,1> k = dlib::mat(kvals, num_kvals);
+                dlib::find_candidate_object_locations(*image, rects, k, min_size, max_merging_iterations);
+            }
+
+            return from_dlib_rects(rects);
+        })
+    }
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn test_candidate_object_locations() {
+    let image = image::open("assets/obama_1.jpg").unwrap().to_rgb();
+    let matrix = ImageMatrix::from_image(&image);
+
+    let candidates = find_candidate_object_locations(&matrix, &CandidateOptions::default());
+    assert!(!candidates.is_empty());
+    assert!(candidates
+        .iter()
+        .all(|rect| rect.right <= matrix.width() as i64 && rect.bottom <= matrix.height() as i64));
+
+    let none = CandidateOptions {
+        kvals: Vec::new(),
+        ..Default::default()
+    };
+    assert!(find_candidate_object_locations(&matrix, &none).is_empty());
+}
@@ -0,0 +1,108 @@
+use super::base::FaceDetectorTrait;
+use super::detection::{FaceDetection, FaceDetections};
+use super::location::FaceLocations;
+use super::nms::{non_max_suppression, NmsOptions};
+use crate::geometry::Rectangle;
+use crate::matrix::ImageMatrix;
+
+/// A detector that also searches rotated copies of the image, for photos that were taken sideways.
+///
+/// The image is rotated counter-clockwise by each of the configured angles in turn, and the detections
+/// are mapped back into the original image and merged with non-max suppression. Faces found at angles
+/// other than multiples of 90 degrees are returned as the bounding box of their rotated rectangle.
+#[derive(Clone)]
+pub struct RotatingDetector<D> {
+    detector: D,
+    angles: Vec<f64>,
+    nms: NmsOptions,
+}
+
+impl<D: FaceDetectorTrait> RotatingDetector<D> {
+    /// Wrap a detector to search the image upright, rotated by 90 degrees either way, and upside down.
+    pub fn new(detector: D) -> Self {
+        Self::with_angles(detector, &[0.0, 90.0, -90.0, 180.0])
+    }
+
+    /// Wrap a detector to search the image at the given angles in degrees.
+    pub fn with_angles(detector: D, angles: &[f64]) -> Self {
+        Self {
+            detector,
+            angles: angles.to_vec(),
+            nms: NmsOptions::default(),
+        }
+    }
+
+    pub fn angles(&self) -> &[f64] {
+        &self.angles
+    }
+
+    /// Set how detections of the same face at different angles are merged.
+    pub fn set_nms_options(&mut self, nms: NmsOptions) {
+        self.nms = nms;
+    }
+
+    pub fn nms_options(&self) -> NmsOptions {
+        self.nms
+    }
+
+    pub fn into_inner(self) -> D {
+        self.detector
+    }
+}
+
+impl<D: FaceDetectorTrait> FaceDetectorTrait for RotatingDetector<D> {
+    fn face_locations(&self, image: &ImageMatrix) -> FaceLocations {
+        let rectangles: Vec<Rectangle> = self
+            .face_locations_with_confidence(image)
+            .iter()
+            .map(|detection| detection.rect)
+            .collect();
+
+        FaceLocations::from_rectangles(&rectangles)
+    }
+
+    fn face_locations_with_confidence(&self, image: &ImageMatrix) -> FaceDetections {
+        let mut detections = Vec::new();
+
+        for &angle in &self.angles {
+            if angle == 0.0 {
+                detections.extend_from_slice(&self.detector.face_locations_with_confidence(image));
+                continue;
+            }
+
+            let (rotated, transform) = image.rotated(angle);
+            let inverse = transform.inverse().unwrap();
+
+            detections.extend(
+                self.detector
+                    .face_locations_with_confidence(&rotated)
+                    .iter()
+                    .map(|detection| FaceDetection {
+                        rect: inverse.apply_rect(&detection.rect),
+                        ..*detection
+                    }),
+            );
+        }
+
+        FaceDetections::from_detections(&non_max_suppression(&detections, &self.nms))
+    }
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn test_rotated_detection() {
+    use super::hog::FaceDetector;
+
+    let image = image::open("assets/obama_1.jpg").unwrap().to_rgb();
+    let matrix = ImageMatrix::from_image(&image);
+    let upright = FaceDetector::new().face_locations(&matrix)[0];
+
+    let (sideways, transform) = matrix.rotated(90.0);
+    let expected = transform.apply_rect(&upright);
+
+    let detector = RotatingDetector::new(FaceDetector::new());
+    let locations = detector.face_locations(&sideways);
+
+    assert_eq!(locations.len(), 1);
+    assert!(locations[0].iou(&expected) > 0.5);
+}
@@ -0,0 +1,97 @@
+use super::base::FaceDetectorTrait;
+use super::detection::{FaceDetection, FaceDetections};
+use super::location::FaceLocations;
+use crate::geometry::Rectangle;
+use crate::matrix::ImageMatrix;
+
+/// A detector that discards faces outside of a range of sizes.
+///
+/// The size of a face is the larger of its width and height. Very small faces produce unreliable landmarks
+/// and encodings, so filtering them out before further processing saves time as well as improving results.
+#[derive(Clone)]
+pub struct SizeFilter<D> {
+    detector: D,
+    min_size: i64,
+    max_size: i64,
+}
+
+impl<D: FaceDetectorTrait> SizeFilter<D> {
+    /// Wrap a detector to only return faces between `min_size` and `max_size` pixels, inclusive.
+    pub fn new(detector: D, min_size: i64, max_size: i64) -> Self {
+        Self {
+            detector,
+            min_size,
+            max_size,
+        }
+    }
+
+    /// Wrap a detector to only return faces of at least `min_size` pixels.
+    pub fn with_min_size(detector: D, min_size: i64) -> Self {
+        Self::new(detector, min_size, i64::MAX)
+    }
+
+    pub fn min_size(&self) -> i64 {
+        self.min_size
+    }
+
+    pub fn max_size(&self) -> i64 {
+        self.max_size
+    }
+
+    pub fn into_inner(self) -> D {
+        self.detector
+    }
+
+    fn accepts(&self, rect: &Rectangle) -> bool {
+        let size = rect.width().max(rect.height());
+        size >= self.min_size && size <= self.max_size
+    }
+}
+
+impl<D: FaceDetectorTrait> FaceDetectorTrait for SizeFilter<D> {
+    fn face_locations(&self, image: &ImageMatrix) -> FaceLocations {
+        let rectangles: Vec<Rectangle> = self
+            .detector
+            .face_locations(image)
+            .iter()
+            .filter(|rect| self.accepts(rect))
+            .cloned()
+            .collect();
+
+        FaceLocations::from_rectangles(&rectangles)
+    }
+
+    fn face_locations_with_confidence(&self, image: &ImageMatrix) -> FaceDetections {
+        let detections: Vec<FaceDetection> = self
+            .detector
+            .face_locations_with_confidence(image)
+            .iter()
+            .filter(|detection| self.accepts(&detection.rect))
+            .cloned()
+            .collect();
+
+        FaceDetections::from_detections(&detections)
+    }
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn test_size_filter() {
+    use super::hog::FaceDetector;
+
+    let image = image::open("assets/obama_1.jpg").unwrap().to_rgb();
+    let matrix = ImageMatrix::from_image(&image);
+
+    assert_eq!(
+        SizeFilter::new(FaceDetector::new(), 100, 300)
+            .face_locations(&matrix)
+            .len(),
+        1
+    );
+    assert!(SizeFilter::with_min_size(FaceDetector::new(), 300)
+        .face_locations(&matrix)
+        .is_empty());
+    assert!(SizeFilter::new(FaceDetector::new(), 0, 100)
+        .face_locations_with_confidence(&matrix)
+        .is_empty());
+}
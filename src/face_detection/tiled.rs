@@ -0,0 +1,179 @@
+use super::base::FaceDetectorTrait;
+use super::detection::{FaceDetection, FaceDetections};
+use super::location::FaceLocations;
+use super::nms::{non_max_suppression, NmsOptions};
+use crate::geometry::Rectangle;
+use crate::matrix::ImageMatrix;
+
+/// A detector that scans very large images in overlapping tiles.
+///
+/// Each tile is run through the wrapped detector separately, and detections of the same face
+/// from neighbouring tiles are merged with non-max suppression. The overlap between tiles should be
+/// at least as large as the biggest face expected, so that every face fits entirely inside a tile.
+#[derive(Clone)]
+pub struct TiledDetector<D> {
+    detector: D,
+    tile_size: usize,
+    overlap: usize,
+    nms: NmsOptions,
+}
+
+impl<D: FaceDetectorTrait> TiledDetector<D> {
+    /// Wrap a detector to scan tiles of `tile_size` pixels, overlapping by `overlap` pixels.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `overlap` is not smaller than `tile_size`.
+    pub fn new(detector: D, tile_size: usize, overlap: usize) -> Self {
+        assert!(
+            overlap < tile_size,
+            "Tile overlap must be smaller than the tile size"
+        );
+
+        Self {
+            detector,
+            tile_size,
+            overlap,
+            nms: NmsOptions::default(),
+        }
+    }
+
+    /// Set how detections of the same face from neighbouring tiles are merged.
+    pub fn set_nms_options(&mut self, nms: NmsOptions) {
+        self.nms = nms;
+    }
+
+    pub fn nms_options(&self) -> NmsOptions {
+        self.nms
+    }
+
+    pub fn into_inner(self) -> D {
+        self.detector
+    }
+
+    fn tiles(&self, width: usize, height: usize) -> Vec<Rectangle> {
+        let step = self.tile_size - self.overlap;
+        let mut tiles = Vec::new();
+
+        let mut top = 0;
+        loop {
+            let mut left = 0;
+            loop {
+                tiles.push(Rectangle {
+                    left: left as i64,
+                    top: top as i64,
+                    right: (left + self.tile_size).min(width) as i64,
+                    bottom: (top + self.tile_size).min(height) as i64,
+                });
+
+                if left + self.tile_size >= width {
+                    break;
+                }
+                left += step;
+            }
+
+            if top + self.tile_size >= height {
+                break;
+            }
+            top += step;
+        }
+
+        tiles
+    }
+}
+
+impl<D: FaceDetectorTrait> FaceDetectorTrait for TiledDetector<D> {
+    fn face_locations(&self, image: &ImageMatrix) -> FaceLocations {
+        let rectangles: Vec<Rectangle> = self
+            .face_locations_with_confidence(image)
+            .iter()
+            .map(|detection| detection.rect)
+            .collect();
+
+        FaceLocations::from_rectangles(&rectangles)
+    }
+
+    fn face_locations_with_confidence(&self, image: &ImageMatrix) -> FaceDetections {
+        let mut detections = Vec::new();
+
+        for tile in self.tiles(image.width(), image.height()) {
+            let crop = image.crop(&tile);
+
+            for detection in self.detector.face_locations_with_confidence(&crop).iter() {
+                let rect = detection.rect;
+
+                detections.push(FaceDetection {
+                    rect: Rectangle {
+                        left: rect.left + tile.left,
+                        top: rect.top + tile.top,
+                        right: rect.right + tile.left,
+                        bottom: rect.bottom + tile.top,
+                    },
+                    ..*detection
+                });
+            }
+        }
+
+        FaceDetections::from_detections(&non_max_suppression(&detections, &self.nms))
+    }
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn test_tiled_detection() {
+    use super::hog::FaceDetector;
+
+    let image = image::open("assets/obama_1.jpg").unwrap().to_rgb();
+    let matrix = ImageMatrix::from_image(&image);
+    let detector = TiledDetector::new(FaceDetector::new(), 400, 300);
+
+    assert!(detector.tiles(matrix.width(), matrix.height()).len() > 1);
+
+    let locations = detector.face_locations(&matrix);
+
+    assert_eq!(locations.len(), 1);
+    assert!((locations[0].center_x() - 412.5).abs() < 20.0);
+    assert!((locations[0].center_y() - 220.5).abs() < 20.0);
+}
+
+// Finds a 100 pixel face wherever a pixel on the 100 pixel grid isn't black.
+#[cfg(test)]
+struct GridDetector;
+
+#[cfg(test)]
+impl FaceDetectorTrait for GridDetector {
+    fn face_locations(&self, image: &ImageMatrix) -> FaceLocations {
+        let mut rects = Vec::new();
+        for top in (0..image.height()).step_by(100) {
+            for left in (0..image.width()).step_by(100) {
+                if image.get_pixel(left, top).unwrap() != [0, 0, 0] {
+                    rects.push(Rectangle {
+                        left: left as i64,
+                        top: top as i64,
+                        right: left as i64 + 100,
+                        bottom: top as i64 + 100,
+                    });
+                }
+            }
+        }
+        FaceLocations::from_rectangles(&rects)
+    }
+}
+
+#[test]
+fn test_nms_options() {
+    let mut image = ImageMatrix::blank(200, 200);
+    image.set_pixel(0, 0, [255, 255, 255]);
+    image.set_pixel(100, 100, [255, 255, 255]);
+
+    // the second face is found in all four 150 pixel tiles, and the first only in the top left one
+    let mut detector = TiledDetector::new(GridDetector, 150, 50);
+    assert_eq!(detector.face_locations(&image).len(), 2);
+
+    detector.set_nms_options(NmsOptions {
+        iou_threshold: 1.0,
+        ..NmsOptions::default()
+    });
+    assert_eq!(detector.nms_options().iou_threshold, 1.0);
+    assert_eq!(detector.face_locations(&image).len(), 5);
+}
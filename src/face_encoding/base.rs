@@ -1,15 +1,49 @@
 use super::encodings::FaceEncodings;
+use crate::geometry::{Point, Rectangle};
 use crate::landmark_prediction::FaceLandmarks;
 use crate::matrix::ImageMatrix;
 
 pub trait FaceEncoderTrait {
     /// Get a number of face encodings from an image and a list of landmarks, and jitter them a certain amount.
     ///
-    /// It is recommended to keep `num_jitters` at 0 unless you know what you're doing.
+    /// The landmarks can come from either the 68-point or the 5-point landmark predictor.
+    ///
+    /// With a `num_jitters` above 1, each face is encoded that many times from randomly moved, rotated, scaled
+    /// and mirrored copies of its chip, and the encodings are averaged, the same as `num_jitters` in dlib's python
+    /// API. This makes encodings slightly more accurate, which is worthwhile for enrollment photos, but is
+    /// `num_jitters` times slower. 0 and 1 both encode each face once, as is.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any of the landmarks don't have either 68 or 5 points.
     fn get_face_encodings(
-        &mut self,
+        &self,
         image: &ImageMatrix,
         landmarks: &[FaceLandmarks],
         num_jitters: u32,
     ) -> FaceEncodings;
+
+    /// Encode a blank face, so that any lazy initialization (such as allocating gpu memory)
+    /// happens now rather than on the first real image.
+    fn warm_up(&self) {
+        let rect = Rectangle {
+            left: 0,
+            top: 0,
+            right: 150,
+            bottom: 150,
+        };
+        let parts = [
+            Point::new(105, 55),
+            Point::new(90, 55),
+            Point::new(45, 55),
+            Point::new(60, 55),
+            Point::new(75, 95),
+        ];
+
+        self.get_face_encodings(
+            &ImageMatrix::blank(150, 150),
+            &[FaceLandmarks::new(&rect, &parts)],
+            0,
+        );
+    }
 }
@@ -0,0 +1,104 @@
+use super::encoding::FaceEncoding;
+
+/// A logistic mapping from euclidean distance to the probability that two encodings are the same person.
+///
+/// The probability is `1 / (1 + exp(-(intercept + slope * distance)))`. A calibration is usually fitted
+/// from labelled pairs of encodings collected from the deployment it will be used in.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DistanceCalibration {
+    pub intercept: f64,
+    pub slope: f64,
+}
+
+impl DistanceCalibration {
+    pub fn new(intercept: f64, slope: f64) -> Self {
+        Self { intercept, slope }
+    }
+
+    /// Fit a calibration by logistic regression over `(distance, is_same_person)` samples.
+    ///
+    /// Both same-person and different-person samples are required.
+    pub fn fit(samples: &[(f64, bool)]) -> Result<Self, String> {
+        if !samples.iter().any(|(_, same)| *same) || !samples.iter().any(|(_, same)| !*same) {
+            return Err("Calibration requires both matching and non-matching samples".into());
+        }
+
+        // A little regularisation keeps the fit finite when the samples are perfectly separable.
+        const REGULARISATION: f64 = 1e-3;
+        const MAX_ITERATIONS: usize = 100;
+
+        let mut calibration = Self::new(0.0, 0.0);
+
+        for _ in 0..MAX_ITERATIONS {
+            let mut gradient = [
+                REGULARISATION * calibration.intercept,
+                REGULARISATION * calibration.slope,
+            ];
+            let mut hessian = [[REGULARISATION, 0.0], [0.0, REGULARISATION]];
+
+            for &(distance, same) in samples {
+                let probability = calibration.probability(distance);
+                let error = probability - if same { 1.0 } else { 0.0 };
+                let weight = probability * (1.0 - probability);
+
+                gradient[0] += error;
+                gradient[1] += error * distance;
+                hessian[0][0] += weight;
+                hessian[0][1] += weight * distance;
+                hessian[1][1] += weight * distance * distance;
+            }
+            hessian[1][0] = hessian[0][1];
+
+            let determinant = hessian[0][0] * hessian[1][1] - hessian[0][1] * hessian[1][0];
+            if determinant.abs() < f64::EPSILON {
+                break;
+            }
+
+            let step = [
+                (hessian[1][1] * gradient[0] - hessian[0][1] * gradient[1]) / determinant,
+                (hessian[0][0] * gradient[1] - hessian[1][0] * gradient[0]) / determinant,
+            ];
+
+            calibration.intercept -= step[0];
+            calibration.slope -= step[1];
+
+            if step[0].abs() < 1e-10 && step[1].abs() < 1e-10 {
+                break;
+            }
+        }
+
+        Ok(calibration)
+    }
+
+    /// The probability that two encodings separated by `distance` belong to the same person.
+    pub fn probability(&self, distance: f64) -> f64 {
+        1.0 / (1.0 + (-(self.intercept + self.slope * distance)).exp())
+    }
+}
+
+impl FaceEncoding {
+    /// The calibrated probability that this encoding and `other` belong to the same person.
+    pub fn match_probability(&self, other: &Self, calibration: &DistanceCalibration) -> f64 {
+        calibration.probability(self.distance(other))
+    }
+}
+
+#[test]
+fn test_calibration() {
+    let mut samples = Vec::new();
+    for i in 0..20 {
+        let offset = i as f64 * 0.01;
+        samples.push((0.3 + offset, true));
+        samples.push((0.65 + offset, false));
+    }
+    samples.push((0.62, true));
+    samples.push((0.55, false));
+
+    let calibration = DistanceCalibration::fit(&samples).unwrap();
+
+    assert!(calibration.slope < 0.0);
+    assert!(calibration.probability(0.3) > 0.9);
+    assert!(calibration.probability(0.9) < 0.1);
+
+    assert!(DistanceCalibration::fit(&[(0.1, true)]).is_err());
+}
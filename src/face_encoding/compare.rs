@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 
 use super::encoding::FaceEncoding;
+use super::verify::DEFAULT_MATCH_THRESHOLD;
 
 #[derive(Default)]
 pub struct FaceComparer {
@@ -22,15 +23,13 @@ impl FaceComparer {
     }
 
     pub fn find(&self, face: &FaceEncoding) -> Option<usize> {
-        const TOLERANCE: f64 = 0.6;
-
         if let Some((key, x)) = self
             .values
             .iter()
             .map(|(i, f)| (i, f.distance(face)))
             .min_by(|(_, x), (_, y)| x.partial_cmp(y).unwrap())
         {
-            if x <= TOLERANCE {
+            if x <= DEFAULT_MATCH_THRESHOLD {
                 Some(*key)
             } else {
                 None
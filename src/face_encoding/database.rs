@@ -0,0 +1,418 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use super::encoding::FaceEncoding;
+use super::verify::DEFAULT_MATCH_THRESHOLD;
+
+const MAGIC: &[u8] = b"DFRDB";
+const VERSION: u32 = 2;
+const FLAG_COMPRESSED: u8 = 1;
+
+/// A labelled collection of encodings for identifying faces.
+///
+/// Each label is represented by the running centroid (mean) of every encoding enrolled for it,
+/// so identification improves as more sightings of a person are enrolled. Probes are identified by
+/// finding the closest centroid and accepting it if it is within the database's threshold.
+#[derive(Clone, Debug)]
+pub struct FaceDatabase<L = String> {
+    labels: Vec<L>,
+    encodings: Vec<FaceEncoding>,
+    counts: Vec<u32>,
+    threshold: f64,
+}
+
+impl<L> Default for FaceDatabase<L> {
+    fn default() -> Self {
+        Self::with_threshold(DEFAULT_MATCH_THRESHOLD)
+    }
+}
+
+impl<L> FaceDatabase<L> {
+    /// Create an empty database using [`DEFAULT_MATCH_THRESHOLD`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create an empty database that accepts matches within `threshold`.
+    pub fn with_threshold(threshold: f64) -> Self {
+        Self {
+            labels: Vec::new(),
+            encodings: Vec::new(),
+            counts: Vec::new(),
+            threshold,
+        }
+    }
+
+    pub fn threshold(&self) -> f64 {
+        self.threshold
+    }
+
+    pub fn set_threshold(&mut self, threshold: f64) {
+        self.threshold = threshold;
+    }
+
+    pub fn len(&self) -> usize {
+        self.labels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+
+    /// The enrolled labels, in enrollment order.
+    pub fn labels(&self) -> &[L] {
+        &self.labels
+    }
+
+    /// Iterate over the enrolled labels and their centroid encodings.
+    pub fn iter(&self) -> impl Iterator<Item = (&L, &FaceEncoding)> {
+        self.labels.iter().zip(self.encodings.iter())
+    }
+
+    /// Find the closest enrolled identity to a probe.
+    ///
+    /// Returns the label and distance if the closest encoding is within the threshold.
+    pub fn identify(&self, probe: &FaceEncoding) -> Option<(&L, f64)> {
+        probe
+            .best_match(&self.encodings)
+            .filter(|(_, distance)| *distance <= self.threshold)
+            .map(|(index, distance)| (&self.labels[index], distance))
+    }
+}
+
+impl<L: PartialEq> FaceDatabase<L> {
+    fn position(&self, label: &L) -> Option<usize> {
+        self.labels.iter().position(|l| l == label)
+    }
+
+    /// Enroll an encoding under a label.
+    ///
+    /// If the label is already enrolled, the encoding is folded into its running centroid.
+    pub fn enroll(&mut self, label: L, encoding: FaceEncoding) {
+        if let Some(index) = self.position(&label) {
+            let count = self.counts[index];
+            let weight = 1.0 / (count as f64 + 1.0);
+
+            let mut elements = self.encodings[index].to_elements();
+            for (element, value) in elements.iter_mut().zip(encoding.iter()) {
+                *element += (value - *element) * weight;
+            }
+
+            self.encodings[index] = FaceEncoding::new(&elements);
+            self.counts[index] = count.saturating_add(1);
+        } else {
+            self.labels.push(label);
+            self.encodings.push(encoding);
+            self.counts.push(1);
+        }
+    }
+
+    /// Enroll an encoding under a label, discarding anything previously enrolled for it.
+    pub fn replace(&mut self, label: L, encoding: FaceEncoding) {
+        self.remove(&label);
+        self.enroll(label, encoding);
+    }
+
+    /// Remove a label from the database, returning its centroid encoding if it was enrolled.
+    pub fn remove(&mut self, label: &L) -> Option<FaceEncoding> {
+        let index = self.position(label)?;
+        self.labels.remove(index);
+        self.counts.remove(index);
+        Some(self.encodings.remove(index))
+    }
+
+    /// The number of encodings that have been enrolled for a label.
+    pub fn enrollment_count(&self, label: &L) -> Option<u32> {
+        self.position(label).map(|index| self.counts[index])
+    }
+
+    pub fn contains(&self, label: &L) -> bool {
+        self.position(label).is_some()
+    }
+
+    /// Get the centroid encoding enrolled for a label.
+    pub fn get(&self, label: &L) -> Option<&FaceEncoding> {
+        self.position(label).map(|index| &self.encodings[index])
+    }
+}
+
+fn io_error(error: std::io::Error) -> String {
+    error.to_string()
+}
+
+impl FaceDatabase<String> {
+    fn write_payload<W: Write>(&self, writer: &mut W) -> Result<(), String> {
+        writer
+            .write_all(&self.threshold.to_le_bytes())
+            .map_err(io_error)?;
+        writer
+            .write_all(&(self.len() as u64).to_le_bytes())
+            .map_err(io_error)?;
+
+        for ((label, encoding), count) in self.iter().zip(self.counts.iter()) {
+            writer
+                .write_all(&(label.len() as u32).to_le_bytes())
+                .map_err(io_error)?;
+            writer.write_all(label.as_bytes()).map_err(io_error)?;
+            writer.write_all(&count.to_le_bytes()).map_err(io_error)?;
+
+            for element in encoding.iter() {
+                writer.write_all(&element.to_le_bytes()).map_err(io_error)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn read_payload<R: Read>(reader: &mut R, version: u32) -> Result<Self, String> {
+        let mut buffer = [0u8; 8];
+
+        reader.read_exact(&mut buffer).map_err(io_error)?;
+        let mut database = Self::with_threshold(f64::from_le_bytes(buffer));
+
+        reader.read_exact(&mut buffer).map_err(io_error)?;
+        let len = u64::from_le_bytes(buffer);
+
+        let mut elements = [0f64; 128];
+
+        for _ in 0..len {
+            let mut label_len = [0u8; 4];
+            reader.read_exact(&mut label_len).map_err(io_error)?;
+
+            // The length comes from the file, so only allocate for the label that is actually there.
+            let label_len = u32::from_le_bytes(label_len) as usize;
+            let mut label = Vec::new();
+            reader
+                .by_ref()
+                .take(label_len as u64)
+                .read_to_end(&mut label)
+                .map_err(io_error)?;
+            if label.len() != label_len {
+                return Err("Truncated label".into());
+            }
+            let label = String::from_utf8(label).map_err(|_| "Malformed label".to_string())?;
+
+            // Version 1 databases only stored a single encoding per label.
+            let count = if version >= 2 {
+                let mut count = [0u8; 4];
+                reader.read_exact(&mut count).map_err(io_error)?;
+                u32::from_le_bytes(count)
+            } else {
+                1
+            };
+
+            for element in elements.iter_mut() {
+                reader.read_exact(&mut buffer).map_err(io_error)?;
+                *element = f64::from_le_bytes(buffer);
+            }
+
+            database.labels.push(label);
+            database.encodings.push(FaceEncoding::new(&elements));
+            database.counts.push(count);
+        }
+
+        Ok(database)
+    }
+
+    #[cfg(feature = "compression")]
+    fn write_compressed_payload<W: Write>(&self, writer: &mut W) -> Result<(), String> {
+        let mut encoder =
+            flate2::write::DeflateEncoder::new(writer, flate2::Compression::default());
+        self.write_payload(&mut encoder)?;
+        encoder.finish().map_err(io_error)?;
+        Ok(())
+    }
+
+    #[cfg(not(feature = "compression"))]
+    fn write_compressed_payload<W: Write>(&self, _writer: &mut W) -> Result<(), String> {
+        Self::check_compression(true)
+    }
+
+    // Fail before anything is written if compression was asked for but isn't compiled in.
+    fn check_compression(compress: bool) -> Result<(), String> {
+        if compress && cfg!(not(feature = "compression")) {
+            return Err(
+                "Writing a compressed face database requires the 'compression' feature".into(),
+            );
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "compression")]
+    fn read_compressed_payload<R: Read>(reader: R, version: u32) -> Result<Self, String> {
+        Self::read_payload(&mut flate2::read::DeflateDecoder::new(reader), version)
+    }
+
+    #[cfg(not(feature = "compression"))]
+    fn read_compressed_payload<R: Read>(_reader: R, _version: u32) -> Result<Self, String> {
+        Err("Reading a compressed face database requires the 'compression' feature".into())
+    }
+
+    /// Write the database to a stream, optionally compressing it.
+    ///
+    /// Compression requires the `compression` feature flag.
+    pub fn write_to<W: Write>(&self, mut writer: W, compress: bool) -> Result<(), String> {
+        Self::check_compression(compress)?;
+
+        writer.write_all(MAGIC).map_err(io_error)?;
+        writer.write_all(&VERSION.to_le_bytes()).map_err(io_error)?;
+        writer
+            .write_all(&[if compress { FLAG_COMPRESSED } else { 0 }])
+            .map_err(io_error)?;
+
+        if compress {
+            self.write_compressed_payload(&mut writer)?;
+        } else {
+            self.write_payload(&mut writer)?;
+        }
+
+        writer.flush().map_err(io_error)
+    }
+
+    /// Read a database previously written with [`FaceDatabase::write_to`].
+    pub fn read_from<R: Read>(mut reader: R) -> Result<Self, String> {
+        let mut magic = [0u8; 5];
+        reader.read_exact(&mut magic).map_err(io_error)?;
+        if magic != MAGIC {
+            return Err("Not a face database".into());
+        }
+
+        let mut version = [0u8; 4];
+        reader.read_exact(&mut version).map_err(io_error)?;
+        let version = u32::from_le_bytes(version);
+        if version == 0 || version > VERSION {
+            return Err(format!("Unsupported face database version {}", version));
+        }
+
+        let mut flags = [0u8; 1];
+        reader.read_exact(&mut flags).map_err(io_error)?;
+
+        if flags[0] & FLAG_COMPRESSED != 0 {
+            Self::read_compressed_payload(reader, version)
+        } else {
+            Self::read_payload(&mut reader, version)
+        }
+    }
+
+    /// Save the database to a file, optionally compressing it.
+    ///
+    /// Compression requires the `compression` feature flag.
+    pub fn save<P: AsRef<Path>>(&self, filename: P, compress: bool) -> Result<(), String> {
+        Self::check_compression(compress)?;
+
+        let file = File::create(filename.as_ref()).map_err(|error| {
+            format!(
+                "Failed to create '{}': {}",
+                filename.as_ref().display(),
+                error
+            )
+        })?;
+
+        self.write_to(BufWriter::new(file), compress)
+    }
+
+    /// Load a database from a file, whether or not it was compressed.
+    pub fn load<P: AsRef<Path>>(filename: P) -> Result<Self, String> {
+        let file = File::open(filename.as_ref()).map_err(|error| {
+            format!(
+                "Failed to open '{}': {}",
+                filename.as_ref().display(),
+                error
+            )
+        })?;
+
+        Self::read_from(BufReader::new(file))
+    }
+}
+
+#[test]
+fn test_identify() {
+    let mut database = FaceDatabase::new();
+    database.enroll("a".to_string(), FaceEncoding::new_from_scalar(0.0));
+    database.enroll("b".to_string(), FaceEncoding::new_from_scalar(1.0));
+    database.replace("b".to_string(), FaceEncoding::new_from_scalar(2.0));
+
+    assert_eq!(database.len(), 2);
+
+    let (label, _) = database
+        .identify(&FaceEncoding::new_from_scalar(0.01))
+        .unwrap();
+    assert_eq!(label, "a");
+    assert!(database
+        .identify(&FaceEncoding::new_from_scalar(1.0))
+        .is_none());
+
+    assert!(database.remove(&"a".to_string()).is_some());
+    assert!(database
+        .identify(&FaceEncoding::new_from_scalar(0.01))
+        .is_none());
+    assert_eq!(database.labels(), &["b".to_string()]);
+}
+
+#[test]
+fn enrolling_updates_the_centroid() {
+    let mut database = FaceDatabase::new();
+    database.enroll(1, FaceEncoding::new_from_scalar(1.0));
+    database.enroll(1, FaceEncoding::new_from_scalar(2.0));
+    database.enroll(1, FaceEncoding::new_from_scalar(6.0));
+
+    assert_eq!(database.len(), 1);
+    assert_eq!(database.enrollment_count(&1), Some(3));
+    assert_eq!(database.get(&1), Some(&FaceEncoding::new_from_scalar(3.0)));
+}
+
+#[test]
+fn can_round_trip_database() {
+    let mut database = FaceDatabase::with_threshold(0.5);
+    database.enroll("a".to_string(), FaceEncoding::new_from_scalar(0.25));
+    database.enroll("b".to_string(), FaceEncoding::new_from_scalar(-0.25));
+
+    let mut buffer = Vec::new();
+    database.write_to(&mut buffer, false).unwrap();
+    let loaded = FaceDatabase::read_from(buffer.as_slice()).unwrap();
+
+    assert_eq!(loaded.threshold(), 0.5);
+    assert_eq!(loaded.enrollment_count(&"a".to_string()), Some(1));
+    assert_eq!(loaded.labels(), database.labels());
+    assert_eq!(
+        loaded.get(&"b".to_string()),
+        Some(&FaceEncoding::new_from_scalar(-0.25))
+    );
+
+    #[cfg(feature = "compression")]
+    {
+        let mut compressed = Vec::new();
+        database.write_to(&mut compressed, true).unwrap();
+        let loaded = FaceDatabase::read_from(compressed.as_slice()).unwrap();
+        assert_eq!(loaded.labels(), database.labels());
+    }
+
+    #[cfg(not(feature = "compression"))]
+    {
+        let mut compressed = Vec::new();
+        assert!(database.write_to(&mut compressed, true).is_err());
+        assert!(compressed.is_empty());
+
+        let path = std::env::temp_dir().join("dlib-face-recognition-uncompressible.db");
+        let _ = std::fs::remove_file(&path);
+        assert!(database.save(&path, true).is_err());
+        assert!(!path.exists());
+    }
+}
+
+#[test]
+fn rejects_truncated_labels() {
+    let mut bytes = Vec::new();
+    FaceDatabase::<String>::new()
+        .write_to(&mut bytes, false)
+        .unwrap();
+
+    // one entry, whose label claims to be 4 GiB long but isn't there
+    let count = bytes.len() - 8;
+    bytes[count..].copy_from_slice(&1u64.to_le_bytes());
+    bytes.extend_from_slice(&u32::MAX.to_le_bytes());
+    bytes.extend_from_slice(b"short");
+
+    assert!(FaceDatabase::<String>::read_from(bytes.as_slice()).is_err());
+}
@@ -1,7 +1,24 @@
+use std::convert::TryFrom;
 use std::fmt;
 use std::ops::Deref;
 use std::slice;
 
+use crate::base::CppBytes;
+
+/// A measure of dissimilarity between two encodings.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum DistanceMetric {
+    /// The euclidean (L2) distance.
+    #[default]
+    Euclidean,
+    /// The squared euclidean distance. Cheaper to compute and preserves ordering.
+    SquaredEuclidean,
+    /// The manhattan (L1) distance.
+    Manhattan,
+    /// One minus the cosine similarity, in the range `[0, 2]`.
+    Cosine,
+}
+
 /// A wrapper around a `matrix<double,0,1>>`, an encoding.
 #[derive(Clone)]
 pub struct FaceEncoding {
@@ -44,6 +61,123 @@ impl FaceEncoding {
         Self { inner }
     }
 
+    /// Create a new encoding from a slice of elements.
+    ///
+    /// Returns an error if the slice does not contain exactly 128 elements.
+    pub fn from_slice(elements: &[f64]) -> Result<Self, String> {
+        if elements.len() != 128 {
+            return Err(format!(
+                "Expected 128 elements for a face encoding, got {}",
+                elements.len()
+            ));
+        }
+
+        let mut array = [0f64; 128];
+        array.copy_from_slice(elements);
+
+        Ok(Self::new(&array))
+    }
+
+    /// Deserialize an encoding written by dlib's `serialize` for a `matrix<double,0,1>`.
+    ///
+    /// This allows encodings produced by C++ dlib applications to be loaded directly.
+    pub fn from_dlib_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let inner = FaceEncodingInner::default();
+
+        let deserialized = unsafe {
+            let data = bytes.as_ptr();
+            let len = bytes.len();
+            let matrix = &inner;
+
+            cpp!([data as "const char*", len as "size_t", matrix as "dlib::matrix<double,0,1>*"] -> bool as "bool" {
+                try {
+                    std::istringstream stream(std::string(data, len));
+                    dlib::deserialize(*matrix, stream);
+                    return true;
+                } catch (const std::exception& exception) {
+                    return false;
+                }
+            })
+        };
+
+        if !deserialized {
+            return Err("Failed to deserialize face encoding".into());
+        }
+
+        let encoding = Self { inner };
+
+        if encoding.len() != 128 {
+            Err(format!(
+                "Expected 128 elements for a face encoding, got {}",
+                encoding.len()
+            ))
+        } else {
+            Ok(encoding)
+        }
+    }
+
+    /// Serialize the encoding in dlib's native binary format for a `matrix<double,0,1>`.
+    pub fn to_dlib_bytes(&self) -> Vec<u8> {
+        let bytes = unsafe {
+            cpp!([self as "const dlib::matrix<double,0,1>*"] -> CppBytes as "std::string" {
+                std::ostringstream stream;
+                dlib::serialize(*self, stream);
+                return stream.str();
+            })
+        };
+
+        bytes.to_vec()
+    }
+
+    /// Calculate the element-wise mean of a number of encodings.
+    ///
+    /// This can be used to build a single representative template from several photos of the same person.
+    /// Use [`FaceEncoding::normalized`] on the result if a unit-length template is required.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `encodings` is empty.
+    pub fn mean(encodings: &[FaceEncoding]) -> Self {
+        assert!(
+            !encodings.is_empty(),
+            "Cannot calculate the mean of zero encodings"
+        );
+
+        let len = encodings.len();
+        let encodings = encodings.as_ptr();
+
+        let inner = unsafe {
+            cpp!([encodings as "const dlib::matrix<double,0,1>*", len as "size_t"] -> FaceEncodingInner as "dlib::matrix<double,0,1>" {
+                dlib::matrix<double,0,1> sum = encodings[0];
+                for (size_t i = 1; i < len; ++i) {
+                    sum += encodings[i];
+                }
+
+                return sum / static_cast<double>(len);
+            })
+        };
+
+        Self { inner }
+    }
+
+    /// Return a copy of this encoding scaled to unit (L2) length.
+    ///
+    /// An encoding with zero length is returned unchanged.
+    pub fn normalized(&self) -> Self {
+        let inner = unsafe {
+            cpp!([self as "const dlib::matrix<double,0,1>*"] -> FaceEncodingInner as "dlib::matrix<double,0,1>" {
+                double length = dlib::length(*self);
+                if (length == 0) {
+                    return *self;
+                }
+
+                return *self / length;
+            })
+        };
+
+        Self { inner }
+    }
+
     /// Calculate the euclidean distance between two encodings.
     ///
     /// This value can be compared to a constant to determine if the faces are the same or not.
@@ -56,6 +190,112 @@ impl FaceEncoding {
         }
     }
 
+    /// Calculate the euclidean distances between this encoding and every encoding in a gallery.
+    ///
+    /// The distances are computed in a single call and are returned in the same order as the gallery.
+    pub fn distances(&self, gallery: &[FaceEncoding]) -> Vec<f64> {
+        let len = gallery.len();
+        let mut distances = vec![0f64; len];
+
+        unsafe {
+            let gallery = gallery.as_ptr();
+            let out = distances.as_mut_ptr();
+
+            cpp!([self as "const dlib::matrix<double,0,1>*", gallery as "const dlib::matrix<double,0,1>*", len as "size_t", out as "double*"] {
+                for (size_t i = 0; i < len; ++i) {
+                    out[i] = dlib::length(*self - gallery[i]);
+                }
+            });
+        }
+
+        distances
+    }
+
+    /// Find the closest encoding in a gallery.
+    ///
+    /// Returns the index of the closest encoding and its euclidean distance,
+    /// or `None` if the gallery is empty.
+    pub fn best_match(&self, gallery: &[FaceEncoding]) -> Option<(usize, f64)> {
+        let len = gallery.len();
+        let mut index = 0usize;
+        let mut distance = 0f64;
+
+        let found = unsafe {
+            let gallery = gallery.as_ptr();
+            let index = &mut index as *mut usize;
+            let distance = &mut distance as *mut f64;
+
+            cpp!([self as "const dlib::matrix<double,0,1>*", gallery as "const dlib::matrix<double,0,1>*", len as "size_t", index as "size_t*", distance as "double*"] -> bool as "bool" {
+                if (len == 0) {
+                    return false;
+                }
+
+                *index = 0;
+                *distance = dlib::length(*self - gallery[0]);
+
+                for (size_t i = 1; i < len; ++i) {
+                    double current = dlib::length(*self - gallery[i]);
+                    if (current < *distance) {
+                        *index = i;
+                        *distance = current;
+                    }
+                }
+
+                return true;
+            })
+        };
+
+        if found {
+            Some((index, distance))
+        } else {
+            None
+        }
+    }
+
+    /// Calculate the squared euclidean distance between two encodings.
+    pub fn squared_distance(&self, other: &Self) -> f64 {
+        unsafe {
+            cpp!([self as "const dlib::matrix<double,0,1>*", other as "const dlib::matrix<double,0,1>*"] -> f64 as "double" {
+                return dlib::length_squared(*self - *other);
+            })
+        }
+    }
+
+    /// Calculate the manhattan distance (sum of absolute differences) between two encodings.
+    pub fn manhattan_distance(&self, other: &Self) -> f64 {
+        unsafe {
+            cpp!([self as "const dlib::matrix<double,0,1>*", other as "const dlib::matrix<double,0,1>*"] -> f64 as "double" {
+                return dlib::sum(dlib::abs(*self - *other));
+            })
+        }
+    }
+
+    /// Calculate the cosine similarity between two encodings.
+    ///
+    /// `1.0` means the encodings point in the same direction, `-1.0` means they are opposite.
+    /// Returns `0.0` if either encoding has zero length.
+    pub fn cosine_similarity(&self, other: &Self) -> f64 {
+        unsafe {
+            cpp!([self as "const dlib::matrix<double,0,1>*", other as "const dlib::matrix<double,0,1>*"] -> f64 as "double" {
+                double lengths = dlib::length(*self) * dlib::length(*other);
+                if (lengths == 0) {
+                    return 0;
+                }
+                return dlib::dot(*self, *other) / lengths;
+            })
+        }
+    }
+
+    /// Calculate the distance between two encodings using the given metric.
+    pub fn distance_with(&self, other: &Self, metric: DistanceMetric) -> f64 {
+        match metric {
+            DistanceMetric::Euclidean => self.distance(other),
+            DistanceMetric::SquaredEuclidean => self.squared_distance(other),
+            DistanceMetric::Manhattan => self.manhattan_distance(other),
+            DistanceMetric::Cosine => 1.0 - self.cosine_similarity(other),
+        }
+    }
+
     pub fn to_elements(&self) -> [f64; 128] {
         let elements = [0f64; 128];
         unsafe {
@@ -96,6 +336,20 @@ impl Deref for FaceEncoding {
     }
 }
 
+impl From<[f64; 128]> for FaceEncoding {
+    fn from(elements: [f64; 128]) -> Self {
+        Self::new(&elements)
+    }
+}
+
+impl TryFrom<&[f64]> for FaceEncoding {
+    type Error = String;
+
+    fn try_from(elements: &[f64]) -> Result<Self, Self::Error> {
+        Self::from_slice(elements)
+    }
+}
+
 impl fmt::Debug for FaceEncoding {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         self.deref().fmt(fmt)
@@ -108,6 +362,46 @@ impl PartialEq for FaceEncoding {
     }
 }
 
+#[cfg(feature = "ndarray")]
+impl From<&FaceEncoding> for ndarray::Array1<f64> {
+    fn from(encoding: &FaceEncoding) -> Self {
+        ndarray::Array1::from(encoding.to_vec())
+    }
+}
+
+#[cfg(feature = "ndarray")]
+impl TryFrom<ndarray::ArrayView1<'_, f64>> for FaceEncoding {
+    type Error = String;
+
+    /// Copy an encoding from an array of 128 elements, which doesn't have to be contiguous.
+    fn try_from(elements: ndarray::ArrayView1<'_, f64>) -> Result<Self, Self::Error> {
+        let elements: Vec<f64> = elements.iter().copied().collect();
+        Self::from_slice(&elements)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for FaceEncoding {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for FaceEncoding {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+
+        let elements = Vec::<f64>::deserialize(deserializer)?;
+
+        if elements.len() != 128 {
+            return Err(D::Error::invalid_length(elements.len(), &"128 elements"));
+        }
+
+        Self::from_slice(&elements).map_err(D::Error::custom)
+    }
+}
+
 #[test]
 fn encoding_test() {
     let encoding_a = FaceEncoding::new_from_scalar(0.0);
@@ -119,6 +413,50 @@ fn encoding_test() {
     assert_eq!(encoding_a.distance(&encoding_b), 128.0_f64.sqrt());
 }
 
+#[test]
+fn distance_metrics() {
+    let encoding_a = FaceEncoding::new_from_scalar(1.0);
+    let encoding_b = FaceEncoding::new_from_scalar(3.0);
+
+    assert_eq!(encoding_a.squared_distance(&encoding_b), 512.0);
+    assert_eq!(encoding_a.manhattan_distance(&encoding_b), 256.0);
+    assert!((encoding_a.cosine_similarity(&encoding_b) - 1.0).abs() < 1e-12);
+    assert_eq!(
+        encoding_a.distance_with(&encoding_b, DistanceMetric::Euclidean),
+        encoding_a.distance(&encoding_b)
+    );
+}
+
+#[test]
+fn gallery_distances() {
+    let probe = FaceEncoding::new_from_scalar(0.0);
+    let gallery = vec![
+        FaceEncoding::new_from_scalar(2.0),
+        FaceEncoding::new_from_scalar(1.0),
+        FaceEncoding::new_from_scalar(3.0),
+    ];
+
+    let distances = probe.distances(&gallery);
+    assert_eq!(distances.len(), 3);
+    assert_eq!(distances[1], probe.distance(&gallery[1]));
+
+    assert_eq!(probe.best_match(&gallery), Some((1, 128.0_f64.sqrt())));
+    assert_eq!(probe.best_match(&[]), None);
+}
+
+#[test]
+fn mean_and_normalization() {
+    let mean = FaceEncoding::mean(&[
+        FaceEncoding::new_from_scalar(1.0),
+        FaceEncoding::new_from_scalar(3.0),
+    ]);
+    assert_eq!(mean, FaceEncoding::new_from_scalar(2.0));
+
+    let normalized = mean.normalized();
+    assert!((normalized.distance(&FaceEncoding::new_from_scalar(0.0)) - 1.0).abs() < 1e-12);
+    assert!((normalized.cosine_similarity(&mean) - 1.0).abs() < 1e-12);
+}
+
 #[test]
 fn can_convert_to_and_from_elements() {
     let mut elements = [0f64; 128];
@@ -127,3 +465,52 @@ fn can_convert_to_and_from_elements() {
     }
     assert_eq!(FaceEncoding::new(&elements).to_elements(), elements);
 }
+
+#[test]
+fn can_convert_from_slices() {
+    let elements = vec![0.25f64; 128];
+
+    let encoding = FaceEncoding::try_from(elements.as_slice()).unwrap();
+    assert_eq!(encoding, FaceEncoding::from([0.25f64; 128]));
+
+    assert!(FaceEncoding::from_slice(&elements[..127]).is_err());
+    assert!(FaceEncoding::from_slice(&[0.0; 129]).is_err());
+}
+
+#[test]
+fn can_round_trip_dlib_bytes() {
+    let encoding = FaceEncoding::new_from_scalar(0.75);
+    let bytes = encoding.to_dlib_bytes();
+
+    assert_eq!(FaceEncoding::from_dlib_bytes(&bytes).unwrap(), encoding);
+    assert!(FaceEncoding::from_dlib_bytes(&bytes[..bytes.len() / 2]).is_err());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn can_serialize_and_deserialize() {
+    let encoding = FaceEncoding::new_from_scalar(0.5);
+
+    let json = serde_json::to_string(&encoding).unwrap();
+    let decoded: FaceEncoding = serde_json::from_str(&json).unwrap();
+    assert_eq!(decoded, encoding);
+
+    assert!(serde_json::from_str::<FaceEncoding>("[0.0, 1.0]").is_err());
+}
+
+#[cfg(feature = "ndarray")]
+#[test]
+fn ndarray_round_trip() {
+    let encoding = FaceEncoding::new_from_scalar(0.5);
+
+    let array = ndarray::Array1::from(&encoding);
+    assert_eq!(array.len(), 128);
+    assert_eq!(FaceEncoding::try_from(array.view()).unwrap(), encoding);
+
+    // every other element of a longer array
+    let strided = ndarray::Array1::from_elem(256, 0.5);
+    let strided = strided.slice(ndarray::s![..;2]);
+    assert_eq!(FaceEncoding::try_from(strided).unwrap(), encoding);
+
+    assert!(FaceEncoding::try_from(ndarray::Array1::<f64>::zeros(5).view()).is_err());
+}
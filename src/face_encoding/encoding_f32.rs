@@ -0,0 +1,121 @@
+use std::fmt;
+use std::ops::Deref;
+
+use super::encoding::FaceEncoding;
+
+/// A face encoding stored in single precision.
+///
+/// Takes half the memory of a [`FaceEncoding`] and lives entirely on the Rust side,
+/// which makes it a better fit for very large galleries. The loss of precision has no
+/// practical effect on matching.
+#[derive(Copy, Clone)]
+pub struct FaceEncodingF32([f32; 128]);
+
+impl FaceEncodingF32 {
+    /// Create a new encoding from its elements.
+    pub fn new(elements: [f32; 128]) -> Self {
+        Self(elements)
+    }
+
+    /// Calculate the euclidean distance between two encodings.
+    pub fn distance(&self, other: &Self) -> f32 {
+        self.squared_distance(other).sqrt()
+    }
+
+    /// Calculate the squared euclidean distance between two encodings.
+    pub fn squared_distance(&self, other: &Self) -> f32 {
+        self.0
+            .iter()
+            .zip(other.0.iter())
+            .map(|(a, b)| (a - b) * (a - b))
+            .sum()
+    }
+
+    /// Convert back into a double precision encoding.
+    pub fn to_f64(&self) -> FaceEncoding {
+        FaceEncoding::from(self)
+    }
+
+    pub fn to_elements(&self) -> [f32; 128] {
+        self.0
+    }
+}
+
+impl From<&FaceEncoding> for FaceEncodingF32 {
+    fn from(encoding: &FaceEncoding) -> Self {
+        let mut elements = [0f32; 128];
+        for (element, value) in elements.iter_mut().zip(encoding.iter()) {
+            *element = *value as f32;
+        }
+
+        Self(elements)
+    }
+}
+
+impl From<&FaceEncodingF32> for FaceEncoding {
+    fn from(encoding: &FaceEncodingF32) -> Self {
+        let mut elements = [0f64; 128];
+        for (element, value) in elements.iter_mut().zip(encoding.0.iter()) {
+            *element = *value as f64;
+        }
+
+        FaceEncoding::new(&elements)
+    }
+}
+
+impl Deref for FaceEncodingF32 {
+    type Target = [f32];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl fmt::Debug for FaceEncodingF32 {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        self.deref().fmt(fmt)
+    }
+}
+
+impl PartialEq for FaceEncodingF32 {
+    fn eq(&self, other: &Self) -> bool {
+        self.deref().eq(other.deref())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for FaceEncodingF32 {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for FaceEncodingF32 {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+
+        let elements = Vec::<f32>::deserialize(deserializer)?;
+
+        if elements.len() != 128 {
+            return Err(D::Error::invalid_length(elements.len(), &"128 elements"));
+        }
+
+        let mut array = [0f32; 128];
+        array.copy_from_slice(&elements);
+
+        Ok(Self(array))
+    }
+}
+
+#[test]
+fn can_convert_between_precisions() {
+    let encoding = FaceEncoding::new_from_scalar(0.5);
+    let single = FaceEncodingF32::from(&encoding);
+
+    assert_eq!(single.to_f64(), encoding);
+    assert_eq!(
+        single.distance(&FaceEncodingF32::new([1.5; 128])),
+        128.0_f32.sqrt()
+    );
+}
@@ -8,6 +8,19 @@ cpp_class!(
     pub unsafe struct FaceEncodings as "std::vector<dlib::matrix<double,0,1>>"
 );
 
+impl FaceEncodings {
+    pub(crate) fn from_encodings(encodings: &[FaceEncoding]) -> Self {
+        let len = encodings.len();
+        let pointer = encodings.as_ptr();
+
+        unsafe {
+            cpp!([pointer as "const dlib::matrix<double,0,1>*", len as "size_t"] -> FaceEncodings as "std::vector<dlib::matrix<double,0,1>>" {
+                return std::vector<dlib::matrix<double,0,1>>(pointer, pointer + len);
+            })
+        }
+    }
+}
+
 impl Deref for FaceEncodings {
     type Target = [FaceEncoding];
 
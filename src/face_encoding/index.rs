@@ -0,0 +1,317 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use super::encoding::FaceEncoding;
+use super::set::EncodingSet;
+
+const MAGIC: &[u8] = b"DFRIDX";
+const VERSION: u32 = 1;
+
+#[derive(Copy, Clone, Default)]
+struct Node {
+    dimension: usize,
+    left: Option<usize>,
+    right: Option<usize>,
+}
+
+struct Candidate {
+    index: usize,
+    squared_distance: f64,
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.squared_distance
+            .partial_cmp(&other.squared_distance)
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+fn squared_distance(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(a, b)| (a - b) * (a - b)).sum()
+}
+
+/// A KD-tree index over encodings for nearest neighbour search.
+///
+/// Encodings can be inserted incrementally and are identified by the order they were inserted in.
+/// [`EncodingIndex::search`] is exact, while [`EncodingIndex::search_approximate`] bounds the number
+/// of encodings visited, trading accuracy for speed on very large galleries.
+#[derive(Clone, Default)]
+pub struct EncodingIndex {
+    encodings: EncodingSet,
+    nodes: Vec<Node>,
+}
+
+impl EncodingIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// The encodings in the index, in insertion order.
+    pub fn encodings(&self) -> &EncodingSet {
+        &self.encodings
+    }
+
+    /// Insert an encoding into the index, returning its index.
+    ///
+    /// Returns an error if the encoding does not have 128 elements.
+    pub fn insert(&mut self, encoding: &FaceEncoding) -> Result<usize, String> {
+        let index = self.nodes.len();
+        self.encodings.push(encoding)?;
+
+        if index == 0 {
+            self.nodes.push(Node::default());
+            return Ok(index);
+        }
+
+        let point = self.encodings.get(index).unwrap();
+        let mut current = 0;
+        let mut depth = 0;
+
+        loop {
+            let node = self.nodes[current];
+            let parent = self.encodings.get(current).unwrap();
+            depth += 1;
+
+            let child = if point[node.dimension] < parent[node.dimension] {
+                &mut self.nodes[current].left
+            } else {
+                &mut self.nodes[current].right
+            };
+
+            match *child {
+                Some(next) => current = next,
+                None => {
+                    *child = Some(index);
+                    break;
+                }
+            }
+        }
+
+        self.nodes.push(Node {
+            dimension: depth % point.len(),
+            left: None,
+            right: None,
+        });
+
+        Ok(index)
+    }
+
+    /// Find the `k` nearest encodings to a probe.
+    ///
+    /// Returns `(index, distance)` pairs sorted from closest to furthest.
+    pub fn search(&self, probe: &FaceEncoding, k: usize) -> Vec<(usize, f64)> {
+        self.search_approximate(probe, k, usize::MAX)
+    }
+
+    /// Find approximately the `k` nearest encodings to a probe, visiting at most `max_checks` encodings.
+    ///
+    /// Returns `(index, distance)` pairs sorted from closest to furthest.
+    pub fn search_approximate(
+        &self,
+        probe: &FaceEncoding,
+        k: usize,
+        max_checks: usize,
+    ) -> Vec<(usize, f64)> {
+        if k == 0 || self.is_empty() {
+            return Vec::new();
+        }
+
+        let mut heap = BinaryHeap::with_capacity(k + 1);
+        let mut stack = vec![(0, 0f64)];
+        let mut checks = 0;
+
+        while let Some((current, bound)) = stack.pop() {
+            if heap.len() == k
+                && bound >= heap.peek().map_or(0.0, |c: &Candidate| c.squared_distance)
+            {
+                continue;
+            }
+
+            if checks == max_checks {
+                break;
+            }
+            checks += 1;
+
+            let point = self.encodings.get(current).unwrap();
+            let node = self.nodes[current];
+
+            heap.push(Candidate {
+                index: current,
+                squared_distance: squared_distance(point, probe),
+            });
+            if heap.len() > k {
+                heap.pop();
+            }
+
+            let difference = probe[node.dimension] - point[node.dimension];
+            let (near, far) = if difference < 0.0 {
+                (node.left, node.right)
+            } else {
+                (node.right, node.left)
+            };
+
+            if let Some(far) = far {
+                stack.push((far, bound.max(difference * difference)));
+            }
+            if let Some(near) = near {
+                stack.push((near, bound));
+            }
+        }
+
+        heap.into_sorted_vec()
+            .into_iter()
+            .map(|candidate| (candidate.index, candidate.squared_distance.sqrt()))
+            .collect()
+    }
+
+    /// Write the index to a stream.
+    pub fn write_to<W: Write>(&self, mut writer: W) -> Result<(), String> {
+        let io_error = |error: std::io::Error| error.to_string();
+
+        writer.write_all(MAGIC).map_err(io_error)?;
+        writer.write_all(&VERSION.to_le_bytes()).map_err(io_error)?;
+        writer
+            .write_all(&(self.len() as u64).to_le_bytes())
+            .map_err(io_error)?;
+
+        for elements in self.encodings.iter() {
+            for element in elements {
+                writer.write_all(&element.to_le_bytes()).map_err(io_error)?;
+            }
+        }
+
+        writer.flush().map_err(io_error)
+    }
+
+    /// Read an index previously written with [`EncodingIndex::write_to`].
+    pub fn read_from<R: Read>(mut reader: R) -> Result<Self, String> {
+        let io_error = |error: std::io::Error| error.to_string();
+
+        let mut magic = [0u8; 6];
+        reader.read_exact(&mut magic).map_err(io_error)?;
+        if magic != MAGIC {
+            return Err("Not an encoding index".into());
+        }
+
+        let mut version = [0u8; 4];
+        reader.read_exact(&mut version).map_err(io_error)?;
+        let version = u32::from_le_bytes(version);
+        if version != VERSION {
+            return Err(format!("Unsupported encoding index version {}", version));
+        }
+
+        let mut len = [0u8; 8];
+        reader.read_exact(&mut len).map_err(io_error)?;
+        let len = u64::from_le_bytes(len);
+
+        let mut index = Self::new();
+        let mut buffer = [0u8; 8];
+        let mut elements = [0f64; 128];
+
+        for _ in 0..len {
+            for element in elements.iter_mut() {
+                reader.read_exact(&mut buffer).map_err(io_error)?;
+                *element = f64::from_le_bytes(buffer);
+            }
+
+            // Re-inserting in the original order rebuilds exactly the same tree.
+            index.insert(&FaceEncoding::new(&elements))?;
+        }
+
+        Ok(index)
+    }
+
+    /// Save the index to a file.
+    pub fn save<P: AsRef<Path>>(&self, filename: P) -> Result<(), String> {
+        let file = File::create(filename.as_ref()).map_err(|error| {
+            format!(
+                "Failed to create '{}': {}",
+                filename.as_ref().display(),
+                error
+            )
+        })?;
+
+        self.write_to(BufWriter::new(file))
+    }
+
+    /// Load an index from a file.
+    pub fn load<P: AsRef<Path>>(filename: P) -> Result<Self, String> {
+        let file = File::open(filename.as_ref()).map_err(|error| {
+            format!(
+                "Failed to open '{}': {}",
+                filename.as_ref().display(),
+                error
+            )
+        })?;
+
+        Self::read_from(BufReader::new(file))
+    }
+}
+
+#[test]
+fn search_matches_brute_force() {
+    let mut seed = 12345u64;
+    let mut random = move || {
+        seed = seed
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (seed >> 33) as f64 / (1u64 << 31) as f64 - 0.5
+    };
+
+    let mut encodings = Vec::new();
+    let mut index = EncodingIndex::new();
+    for _ in 0..200 {
+        let mut elements = [0f64; 128];
+        for element in elements.iter_mut() {
+            *element = random();
+        }
+        let encoding = FaceEncoding::new(&elements);
+        index.insert(&encoding).unwrap();
+        encodings.push(encoding);
+    }
+
+    let probe = encodings[17].clone();
+    let mut expected: Vec<(usize, f64)> = probe
+        .distances(&encodings)
+        .into_iter()
+        .enumerate()
+        .collect();
+    expected.sort_by(|(_, x), (_, y)| x.total_cmp(y));
+
+    let found = index.search(&probe, 5);
+    assert_eq!(
+        found.iter().map(|(i, _)| *i).collect::<Vec<_>>(),
+        expected[..5].iter().map(|(i, _)| *i).collect::<Vec<_>>()
+    );
+
+    let mut buffer = Vec::new();
+    index.write_to(&mut buffer).unwrap();
+    let loaded = EncodingIndex::read_from(buffer.as_slice()).unwrap();
+    assert_eq!(loaded.search(&probe, 5), found);
+}
@@ -1,13 +1,36 @@
 //! Face encoding structs.
 
 mod base;
+mod calibration;
 mod compare;
+mod database;
 mod encoding;
+mod encoding_f32;
 mod encodings;
+mod index;
 mod nn;
+pub mod numpy;
+#[cfg(feature = "onnx")]
+mod onnx;
+mod pairwise;
+mod pca;
+mod quantized;
+mod set;
+mod verify;
 
 pub use self::base::FaceEncoderTrait;
+pub use self::calibration::DistanceCalibration;
 pub use self::compare::FaceComparer;
-pub use self::encoding::FaceEncoding;
+pub use self::database::FaceDatabase;
+pub use self::encoding::{DistanceMetric, FaceEncoding};
+pub use self::encoding_f32::FaceEncodingF32;
 pub use self::encodings::FaceEncodings;
-pub use self::nn::FaceEncoderNetwork;
+pub use self::index::EncodingIndex;
+pub use self::nn::{FaceEncoderNetwork, MetricTrainingOptions};
+#[cfg(feature = "onnx")]
+pub use self::onnx::{OnnxChipModel, OnnxEncoderOptions, OnnxFaceEncoder};
+pub use self::pairwise::{pairwise_distances, DistanceMatrix};
+pub use self::pca::PcaProjector;
+pub use self::quantized::FaceEncodingQ8;
+pub use self::set::EncodingSet;
+pub use self::verify::{verify, Verification, DEFAULT_MATCH_THRESHOLD};
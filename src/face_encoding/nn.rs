@@ -1,15 +1,78 @@
 use std::path::Path;
+use std::sync::Mutex;
 
 use super::base::FaceEncoderTrait;
+use super::encoding_f32::FaceEncodingF32;
 use super::encodings::FaceEncodings;
-use crate::base::path_as_cstring;
+use crate::base::{path_as_cstring, CppBytes};
+use crate::chip_model::ChipModel;
+use crate::face_alignment::{face_chips, ChipOptions};
 use crate::landmark_prediction::FaceLandmarks;
 use crate::matrix::ImageMatrix;
 
+// The width and height of the face chips the network takes as input.
+const CHIP_SIZE: usize = 150;
+
+/// Options for fine-tuning a [`FaceEncoderNetwork`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[repr(C)]
+pub struct MetricTrainingOptions {
+    pub learning_rate: f64,
+    /// Training stops once the learning rate has been lowered below this.
+    pub min_learning_rate: f64,
+    pub weight_decay: f64,
+    pub momentum: f64,
+    /// How many steps the loss can go without improving before the learning rate is lowered.
+    pub iterations_without_progress: usize,
+    /// The most steps to train for, whatever the learning rate.
+    pub max_iterations: usize,
+    /// How many different identities each mini-batch contains.
+    pub identities_per_batch: usize,
+    /// How many chips of each identity each mini-batch contains.
+    pub images_per_identity: usize,
+    /// Only train the final fully connected layer, leaving the rest of the network as it is.
+    ///
+    /// This needs much less data than training the whole network, and is far less likely to make it worse.
+    pub final_layer_only: bool,
+}
+
+impl Default for MetricTrainingOptions {
+    fn default() -> Self {
+        Self {
+            learning_rate: 0.0001,
+            min_learning_rate: 0.000001,
+            weight_decay: 0.0001,
+            momentum: 0.9,
+            iterations_without_progress: 300,
+            max_iterations: 10000,
+            identities_per_batch: 8,
+            images_per_identity: 4,
+            final_layer_only: true,
+        }
+    }
+}
+
 /// A face encoding network.
-#[derive(Clone)]
+///
+/// Encoding writes through the network's layers, so it holds a lock for the whole batch and threads sharing
+/// an encoder encode one after another. Threads that need to encode at the same time should each have a
+/// clone, which copies the model.
 pub struct FaceEncoderNetwork {
-    inner: FaceEncoderNetworkInner,
+    inner: Mutex<FaceEncoderNetworkInner>,
+    jitter_seed: Option<u64>,
+    batch_size: usize,
+    chip_options: ChipOptions,
+}
+
+impl Clone for FaceEncoderNetwork {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Mutex::new(self.inner.lock().unwrap().clone()),
+            jitter_seed: self.jitter_seed,
+            batch_size: self.batch_size,
+            chip_options: self.chip_options,
+        }
+    }
 }
 
 cpp_class!(unsafe struct FaceEncoderNetworkInner as "face_encoding_nn");
@@ -41,11 +104,364 @@ impl FaceEncoderNetwork {
                 filename.as_ref().display()
             ))
         } else {
-            Ok(Self { inner })
+            Ok(Self::from_inner(inner))
+        }
+    }
+
+    /// Deserialize the face encoding network from a model file that has already been read into memory, e.g. with `include_bytes!`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let inner = FaceEncoderNetworkInner::default();
+
+        let deserialized = unsafe {
+            let data = bytes.as_ptr();
+            let len = bytes.len();
+            let network = &inner;
+
+            cpp!([data as "const char*", len as "size_t", network as "face_encoding_nn*"] -> bool as "bool" {
+                try {
+                    std::istringstream stream(std::string(data, len));
+                    dlib::deserialize(*network, stream);
+                    return true;
+                } catch (const dlib::error& exception) {
+                    return false;
+                }
+            })
+        };
+
+        if !deserialized {
+            Err("Failed to deserialize face encoder network".into())
+        } else {
+            Ok(Self::from_inner(inner))
+        }
+    }
+
+    fn from_inner(inner: FaceEncoderNetworkInner) -> Self {
+        Self {
+            inner: Mutex::new(inner),
+            jitter_seed: None,
+            batch_size: 16,
+            chip_options: ChipOptions::default(),
+        }
+    }
+
+    /// Seed the random jitter applied when `num_jitters` is above 1, so that the same face always gets the
+    /// same encoding. Without a seed, jittered encodings vary slightly between calls.
+    pub fn set_jitter_seed(&mut self, seed: Option<u64>) {
+        self.jitter_seed = seed;
+    }
+
+    pub fn jitter_seed(&self) -> Option<u64> {
+        self.jitter_seed
+    }
+
+    /// Set how many face chips are run through the network at once. Larger batches are faster, especially on
+    /// a gpu, but use more memory. Defaults to 16.
+    pub fn set_batch_size(&mut self, batch_size: usize) {
+        self.batch_size = batch_size.max(1);
+    }
+
+    pub fn batch_size(&self) -> usize {
+        self.batch_size
+    }
+
+    /// Set how face chips are extracted before being encoded.
+    ///
+    /// The padding can be changed freely, e.g. for a network fine-tuned on tighter crops, but the size can't:
+    /// dlib fixes the input size of a network in its type, and the one this crate is built with takes
+    /// 150x150 chips, so any other size is an error. Models that expect other sizes, such as 112x112, can be
+    /// run as a [`ChipModel`] with their own [`ChipOptions`] instead.
+    pub fn set_chip_options(&mut self, options: ChipOptions) -> Result<(), String> {
+        options.validate()?;
+
+        if options.size != CHIP_SIZE as u64 {
+            return Err(format!(
+                "The face encoder network takes {}x{} chips, not {}x{}",
+                CHIP_SIZE, CHIP_SIZE, options.size, options.size
+            ));
+        }
+
+        self.chip_options = options;
+        Ok(())
+    }
+
+    pub fn chip_options(&self) -> ChipOptions {
+        self.chip_options
+    }
+
+    /// Encode face chips that have already been aligned and cropped, e.g. by [`crate::face_alignment::face_chip`].
+    ///
+    /// Every chip is run through the network in as few batches as possible. Fails if any of the chips isn't 150x150.
+    pub fn encode_chips(&self, chips: &[ImageMatrix]) -> Result<FaceEncodings, String> {
+        check_chip_sizes(chips)?;
+
+        let num_chips = chips.len();
+        let chips = chips.as_ptr();
+        let batch_size = self.batch_size;
+        let mut inner = self.inner.lock().unwrap();
+        let net = &mut *inner;
+
+        Ok(unsafe {
+            cpp!([
+                    net as "face_encoding_nn*",
+                    chips as "const dlib::matrix<dlib::rgb_pixel>*",
+                    num_chips as "size_t",
+                    batch_size as "size_t"
+                ] -> FaceEncodings as "std::vector<dlib::matrix<double,0,1>>" {
+                std::vector<dlib::matrix<dlib::rgb_pixel>> batch(chips, chips + num_chips);
+
+                std::vector<dlib::matrix<double,0,1>> encodings;
+                encodings.reserve(num_chips);
+
+                for (auto& float_encoding : (*net)(batch, batch_size)) {
+                    encodings.push_back(dlib::matrix_cast<double>(float_encoding));
+                }
+
+                return encodings;
+            })
+        })
+    }
+
+    /// Encode every face from several images in one go, returning the encodings for each image in turn.
+    ///
+    /// This is the same as calling [`FaceEncoderTrait::get_face_encodings`] on each image without any jitter, but
+    /// all of the faces share forward passes through the network, which is much faster for lots of small groups
+    /// of faces such as video frames.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any of the landmarks don't have either 68 or 5 points.
+    pub fn get_face_encodings_batch(
+        &self,
+        frames: &[(&ImageMatrix, &[FaceLandmarks])],
+    ) -> Vec<FaceEncodings> {
+        let chips: Vec<ImageMatrix> = frames
+            .iter()
+            .flat_map(|(image, landmarks)| {
+                face_chips(image, landmarks, &self.chip_options)
+                    .unwrap_or_else(|error| panic!("{}", error))
+            })
+            .collect();
+
+        let encodings = self
+            .encode_chips(&chips)
+            .expect("Face chips are always the right size");
+
+        let mut offset = 0;
+        frames
+            .iter()
+            .map(|(_, landmarks)| {
+                let frame =
+                    FaceEncodings::from_encodings(&encodings[offset..offset + landmarks.len()]);
+                offset += landmarks.len();
+                frame
+            })
+            .collect()
+    }
+}
+
+impl FaceEncoderNetwork {
+    /// Encode faces, also returning the aligned chip each encoding was computed from.
+    ///
+    /// The chips are exactly what the network sees (before any jitter), which makes them useful for working out
+    /// why two faces did or didn't match, or for keeping a record of what was encoded.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any of the landmarks don't have either 68 or 5 points.
+    pub fn get_face_encodings_with_chips(
+        &self,
+        image: &ImageMatrix,
+        landmarks: &[FaceLandmarks],
+        num_jitters: u32,
+    ) -> (FaceEncodings, Vec<ImageMatrix>) {
+        let encodings = self.get_face_encodings(image, landmarks, num_jitters);
+        let chips = face_chips(image, landmarks, &self.chip_options)
+            .unwrap_or_else(|error| panic!("{}", error));
+
+        (encodings, chips)
+    }
+
+    /// Encode faces in single precision, without jitter.
+    ///
+    /// The network itself always runs in single precision, on the CPU and with CUDA alike, and
+    /// [`FaceEncoderTrait::get_face_encodings`] widens its output to doubles. This skips that step and
+    /// the dlib-side storage, so the encodings can go straight into galleries of [`FaceEncodingF32`]. The
+    /// encodings are identical to narrowing the double precision ones. dlib has no half precision
+    /// support, so this is the lowest precision the network can run at.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any of the landmarks don't have either 68 or 5 points.
+    pub fn get_face_encodings_f32(
+        &self,
+        image: &ImageMatrix,
+        landmarks: &[FaceLandmarks],
+    ) -> Vec<FaceEncodingF32> {
+        let chips = face_chips(image, landmarks, &self.chip_options)
+            .unwrap_or_else(|error| panic!("{}", error));
+        let mut elements = vec![0f32; chips.len() * 128];
+
+        let num_chips = chips.len();
+        let chips = chips.as_ptr();
+        let out = elements.as_mut_ptr();
+        let batch_size = self.batch_size;
+        let mut inner = self.inner.lock().unwrap();
+        let net = &mut *inner;
+
+        unsafe {
+            cpp!([
+                    net as "face_encoding_nn*",
+                    chips as "const dlib::matrix<dlib::rgb_pixel>*",
+                    num_chips as "size_t",
+                    batch_size as "size_t",
+                    out as "float*"
+                ] {
+                std::vector<dlib::matrix<dlib::rgb_pixel>> batch(chips, chips + num_chips);
+
+                float* cursor = out;
+                for (auto& encoding : (*net)(batch, batch_size)) {
+                    std::copy(encoding.begin(), encoding.end(), cursor);
+                    cursor += 128;
+                }
+            })
+        }
+
+        elements
+            .chunks_exact(128)
+            .map(|chunk| {
+                let mut array = [0f32; 128];
+                array.copy_from_slice(chunk);
+                FaceEncodingF32::new(array)
+            })
+            .collect()
+    }
+}
+
+impl FaceEncoderNetwork {
+    /// Fine-tune the network on face chips labelled with the identity they belong to.
+    ///
+    /// The chips should be extracted with [`face_chips`] and the default [`ChipOptions`], so they match what
+    /// the network sees when encoding. Training uses dlib's metric loss, pulling the encodings of the same
+    /// identity together and pushing different identities apart, so only identities with at least two chips
+    /// are used. This can take a long time, especially without CUDA.
+    ///
+    /// Fails if any of the chips isn't 150x150, or if there aren't at least two identities with two or more chips.
+    pub fn fine_tune(
+        &mut self,
+        chips: &[ImageMatrix],
+        labels: &[u64],
+        options: &MetricTrainingOptions,
+    ) -> Result<(), String> {
+        if chips.len() != labels.len() {
+            return Err(format!(
+                "Got {} chips but {} labels",
+                chips.len(),
+                labels.len()
+            ));
+        }
+
+        if options.identities_per_batch < 2 || options.images_per_identity < 2 {
+            return Err("Mini-batches need at least two chips from each of two identities".into());
+        }
+
+        check_chip_sizes(chips)?;
+
+        let net = self.inner.get_mut().unwrap();
+        let error = CppBytes::default();
+
+        let trained = unsafe {
+            let len = chips.len();
+            let chips = chips.as_ptr();
+            let labels = labels.as_ptr();
+            let error = &error;
+
+            cpp!([net as "face_encoding_nn*", chips as "const dlib::matrix<dlib::rgb_pixel>*", labels as "const uint64_t*", len as "size_t", options as "const metric_training_options*", error as "std::string*"] -> bool as "bool" {
+                std::vector<dlib::matrix<dlib::rgb_pixel>> chip_copies(chips, chips + len);
+                std::vector<unsigned long> label_copies(labels, labels + len);
+
+                try {
+                    fine_tune_face_encoder(*net, chip_copies, label_copies, *options);
+                    return true;
+                } catch (const std::exception& exception) {
+                    *error = exception.what();
+                    return false;
+                }
+            })
+        };
+
+        if !trained {
+            Err(format!(
+                "Failed to fine-tune face encoder network: {}",
+                String::from_utf8_lossy(&error)
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Save the network to a file so it can be loaded later with [`FaceEncoderNetwork::new`].
+    pub fn save<P: AsRef<Path>>(&self, filename: P) -> Result<(), String> {
+        let string = path_as_cstring(filename.as_ref())?;
+        let mut inner = self.inner.lock().unwrap();
+        let net = &mut *inner;
+
+        let serialized = unsafe {
+            let filename = string.as_ptr();
+
+            cpp!([filename as "char*", net as "face_encoding_nn*"] -> bool as "bool" {
+                try {
+                    dlib::serialize(filename) << *net;
+                    return true;
+                } catch (const dlib::error& exception) {
+                    return false;
+                }
+            })
+        };
+
+        if !serialized {
+            Err(format!(
+                "Failed to serialize '{}'",
+                filename.as_ref().display()
+            ))
+        } else {
+            Ok(())
         }
     }
 }
 
+fn check_chip_sizes(chips: &[ImageMatrix]) -> Result<(), String> {
+    for (index, chip) in chips.iter().enumerate() {
+        if chip.width() != CHIP_SIZE || chip.height() != CHIP_SIZE {
+            return Err(format!(
+                "Face chips must be {}x{}, but chip {} is {}x{}",
+                CHIP_SIZE,
+                CHIP_SIZE,
+                index,
+                chip.width(),
+                chip.height()
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+impl ChipModel for FaceEncoderNetwork {
+    fn chip_options(&self) -> ChipOptions {
+        self.chip_options
+    }
+
+    /// Encode the chips, as with [`FaceEncoderNetwork::encode_chips`].
+    fn run_chips(&self, chips: &[ImageMatrix]) -> Result<Vec<Vec<f32>>, String> {
+        let encodings = self.encode_chips(chips)?;
+
+        Ok(encodings
+            .iter()
+            .map(|encoding| encoding.iter().map(|&value| value as f32).collect())
+            .collect())
+    }
+}
+
 #[cfg(feature = "embed-fe-nn")]
 impl Default for FaceEncoderNetwork {
     fn default() -> Self {
@@ -56,24 +472,47 @@ impl Default for FaceEncoderNetwork {
     }
 }
 
-impl FaceEncoderTrait for FaceEncoderNetwork {
-    fn get_face_encodings(
-        &mut self,
+impl FaceEncoderNetwork {
+    /// Like [`FaceEncoderTrait::get_face_encodings`], but returns an error rather than panicking if any of the
+    /// landmarks don't have either 68 or 5 points.
+    pub fn try_get_face_encodings(
+        &self,
         image: &ImageMatrix,
         landmarks: &[FaceLandmarks],
         num_jitters: u32,
-    ) -> FaceEncodings {
+    ) -> Result<FaceEncodings, String> {
+        for (index, face) in landmarks.iter().enumerate() {
+            if face.num_parts() != 68 && face.num_parts() != 5 {
+                return Err(format!(
+                    "Face encoding requires 68 or 5 landmarks, but face {} has {}",
+                    index,
+                    face.num_parts()
+                ));
+            }
+        }
+
         let num_faces = landmarks.len();
         let landmarks = landmarks.as_ptr();
-        let net = &self.inner;
+        let seeded = self.jitter_seed.is_some();
+        let seed = self.jitter_seed.unwrap_or(0);
+        let batch_size = self.batch_size;
+        let chip_size = self.chip_options.size;
+        let chip_padding = self.chip_options.padding;
+        let mut inner = self.inner.lock().unwrap();
+        let net = &mut *inner;
 
-        unsafe {
+        let encodings = unsafe {
             cpp!([
                     net as "face_encoding_nn*",
                     image as "dlib::matrix<dlib::rgb_pixel>*",
                     landmarks as "dlib::full_object_detection*",
                     num_faces as "size_t",
-                    num_jitters as "uint"
+                    num_jitters as "uint",
+                    seeded as "bool",
+                    seed as "uint64_t",
+                    batch_size as "size_t",
+                    chip_size as "uint64_t",
+                    chip_padding as "double"
                 ] -> FaceEncodings as "std::vector<dlib::matrix<double,0,1>>" {
                 std::vector<dlib::matrix<double,0,1>> encodings;
                 encodings.reserve(num_faces);
@@ -85,7 +524,7 @@ impl FaceEncoderTrait for FaceEncoderNetwork {
 
                 dlib::array<dlib::matrix<dlib::rgb_pixel>> face_chips;
                 for (size_t offset = 0; offset < num_faces; offset++) {
-                    dlib::chip_details details = dlib::get_face_chip_details(*(landmarks + offset), 150, 0.25);
+                    dlib::chip_details details = dlib::get_face_chip_details(*(landmarks + offset), chip_size, chip_padding);
                     dets.push_back(details);
                 }
                 dlib::extract_image_chips(*image, dets, face_chips);
@@ -93,13 +532,16 @@ impl FaceEncoderTrait for FaceEncoderNetwork {
                 // extract descriptors and convert from float vectors to double vectors
 
                 if (num_jitters <= 1) {
-                    auto network_output = (*net)(face_chips, 16);
+                    auto network_output = (*net)(face_chips, batch_size);
                     for (auto& float_encoding : network_output) {
                         encodings.push_back((dlib::matrix_cast<double>(float_encoding)));
                     }
                 } else {
+                    dlib::rand seeded_generator(static_cast<time_t>(seed));
+
                     for (auto& chip : face_chips) {
-                        auto network_output = (*net)(jitter_image(chip, num_jitters), 16);
+                        auto jittered = seeded ? jitter_image(chip, num_jitters, seeded_generator) : jitter_image(chip, num_jitters);
+                        auto network_output = (*net)(jittered, batch_size);
                         dlib::matrix<float,0,1> float_encoding = dlib::mean(dlib::mat(network_output));
 
                         encodings.push_back(dlib::matrix_cast<double>(float_encoding));
@@ -108,6 +550,20 @@ impl FaceEncoderTrait for FaceEncoderNetwork {
 
                 return encodings;
             })
-        }
+        };
+
+        Ok(encodings)
+    }
+}
+
+impl FaceEncoderTrait for FaceEncoderNetwork {
+    fn get_face_encodings(
+        &self,
+        image: &ImageMatrix,
+        landmarks: &[FaceLandmarks],
+        num_jitters: u32,
+    ) -> FaceEncodings {
+        self.try_get_face_encodings(image, landmarks, num_jitters)
+            .unwrap_or_else(|error| panic!("{}", error))
     }
 }
@@ -0,0 +1,331 @@
+//! Reading and writing encodings in NumPy's `.npy` (and, with the `npz` feature, `.npz`) formats.
+//!
+//! The Python `face_recognition` package produces encodings as `float64` NumPy arrays, either a single
+//! array of shape `(128,)` or a stacked array of shape `(n, 128)`. Galleries saved with `numpy.save`
+//! or `numpy.savez` can be loaded with the functions here, and encodings written by them can be read
+//! back with `numpy.load`.
+
+use std::convert::TryInto;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use super::encoding::FaceEncoding;
+
+const MAGIC: &[u8] = b"\x93NUMPY";
+
+#[derive(Copy, Clone)]
+enum Element {
+    F32,
+    F64,
+}
+
+struct Header {
+    element: Element,
+    fortran_order: bool,
+    shape: Vec<usize>,
+}
+
+fn parse_header(header: &str) -> Result<Header, String> {
+    fn value_of<'a>(header: &'a str, key: &str) -> Result<&'a str, String> {
+        let key = format!("'{}'", key);
+        let start = header
+            .find(&key)
+            .ok_or_else(|| format!("Missing {} in npy header", key))?;
+        let rest = &header[start + key.len()..];
+        let colon = rest
+            .find(':')
+            .ok_or_else(|| format!("Malformed {} in npy header", key))?;
+        Ok(rest[colon + 1..].trim_start())
+    }
+
+    let descr = value_of(header, "descr")?;
+    let descr = descr
+        .get(1..)
+        .and_then(|descr| descr.split(['\'', '"']).next())
+        .ok_or_else(|| "Malformed descr in npy header".to_string())?;
+    let element = match descr {
+        "<f8" | "=f8" => Element::F64,
+        "<f4" | "=f4" => Element::F32,
+        other => return Err(format!("Unsupported npy element type '{}'", other)),
+    };
+
+    let fortran_order = value_of(header, "fortran_order")?.starts_with("True");
+
+    let shape = value_of(header, "shape")?;
+    let end = shape
+        .find(')')
+        .ok_or_else(|| "Malformed shape in npy header".to_string())?;
+    let shape = shape[..end]
+        .trim_start_matches('(')
+        .split(',')
+        .map(str::trim)
+        .filter(|dim| !dim.is_empty())
+        .map(|dim| {
+            dim.trim_end_matches('L')
+                .parse::<usize>()
+                .map_err(|_| format!("Malformed dimension '{}' in npy header", dim))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(Header {
+        element,
+        fortran_order,
+        shape,
+    })
+}
+
+fn io_error(error: std::io::Error) -> String {
+    error.to_string()
+}
+
+/// Read encodings from an `.npy` stream.
+///
+/// The array must contain `float64` or `float32` values and have a shape of either `(128,)` or `(n, 128)`.
+pub fn read_npy<R: Read>(mut reader: R) -> Result<Vec<FaceEncoding>, String> {
+    let mut preamble = [0u8; 8];
+    reader.read_exact(&mut preamble).map_err(io_error)?;
+
+    if &preamble[..6] != MAGIC {
+        return Err("Not an npy file".into());
+    }
+
+    let header_len = match preamble[6] {
+        1 => {
+            let mut len = [0u8; 2];
+            reader.read_exact(&mut len).map_err(io_error)?;
+            u16::from_le_bytes(len) as usize
+        }
+        2 | 3 => {
+            let mut len = [0u8; 4];
+            reader.read_exact(&mut len).map_err(io_error)?;
+            u32::from_le_bytes(len) as usize
+        }
+        version => return Err(format!("Unsupported npy version {}", version)),
+    };
+
+    let mut header = vec![0u8; header_len];
+    reader.read_exact(&mut header).map_err(io_error)?;
+    let header = String::from_utf8(header).map_err(|_| "Malformed npy header".to_string())?;
+    let header = parse_header(&header)?;
+
+    let (rows, columns) = match header.shape.as_slice() {
+        [128] => (1, 128),
+        [rows, 128] => (*rows, 128),
+        shape => {
+            return Err(format!(
+                "Expected an array of shape (128,) or (n, 128), got {:?}",
+                shape
+            ))
+        }
+    };
+
+    let too_large = || format!("The npy array of {} rows is too large", rows);
+    let count = rows.checked_mul(columns).ok_or_else(too_large)?;
+    let element_size = match header.element {
+        Element::F64 => 8,
+        Element::F32 => 4,
+    };
+    let len = count.checked_mul(element_size).ok_or_else(too_large)?;
+
+    // The shape comes from the file, so only allocate for the data that is actually there.
+    let mut data = Vec::new();
+    reader
+        .take(len as u64)
+        .read_to_end(&mut data)
+        .map_err(io_error)?;
+    if data.len() != len {
+        return Err(format!(
+            "Expected {} bytes of npy data, got {}",
+            len,
+            data.len()
+        ));
+    }
+
+    let values: Vec<f64> = match header.element {
+        Element::F64 => data
+            .chunks_exact(8)
+            .map(|bytes| f64::from_le_bytes(bytes.try_into().unwrap()))
+            .collect(),
+        Element::F32 => data
+            .chunks_exact(4)
+            .map(|bytes| f32::from_le_bytes(bytes.try_into().unwrap()) as f64)
+            .collect(),
+    };
+
+    let mut elements = [0f64; 128];
+
+    Ok((0..rows)
+        .map(|row| {
+            for (column, element) in elements.iter_mut().enumerate() {
+                *element = if header.fortran_order {
+                    values[column * rows + row]
+                } else {
+                    values[row * columns + column]
+                };
+            }
+
+            FaceEncoding::new(&elements)
+        })
+        .collect())
+}
+
+/// Write encodings to an `.npy` stream as a `float64` array of shape `(n, 128)`.
+pub fn write_npy<W: Write>(mut writer: W, encodings: &[FaceEncoding]) -> Result<(), String> {
+    let mut header = format!(
+        "{{'descr': '<f8', 'fortran_order': False, 'shape': ({}, 128), }}",
+        encodings.len()
+    );
+
+    // Pad the header with spaces so that the data is 64-byte aligned, as numpy does.
+    let unpadded = MAGIC.len() + 2 + 2 + header.len() + 1;
+    header.push_str(&" ".repeat((64 - unpadded % 64) % 64));
+    header.push('\n');
+
+    writer.write_all(MAGIC).map_err(io_error)?;
+    writer.write_all(&[1, 0]).map_err(io_error)?;
+    writer
+        .write_all(&(header.len() as u16).to_le_bytes())
+        .map_err(io_error)?;
+    writer.write_all(header.as_bytes()).map_err(io_error)?;
+
+    for encoding in encodings {
+        for element in encoding.iter() {
+            writer.write_all(&element.to_le_bytes()).map_err(io_error)?;
+        }
+    }
+
+    writer.flush().map_err(io_error)
+}
+
+/// Load encodings from an `.npy` file, as written by `numpy.save`.
+pub fn load_npy<P: AsRef<Path>>(filename: P) -> Result<Vec<FaceEncoding>, String> {
+    let file = File::open(filename.as_ref()).map_err(|error| {
+        format!(
+            "Failed to open '{}': {}",
+            filename.as_ref().display(),
+            error
+        )
+    })?;
+
+    read_npy(BufReader::new(file))
+}
+
+/// Save encodings to an `.npy` file that can be read with `numpy.load`.
+pub fn save_npy<P: AsRef<Path>>(filename: P, encodings: &[FaceEncoding]) -> Result<(), String> {
+    let file = File::create(filename.as_ref()).map_err(|error| {
+        format!(
+            "Failed to create '{}': {}",
+            filename.as_ref().display(),
+            error
+        )
+    })?;
+
+    write_npy(BufWriter::new(file), encodings)
+}
+
+/// Load every array from an `.npz` archive, as written by `numpy.savez` or `numpy.savez_compressed`.
+///
+/// Returns the array names (without the `.npy` extension) alongside their encodings.
+#[cfg(feature = "npz")]
+pub fn load_npz<P: AsRef<Path>>(filename: P) -> Result<Vec<(String, Vec<FaceEncoding>)>, String> {
+    let file = File::open(filename.as_ref()).map_err(|error| {
+        format!(
+            "Failed to open '{}': {}",
+            filename.as_ref().display(),
+            error
+        )
+    })?;
+    let mut archive = zip::ZipArchive::new(BufReader::new(file)).map_err(|e| e.to_string())?;
+
+    (0..archive.len())
+        .map(|index| {
+            let entry = archive.by_index(index).map_err(|e| e.to_string())?;
+            let name = entry.name().trim_end_matches(".npy").to_string();
+            let encodings = read_npy(entry)?;
+            Ok((name, encodings))
+        })
+        .collect()
+}
+
+/// Save named arrays of encodings to an uncompressed `.npz` archive that can be read with `numpy.load`.
+#[cfg(feature = "npz")]
+pub fn save_npz<P: AsRef<Path>>(
+    filename: P,
+    arrays: &[(&str, &[FaceEncoding])],
+) -> Result<(), String> {
+    let file = File::create(filename.as_ref()).map_err(|error| {
+        format!(
+            "Failed to create '{}': {}",
+            filename.as_ref().display(),
+            error
+        )
+    })?;
+    let mut archive = zip::ZipWriter::new(BufWriter::new(file));
+    let options =
+        zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+
+    for (name, encodings) in arrays {
+        archive
+            .start_file(format!("{}.npy", name), options)
+            .map_err(|e| e.to_string())?;
+        write_npy(&mut archive, encodings)?;
+    }
+
+    archive.finish().map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[test]
+fn can_round_trip_npy() {
+    let encodings = vec![
+        FaceEncoding::new_from_scalar(0.5),
+        FaceEncoding::new_from_scalar(-1.0),
+    ];
+
+    let mut buffer = Vec::new();
+    write_npy(&mut buffer, &encodings).unwrap();
+    assert_eq!((buffer.len() - 2 * 128 * 8) % 64, 0);
+
+    assert_eq!(read_npy(buffer.as_slice()).unwrap(), encodings);
+}
+
+#[test]
+fn can_read_single_f32_npy() {
+    let header = "{'descr': '<f4', 'fortran_order': False, 'shape': (128,), }\n";
+
+    let mut buffer = Vec::new();
+    buffer.extend_from_slice(MAGIC);
+    buffer.extend_from_slice(&[1, 0]);
+    buffer.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    buffer.extend_from_slice(header.as_bytes());
+    for _ in 0..128 {
+        buffer.extend_from_slice(&0.25f32.to_le_bytes());
+    }
+
+    let encodings = read_npy(buffer.as_slice()).unwrap();
+    assert_eq!(encodings, vec![FaceEncoding::new_from_scalar(0.25)]);
+}
+
+#[test]
+fn rejects_npy_larger_than_its_data() {
+    let npy = |shape: &str| {
+        let header = format!(
+            "{{'descr': '<f8', 'fortran_order': False, 'shape': ({}, 128), }}\n",
+            shape
+        );
+
+        let mut buffer = Vec::new();
+        buffer.extend_from_slice(MAGIC);
+        buffer.extend_from_slice(&[1, 0]);
+        buffer.extend_from_slice(&(header.len() as u16).to_le_bytes());
+        buffer.extend_from_slice(header.as_bytes());
+        buffer.extend_from_slice(&[0u8; 128 * 8]);
+        buffer
+    };
+
+    assert_eq!(read_npy(npy("1").as_slice()).unwrap().len(), 1);
+    assert!(read_npy(npy("2").as_slice()).is_err());
+    assert!(read_npy(npy("1000000000000").as_slice()).is_err());
+    assert!(read_npy(npy(&usize::MAX.to_string()).as_slice()).is_err());
+}
@@ -0,0 +1,217 @@
+use std::path::Path;
+
+use ndarray::{Array4, CowArray};
+use ort::{Environment, Session, SessionBuilder, Value};
+
+use super::base::FaceEncoderTrait;
+use super::encoding::FaceEncoding;
+use super::encodings::FaceEncodings;
+use crate::chip_model::ChipModel;
+use crate::face_alignment::{face_chip, ChipOptions};
+use crate::landmark_prediction::FaceLandmarks;
+use crate::matrix::ImageMatrix;
+
+/// How face chips are prepared for an ONNX model.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct OnnxEncoderOptions {
+    /// How the face chips are extracted. Most models take 112x112 chips.
+    pub chip: ChipOptions,
+    /// Subtracted from each channel, in the model's channel order.
+    pub mean: [f32; 3],
+    /// Each channel is divided by this after subtracting the mean.
+    pub std: [f32; 3],
+    /// Feed the channels in blue, green, red order rather than red, green, blue.
+    pub bgr: bool,
+}
+
+impl Default for OnnxEncoderOptions {
+    /// The preprocessing used by ArcFace-style models.
+    fn default() -> Self {
+        Self {
+            chip: ChipOptions {
+                size: 112,
+                padding: 0.0,
+            },
+            mean: [127.5; 3],
+            std: [127.5; 3],
+            bgr: false,
+        }
+    }
+}
+
+/// An ONNX model that runs on face chips, run with ONNX Runtime.
+///
+/// The model must take a `1x3xHxW` float tensor of a single face chip. Its first output is returned as is,
+/// which makes this suitable for auxiliary models such as age or gender estimators.
+pub struct OnnxChipModel {
+    session: Session,
+    options: OnnxEncoderOptions,
+}
+
+impl OnnxChipModel {
+    /// Load an ONNX model from a file path.
+    pub fn new<P: AsRef<Path>>(filename: P, options: OnnxEncoderOptions) -> Result<Self, String> {
+        let environment = Environment::builder()
+            .with_name("dlib-face-recognition")
+            .build()
+            .map_err(|error| error.to_string())?
+            .into_arc();
+
+        let session = SessionBuilder::new(&environment)
+            .and_then(|builder| builder.with_model_from_file(filename.as_ref()))
+            .map_err(|error| {
+                format!(
+                    "Failed to load '{}': {}",
+                    filename.as_ref().display(),
+                    error
+                )
+            })?;
+
+        Ok(Self { session, options })
+    }
+
+    pub fn options(&self) -> OnnxEncoderOptions {
+        self.options
+    }
+
+    /// Run the model on an already extracted face chip.
+    pub fn run(&self, chip: &ImageMatrix) -> Result<Vec<f32>, String> {
+        let width = chip.width();
+        let height = chip.height();
+        let tensor = chip_tensor(chip, &self.options);
+
+        let array = Array4::from_shape_vec((1, 3, height, width), tensor)
+            .map_err(|error| error.to_string())?
+            .into_dyn();
+        let array = CowArray::from(array);
+
+        let input = Value::from_array(self.session.allocator(), &array)
+            .map_err(|error| error.to_string())?;
+        let outputs = self
+            .session
+            .run(vec![input])
+            .map_err(|error| error.to_string())?;
+
+        let output = outputs
+            .first()
+            .ok_or_else(|| "The model has no outputs".to_string())?
+            .try_extract::<f32>()
+            .map_err(|error| error.to_string())?;
+
+        let values = output.view().iter().copied().collect();
+        Ok(values)
+    }
+}
+
+impl ChipModel for OnnxChipModel {
+    fn chip_options(&self) -> ChipOptions {
+        self.options.chip
+    }
+
+    fn run_chips(&self, chips: &[ImageMatrix]) -> Result<Vec<Vec<f32>>, String> {
+        chips.iter().map(|chip| self.run(chip)).collect()
+    }
+}
+
+/// A face encoder backed by an ONNX model, run with ONNX Runtime.
+///
+/// The model must take a `1x3xHxW` float tensor of a single face chip and output a 128 dimensional
+/// encoding, such as SFace or an ArcFace model with a 128 dimensional head. Encodings are normalized to unit
+/// length, so they can be compared with the same distances as the encodings of [`FaceEncoderNetwork`],
+/// although the thresholds that separate matches will differ.
+///
+/// [`FaceEncoderNetwork`]: crate::FaceEncoderNetwork
+pub struct OnnxFaceEncoder {
+    model: OnnxChipModel,
+}
+
+impl OnnxFaceEncoder {
+    /// Load an ONNX model from a file path.
+    ///
+    /// Returns an error if the model can't be loaded, or if it doesn't output 128 values for a face.
+    pub fn new<P: AsRef<Path>>(filename: P, options: OnnxEncoderOptions) -> Result<Self, String> {
+        let model = OnnxChipModel::new(filename, options)?;
+
+        let size = options.chip.size as usize;
+        let outputs = model.run(&ImageMatrix::blank(size, size))?;
+        if outputs.len() != 128 {
+            return Err(format!(
+                "Expected the model to output 128 values for a face, got {}",
+                outputs.len()
+            ));
+        }
+
+        Ok(Self { model })
+    }
+
+    pub fn options(&self) -> OnnxEncoderOptions {
+        self.model.options()
+    }
+
+    /// Encode an already extracted face chip.
+    pub fn encode_chip(&self, chip: &ImageMatrix) -> Result<FaceEncoding, String> {
+        let outputs = self.model.run(chip)?;
+        let elements: Vec<f64> = outputs.iter().map(|&value| value as f64).collect();
+        Ok(FaceEncoding::from_slice(&elements)?.normalized())
+    }
+}
+
+impl FaceEncoderTrait for OnnxFaceEncoder {
+    /// Encode each face from a chip extracted with the encoder's [`OnnxEncoderOptions`].
+    ///
+    /// `num_jitters` is ignored, each face is always encoded once.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the landmarks or chip options can't be used to extract a chip, or ONNX Runtime fails to run
+    /// the model.
+    fn get_face_encodings(
+        &self,
+        image: &ImageMatrix,
+        landmarks: &[FaceLandmarks],
+        _num_jitters: u32,
+    ) -> FaceEncodings {
+        let encodings: Vec<FaceEncoding> = landmarks
+            .iter()
+            .map(|landmarks| {
+                let chip = face_chip(image, landmarks, &self.model.options.chip)
+                    .unwrap_or_else(|error| panic!("{}", error));
+                self.encode_chip(&chip).unwrap()
+            })
+            .collect();
+
+        FaceEncodings::from_encodings(&encodings)
+    }
+}
+
+/// Copy a chip into a planar `3xHxW` buffer, normalizing each channel.
+fn chip_tensor(chip: &ImageMatrix, options: &OnnxEncoderOptions) -> Vec<f32> {
+    let mut tensor = vec![0f32; 3 * chip.width() * chip.height()];
+
+    let order: [usize; 3] = if options.bgr { [2, 1, 0] } else { [0, 1, 2] };
+
+    unsafe {
+        let out = tensor.as_mut_ptr();
+        let order = order.as_ptr();
+        let mean = options.mean.as_ptr();
+        let std = options.std.as_ptr();
+
+        cpp!([chip as "const dlib::matrix<dlib::rgb_pixel>*", out as "float*", order as "const size_t*", mean as "const float*", std as "const float*"] {
+            size_t plane = chip->nr() * chip->nc();
+
+            for (long y = 0; y < chip->nr(); ++y) {
+                for (long x = 0; x < chip->nc(); ++x) {
+                    const dlib::rgb_pixel& pixel = (*chip)(y, x);
+                    float channels[3] = {(float)pixel.red, (float)pixel.green, (float)pixel.blue};
+                    size_t offset = y * chip->nc() + x;
+
+                    for (size_t c = 0; c < 3; ++c) {
+                        out[c * plane + offset] = (channels[order[c]] - mean[c]) / std[c];
+                    }
+                }
+            }
+        })
+    }
+
+    tensor
+}
@@ -0,0 +1,90 @@
+use super::encoding::FaceEncoding;
+
+/// The euclidean distances between every pair of a set of encodings.
+///
+/// Only the upper triangle is stored, so the memory required is `n * (n - 1) / 2` distances.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DistanceMatrix {
+    len: usize,
+    distances: Vec<f64>,
+}
+
+impl DistanceMatrix {
+    /// The number of encodings the distances were computed for.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Get the distance between the encodings at `i` and `j`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either index is out of bounds.
+    pub fn get(&self, i: usize, j: usize) -> f64 {
+        assert!(i < self.len && j < self.len, "Index out of bounds");
+
+        match i.cmp(&j) {
+            std::cmp::Ordering::Equal => 0.0,
+            std::cmp::Ordering::Less => self.distances[self.offset(i) + j - i - 1],
+            std::cmp::Ordering::Greater => self.distances[self.offset(j) + i - j - 1],
+        }
+    }
+
+    /// The condensed upper triangle of the matrix, row by row, in the same layout as scipy's `pdist`.
+    pub fn condensed(&self) -> &[f64] {
+        &self.distances
+    }
+
+    fn offset(&self, row: usize) -> usize {
+        row * self.len - row * (row + 1) / 2
+    }
+}
+
+/// Compute the distances between every pair of encodings.
+///
+/// The distances are computed in a single call, spread across dlib's thread pool.
+pub fn pairwise_distances(encodings: &[FaceEncoding]) -> DistanceMatrix {
+    let len = encodings.len();
+    let mut distances = vec![0f64; len * len.saturating_sub(1) / 2];
+
+    if !distances.is_empty() {
+        unsafe {
+            let encodings = encodings.as_ptr();
+            let out = distances.as_mut_ptr();
+
+            cpp!([encodings as "const dlib::matrix<double,0,1>*", len as "size_t", out as "double*"] {
+                dlib::parallel_for(0, static_cast<long>(len), [&](long i) {
+                    const size_t row = static_cast<size_t>(i);
+                    double* row_out = out + row * len - row * (row + 1) / 2;
+
+                    for (size_t j = row + 1; j < len; ++j) {
+                        row_out[j - row - 1] = dlib::length(encodings[row] - encodings[j]);
+                    }
+                });
+            });
+        }
+    }
+
+    DistanceMatrix { len, distances }
+}
+
+#[test]
+fn test_pairwise_distances() {
+    let encodings: Vec<FaceEncoding> = (0..4)
+        .map(|i| FaceEncoding::new_from_scalar(i as f64))
+        .collect();
+
+    let matrix = pairwise_distances(&encodings);
+
+    assert_eq!(matrix.len(), 4);
+    assert_eq!(matrix.condensed().len(), 6);
+    for i in 0..4 {
+        for j in 0..4 {
+            assert_eq!(matrix.get(i, j), encodings[i].distance(&encodings[j]));
+        }
+    }
+}
@@ -0,0 +1,241 @@
+use std::fs;
+use std::path::Path;
+
+use super::encoding::FaceEncoding;
+use crate::base::CppBytes;
+
+/// A principal component analysis projection that reduces encodings to fewer dimensions.
+///
+/// Projected encodings can be compared with the euclidean distance as usual, and keeping
+/// the leading 64 components usually retains most of the matching accuracy.
+#[derive(Clone)]
+pub struct PcaProjector {
+    inner: PcaProjectorInner,
+}
+
+cpp_class!(unsafe struct PcaProjectorInner as "pca_projector");
+
+impl PcaProjector {
+    /// Train a projection onto the `dimensions` principal components of a set of encodings.
+    pub fn train(encodings: &[FaceEncoding], dimensions: usize) -> Result<Self, String> {
+        if encodings.len() < 2 {
+            return Err("At least two encodings are required to train a projection".into());
+        }
+
+        if encodings
+            .iter()
+            .any(|encoding| encoding.len() != encodings[0].len())
+        {
+            return Err("Every encoding must have the same number of dimensions".into());
+        }
+
+        if dimensions == 0 || dimensions > encodings[0].len() {
+            return Err(format!(
+                "Cannot project {} dimensional encodings onto {} dimensions",
+                encodings[0].len(),
+                dimensions
+            ));
+        }
+
+        let len = encodings.len();
+        let encodings = encodings.as_ptr();
+
+        let inner = unsafe {
+            cpp!([encodings as "const dlib::matrix<double,0,1>*", len as "size_t", dimensions as "size_t"] -> PcaProjectorInner as "pca_projector" {
+                pca_projector projector;
+
+                projector.mean = encodings[0];
+                for (size_t i = 1; i < len; ++i) {
+                    projector.mean += encodings[i];
+                }
+                projector.mean /= static_cast<double>(len);
+
+                const long size = projector.mean.size();
+                dlib::matrix<double> covariance = dlib::zeros_matrix<double>(size, size);
+                for (size_t i = 0; i < len; ++i) {
+                    const dlib::matrix<double,0,1> centered = encodings[i] - projector.mean;
+                    covariance += centered * dlib::trans(centered);
+                }
+                covariance /= static_cast<double>(len - 1);
+
+                dlib::eigenvalue_decomposition<dlib::matrix<double>> eigen(dlib::make_symmetric(covariance));
+                const dlib::matrix<double,0,1> values = eigen.get_real_eigenvalues();
+                const dlib::matrix<double> vectors = eigen.get_pseudo_v();
+
+                std::vector<long> order(size);
+                std::iota(order.begin(), order.end(), 0);
+                std::sort(order.begin(), order.end(), [&](long a, long b) {
+                    return values(a) > values(b);
+                });
+
+                projector.projection.set_size(dimensions, size);
+                for (size_t row = 0; row < dimensions; ++row) {
+                    dlib::set_rowm(projector.projection, row) = dlib::trans(dlib::colm(vectors, order[row]));
+                }
+
+                return projector;
+            })
+        };
+
+        Ok(Self { inner })
+    }
+
+    /// The number of dimensions encodings are projected onto.
+    pub fn dimensions(&self) -> usize {
+        let projector = &self.inner;
+
+        unsafe {
+            cpp!([projector as "const pca_projector*"] -> usize as "size_t" {
+                return projector->projection.nr();
+            })
+        }
+    }
+
+    /// The number of dimensions of the encodings the projection accepts.
+    pub fn input_dimensions(&self) -> usize {
+        let projector = &self.inner;
+
+        unsafe {
+            cpp!([projector as "const pca_projector*"] -> usize as "size_t" {
+                return projector->mean.size();
+            })
+        }
+    }
+
+    // Whether the projection matrix fits the mean, which a corrupt or crafted file might not.
+    fn is_consistent(&self) -> bool {
+        let projector = &self.inner;
+
+        unsafe {
+            cpp!([projector as "const pca_projector*"] -> bool as "bool" {
+                return projector->mean.size() > 0
+                    && projector->projection.nr() > 0
+                    && projector->projection.nc() == projector->mean.size();
+            })
+        }
+    }
+
+    /// Project an encoding onto the principal components.
+    ///
+    /// Fails if the encoding doesn't have [`PcaProjector::input_dimensions`] dimensions.
+    pub fn project(&self, encoding: &FaceEncoding) -> Result<Vec<f64>, String> {
+        if encoding.len() != self.input_dimensions() {
+            return Err(format!(
+                "Cannot project a {} dimensional encoding with a projection from {} dimensions",
+                encoding.len(),
+                self.input_dimensions()
+            ));
+        }
+
+        let mut projected = vec![0f64; self.dimensions()];
+        let projector = &self.inner;
+
+        unsafe {
+            let out = projected.as_mut_ptr();
+
+            cpp!([projector as "const pca_projector*", encoding as "const dlib::matrix<double,0,1>*", out as "double*"] {
+                const dlib::matrix<double,0,1> result = projector->projection * (*encoding - projector->mean);
+                for (long i = 0; i < result.size(); ++i) {
+                    out[i] = result(i);
+                }
+            });
+        }
+
+        Ok(projected)
+    }
+
+    /// Deserialize a projection written by [`PcaProjector::to_bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let inner = PcaProjectorInner::default();
+
+        let deserialized = unsafe {
+            let data = bytes.as_ptr();
+            let len = bytes.len();
+            let projector = &inner;
+
+            cpp!([data as "const char*", len as "size_t", projector as "pca_projector*"] -> bool as "bool" {
+                try {
+                    std::istringstream stream(std::string(data, len));
+                    deserialize(*projector, stream);
+                    return true;
+                } catch (const std::exception& exception) {
+                    return false;
+                }
+            })
+        };
+
+        if !deserialized {
+            return Err("Failed to deserialize PCA projection".into());
+        }
+
+        let projector = Self { inner };
+        if !projector.is_consistent() {
+            return Err("The PCA projection doesn't match its mean".into());
+        }
+        Ok(projector)
+    }
+
+    /// Serialize the projection in dlib's binary format.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let projector = &self.inner;
+
+        let bytes = unsafe {
+            cpp!([projector as "const pca_projector*"] -> CppBytes as "std::string" {
+                std::ostringstream stream;
+                serialize(*projector, stream);
+                return stream.str();
+            })
+        };
+
+        bytes.to_vec()
+    }
+
+    /// Load a projection from a file.
+    pub fn load<P: AsRef<Path>>(filename: P) -> Result<Self, String> {
+        let bytes = fs::read(filename.as_ref()).map_err(|error| {
+            format!(
+                "Failed to open '{}': {}",
+                filename.as_ref().display(),
+                error
+            )
+        })?;
+
+        Self::from_bytes(&bytes)
+    }
+
+    /// Save the projection to a file.
+    pub fn save<P: AsRef<Path>>(&self, filename: P) -> Result<(), String> {
+        fs::write(filename.as_ref(), self.to_bytes()).map_err(|error| {
+            format!(
+                "Failed to create '{}': {}",
+                filename.as_ref().display(),
+                error
+            )
+        })
+    }
+}
+
+#[test]
+fn test_pca_projection() {
+    let encodings: Vec<FaceEncoding> = (0..5)
+        .map(|i| FaceEncoding::new_from_scalar(i as f64))
+        .collect();
+
+    let projector = PcaProjector::train(&encodings, 1).unwrap();
+    assert_eq!(projector.dimensions(), 1);
+
+    assert_eq!(projector.input_dimensions(), 128);
+
+    let a = projector.project(&encodings[0]).unwrap();
+    let b = projector.project(&encodings[4]).unwrap();
+    assert!(((a[0] - b[0]).abs() - encodings[0].distance(&encodings[4])).abs() < 1e-6);
+
+    let loaded = PcaProjector::from_bytes(&projector.to_bytes()).unwrap();
+    assert_eq!(loaded.project(&encodings[4]).unwrap(), b);
+
+    let bytes = projector.to_bytes();
+    assert!(PcaProjector::from_bytes(&bytes[..bytes.len() / 2]).is_err());
+
+    assert!(PcaProjector::train(&encodings[..1], 1).is_err());
+    assert!(PcaProjector::train(&encodings, 129).is_err());
+}
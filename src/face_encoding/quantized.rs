@@ -0,0 +1,114 @@
+use std::fmt;
+use std::ops::Deref;
+
+use super::encoding::FaceEncoding;
+
+/// An 8-bit quantized face encoding.
+///
+/// Each element is stored as `round(value / scale)` clamped to `[-127, 127]`. The integer distance
+/// between two quantized encodings is very cheap to compute and is intended for pre-filtering large
+/// galleries, with the candidates then re-ranked using the exact [`FaceEncoding::distance`].
+///
+/// Encodings can only be meaningfully compared if they were quantized with the same scale, which is
+/// usually obtained once from a representative sample via [`FaceEncodingQ8::calibrate_scale`].
+#[derive(Copy, Clone)]
+pub struct FaceEncodingQ8 {
+    elements: [i8; 128],
+    scale: f32,
+}
+
+impl FaceEncodingQ8 {
+    /// Find a scale factor that makes full use of the 8-bit range for a sample of encodings.
+    ///
+    /// Returns `1.0 / 127.0` if the sample is empty or entirely zero.
+    pub fn calibrate_scale(encodings: &[FaceEncoding]) -> f32 {
+        let max = encodings
+            .iter()
+            .flat_map(|encoding| encoding.iter())
+            .fold(0f64, |max, value| max.max(value.abs()));
+
+        if max == 0.0 {
+            1.0 / 127.0
+        } else {
+            (max / 127.0) as f32
+        }
+    }
+
+    /// Quantize an encoding using the given scale factor.
+    pub fn quantize(encoding: &FaceEncoding, scale: f32) -> Self {
+        let mut elements = [0i8; 128];
+        for (element, value) in elements.iter_mut().zip(encoding.iter()) {
+            *element = (*value / scale as f64).round().clamp(-127.0, 127.0) as i8;
+        }
+
+        Self { elements, scale }
+    }
+
+    /// The scale factor this encoding was quantized with.
+    pub fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    /// Calculate the squared euclidean distance between two encodings in quantized units.
+    ///
+    /// Both encodings must share the same scale.
+    pub fn squared_distance_q8(&self, other: &Self) -> u32 {
+        debug_assert_eq!(self.scale, other.scale);
+
+        self.elements
+            .iter()
+            .zip(other.elements.iter())
+            .map(|(&a, &b)| {
+                let difference = a as i32 - b as i32;
+                (difference * difference) as u32
+            })
+            .sum()
+    }
+
+    /// Calculate the approximate euclidean distance between two encodings.
+    ///
+    /// Both encodings must share the same scale. The result is comparable to [`FaceEncoding::distance`].
+    pub fn distance(&self, other: &Self) -> f32 {
+        (self.squared_distance_q8(other) as f32).sqrt() * self.scale
+    }
+
+    /// Reconstruct an approximation of the original encoding.
+    pub fn dequantize(&self) -> FaceEncoding {
+        let mut elements = [0f64; 128];
+        for (element, value) in elements.iter_mut().zip(self.elements.iter()) {
+            *element = *value as f64 * self.scale as f64;
+        }
+
+        FaceEncoding::new(&elements)
+    }
+}
+
+impl Deref for FaceEncodingQ8 {
+    type Target = [i8];
+
+    fn deref(&self) -> &Self::Target {
+        &self.elements
+    }
+}
+
+impl fmt::Debug for FaceEncodingQ8 {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("FaceEncodingQ8")
+            .field("elements", &self.deref())
+            .field("scale", &self.scale)
+            .finish()
+    }
+}
+
+#[test]
+fn quantized_distances_approximate_exact_ones() {
+    let a = FaceEncoding::new_from_scalar(0.1);
+    let b = FaceEncoding::new_from_scalar(-0.2);
+
+    let scale = FaceEncodingQ8::calibrate_scale(&[a.clone(), b.clone()]);
+    let qa = FaceEncodingQ8::quantize(&a, scale);
+    let qb = FaceEncodingQ8::quantize(&b, scale);
+
+    assert!((qa.distance(&qb) as f64 - a.distance(&b)).abs() < 0.01);
+    assert!(qa.dequantize().distance(&a) < 0.02);
+}
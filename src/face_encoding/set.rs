@@ -0,0 +1,175 @@
+use std::convert::TryFrom;
+use std::slice;
+
+use super::encoding::FaceEncoding;
+
+const DIMENSIONS: usize = 128;
+
+/// A collection of encodings stored contiguously in a single buffer.
+///
+/// Unlike a `Vec<FaceEncoding>`, where each encoding is a separately allocated dlib matrix,
+/// the elements of every encoding are laid out one after another. This makes scanning the whole set
+/// against a probe encoding considerably more cache friendly.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct EncodingSet {
+    elements: Vec<f64>,
+}
+
+fn squared_distance(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(a, b)| (a - b) * (a - b)).sum()
+}
+
+impl EncodingSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            elements: Vec::with_capacity(capacity * DIMENSIONS),
+        }
+    }
+
+    /// Create a set from encodings, failing if any of them don't have 128 elements.
+    pub fn from_encodings<'a, I>(encodings: I) -> Result<Self, String>
+    where
+        I: IntoIterator<Item = &'a FaceEncoding>,
+    {
+        let mut set = Self::new();
+        for encoding in encodings {
+            set.push(encoding)?;
+        }
+        Ok(set)
+    }
+
+    /// Append an encoding to the end of the set.
+    ///
+    /// Returns an error if the encoding does not have 128 elements.
+    pub fn push(&mut self, encoding: &FaceEncoding) -> Result<(), String> {
+        if encoding.len() != DIMENSIONS {
+            return Err(format!(
+                "Expected an encoding of {} elements, got {}",
+                DIMENSIONS,
+                encoding.len()
+            ));
+        }
+
+        self.elements.extend_from_slice(encoding);
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.elements.len() / DIMENSIONS
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.elements.is_empty()
+    }
+
+    /// Get the elements of the encoding at `index`.
+    pub fn get(&self, index: usize) -> Option<&[f64]> {
+        let start = index.checked_mul(DIMENSIONS)?;
+        self.elements.get(start..start.checked_add(DIMENSIONS)?)
+    }
+
+    /// Copy the encoding at `index` out of the set.
+    pub fn encoding(&self, index: usize) -> Option<FaceEncoding> {
+        self.get(index)
+            .map(|elements| FaceEncoding::from_slice(elements).unwrap())
+    }
+
+    /// Remove the encoding at `index`, shifting all following encodings down.
+    ///
+    /// Returns the removed encoding, or `None` if `index` is out of range.
+    pub fn remove(&mut self, index: usize) -> Option<FaceEncoding> {
+        let encoding = self.encoding(index)?;
+        self.elements
+            .drain(index * DIMENSIONS..(index + 1) * DIMENSIONS);
+        Some(encoding)
+    }
+
+    /// Iterate over the elements of each encoding in the set.
+    pub fn iter(&self) -> slice::ChunksExact<'_, f64> {
+        self.elements.chunks_exact(DIMENSIONS)
+    }
+
+    /// Calculate the euclidean distance between a probe and every encoding in the set.
+    pub fn distances(&self, probe: &FaceEncoding) -> Vec<f64> {
+        self.iter()
+            .map(|elements| squared_distance(elements, probe).sqrt())
+            .collect()
+    }
+
+    /// Find the `k` closest encodings to a probe.
+    ///
+    /// Returns `(index, distance)` pairs sorted from closest to furthest.
+    pub fn top_k_matches(&self, probe: &FaceEncoding, k: usize) -> Vec<(usize, f64)> {
+        let mut matches: Vec<(usize, f64)> =
+            self.distances(probe).into_iter().enumerate().collect();
+
+        matches.sort_by(|(_, x), (_, y)| x.total_cmp(y));
+        matches.truncate(k);
+        matches
+    }
+
+    /// Find every encoding within `threshold` euclidean distance of a probe.
+    ///
+    /// Returns `(index, distance)` pairs sorted from closest to furthest.
+    pub fn within_threshold(&self, probe: &FaceEncoding, threshold: f64) -> Vec<(usize, f64)> {
+        let mut matches: Vec<(usize, f64)> = self
+            .distances(probe)
+            .into_iter()
+            .enumerate()
+            .filter(|(_, distance)| *distance <= threshold)
+            .collect();
+
+        matches.sort_by(|(_, x), (_, y)| x.total_cmp(y));
+        matches
+    }
+}
+
+impl TryFrom<&[FaceEncoding]> for EncodingSet {
+    type Error = String;
+
+    fn try_from(encodings: &[FaceEncoding]) -> Result<Self, Self::Error> {
+        Self::from_encodings(encodings)
+    }
+}
+
+#[test]
+fn test_top_k_matches() {
+    let encodings: Vec<FaceEncoding> = (0..5)
+        .map(|i| FaceEncoding::new_from_scalar(i as f64))
+        .collect();
+    let set = EncodingSet::try_from(encodings.as_slice()).unwrap();
+    let probe = FaceEncoding::new_from_scalar(2.2);
+
+    assert_eq!(set.len(), 5);
+    assert_eq!(set.encoding(3), Some(FaceEncoding::new_from_scalar(3.0)));
+
+    let matches = set.top_k_matches(&probe, 2);
+    assert_eq!(
+        matches.iter().map(|(i, _)| *i).collect::<Vec<_>>(),
+        vec![2, 3]
+    );
+    assert!((matches[0].1 - probe.distance(&set.encoding(2).unwrap())).abs() < 1e-9);
+
+    assert_eq!(set.within_threshold(&probe, 3.0).len(), 1);
+}
+
+#[test]
+fn test_bad_encodings() {
+    let mut set = EncodingSet::new();
+    set.push(&FaceEncoding::new_from_scalar(1.0)).unwrap();
+    assert!(set.remove(1).is_none());
+    assert!(set.get(usize::MAX / DIMENSIONS).is_none());
+    assert!(set.remove(usize::MAX).is_none());
+    assert_eq!(set.remove(0), Some(FaceEncoding::new_from_scalar(1.0)));
+    assert!(set.is_empty());
+
+    // NaN distances sort last rather than panicking
+    set.push(&FaceEncoding::new_from_scalar(f64::NAN)).unwrap();
+    set.push(&FaceEncoding::new_from_scalar(0.0)).unwrap();
+    let matches = set.top_k_matches(&FaceEncoding::new_from_scalar(0.0), 2);
+    assert_eq!(matches[0], (1, 0.0));
+}
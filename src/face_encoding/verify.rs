@@ -0,0 +1,57 @@
+use super::encoding::FaceEncoding;
+
+/// The euclidean distance below which two dlib encodings are generally considered the same face.
+pub const DEFAULT_MATCH_THRESHOLD: f64 = 0.6;
+
+/// The result of comparing two encodings against a threshold.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Verification {
+    /// Whether the distance is within the threshold.
+    pub is_match: bool,
+    /// The euclidean distance between the encodings.
+    pub distance: f64,
+    /// How far the distance is from the threshold, relative to the threshold, in the range `[0, 1]`.
+    ///
+    /// A value close to `0.0` means the distance was right on the threshold and the decision is
+    /// borderline; `1.0` means the decision was as clear as it can be.
+    pub confidence: f64,
+}
+
+/// Compare two encodings against a distance threshold.
+///
+/// [`DEFAULT_MATCH_THRESHOLD`] is appropriate for the dlib encodings.
+pub fn verify(a: &FaceEncoding, b: &FaceEncoding, threshold: f64) -> Verification {
+    let distance = a.distance(b);
+    let confidence = if threshold > 0.0 {
+        ((distance - threshold).abs() / threshold).min(1.0)
+    } else {
+        1.0
+    };
+
+    Verification {
+        is_match: distance <= threshold,
+        distance,
+        confidence,
+    }
+}
+
+#[test]
+fn test_verify() {
+    let a = FaceEncoding::new_from_scalar(0.0);
+
+    let same = verify(
+        &a,
+        &FaceEncoding::new_from_scalar(0.01),
+        DEFAULT_MATCH_THRESHOLD,
+    );
+    assert!(same.is_match);
+    assert!(same.confidence > 0.5);
+
+    let different = verify(
+        &a,
+        &FaceEncoding::new_from_scalar(1.0),
+        DEFAULT_MATCH_THRESHOLD,
+    );
+    assert!(!different.is_match);
+    assert_eq!(different.confidence, 1.0);
+}
@@ -1,6 +1,7 @@
 use std::ops::Deref;
 
 #[derive(Copy, Clone, Default, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(C)]
 /// A 2D Point.
 pub struct Point([i64; 2]);
@@ -34,8 +35,14 @@ impl Point {
 }
 
 #[derive(Copy, Clone, Default, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(C)]
 /// A Rectangle.
+///
+/// `right` and `bottom` are exclusive, so `width` is `right - left` and the rectangle covering only the
+/// pixel at `(x, y)` is `{ left: x, top: y, right: x + 1, bottom: y + 1 }`. dlib's rectangles are
+/// inclusive instead, so every rectangle is converted as it passes to or from dlib, whether it is
+/// cropped, handed to a predictor or tracker, or returned from a detector.
 pub struct Rectangle {
     pub left: i64,
     pub top: i64,
@@ -67,6 +74,156 @@ impl Rectangle {
     pub fn center(&self) -> [f64; 2] {
         [self.center_x(), self.center_y()]
     }
+
+    /// The area of the rectangle, or zero if it is empty.
+    pub fn area(&self) -> i64 {
+        self.width().max(0) * self.height().max(0)
+    }
+
+    /// The overlapping region of two rectangles.
+    ///
+    /// If the rectangles don't overlap the result has zero area.
+    pub fn intersect(&self, other: &Self) -> Self {
+        Self {
+            left: self.left.max(other.left),
+            top: self.top.max(other.top),
+            right: self.right.min(other.right),
+            bottom: self.bottom.min(other.bottom),
+        }
+    }
+
+    /// The smallest rectangle containing both rectangles.
+    pub fn union(&self, other: &Self) -> Self {
+        Self {
+            left: self.left.min(other.left),
+            top: self.top.min(other.top),
+            right: self.right.max(other.right),
+            bottom: self.bottom.max(other.bottom),
+        }
+    }
+
+    /// The rectangle scaled by `factor` about its center.
+    pub fn scaled(&self, factor: f32) -> Self {
+        let [x, y] = self.center();
+        let half_width = self.width() as f64 * factor as f64 / 2.0;
+        let half_height = self.height() as f64 * factor as f64 / 2.0;
+
+        Self {
+            left: (x - half_width).round() as i64,
+            top: (y - half_height).round() as i64,
+            right: (x + half_width).round() as i64,
+            bottom: (y + half_height).round() as i64,
+        }
+    }
+
+    /// The rectangle grown by `px` pixels on every side, or shrunk if `px` is negative.
+    pub fn padded(&self, px: i64) -> Self {
+        Self {
+            left: self.left - px,
+            top: self.top - px,
+            right: self.right + px,
+            bottom: self.bottom + px,
+        }
+    }
+
+    /// The intersection over union of two rectangles, between `0.0` and `1.0`.
+    pub fn iou(&self, other: &Self) -> f64 {
+        let intersection = self.intersect(other).area();
+        let union = self.area() + other.area() - intersection;
+
+        if union == 0 {
+            0.0
+        } else {
+            intersection as f64 / union as f64
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A 2D affine transformation, mapping `p` to `matrix * p + offset`.
+pub struct AffineTransform {
+    pub matrix: [[f64; 2]; 2],
+    pub offset: [f64; 2],
+}
+
+impl Default for AffineTransform {
+    fn default() -> Self {
+        Self::identity()
+    }
+}
+
+impl AffineTransform {
+    pub fn identity() -> Self {
+        Self {
+            matrix: [[1.0, 0.0], [0.0, 1.0]],
+            offset: [0.0, 0.0],
+        }
+    }
+
+    /// Transform a point.
+    pub fn apply(&self, point: [f64; 2]) -> [f64; 2] {
+        let [x, y] = point;
+        let [[a, b], [c, d]] = self.matrix;
+
+        [
+            a * x + b * y + self.offset[0],
+            c * x + d * y + self.offset[1],
+        ]
+    }
+
+    /// Transform a rectangle, returning the bounding box of its transformed corners.
+    pub fn apply_rect(&self, rect: &Rectangle) -> Rectangle {
+        let corners = [
+            self.apply([rect.left as f64, rect.top as f64]),
+            self.apply([rect.right as f64, rect.top as f64]),
+            self.apply([rect.left as f64, rect.bottom as f64]),
+            self.apply([rect.right as f64, rect.bottom as f64]),
+        ];
+
+        let min = |axis: usize| {
+            corners
+                .iter()
+                .map(|corner| corner[axis])
+                .fold(f64::INFINITY, f64::min)
+        };
+        let max = |axis: usize| {
+            corners
+                .iter()
+                .map(|corner| corner[axis])
+                .fold(f64::NEG_INFINITY, f64::max)
+        };
+
+        Rectangle {
+            left: min(0).round() as i64,
+            top: min(1).round() as i64,
+            right: max(0).round() as i64,
+            bottom: max(1).round() as i64,
+        }
+    }
+
+    /// The transform that undoes this one.
+    ///
+    /// Returns `None` if the transform is not invertible.
+    pub fn inverse(&self) -> Option<Self> {
+        let [[a, b], [c, d]] = self.matrix;
+        let determinant = a * d - b * c;
+
+        if determinant == 0.0 {
+            return None;
+        }
+
+        let matrix = [
+            [d / determinant, -b / determinant],
+            [-c / determinant, a / determinant],
+        ];
+        let offset = [
+            -(matrix[0][0] * self.offset[0] + matrix[0][1] * self.offset[1]),
+            -(matrix[1][0] * self.offset[0] + matrix[1][1] * self.offset[1]),
+        ];
+
+        Some(Self { matrix, offset })
+    }
 }
 
 #[test]
@@ -75,7 +232,7 @@ fn test_default_image() {
     use crate::matrix::ImageMatrix;
 
     let matrix = ImageMatrix::default();
-    let mut face_det = FaceDetector::default();
+    let face_det = FaceDetector::default();
 
     let locations = face_det.face_locations(&matrix);
 
@@ -84,6 +241,67 @@ fn test_default_image() {
     assert_eq!(locations.get(0), None);
 }
 
+#[test]
+fn test_rectangle_overlap() {
+    let a = Rectangle {
+        left: 0,
+        top: 0,
+        right: 10,
+        bottom: 10,
+    };
+    let b = Rectangle {
+        left: 5,
+        top: 0,
+        right: 15,
+        bottom: 10,
+    };
+    let c = Rectangle {
+        left: 20,
+        top: 20,
+        right: 30,
+        bottom: 30,
+    };
+
+    assert_eq!(a.area(), 100);
+    assert_eq!(a.intersect(&b).area(), 50);
+    assert_eq!(a.intersect(&c).area(), 0);
+    assert!((a.iou(&b) - 1.0 / 3.0).abs() < 1e-9);
+    assert_eq!(a.iou(&c), 0.0);
+    assert_eq!(a.iou(&a), 1.0);
+
+    assert_eq!(
+        a.union(&c),
+        Rectangle {
+            left: 0,
+            top: 0,
+            right: 30,
+            bottom: 30,
+        }
+    );
+    assert_eq!(
+        a.scaled(2.0),
+        Rectangle {
+            left: -5,
+            top: -5,
+            right: 15,
+            bottom: 15,
+        }
+    );
+    assert_eq!(a.padded(5), a.scaled(2.0));
+    assert_eq!(a.padded(-5).area(), 0);
+
+    let pixel = Rectangle {
+        left: 3,
+        top: 4,
+        right: 4,
+        bottom: 5,
+    };
+    assert_eq!(pixel.area(), 1);
+    assert_eq!(pixel.iou(&pixel), 1.0);
+    assert_eq!(pixel.intersect(&a).area(), 1);
+    assert_eq!(pixel.iou(&a), 0.01);
+}
+
 #[test]
 fn test_point() {
     let point = unsafe {
@@ -94,3 +312,40 @@ fn test_point() {
 
     assert_eq!(point, Point::new(42, -1000));
 }
+
+#[test]
+fn test_affine_transform() {
+    let transform = AffineTransform {
+        matrix: [[0.0, -1.0], [1.0, 0.0]],
+        offset: [100.0, 0.0],
+    };
+
+    assert_eq!(transform.apply([10.0, 20.0]), [80.0, 10.0]);
+
+    let inverse = transform.inverse().unwrap();
+    assert_eq!(inverse.apply(transform.apply([10.0, 20.0])), [10.0, 20.0]);
+
+    let rect = Rectangle {
+        left: 10,
+        top: 20,
+        right: 30,
+        bottom: 60,
+    };
+    assert_eq!(
+        transform.apply_rect(&rect),
+        Rectangle {
+            left: 40,
+            top: 10,
+            right: 80,
+            bottom: 30,
+        }
+    );
+    assert_eq!(inverse.apply_rect(&transform.apply_rect(&rect)), rect);
+
+    assert!(AffineTransform {
+        matrix: [[0.0; 2]; 2],
+        offset: [0.0; 2]
+    }
+    .inverse()
+    .is_none());
+}
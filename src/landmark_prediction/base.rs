@@ -1,10 +1,140 @@
 use super::landmarks::FaceLandmarks;
-use crate::geometry::Rectangle;
+use crate::geometry::{Point, Rectangle};
 use crate::matrix::ImageMatrix;
 
 pub trait LandmarkPredictorTrait {
     /// Detect face landmarks.
     ///
     /// This will generally always return the number of landmarks as defined by the model.
-    fn face_landmarks(&mut self, image: &ImageMatrix, rect: &Rectangle) -> FaceLandmarks;
+    fn face_landmarks(&self, image: &ImageMatrix, rect: &Rectangle) -> FaceLandmarks;
+
+    /// Run the predictor on a blank image, so that any lazy initialization happens now rather than on the first real image.
+    fn warm_up(&self) {
+        let rect = Rectangle {
+            left: 0,
+            top: 0,
+            right: 150,
+            bottom: 150,
+        };
+
+        self.face_landmarks(&ImageMatrix::blank(150, 150), &rect);
+    }
+
+    /// Detect face landmarks along with an estimate of how badly they fit the face.
+    ///
+    /// The landmarks are predicted again from slightly shifted and scaled copies of `rect`, and the fit error
+    /// is the average distance the landmarks move, relative to the size of the face. Good fits are anchored to
+    /// features in the image and barely move, while occluded or wrong fits tend to follow the rectangle around.
+    /// Fit errors below about `0.02` are typical of well-predicted faces.
+    ///
+    /// The fit error is `None` if there are no landmarks, or if the predictor returns a different number of
+    /// landmarks for any of the copies.
+    fn face_landmarks_with_fit_error(
+        &self,
+        image: &ImageMatrix,
+        rect: &Rectangle,
+    ) -> (FaceLandmarks, Option<f64>) {
+        let landmarks = self.face_landmarks(image, rect);
+
+        let size = rect.width().max(rect.height()).max(1) as f64;
+        let shift = (size * 0.05).round() as i64;
+        let perturbed = [
+            Rectangle {
+                left: rect.left - shift,
+                right: rect.right - shift,
+                ..*rect
+            },
+            Rectangle {
+                left: rect.left + shift,
+                right: rect.right + shift,
+                ..*rect
+            },
+            Rectangle {
+                top: rect.top - shift,
+                bottom: rect.bottom - shift,
+                ..*rect
+            },
+            Rectangle {
+                top: rect.top + shift,
+                bottom: rect.bottom + shift,
+                ..*rect
+            },
+            rect.scaled(0.9),
+            rect.scaled(1.1),
+        ];
+
+        let total: Option<f64> = perturbed
+            .iter()
+            .map(|rect| mean_displacement(&landmarks, &self.face_landmarks(image, rect)))
+            .sum();
+
+        let fit_error = total.map(|total| total / perturbed.len() as f64 / size);
+        (landmarks, fit_error)
+    }
+}
+
+// The mean distance between corresponding points, or `None` if there aren't any or they don't correspond.
+fn mean_displacement(a: &[Point], b: &[Point]) -> Option<f64> {
+    if a.is_empty() || a.len() != b.len() {
+        return None;
+    }
+
+    let total: f64 = a
+        .iter()
+        .zip(b)
+        .map(|(a, b)| {
+            let dx = (a.x() - b.x()) as f64;
+            let dy = (a.y() - b.y()) as f64;
+            (dx * dx + dy * dy).sqrt()
+        })
+        .sum();
+
+    Some(total / a.len() as f64)
+}
+
+#[test]
+fn test_fit_error() {
+    struct Fixed;
+
+    impl LandmarkPredictorTrait for Fixed {
+        fn face_landmarks(&self, _image: &ImageMatrix, rect: &Rectangle) -> FaceLandmarks {
+            FaceLandmarks::new(rect, &[Point::new(10, 10), Point::new(40, 10)])
+        }
+    }
+
+    struct FollowsRect;
+
+    impl LandmarkPredictorTrait for FollowsRect {
+        fn face_landmarks(&self, _image: &ImageMatrix, rect: &Rectangle) -> FaceLandmarks {
+            FaceLandmarks::new(rect, &[Point::new(rect.left, rect.top)])
+        }
+    }
+
+    let image = ImageMatrix::blank(100, 100);
+    let rect = Rectangle {
+        left: 0,
+        top: 0,
+        right: 100,
+        bottom: 100,
+    };
+
+    // Finds more landmarks the further right the rectangle is.
+    struct Inconsistent;
+
+    impl LandmarkPredictorTrait for Inconsistent {
+        fn face_landmarks(&self, _image: &ImageMatrix, rect: &Rectangle) -> FaceLandmarks {
+            let parts = vec![Point::new(0, 0); (rect.left.max(0) + 1) as usize];
+            FaceLandmarks::new(rect, &parts)
+        }
+    }
+
+    let (landmarks, error) = Fixed.face_landmarks_with_fit_error(&image, &rect);
+    assert_eq!(landmarks.num_parts(), 2);
+    assert_eq!(error, Some(0.0));
+
+    let (_, error) = FollowsRect.face_landmarks_with_fit_error(&image, &rect);
+    assert!(error.unwrap() > 0.02);
+
+    let (_, error) = Inconsistent.face_landmarks_with_fit_error(&image, &rect);
+    assert_eq!(error, None);
 }
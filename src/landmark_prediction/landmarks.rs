@@ -1,14 +1,218 @@
-use std::ops::Deref;
+use std::ffi::c_void;
+use std::fmt;
+use std::ops::{Deref, DerefMut, Range};
 use std::slice;
 
-use crate::geometry::Point;
+use crate::geometry::{Point, Rectangle};
 
 cpp_class!(
     /// A wrapper around the dlib `full_object_detection` class, which internally has a `std::vector<point>`.
     /// https://github.com/davisking/dlib/blob/master/dlib/image_processing/full_object_detection.h#L21
+    ///
+    /// Named groups of landmarks follow the iBUG 300-W layout used by the 68-point model, with "left" and "right"
+    /// following the same convention as the python `face_recognition` package.
+    ///
+    /// Landmarks computed elsewhere can be wrapped with [`FaceLandmarks::new`], and then used for face chips and
+    /// encodings like any predicted landmarks. Custom C++ code can use [`FaceLandmarks::as_ptr`] to get at the
+    /// `full_object_detection` itself, whose rectangle is dlib's inclusive one rather than a [`Rectangle`].
     pub unsafe struct FaceLandmarks as "dlib::full_object_detection"
 );
 
+/// The landmarks predicted by the 5-point model.
+///
+/// "Left" and "right" follow the same convention as the python `face_recognition` package.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct FivePointLandmarks {
+    /// The two corners of the right eye.
+    pub right_eye: [Point; 2],
+    /// The two corners of the left eye.
+    pub left_eye: [Point; 2],
+    /// The bottom of the nose.
+    pub nose_tip: Point,
+}
+
+impl FaceLandmarks {
+    /// Create landmarks from a face rectangle and the points within it.
+    pub fn new(rect: &Rectangle, parts: &[Point]) -> Self {
+        let len = parts.len();
+        let parts = parts.as_ptr();
+
+        unsafe {
+            cpp!([rect as "const dlib::rectangle*", parts as "const dlib::point*", len as "size_t"] -> FaceLandmarks as "dlib::full_object_detection" {
+                return dlib::full_object_detection(to_dlib_rect(*rect), std::vector<dlib::point>(parts, parts + len));
+            })
+        }
+    }
+
+    /// The face rectangle the landmarks were predicted from.
+    pub fn rect(&self) -> Rectangle {
+        unsafe {
+            cpp!([self as "const dlib::full_object_detection*"] -> Rectangle as "dlib::rectangle" {
+                return from_dlib_rect(self->get_rect());
+            })
+        }
+    }
+
+    /// Replace the face rectangle, e.g. with [`FaceLandmarks::refined_rect`].
+    pub fn set_rect(&mut self, rect: &Rectangle) {
+        unsafe {
+            cpp!([self as "dlib::full_object_detection*", rect as "const dlib::rectangle*"] {
+                self->get_rect() = to_dlib_rect(*rect);
+            })
+        }
+    }
+
+    /// The face rectangle and the landmarks within it.
+    pub fn into_parts(self) -> (Rectangle, Vec<Point>) {
+        (self.rect(), self.to_vec())
+    }
+
+    /// A pointer to the underlying `dlib::full_object_detection`, for passing to custom C++ code.
+    pub fn as_ptr(&self) -> *const c_void {
+        self as *const Self as *const c_void
+    }
+
+    /// A mutable pointer to the underlying `dlib::full_object_detection`, for passing to custom C++ code.
+    pub fn as_mut_ptr(&mut self) -> *mut c_void {
+        self as *mut Self as *mut c_void
+    }
+
+    /// The number of landmarks, which depends on the model that predicted them.
+    pub fn num_parts(&self) -> usize {
+        self.len()
+    }
+
+    /// Get the landmark at `index`, or `None` if the model has fewer landmarks.
+    pub fn part(&self, index: usize) -> Option<Point> {
+        self.get(index).cloned()
+    }
+
+    /// Interpret the landmarks as the output of the 5-point model.
+    ///
+    /// Returns `None` if there aren't exactly 5 landmarks.
+    pub fn five_point(&self) -> Option<FivePointLandmarks> {
+        match **self {
+            [a, b, c, d, nose_tip] => Some(FivePointLandmarks {
+                right_eye: [a, b],
+                left_eye: [c, d],
+                nose_tip,
+            }),
+            _ => None,
+        }
+    }
+
+    /// The smallest rectangle containing all of the landmarks, so a single landmark gives a one pixel box.
+    ///
+    /// Returns `None` if there are no landmarks.
+    pub fn bounding_box(&self) -> Option<Rectangle> {
+        let first = self.first()?;
+        let initial = Rectangle {
+            left: first.x(),
+            top: first.y(),
+            right: first.x() + 1,
+            bottom: first.y() + 1,
+        };
+
+        Some(self.iter().fold(initial, |rect, point| Rectangle {
+            left: rect.left.min(point.x()),
+            top: rect.top.min(point.y()),
+            right: rect.right.max(point.x() + 1),
+            bottom: rect.bottom.max(point.y() + 1),
+        }))
+    }
+
+    /// A square face rectangle centred on the landmarks, with sides `1 + 2 * margin` times the larger
+    /// dimension of their bounding box.
+    ///
+    /// Detector rectangles are often off-centre, so predicting the landmarks again from this rectangle
+    /// tends to give a more stable fit.
+    ///
+    /// Returns `None` if there are no landmarks.
+    pub fn refined_rect(&self, margin: f64) -> Option<Rectangle> {
+        let bounds = self.bounding_box()?;
+        let [x, y] = bounds.center();
+        let half_size = bounds.width().max(bounds.height()) as f64 * (1.0 + 2.0 * margin) / 2.0;
+
+        Some(Rectangle {
+            left: (x - half_size).round() as i64,
+            top: (y - half_size).round() as i64,
+            right: (x + half_size).round() as i64,
+            bottom: (y + half_size).round() as i64,
+        })
+    }
+
+    fn sixty_eight_point_range(&self, range: Range<usize>) -> Option<&[Point]> {
+        if self.len() == 68 {
+            Some(&self[range])
+        } else {
+            None
+        }
+    }
+
+    /// The 17 points along the jaw, from ear to ear.
+    ///
+    /// Returns `None` if these aren't 68-point landmarks.
+    pub fn jawline(&self) -> Option<&[Point]> {
+        self.sixty_eight_point_range(0..17)
+    }
+
+    /// The 5 points of the left eyebrow.
+    ///
+    /// Returns `None` if these aren't 68-point landmarks.
+    pub fn left_eyebrow(&self) -> Option<&[Point]> {
+        self.sixty_eight_point_range(17..22)
+    }
+
+    /// The 5 points of the right eyebrow.
+    ///
+    /// Returns `None` if these aren't 68-point landmarks.
+    pub fn right_eyebrow(&self) -> Option<&[Point]> {
+        self.sixty_eight_point_range(22..27)
+    }
+
+    /// The 4 points down the bridge of the nose.
+    ///
+    /// Returns `None` if these aren't 68-point landmarks.
+    pub fn nose_bridge(&self) -> Option<&[Point]> {
+        self.sixty_eight_point_range(27..31)
+    }
+
+    /// The 5 points along the bottom of the nose.
+    ///
+    /// Returns `None` if these aren't 68-point landmarks.
+    pub fn nose_tip(&self) -> Option<&[Point]> {
+        self.sixty_eight_point_range(31..36)
+    }
+
+    /// The 6 points around the left eye.
+    ///
+    /// Returns `None` if these aren't 68-point landmarks.
+    pub fn left_eye(&self) -> Option<&[Point]> {
+        self.sixty_eight_point_range(36..42)
+    }
+
+    /// The 6 points around the right eye.
+    ///
+    /// Returns `None` if these aren't 68-point landmarks.
+    pub fn right_eye(&self) -> Option<&[Point]> {
+        self.sixty_eight_point_range(42..48)
+    }
+
+    /// The 12 points around the outside of the lips.
+    ///
+    /// Returns `None` if these aren't 68-point landmarks.
+    pub fn outer_lips(&self) -> Option<&[Point]> {
+        self.sixty_eight_point_range(48..60)
+    }
+
+    /// The 8 points around the inside of the lips.
+    ///
+    /// Returns `None` if these aren't 68-point landmarks.
+    pub fn inner_lips(&self) -> Option<&[Point]> {
+        self.sixty_eight_point_range(60..68)
+    }
+}
+
 impl Deref for FaceLandmarks {
     type Target = [Point];
 
@@ -34,6 +238,110 @@ impl Deref for FaceLandmarks {
     }
 }
 
+impl DerefMut for FaceLandmarks {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        let len = self.len();
+
+        if len == 0 {
+            &mut []
+        } else {
+            unsafe {
+                let pointer = cpp!([self as "dlib::full_object_detection*"] -> *mut Point as "dlib::point*" {
+                    return &self->part(0);
+                });
+
+                slice::from_raw_parts_mut(pointer, len)
+            }
+        }
+    }
+}
+
+impl fmt::Debug for FaceLandmarks {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("FaceLandmarks")
+            .field("rect", &self.rect())
+            .field("parts", &self.deref())
+            .finish()
+    }
+}
+
+impl PartialEq for FaceLandmarks {
+    fn eq(&self, other: &Self) -> bool {
+        self.rect() == other.rect() && self.deref() == other.deref()
+    }
+}
+
+impl From<&FaceLandmarks> for Vec<Point> {
+    fn from(landmarks: &FaceLandmarks) -> Self {
+        landmarks.to_vec()
+    }
+}
+
+impl From<FaceLandmarks> for Vec<Point> {
+    fn from(landmarks: FaceLandmarks) -> Self {
+        landmarks.to_vec()
+    }
+}
+
+impl From<(Rectangle, Vec<Point>)> for FaceLandmarks {
+    fn from((rect, parts): (Rectangle, Vec<Point>)) -> Self {
+        Self::new(&rect, &parts)
+    }
+}
+
+impl From<FaceLandmarks> for (Rectangle, Vec<Point>) {
+    fn from(landmarks: FaceLandmarks) -> Self {
+        landmarks.into_parts()
+    }
+}
+
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SerializedLandmarks {
+    rect: Rectangle,
+    parts: Vec<Point>,
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for FaceLandmarks {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        SerializedLandmarks {
+            rect: self.rect(),
+            parts: self.to_vec(),
+        }
+        .serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for FaceLandmarks {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let landmarks = SerializedLandmarks::deserialize(deserializer)?;
+        Ok(Self::new(&landmarks.rect, &landmarks.parts))
+    }
+}
+
+// The face in assets/obama_1.jpg, with landmarks in the order of dlib's five point predictor: the outer and
+// inner corners of the right eye, the outer and inner corners of the left eye, and the bottom of the nose.
+#[cfg(test)]
+pub(crate) fn obama_five_points() -> FaceLandmarks {
+    FaceLandmarks::new(
+        &Rectangle {
+            left: 305,
+            top: 113,
+            right: 521,
+            bottom: 329,
+        },
+        &[
+            Point::new(460, 190),
+            Point::new(420, 190),
+            Point::new(350, 190),
+            Point::new(390, 190),
+            Point::new(410, 260),
+        ],
+    )
+}
+
 #[test]
 fn test_default_landmarks() {
     // ensure that FaceLandmarks::default() doesnt allow memory violations in safe code
@@ -43,3 +351,146 @@ fn test_default_landmarks() {
     assert_eq!(landmarks.len(), 0);
     assert_eq!(landmarks.get(0), None);
 }
+
+#[test]
+fn test_landmarks_from_parts() {
+    let rect = Rectangle {
+        left: 0,
+        top: 0,
+        right: 10,
+        bottom: 10,
+    };
+    let parts = [Point::new(1, 2), Point::new(3, 4)];
+
+    assert_eq!(&*FaceLandmarks::new(&rect, &parts), &parts);
+}
+
+#[test]
+fn test_five_point_landmarks() {
+    let rect = Rectangle::default();
+    let parts: Vec<Point> = (0..5).map(|i| Point::new(i, i)).collect();
+
+    let five_point = FaceLandmarks::new(&rect, &parts).five_point().unwrap();
+    assert_eq!(five_point.right_eye, [parts[0], parts[1]]);
+    assert_eq!(five_point.left_eye, [parts[2], parts[3]]);
+    assert_eq!(five_point.nose_tip, parts[4]);
+
+    assert!(FaceLandmarks::new(&rect, &parts[..4])
+        .five_point()
+        .is_none());
+}
+
+#[test]
+fn test_sixty_eight_point_landmarks() {
+    let rect = Rectangle::default();
+    let parts: Vec<Point> = (0..68).map(|i| Point::new(i, i)).collect();
+    let landmarks = FaceLandmarks::new(&rect, &parts);
+
+    assert_eq!(landmarks.num_parts(), 68);
+    assert_eq!(landmarks.part(67), Some(Point::new(67, 67)));
+    assert_eq!(landmarks.part(68), None);
+    assert_eq!(landmarks.jawline().unwrap().len(), 17);
+    assert_eq!(landmarks.nose_bridge().unwrap()[0], Point::new(27, 27));
+    assert_eq!(landmarks.left_eye().unwrap()[0], Point::new(36, 36));
+    assert_eq!(landmarks.right_eye().unwrap()[0], Point::new(42, 42));
+    assert_eq!(landmarks.inner_lips().unwrap().last(), parts.last());
+
+    assert!(FaceLandmarks::new(&rect, &parts[..5]).jawline().is_none());
+}
+
+#[test]
+fn test_landmark_conversions() {
+    let rect = Rectangle {
+        left: 1,
+        top: 2,
+        right: 3,
+        bottom: 4,
+    };
+    let parts = vec![Point::new(1, 2), Point::new(3, 4)];
+    let landmarks = FaceLandmarks::new(&rect, &parts);
+
+    assert_eq!(landmarks.rect(), rect);
+    assert_eq!(landmarks.clone(), landmarks);
+    assert_eq!(Vec::from(landmarks), parts);
+}
+
+#[test]
+fn test_landmark_interop() {
+    let rect = Rectangle {
+        left: 1,
+        top: 2,
+        right: 3,
+        bottom: 4,
+    };
+    let parts = vec![Point::new(1, 2), Point::new(3, 4)];
+
+    let mut landmarks = FaceLandmarks::from((rect, parts.clone()));
+    assert_eq!(landmarks, FaceLandmarks::new(&rect, &parts));
+
+    landmarks[1] = Point::new(5, 6);
+    assert_eq!(landmarks.part(1), Some(Point::new(5, 6)));
+
+    let other = Rectangle::default();
+    landmarks.set_rect(&other);
+    assert_eq!(landmarks.rect(), other);
+    assert!(!landmarks.as_ptr().is_null());
+
+    let (rect, parts) = landmarks.into();
+    assert_eq!(rect, other);
+    assert_eq!(parts, vec![Point::new(1, 2), Point::new(5, 6)]);
+}
+
+#[test]
+fn test_refined_rect() {
+    let parts = [Point::new(10, 20), Point::new(50, 30), Point::new(30, 40)];
+    let landmarks = FaceLandmarks::new(&Rectangle::default(), &parts);
+
+    assert_eq!(
+        landmarks.bounding_box(),
+        Some(Rectangle {
+            left: 10,
+            top: 20,
+            right: 51,
+            bottom: 41,
+        })
+    );
+    assert_eq!(
+        landmarks.refined_rect(0.25),
+        Some(Rectangle {
+            left: 0,
+            top: 0,
+            right: 61,
+            bottom: 61,
+        })
+    );
+
+    let single = FaceLandmarks::new(&Rectangle::default(), &parts[..1]);
+    let pixel = single.bounding_box().unwrap();
+    assert_eq!((pixel.left, pixel.top), (10, 20));
+    assert_eq!(pixel.area(), 1);
+
+    let empty = FaceLandmarks::new(&Rectangle::default(), &[]);
+    assert_eq!(empty.bounding_box(), None);
+    assert_eq!(empty.refined_rect(0.25), None);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn can_serialize_and_deserialize() {
+    let rect = Rectangle {
+        left: 1,
+        top: 2,
+        right: 3,
+        bottom: 4,
+    };
+    let landmarks = FaceLandmarks::new(&rect, &[Point::new(1, 2), Point::new(3, 4)]);
+
+    let json = serde_json::to_string(&landmarks).unwrap();
+    assert_eq!(
+        json,
+        r#"{"rect":{"left":1,"top":2,"right":3,"bottom":4},"parts":[[1,2],[3,4]]}"#
+    );
+
+    let decoded: FaceLandmarks = serde_json::from_str(&json).unwrap();
+    assert_eq!(decoded, landmarks);
+}
@@ -3,7 +3,12 @@
 mod base;
 mod landmarks;
 mod model;
+mod normalize;
+mod smoothing;
 
 pub use self::base::LandmarkPredictorTrait;
-pub use self::landmarks::FaceLandmarks;
-pub use self::model::LandmarkPredictor;
+#[cfg(test)]
+pub(crate) use self::landmarks::obama_five_points;
+pub use self::landmarks::{FaceLandmarks, FivePointLandmarks};
+pub use self::model::{LandmarkPredictor, ShapePredictorTrainingOptions};
+pub use self::smoothing::{LandmarkSmoother, SmoothingFilter};
@@ -2,9 +2,37 @@ use std::path::Path;
 
 use super::base::LandmarkPredictorTrait;
 use super::landmarks::FaceLandmarks;
-use crate::base::path_as_cstring;
+use crate::base::{path_as_cstring, path_to_cstring, CppBytes};
 use crate::geometry::Rectangle;
-use crate::matrix::ImageMatrix;
+use crate::matrix::{ImageMatrix, ImageMatrixView};
+
+/// Options for training a [`LandmarkPredictor`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[repr(C)]
+pub struct ShapePredictorTrainingOptions {
+    /// The number of regressors in the cascade. More levels are more accurate but slower to train.
+    pub cascade_depth: usize,
+    /// The depth of each regression tree. The size of the model grows exponentially with this.
+    pub tree_depth: usize,
+    /// The regularization parameter, between 0 and 1. Smaller values generalize better but need more data.
+    pub nu: f64,
+    /// How many randomly perturbed copies of each training shape to use, to make up for small datasets.
+    pub oversampling_amount: usize,
+    /// The number of threads to train with. More threads train faster on machines with the cores for them.
+    pub num_threads: usize,
+}
+
+impl Default for ShapePredictorTrainingOptions {
+    fn default() -> Self {
+        Self {
+            cascade_depth: 10,
+            tree_depth: 4,
+            nu: 0.1,
+            oversampling_amount: 20,
+            num_threads: 4,
+        }
+    }
+}
 
 /// A face landmark predictor.
 #[derive(Clone)]
@@ -43,6 +71,33 @@ impl LandmarkPredictor {
             Ok(Self { inner })
         }
     }
+
+    /// Deserialize the landmark predictor from a model file that has already been read into memory, e.g. with `include_bytes!`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        let inner = LandmarkPredictorInner::default();
+
+        let deserialized = unsafe {
+            let data = bytes.as_ptr();
+            let len = bytes.len();
+            let predictor = &inner;
+
+            cpp!([data as "const char*", len as "size_t", predictor as "dlib::shape_predictor*"] -> bool as "bool" {
+                try {
+                    std::istringstream stream(std::string(data, len));
+                    dlib::deserialize(*predictor, stream);
+                    return true;
+                } catch (const dlib::error& exception) {
+                    return false;
+                }
+            })
+        };
+
+        if !deserialized {
+            Err("Failed to deserialize landmark predictor".into())
+        } else {
+            Ok(Self { inner })
+        }
+    }
 }
 
 #[cfg(feature = "embed-lp")]
@@ -55,14 +110,208 @@ impl Default for LandmarkPredictor {
     }
 }
 
+impl LandmarkPredictor {
+    /// Train a new landmark predictor from images and the landmarks of every face in them.
+    ///
+    /// All of the landmarks must have the same number of points.
+    pub fn train(
+        samples: &[(ImageMatrix, Vec<FaceLandmarks>)],
+        options: &ShapePredictorTrainingOptions,
+    ) -> Result<Self, String> {
+        if samples.iter().all(|(_, shapes)| shapes.is_empty()) {
+            return Err("No landmarks to train the landmark predictor from".into());
+        }
+
+        let images: Vec<*const ImageMatrix> = samples.iter().map(|(image, _)| image as _).collect();
+        let shapes: Vec<*const FaceLandmarks> =
+            samples.iter().map(|(_, shapes)| shapes.as_ptr()).collect();
+        let counts: Vec<usize> = samples.iter().map(|(_, shapes)| shapes.len()).collect();
+
+        let inner = LandmarkPredictorInner::default();
+        let error = CppBytes::default();
+
+        let trained = unsafe {
+            let len = samples.len();
+            let images = images.as_ptr();
+            let shapes = shapes.as_ptr();
+            let counts = counts.as_ptr();
+            let predictor = &inner;
+            let error = &error;
+
+            cpp!([images as "const dlib::matrix<dlib::rgb_pixel>* const*", shapes as "const dlib::full_object_detection* const*", counts as "const size_t*", len as "size_t", options as "const shape_predictor_training_options*", predictor as "dlib::shape_predictor*", error as "std::string*"] -> bool as "bool" {
+                std::vector<dlib::matrix<dlib::rgb_pixel>> image_copies;
+                std::vector<std::vector<dlib::full_object_detection>> image_shapes;
+
+                for (size_t i = 0; i < len; i++) {
+                    image_copies.push_back(*images[i]);
+                    image_shapes.emplace_back(shapes[i], shapes[i] + counts[i]);
+                }
+
+                try {
+                    *predictor = train_shape_predictor(image_copies, image_shapes, *options);
+                    return true;
+                } catch (const std::exception& exception) {
+                    *error = exception.what();
+                    return false;
+                }
+            })
+        };
+
+        if !trained {
+            Err(format!(
+                "Failed to train landmark predictor: {}",
+                String::from_utf8_lossy(&error)
+            ))
+        } else {
+            Ok(Self { inner })
+        }
+    }
+
+    /// Train a new landmark predictor from an `imglab` XML dataset file with part annotations.
+    pub fn train_from_xml<P: AsRef<Path>>(
+        filename: P,
+        options: &ShapePredictorTrainingOptions,
+    ) -> Result<Self, String> {
+        let string = path_as_cstring(filename.as_ref())?;
+        let inner = LandmarkPredictorInner::default();
+        let error = CppBytes::default();
+
+        let trained = unsafe {
+            let filename = string.as_ptr();
+            let predictor = &inner;
+            let error = &error;
+
+            cpp!([filename as "char*", options as "const shape_predictor_training_options*", predictor as "dlib::shape_predictor*", error as "std::string*"] -> bool as "bool" {
+                std::vector<dlib::matrix<dlib::rgb_pixel>> images;
+                std::vector<std::vector<dlib::full_object_detection>> shapes;
+
+                try {
+                    dlib::load_image_dataset(images, shapes, filename);
+                    *predictor = train_shape_predictor(images, shapes, *options);
+                    return true;
+                } catch (const std::exception& exception) {
+                    *error = exception.what();
+                    return false;
+                }
+            })
+        };
+
+        if !trained {
+            Err(format!(
+                "Failed to train landmark predictor from '{}': {}",
+                filename.as_ref().display(),
+                String::from_utf8_lossy(&error)
+            ))
+        } else {
+            Ok(Self { inner })
+        }
+    }
+
+    /// Save the landmark predictor to a file so it can be loaded later with [`LandmarkPredictor::new`].
+    pub fn save<P: AsRef<Path>>(&self, filename: P) -> Result<(), String> {
+        let string = path_to_cstring(filename.as_ref())?;
+
+        let serialized = unsafe {
+            let filename = string.as_ptr();
+            let predictor = &self.inner;
+
+            cpp!([filename as "char*", predictor as "const dlib::shape_predictor*"] -> bool as "bool" {
+                try {
+                    dlib::serialize(filename) << *predictor;
+                    return true;
+                } catch (const dlib::error& exception) {
+                    return false;
+                }
+            })
+        };
+
+        if !serialized {
+            Err(format!(
+                "Failed to serialize '{}'",
+                filename.as_ref().display()
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Predict face landmarks from a view over a borrowed rgb buffer, without copying it.
+    pub fn face_landmarks_view(&self, image: &ImageMatrixView, rect: &Rectangle) -> FaceLandmarks {
+        let predictor = &self.inner;
+
+        unsafe {
+            cpp!([predictor as "dlib::shape_predictor*", image as "const rgb_image_view*", rect as "dlib::rectangle*"] -> FaceLandmarks as "dlib::full_object_detection" {
+                return (*predictor)(*image, to_dlib_rect(*rect));
+            })
+        }
+    }
+
+    /// The number of landmarks the model predicts.
+    ///
+    /// Any trained shape predictor can be loaded, not just the 68 and 5-point face models.
+    pub fn num_parts(&self) -> usize {
+        let predictor = &self.inner;
+
+        unsafe {
+            cpp!([predictor as "const dlib::shape_predictor*"] -> usize as "size_t" {
+                return predictor->num_parts();
+            })
+        }
+    }
+
+    /// Load the 5-point landmark predictor.
+    ///
+    /// This model is much smaller than the default 68-point one, and its landmarks are all the face encoder needs.
+    #[cfg(feature = "embed-lp5")]
+    pub fn default_5_point() -> Self {
+        Self::new(crate::embed::path_for_file(
+            "shape_predictor_5_face_landmarks.dat",
+        ))
+        .unwrap()
+    }
+}
+
 impl LandmarkPredictorTrait for LandmarkPredictor {
-    fn face_landmarks(&mut self, image: &ImageMatrix, rect: &Rectangle) -> FaceLandmarks {
+    fn face_landmarks(&self, image: &ImageMatrix, rect: &Rectangle) -> FaceLandmarks {
         let predictor = &self.inner;
 
         unsafe {
             cpp!([predictor as "dlib::shape_predictor*", image as "dlib::matrix<dlib::rgb_pixel>*", rect as "dlib::rectangle*"] -> FaceLandmarks as "dlib::full_object_detection" {
-                return (*predictor)(*image, *rect);
+                return (*predictor)(*image, to_dlib_rect(*rect));
             })
         }
     }
 }
+
+#[cfg(feature = "image")]
+#[test]
+fn test_train_landmark_predictor() {
+    let image = image::open("assets/obama_1.jpg").unwrap().to_rgb();
+    let matrix = ImageMatrix::from_image(&image);
+    let truth = super::landmarks::obama_five_points();
+    let face = truth.rect();
+
+    let options = ShapePredictorTrainingOptions {
+        cascade_depth: 2,
+        oversampling_amount: 5,
+        ..Default::default()
+    };
+    let samples = [(matrix.clone(), vec![truth])];
+    let predictor = LandmarkPredictor::train(&samples, &options).unwrap();
+    assert_eq!(predictor.num_parts(), 5);
+
+    let landmarks = predictor.face_landmarks(&matrix, &face);
+    assert_eq!(landmarks.num_parts(), 5);
+
+    let path = std::env::temp_dir().join("dlib-face-recognition-trained-predictor.dat");
+    predictor.save(&path).unwrap();
+    assert!(predictor.save("nul\0byte.dat").is_err());
+    assert_eq!(
+        LandmarkPredictor::new(&path)
+            .unwrap()
+            .face_landmarks(&matrix, &face),
+        landmarks
+    );
+
+    assert!(LandmarkPredictor::train(&[], &options).is_err());
+}
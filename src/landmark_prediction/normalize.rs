@@ -0,0 +1,107 @@
+use super::landmarks::FaceLandmarks;
+use crate::geometry::Point;
+
+fn centroid(points: &[Point]) -> [f64; 2] {
+    let n = points.len() as f64;
+    let (x, y) = points
+        .iter()
+        .fold((0.0, 0.0), |(x, y), p| (x + p.x() as f64, y + p.y() as f64));
+    [x / n, y / n]
+}
+
+impl FaceLandmarks {
+    /// The centres of the left and right eyes, following the same naming as [`FaceLandmarks::left_eye`].
+    ///
+    /// Returns `None` unless these are 68 or 5-point landmarks.
+    pub fn eye_centers(&self) -> Option<([f64; 2], [f64; 2])> {
+        if let Some(five_point) = self.five_point() {
+            return Some((
+                centroid(&five_point.left_eye),
+                centroid(&five_point.right_eye),
+            ));
+        }
+
+        Some((centroid(self.left_eye()?), centroid(self.right_eye()?)))
+    }
+
+    /// The landmarks relative to their bounding box, scaled so that its larger side has length 1, which puts
+    /// every landmark between 0 inclusive and 1 exclusive.
+    ///
+    /// Returns an empty vector if there are no landmarks.
+    pub fn normalized_to_unit_box(&self) -> Vec<[f64; 2]> {
+        let bounds = match self.bounding_box() {
+            Some(bounds) => bounds,
+            None => return Vec::new(),
+        };
+
+        let size = bounds.width().max(bounds.height()) as f64;
+
+        self.iter()
+            .map(|point| {
+                [
+                    (point.x() - bounds.left) as f64 / size,
+                    (point.y() - bounds.top) as f64 / size,
+                ]
+            })
+            .collect()
+    }
+
+    /// The landmarks relative to the midpoint between the eyes, scaled so that the eyes are 1 apart.
+    ///
+    /// This removes the position and size of the face but keeps its rotation.
+    ///
+    /// Returns `None` unless these are 68 or 5-point landmarks, or if both eyes are in the same place.
+    pub fn normalized_by_interocular_distance(&self) -> Option<Vec<[f64; 2]>> {
+        let (left, right) = self.eye_centers()?;
+
+        let distance = ((left[0] - right[0]).powi(2) + (left[1] - right[1]).powi(2)).sqrt();
+        if distance == 0.0 {
+            return None;
+        }
+
+        let center = [(left[0] + right[0]) / 2.0, (left[1] + right[1]) / 2.0];
+
+        Some(
+            self.iter()
+                .map(|point| {
+                    [
+                        (point.x() as f64 - center[0]) / distance,
+                        (point.y() as f64 - center[1]) / distance,
+                    ]
+                })
+                .collect(),
+        )
+    }
+}
+
+#[test]
+fn test_normalized_landmarks() {
+    use crate::geometry::Rectangle;
+
+    let rect = Rectangle::default();
+    let parts = [
+        Point::new(40, 10),
+        Point::new(30, 10),
+        Point::new(10, 10),
+        Point::new(20, 10),
+        Point::new(25, 30),
+    ];
+    let landmarks = FaceLandmarks::new(&rect, &parts);
+
+    assert_eq!(landmarks.eye_centers(), Some(([15.0, 10.0], [35.0, 10.0])));
+
+    let unit = landmarks.normalized_to_unit_box();
+    assert_eq!(unit[0], [30.0 / 31.0, 0.0]);
+    assert_eq!(unit[4], [15.0 / 31.0, 20.0 / 31.0]);
+
+    let interocular = landmarks.normalized_by_interocular_distance().unwrap();
+    assert_eq!(interocular[2], [-0.75, 0.0]);
+    assert_eq!(interocular[4], [0.0, 1.0]);
+
+    let other = FaceLandmarks::new(&rect, &parts[..3]);
+    assert_eq!(other.eye_centers(), None);
+    assert_eq!(other.normalized_by_interocular_distance(), None);
+    assert!(FaceLandmarks::new(&rect, &[])
+        .normalized_to_unit_box()
+        .is_empty());
+}
@@ -0,0 +1,282 @@
+use std::collections::HashMap;
+use std::f64::consts::PI;
+
+use crate::geometry::Point;
+use crate::landmark_prediction::FaceLandmarks;
+
+/// How landmark positions are smoothed between frames.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SmoothingFilter {
+    /// Exponential smoothing, moving each landmark `alpha` of the way towards its new position every frame.
+    Exponential {
+        /// The fraction of the way to move each frame, between `0.0` and `1.0`. Lower values are smoother but
+        /// lag further behind movement.
+        alpha: f64,
+    },
+    /// The One Euro filter, which smooths heavily while landmarks are still and lightly as they move,
+    /// reducing jitter without adding much lag.
+    /// https://gery.casiez.net/1euro/
+    OneEuro {
+        /// The cutoff frequency in Hz while the landmarks are still. Lower values reduce jitter.
+        min_cutoff: f64,
+        /// How quickly the cutoff frequency increases with speed in pixels per second. Higher values reduce lag.
+        beta: f64,
+        /// The cutoff frequency in Hz used to smooth the speed estimate.
+        derivative_cutoff: f64,
+    },
+}
+
+impl Default for SmoothingFilter {
+    /// A One Euro filter tuned for landmarks in pixels at video frame rates.
+    fn default() -> Self {
+        SmoothingFilter::OneEuro {
+            min_cutoff: 1.0,
+            beta: 0.05,
+            derivative_cutoff: 1.0,
+        }
+    }
+}
+
+fn smoothing_factor(cutoff: f64, dt: f64) -> f64 {
+    let tau = 1.0 / (2.0 * PI * cutoff);
+    1.0 / (1.0 + tau / dt)
+}
+
+fn lerp(from: [f64; 2], to: [f64; 2], alpha: f64) -> [f64; 2] {
+    [
+        from[0] + alpha * (to[0] - from[0]),
+        from[1] + alpha * (to[1] - from[1]),
+    ]
+}
+
+#[derive(Clone, Debug)]
+struct Track {
+    positions: Vec<[f64; 2]>,
+    velocities: Vec<[f64; 2]>,
+}
+
+/// Smooths the landmarks of faces across video frames, keeping separate state for each tracked face.
+#[derive(Clone, Debug)]
+pub struct LandmarkSmoother {
+    filter: SmoothingFilter,
+    tracks: HashMap<u64, Track>,
+}
+
+impl LandmarkSmoother {
+    /// Create a smoother that applies `filter` to every track.
+    pub fn new(filter: SmoothingFilter) -> Self {
+        Self {
+            filter,
+            tracks: HashMap::new(),
+        }
+    }
+
+    /// The filter applied to every track.
+    pub fn filter(&self) -> SmoothingFilter {
+        self.filter
+    }
+
+    /// Smooth the landmarks of the face with the given track id, `dt` seconds after its previous frame.
+    ///
+    /// The first landmarks seen for a track are returned unchanged. Fails if `dt` isn't positive, as with
+    /// [`LandmarkSmoother::smooth_points`].
+    pub fn smooth(
+        &mut self,
+        track_id: u64,
+        landmarks: &FaceLandmarks,
+        dt: f64,
+    ) -> Result<FaceLandmarks, String> {
+        let points: Vec<[f64; 2]> = landmarks
+            .iter()
+            .map(|point| [point.x() as f64, point.y() as f64])
+            .collect();
+
+        let parts: Vec<Point> = self
+            .smooth_points(track_id, &points, dt)?
+            .iter()
+            .map(|point| Point::new(point[0].round() as i64, point[1].round() as i64))
+            .collect();
+
+        Ok(FaceLandmarks::new(&landmarks.rect(), &parts))
+    }
+
+    /// Smooth sub-pixel landmark positions for the face with the given track id, `dt` seconds after its
+    /// previous frame.
+    ///
+    /// The state of the track is reset if the number of points changes. Fails without touching the track if
+    /// `dt` isn't positive, e.g. for two frames with the same timestamp.
+    pub fn smooth_points(
+        &mut self,
+        track_id: u64,
+        points: &[[f64; 2]],
+        dt: f64,
+    ) -> Result<Vec<[f64; 2]>, String> {
+        if dt.is_nan() || dt <= 0.0 {
+            return Err(format!(
+                "The time between frames must be positive, but got {}",
+                dt
+            ));
+        }
+
+        let filter = self.filter;
+
+        let track = match self.tracks.get_mut(&track_id) {
+            Some(track) if track.positions.len() == points.len() => track,
+            _ => {
+                self.tracks.insert(
+                    track_id,
+                    Track {
+                        positions: points.to_vec(),
+                        velocities: vec![[0.0, 0.0]; points.len()],
+                    },
+                );
+                return Ok(points.to_vec());
+            }
+        };
+
+        for ((position, velocity), point) in track
+            .positions
+            .iter_mut()
+            .zip(track.velocities.iter_mut())
+            .zip(points)
+        {
+            match filter {
+                SmoothingFilter::Exponential { alpha } => {
+                    *position = lerp(*position, *point, alpha);
+                }
+                SmoothingFilter::OneEuro {
+                    min_cutoff,
+                    beta,
+                    derivative_cutoff,
+                } => {
+                    let raw_velocity =
+                        [(point[0] - position[0]) / dt, (point[1] - position[1]) / dt];
+                    *velocity = lerp(
+                        *velocity,
+                        raw_velocity,
+                        smoothing_factor(derivative_cutoff, dt),
+                    );
+
+                    let speed = (velocity[0] * velocity[0] + velocity[1] * velocity[1]).sqrt();
+                    let cutoff = min_cutoff + beta * speed;
+                    *position = lerp(*position, *point, smoothing_factor(cutoff, dt));
+                }
+            }
+        }
+
+        Ok(track.positions.clone())
+    }
+
+    /// Forget the state of a track, e.g. once the face has left the frame.
+    pub fn remove(&mut self, track_id: u64) {
+        self.tracks.remove(&track_id);
+    }
+
+    /// Forget the state of all tracks.
+    pub fn clear(&mut self) {
+        self.tracks.clear();
+    }
+
+    /// The number of tracks being smoothed.
+    pub fn len(&self) -> usize {
+        self.tracks.len()
+    }
+
+    /// Whether there are no tracks being smoothed.
+    pub fn is_empty(&self) -> bool {
+        self.tracks.is_empty()
+    }
+}
+
+impl Default for LandmarkSmoother {
+    fn default() -> Self {
+        Self::new(SmoothingFilter::default())
+    }
+}
+
+#[test]
+fn test_exponential_smoothing() {
+    let mut smoother = LandmarkSmoother::new(SmoothingFilter::Exponential { alpha: 0.5 });
+
+    assert_eq!(
+        smoother.smooth_points(1, &[[0.0, 0.0]], 0.1).unwrap(),
+        vec![[0.0, 0.0]]
+    );
+    assert_eq!(
+        smoother.smooth_points(1, &[[4.0, 8.0]], 0.1).unwrap(),
+        vec![[2.0, 4.0]]
+    );
+    assert_eq!(
+        smoother.smooth_points(1, &[[4.0, 8.0]], 0.1).unwrap(),
+        vec![[3.0, 6.0]]
+    );
+
+    // tracks are independent
+    assert_eq!(
+        smoother.smooth_points(2, &[[4.0, 8.0]], 0.1).unwrap(),
+        vec![[4.0, 8.0]]
+    );
+    assert_eq!(smoother.len(), 2);
+
+    // a different number of points starts the track again
+    assert_eq!(
+        smoother
+            .smooth_points(1, &[[1.0, 1.0], [2.0, 2.0]], 0.1)
+            .unwrap(),
+        vec![[1.0, 1.0], [2.0, 2.0]]
+    );
+
+    smoother.remove(2);
+    assert_eq!(smoother.len(), 1);
+    smoother.clear();
+    assert!(smoother.is_empty());
+}
+
+#[test]
+fn test_one_euro_smoothing() {
+    let mut smoother = LandmarkSmoother::default();
+    let dt = 1.0 / 30.0;
+
+    // jitter around a still point is damped
+    smoother.smooth_points(0, &[[100.0, 100.0]], dt).unwrap();
+    let jittered = smoother.smooth_points(0, &[[102.0, 98.0]], dt).unwrap()[0];
+    assert!((jittered[0] - 100.0).abs() < 1.0);
+    assert!((jittered[1] - 100.0).abs() < 1.0);
+
+    // a point that stops moving is eventually reached
+    let mut position = [0.0, 0.0];
+    for _ in 0..300 {
+        position = smoother.smooth_points(0, &[[150.0, 50.0]], dt).unwrap()[0];
+    }
+    assert!((position[0] - 150.0).abs() < 0.1);
+    assert!((position[1] - 50.0).abs() < 0.1);
+
+    // a repeated timestamp is an error, and leaves the track where it was
+    assert!(smoother.smooth_points(0, &[[0.0, 0.0]], 0.0).is_err());
+    assert!(smoother.smooth_points(0, &[[0.0, 0.0]], f64::NAN).is_err());
+    let next = smoother.smooth_points(0, &[[150.0, 50.0]], dt).unwrap()[0];
+    assert!((next[0] - position[0]).abs() < 0.1);
+}
+
+#[test]
+fn test_smooth_landmarks() {
+    use crate::geometry::Rectangle;
+
+    let mut smoother = LandmarkSmoother::new(SmoothingFilter::Exponential { alpha: 0.5 });
+    let rect = Rectangle {
+        left: 0,
+        top: 0,
+        right: 10,
+        bottom: 10,
+    };
+
+    smoother
+        .smooth(0, &FaceLandmarks::new(&rect, &[Point::new(0, 0)]), 0.1)
+        .unwrap();
+    let smoothed = smoother
+        .smooth(0, &FaceLandmarks::new(&rect, &[Point::new(4, 6)]), 0.1)
+        .unwrap();
+
+    assert_eq!(smoothed.rect(), rect);
+    assert_eq!(&*smoothed, &[Point::new(2, 3)]);
+}
@@ -21,19 +21,69 @@ extern crate cpp;
 
 mod wrapper;
 
+mod anonymize;
 mod base;
+mod chip_model;
+pub mod clustering;
+mod cuda;
+pub mod draw;
 mod embed;
+mod exif;
+pub mod face_alignment;
+pub mod face_analysis;
 mod face_detection;
 mod face_encoding;
 mod geometry;
 mod landmark_prediction;
 mod matrix;
+mod pipeline;
+pub mod quality;
+pub mod video;
 
-pub use self::geometry::{Point, Rectangle};
-pub use self::matrix::ImageMatrix;
+pub use self::anonymize::{anonymize, anonymize_landmarks, Anonymization};
+pub use self::chip_model::ChipModel;
+pub use self::cuda::{cuda_available, cuda_device, cuda_device_count, set_cuda_device};
+pub use self::geometry::{AffineTransform, Point, Rectangle};
+pub use self::matrix::{
+    GrayImageMatrix, ImageMatrix, ImageMatrixView, IntensityScaling, Interpolation,
+};
+pub use self::pipeline::{
+    DetectorKind, Face, FrameAnalysis, LandmarkKind, Pipeline, PipelineBuilder,
+};
 
-pub use self::face_detection::{FaceDetector, FaceDetectorCnn, FaceDetectorTrait, FaceLocations};
+pub use self::face_detection::{find_candidate_object_locations, CandidateOptions};
+pub use self::face_detection::{non_max_suppression, BoxMerge, NmsOptions};
+pub use self::face_detection::{
+    DetectorPool, EnsembleDetector, EnsembleMode, FaceDetection, FaceDetections, FaceDetector,
+    FaceDetectorCnn, FaceDetectorTrait, FaceLocations, ObjectDetector,
+    ObjectDetectorTrainingOptions, RotatingDetector, SizeFilter, TiledDetector,
+};
+pub use self::face_encoding::numpy;
+pub use self::face_encoding::{pairwise_distances, DistanceMatrix};
+pub use self::face_encoding::{verify, Verification, DEFAULT_MATCH_THRESHOLD};
 pub use self::face_encoding::{
-    FaceComparer, FaceEncoderNetwork, FaceEncoderTrait, FaceEncoding, FaceEncodings,
+    DistanceCalibration, DistanceMetric, EncodingIndex, EncodingSet, FaceComparer, FaceDatabase,
+    FaceEncoderNetwork, FaceEncoderTrait, FaceEncoding, FaceEncodingF32, FaceEncodingQ8,
+    FaceEncodings, MetricTrainingOptions, PcaProjector,
 };
-pub use self::landmark_prediction::{LandmarkPredictor, LandmarkPredictorTrait};
+#[cfg(feature = "onnx")]
+pub use self::face_encoding::{OnnxChipModel, OnnxEncoderOptions, OnnxFaceEncoder};
+pub use self::landmark_prediction::{
+    FaceLandmarks, FivePointLandmarks, LandmarkPredictor, LandmarkPredictorTrait, LandmarkSmoother,
+    ShapePredictorTrainingOptions, SmoothingFilter,
+};
+
+#[test]
+fn test_send_sync() {
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    assert_send_sync::<FaceDetector>();
+    assert_send_sync::<FaceDetectorCnn>();
+    assert_send_sync::<ObjectDetector>();
+    assert_send_sync::<TiledDetector<FaceDetector>>();
+    assert_send_sync::<EnsembleDetector>();
+    assert_send_sync::<LandmarkPredictor>();
+    assert_send_sync::<FaceEncoderNetwork>();
+    assert_send_sync::<Pipeline>();
+    assert_send_sync::<video::VideoProcessor>();
+}
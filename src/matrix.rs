@@ -1,6 +1,56 @@
+use std::convert::TryFrom;
+use std::marker::PhantomData;
+#[cfg(feature = "image")]
 use std::ops::Deref;
+use std::path::Path;
 
-use image::{ImageBuffer, Rgb};
+#[cfg(feature = "image")]
+use image::{GrayImage, ImageBuffer, Luma, Rgb, RgbImage};
+
+use crate::base::{path_as_cstring, CppBytes};
+use crate::geometry::{AffineTransform, Rectangle};
+
+const MAGIC: &[u8] = b"DFRIM";
+const VERSION: u32 = 1;
+const FLAG_COMPRESSED: u8 = 1;
+// The magic, version, flags, width and height.
+const HEADER_SIZE: usize = 5 + 4 + 1 + 4 + 4;
+
+/// How pixels are sampled when resizing an image.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Interpolation {
+    /// The nearest pixel. Fastest, but blocky when upscaling.
+    Nearest,
+    /// A weighted average of the four nearest pixels.
+    #[default]
+    Bilinear,
+    /// A quadratic fit of the nine nearest pixels. Sharpest, but slowest.
+    Quadratic,
+}
+
+/// How values from a high bit depth or floating point image are scaled to the `0..=255` range the detectors expect.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum IntensityScaling {
+    /// Map `low` to 0 and `high` to 255, clamping values outside of the range.
+    ///
+    /// Use this when the range of the sensor is known, e.g. `0.0` to `65535.0` for 16-bit images, so that
+    /// frames are scaled consistently.
+    Range { low: f64, high: f64 },
+    /// Map the smallest value in the image to 0 and the largest to 255.
+    MinMax,
+    /// Map the `low` and `high` percentiles of the values, between 0 and 100, to 0 and 255, which is robust
+    /// to the few very hot or cold pixels common in thermal images.
+    Percentile { low: f64, high: f64 },
+}
+
+impl Default for IntensityScaling {
+    fn default() -> Self {
+        IntensityScaling::Percentile {
+            low: 1.0,
+            high: 99.0,
+        }
+    }
+}
 
 cpp_class!(
     /// A wrapper around a `matrix<rgb_pixel>`, dlibs own image class.
@@ -34,6 +84,306 @@ impl ImageMatrix {
         })
     }
 
+    /// Create a black image of the given size.
+    pub fn blank(width: usize, height: usize) -> Self {
+        unsafe {
+            cpp!([width as "size_t", height as "size_t"] -> ImageMatrix as "dlib::matrix<dlib::rgb_pixel>" {
+                dlib::matrix<dlib::rgb_pixel> image(height, width);
+                image = dlib::rgb_pixel(0, 0, 0);
+                return image;
+            })
+        }
+    }
+
+    /// Copy a matrix from a raw buffer of rgb channel values, with rows `stride` bytes apart.
+    ///
+    /// Fails if the stride is too small for a row, or if the buffer is too small for the image.
+    pub fn from_rgb(
+        width: usize,
+        height: usize,
+        stride: usize,
+        data: &[u8],
+    ) -> Result<Self, String> {
+        Self::from_raw(width, height, stride, data, 3, [0, 1, 2])
+    }
+
+    /// Copy a matrix from a raw buffer of bgr channel values, with rows `stride` bytes apart.
+    pub fn from_bgr(
+        width: usize,
+        height: usize,
+        stride: usize,
+        data: &[u8],
+    ) -> Result<Self, String> {
+        Self::from_raw(width, height, stride, data, 3, [2, 1, 0])
+    }
+
+    /// Copy a matrix from a raw buffer of rgba channel values, with rows `stride` bytes apart.
+    ///
+    /// The alpha channel is ignored.
+    pub fn from_rgba(
+        width: usize,
+        height: usize,
+        stride: usize,
+        data: &[u8],
+    ) -> Result<Self, String> {
+        Self::from_raw(width, height, stride, data, 4, [0, 1, 2])
+    }
+
+    /// Copy a matrix from a raw buffer of bgra channel values, with rows `stride` bytes apart.
+    ///
+    /// The alpha channel is ignored.
+    pub fn from_bgra(
+        width: usize,
+        height: usize,
+        stride: usize,
+        data: &[u8],
+    ) -> Result<Self, String> {
+        Self::from_raw(width, height, stride, data, 4, [2, 1, 0])
+    }
+
+    /// Copy a matrix from a raw buffer of grayscale values, with rows `stride` bytes apart.
+    pub fn from_gray(
+        width: usize,
+        height: usize,
+        stride: usize,
+        data: &[u8],
+    ) -> Result<Self, String> {
+        Self::from_raw(width, height, stride, data, 1, [0, 0, 0])
+    }
+
+    /// Convert an NV12 image, a full resolution luma plane followed by a half resolution plane of interleaved
+    /// u and v values, as output by most hardware decoders.
+    ///
+    /// The values are taken to be BT.601 limited range, the usual encoding for cameras.
+    pub fn from_nv12(
+        width: usize,
+        height: usize,
+        y_stride: usize,
+        y: &[u8],
+        uv_stride: usize,
+        uv: &[u8],
+    ) -> Result<Self, String> {
+        check_buffer(width, height, y_stride, y, 1)?;
+        check_buffer(width.div_ceil(2), height.div_ceil(2), uv_stride, uv, 2)?;
+
+        let y = y.as_ptr();
+        let uv = uv.as_ptr();
+
+        Ok(unsafe {
+            cpp!([width as "size_t", height as "size_t", y_stride as "size_t", y as "const uint8_t*", uv_stride as "size_t", uv as "const uint8_t*"] -> ImageMatrix as "dlib::matrix<dlib::rgb_pixel>" {
+                dlib::matrix<dlib::rgb_pixel> image(height, width);
+
+                for (size_t row = 0; row < height; row++) {
+                    const uint8_t* luma = y + row * y_stride;
+                    const uint8_t* chroma = uv + (row / 2) * uv_stride;
+
+                    for (size_t column = 0; column < width; column++) {
+                        const uint8_t* pair = chroma + (column / 2) * 2;
+                        image(row, column) = yuv_to_rgb(luma[column], pair[0], pair[1]);
+                    }
+                }
+
+                return image;
+            })
+        })
+    }
+
+    /// Convert an I420 image, a full resolution luma plane followed by half resolution u and v planes.
+    ///
+    /// The values are taken to be BT.601 limited range, the usual encoding for cameras.
+    pub fn from_i420(
+        width: usize,
+        height: usize,
+        y_stride: usize,
+        y: &[u8],
+        uv_stride: usize,
+        u: &[u8],
+        v: &[u8],
+    ) -> Result<Self, String> {
+        check_buffer(width, height, y_stride, y, 1)?;
+        check_buffer(width.div_ceil(2), height.div_ceil(2), uv_stride, u, 1)?;
+        check_buffer(width.div_ceil(2), height.div_ceil(2), uv_stride, v, 1)?;
+
+        let y = y.as_ptr();
+        let u = u.as_ptr();
+        let v = v.as_ptr();
+
+        Ok(unsafe {
+            cpp!([width as "size_t", height as "size_t", y_stride as "size_t", y as "const uint8_t*", uv_stride as "size_t", u as "const uint8_t*", v as "const uint8_t*"] -> ImageMatrix as "dlib::matrix<dlib::rgb_pixel>" {
+                dlib::matrix<dlib::rgb_pixel> image(height, width);
+
+                for (size_t row = 0; row < height; row++) {
+                    const uint8_t* luma = y + row * y_stride;
+                    const size_t chroma = (row / 2) * uv_stride;
+
+                    for (size_t column = 0; column < width; column++) {
+                        image(row, column) = yuv_to_rgb(luma[column], u[chroma + column / 2], v[chroma + column / 2]);
+                    }
+                }
+
+                return image;
+            })
+        })
+    }
+
+    /// Convert a YUYV (YUY2) image, where each pair of pixels is stored as luma, u, luma, v, as output by
+    /// most webcams.
+    ///
+    /// The values are taken to be BT.601 limited range, the usual encoding for cameras.
+    pub fn from_yuyv(
+        width: usize,
+        height: usize,
+        stride: usize,
+        data: &[u8],
+    ) -> Result<Self, String> {
+        check_buffer(width.div_ceil(2), height, stride, data, 4)?;
+
+        let ptr = data.as_ptr();
+
+        Ok(unsafe {
+            cpp!([width as "size_t", height as "size_t", stride as "size_t", ptr as "const uint8_t*"] -> ImageMatrix as "dlib::matrix<dlib::rgb_pixel>" {
+                dlib::matrix<dlib::rgb_pixel> image(height, width);
+
+                for (size_t row = 0; row < height; row++) {
+                    const uint8_t* pixels = ptr + row * stride;
+
+                    for (size_t column = 0; column < width; column++) {
+                        const uint8_t* pair = pixels + (column / 2) * 4;
+                        image(row, column) = yuv_to_rgb(pair[(column % 2) * 2], pair[1], pair[3]);
+                    }
+                }
+
+                return image;
+            })
+        })
+    }
+
+    /// Convert a 16-bit grayscale image, such as from a NIR sensor, with rows `stride` values apart.
+    ///
+    /// The values are scaled to 8 bits according to `scaling`, and copied to each of the channels.
+    pub fn from_gray16(
+        width: usize,
+        height: usize,
+        stride: usize,
+        data: &[u16],
+        scaling: IntensityScaling,
+    ) -> Result<Self, String> {
+        let values = strided_values(width, height, stride, data, |&value| value as f64)?;
+        let gray = scale_to_u8(&values, scaling);
+        Self::from_gray(width, height, width, &gray)
+    }
+
+    /// Convert a floating point grayscale image, such as from a thermal sensor, with rows `stride` values apart.
+    ///
+    /// The values are scaled to 8 bits according to `scaling`, and copied to each of the channels. Values that
+    /// aren't numbers become 0.
+    pub fn from_gray_f32(
+        width: usize,
+        height: usize,
+        stride: usize,
+        data: &[f32],
+        scaling: IntensityScaling,
+    ) -> Result<Self, String> {
+        let values = strided_values(width, height, stride, data, |&value| value as f64)?;
+        let gray = scale_to_u8(&values, scaling);
+        Self::from_gray(width, height, width, &gray)
+    }
+
+    fn from_raw(
+        width: usize,
+        height: usize,
+        stride: usize,
+        data: &[u8],
+        channels: usize,
+        order: [usize; 3],
+    ) -> Result<Self, String> {
+        check_buffer(width, height, stride, data, channels)?;
+
+        let ptr = data.as_ptr();
+        let order = order.as_ptr();
+
+        Ok(unsafe {
+            cpp!([width as "size_t", height as "size_t", stride as "size_t", ptr as "const uint8_t*", channels as "size_t", order as "const size_t*"] -> ImageMatrix as "dlib::matrix<dlib::rgb_pixel>" {
+                dlib::matrix<dlib::rgb_pixel> image(height, width);
+
+                for (size_t y = 0; y < height; y++) {
+                    const uint8_t* row = ptr + y * stride;
+
+                    for (size_t x = 0; x < width; x++) {
+                        const uint8_t* pixel = row + x * channels;
+                        image(y, x) = dlib::rgb_pixel(pixel[order[0]], pixel[order[1]], pixel[order[2]]);
+                    }
+                }
+
+                return image;
+            })
+        })
+    }
+
+    /// Load an image file with dlib's own image loaders, without needing the `image` crate.
+    ///
+    /// The supported formats depend on how dlib was built, but are usually JPEG, PNG, GIF and BMP. JPEGs are
+    /// rotated and flipped to match their EXIF orientation, so photos taken with a phone held sideways come out
+    /// upright rather than with sideways faces that the detectors can't find.
+    pub fn load<P: AsRef<Path>>(filename: P) -> Result<Self, String> {
+        let image = Self::load_without_orientation(filename.as_ref())?;
+
+        Ok(match crate::exif::read_orientation(filename.as_ref()) {
+            Some(orientation) => image.oriented(orientation),
+            None => image,
+        })
+    }
+
+    /// Load an image file as it's stored, ignoring any EXIF orientation.
+    pub fn load_without_orientation<P: AsRef<Path>>(filename: P) -> Result<Self, String> {
+        let string = path_as_cstring(filename.as_ref())?;
+        let mut image = Self::default();
+        let error = CppBytes::default();
+
+        let loaded = unsafe {
+            let filename = string.as_ptr();
+            let image = &mut image;
+            let error = &error;
+
+            cpp!([filename as "char*", image as "dlib::matrix<dlib::rgb_pixel>*", error as "std::string*"] -> bool as "bool" {
+                try {
+                    dlib::load_image(*image, filename);
+                    return true;
+                } catch (const dlib::error& exception) {
+                    *error = exception.what();
+                    return false;
+                }
+            })
+        };
+
+        if !loaded {
+            Err(format!(
+                "Failed to load '{}': {}",
+                filename.as_ref().display(),
+                String::from_utf8_lossy(&error)
+            ))
+        } else {
+            Ok(image)
+        }
+    }
+
+    #[cfg(feature = "ndarray")]
+    /// Copy a matrix from an array of shape `(height, width, 3)` holding the red, green and blue value of each
+    /// pixel, which doesn't have to be contiguous.
+    pub fn from_array(array: ndarray::ArrayView3<'_, u8>) -> Result<Self, String> {
+        let (height, width, channels) = array.dim();
+        if channels != 3 {
+            return Err(format!(
+                "Expected 3 channels for an rgb image, got {}",
+                channels
+            ));
+        }
+
+        let data: Vec<u8> = array.iter().copied().collect();
+        Self::from_rgb(width, height, width * 3, &data)
+    }
+
+    #[cfg(feature = "image")]
     /// Copy a matrix from an rgb image
     pub fn from_image<C: Deref<Target = [u8]>>(image: &ImageBuffer<Rgb<u8>, C>) -> Self {
         let width = image.width() as usize;
@@ -45,6 +395,230 @@ impl ImageMatrix {
 }
 
 impl ImageMatrix {
+    pub fn width(&self) -> usize {
+        unsafe {
+            cpp!([self as "const dlib::matrix<dlib::rgb_pixel>*"] -> usize as "size_t" {
+                return self->nc();
+            })
+        }
+    }
+
+    pub fn height(&self) -> usize {
+        unsafe {
+            cpp!([self as "const dlib::matrix<dlib::rgb_pixel>*"] -> usize as "size_t" {
+                return self->nr();
+            })
+        }
+    }
+
+    /// The red, green and blue values of the pixel at `x`, `y`, or `None` if it's outside of the image.
+    pub fn get_pixel(&self, x: usize, y: usize) -> Option<[u8; 3]> {
+        let pixel = self.row(y)?.get(x * 3..x * 3 + 3)?;
+        Some([pixel[0], pixel[1], pixel[2]])
+    }
+
+    /// Set the red, green and blue values of the pixel at `x`, `y`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pixel is outside of the image.
+    pub fn set_pixel(&mut self, x: usize, y: usize, pixel: [u8; 3]) {
+        let (width, height) = (self.width(), self.height());
+        assert!(
+            x < width && y < height,
+            "The pixel ({}, {}) is outside of the {}x{} image",
+            x,
+            y,
+            width,
+            height
+        );
+
+        self.row_mut(y).unwrap()[x * 3..x * 3 + 3].copy_from_slice(&pixel);
+    }
+
+    /// The rgb channel values (r, g, b, r, g, b) of row `y`, or `None` if it's outside of the image.
+    pub fn row(&self, y: usize) -> Option<&[u8]> {
+        if y >= self.height() {
+            return None;
+        }
+
+        let width = self.width();
+        if width == 0 {
+            return Some(&[]);
+        }
+
+        unsafe {
+            let data = cpp!([self as "const dlib::matrix<dlib::rgb_pixel>*", y as "size_t"] -> *const u8 as "const uint8_t*" {
+                static_assert(sizeof(dlib::rgb_pixel) == 3, "rgb_pixel must be tightly packed");
+                return reinterpret_cast<const uint8_t*>(&(*self)(y, 0));
+            });
+
+            Some(std::slice::from_raw_parts(data, width * 3))
+        }
+    }
+
+    /// The mutable rgb channel values (r, g, b, r, g, b) of row `y`, or `None` if it's outside of the image.
+    pub fn row_mut(&mut self, y: usize) -> Option<&mut [u8]> {
+        if y >= self.height() {
+            return None;
+        }
+
+        let width = self.width();
+        if width == 0 {
+            return Some(&mut []);
+        }
+
+        unsafe {
+            let data = cpp!([self as "dlib::matrix<dlib::rgb_pixel>*", y as "size_t"] -> *mut u8 as "uint8_t*" {
+                return reinterpret_cast<uint8_t*>(&(*self)(y, 0));
+            });
+
+            Some(std::slice::from_raw_parts_mut(data, width * 3))
+        }
+    }
+
+    /// Copy the image out as rgb channel values (r, g, b, r, g, b), one row after another.
+    pub fn to_raw_rgb(&self) -> Vec<u8> {
+        let mut data = vec![0u8; self.width() * self.height() * 3];
+
+        unsafe {
+            let out = data.as_mut_ptr();
+
+            cpp!([self as "const dlib::matrix<dlib::rgb_pixel>*", out as "uint8_t*"] {
+                uint8_t* cursor = out;
+
+                for (long y = 0; y < self->nr(); y++) {
+                    for (long x = 0; x < self->nc(); x++) {
+                        const dlib::rgb_pixel& pixel = (*self)(y, x);
+                        cursor[0] = pixel.red;
+                        cursor[1] = pixel.green;
+                        cursor[2] = pixel.blue;
+                        cursor += 3;
+                    }
+                }
+            })
+        }
+
+        data
+    }
+
+    /// Serialize the image as its dimensions followed by its rgb channel values, optionally compressed, e.g. to
+    /// cache preprocessed frames on disk or pass them between processes.
+    ///
+    /// Compression requires the `compression` feature flag.
+    pub fn to_bytes(&self, compress: bool) -> Result<Vec<u8>, String> {
+        let mut bytes = Vec::with_capacity(HEADER_SIZE);
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&VERSION.to_le_bytes());
+        bytes.push(if compress { FLAG_COMPRESSED } else { 0 });
+        let too_large = || {
+            format!(
+                "A {}x{} image is too large to serialize",
+                self.width(),
+                self.height()
+            )
+        };
+        let width = u32::try_from(self.width()).map_err(|_| too_large())?;
+        let height = u32::try_from(self.height()).map_err(|_| too_large())?;
+        bytes.extend_from_slice(&width.to_le_bytes());
+        bytes.extend_from_slice(&height.to_le_bytes());
+
+        let pixels = self.to_raw_rgb();
+        if compress {
+            bytes.extend_from_slice(&compress_pixels(&pixels)?);
+        } else {
+            bytes.extend_from_slice(&pixels);
+        }
+
+        Ok(bytes)
+    }
+
+    /// Deserialize an image written with [`ImageMatrix::to_bytes`], whether or not it was compressed.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() < HEADER_SIZE || !bytes.starts_with(MAGIC) {
+            return Err("Not a serialized image".into());
+        }
+
+        let read_u32 = |offset: usize| {
+            let mut buffer = [0u8; 4];
+            buffer.copy_from_slice(&bytes[offset..offset + 4]);
+            u32::from_le_bytes(buffer)
+        };
+
+        let version = read_u32(5);
+        if version == 0 || version > VERSION {
+            return Err(format!("Unsupported serialized image version {}", version));
+        }
+
+        let flags = bytes[9];
+        let width = read_u32(10) as usize;
+        let height = read_u32(14) as usize;
+        let payload = &bytes[HEADER_SIZE..];
+        let corrupt = || {
+            format!(
+                "The pixels of the serialized {}x{} image are truncated or corrupt",
+                width, height
+            )
+        };
+
+        let expected = width
+            .checked_mul(height)
+            .and_then(|pixels| pixels.checked_mul(3))
+            .ok_or_else(corrupt)?;
+
+        let pixels = if flags & FLAG_COMPRESSED != 0 {
+            decompress_pixels(payload, expected)?
+        } else {
+            payload.to_vec()
+        };
+
+        if pixels.len() != expected {
+            return Err(corrupt());
+        }
+
+        Self::from_rgb(width, height, width * 3, &pixels)
+    }
+
+    #[cfg(feature = "ndarray")]
+    /// Copy the matrix into an array of shape `(height, width, 3)` holding the red, green and blue value
+    /// of each pixel.
+    pub fn to_array(&self) -> ndarray::Array3<u8> {
+        ndarray::Array3::from_shape_vec((self.height(), self.width(), 3), self.to_raw_rgb())
+            .expect("The buffer is always the size of the image")
+    }
+
+    #[cfg(feature = "image")]
+    /// Copy the matrix into an rgb image, e.g. to save it.
+    pub fn to_image(&self) -> RgbImage {
+        RgbImage::from_raw(self.width() as u32, self.height() as u32, self.to_raw_rgb())
+            .expect("The buffer is always the size of the image")
+    }
+
+    /// Save the image as a PNG file with dlib's own image writers.
+    pub fn save_png<P: AsRef<Path>>(&self, filename: P) -> Result<(), String> {
+        let string = path_as_cstring(filename.as_ref())?;
+        let error = CppBytes::default();
+
+        let saved = unsafe {
+            let filename = string.as_ptr();
+            let error = &error;
+
+            cpp!([self as "const dlib::matrix<dlib::rgb_pixel>*", filename as "char*", error as "std::string*"] -> bool as "bool" {
+                return save_png_image(*self, filename, *error);
+            })
+        };
+
+        if !saved {
+            Err(format!(
+                "Failed to save '{}': {}",
+                filename.as_ref().display(),
+                String::from_utf8_lossy(&error)
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
     pub fn resize(&self, width: usize, height: usize) -> Self {
         unsafe {
             cpp!([self as "const dlib::matrix<dlib::rgb_pixel>*", width as "size_t", height as "size_t"] -> ImageMatrix as "dlib::matrix<dlib::rgb_pixel>" {
@@ -54,4 +628,1214 @@ impl ImageMatrix {
             })
         }
     }
+
+    /// Resize the image, sampling pixels with the given interpolation.
+    ///
+    /// [`ImageMatrix::resize`] is the same as using [`Interpolation::Bilinear`].
+    pub fn resize_with(&self, width: usize, height: usize, interpolation: Interpolation) -> Self {
+        let interpolation = interpolation as u32;
+
+        unsafe {
+            cpp!([self as "const dlib::matrix<dlib::rgb_pixel>*", width as "size_t", height as "size_t", interpolation as "uint32_t"] -> ImageMatrix as "dlib::matrix<dlib::rgb_pixel>" {
+                dlib::matrix<dlib::rgb_pixel> out(height, width);
+
+                switch (interpolation) {
+                    case 0:
+                        dlib::resize_image(*self, out, dlib::interpolate_nearest_neighbor());
+                        break;
+                    case 1:
+                        dlib::resize_image(*self, out, dlib::interpolate_bilinear());
+                        break;
+                    default:
+                        dlib::resize_image(*self, out, dlib::interpolate_quadratic());
+                        break;
+                }
+
+                return out;
+            })
+        }
+    }
+
+    /// Downsample the image to half its width and height, after smoothing it to avoid aliasing.
+    pub fn pyramid_down(&self) -> Self {
+        unsafe {
+            cpp!([self as "const dlib::matrix<dlib::rgb_pixel>*"] -> ImageMatrix as "dlib::matrix<dlib::rgb_pixel>" {
+                dlib::matrix<dlib::rgb_pixel> out;
+                dlib::pyramid_down<2> pyramid;
+                pyramid(*self, out);
+                return out;
+            })
+        }
+    }
+
+    /// Copy the part of the image inside a rectangle.
+    ///
+    /// The rectangle is clamped to the bounds of the image.
+    pub fn crop(&self, rect: &Rectangle) -> Self {
+        unsafe {
+            cpp!([self as "const dlib::matrix<dlib::rgb_pixel>*", rect as "const dlib::rectangle*"] -> ImageMatrix as "dlib::matrix<dlib::rgb_pixel>" {
+                dlib::rectangle area = to_dlib_rect(*rect).intersect(dlib::get_rect(*self));
+                if (area.is_empty()) {
+                    return dlib::matrix<dlib::rgb_pixel>();
+                }
+                return dlib::matrix<dlib::rgb_pixel>(dlib::subm(*self, area));
+            })
+        }
+    }
+
+    /// Copy the part of the image inside a rectangle, filling any of the rectangle outside the image with `fill`.
+    ///
+    /// Unlike [`ImageMatrix::crop`], the result is always the size of the rectangle, so faces near the border
+    /// of the image keep their position within the crop.
+    pub fn crop_padded(&self, rect: &Rectangle, fill: [u8; 3]) -> Self {
+        let [red, green, blue] = fill;
+
+        unsafe {
+            cpp!([self as "const dlib::matrix<dlib::rgb_pixel>*", rect as "const dlib::rectangle*", red as "uint8_t", green as "uint8_t", blue as "uint8_t"] -> ImageMatrix as "dlib::matrix<dlib::rgb_pixel>" {
+                const dlib::rectangle inclusive = to_dlib_rect(*rect);
+                if (inclusive.is_empty()) {
+                    return dlib::matrix<dlib::rgb_pixel>();
+                }
+
+                dlib::matrix<dlib::rgb_pixel> out(inclusive.height(), inclusive.width());
+                out = dlib::rgb_pixel(red, green, blue);
+
+                dlib::rectangle area = inclusive.intersect(dlib::get_rect(*self));
+                if (!area.is_empty()) {
+                    dlib::set_subm(out, dlib::translate_rect(area, -inclusive.left(), -inclusive.top())) = dlib::subm(*self, area);
+                }
+
+                return out;
+            })
+        }
+    }
+
+    /// Scale the image to fit within `width` x `height` without changing its aspect ratio, and centre it on a
+    /// canvas of exactly that size filled with `fill`.
+    ///
+    /// Returns the letterboxed image along with the transform that maps points in it back to this image, e.g.
+    /// to move detections made on the letterboxed image back to the original.
+    pub fn letterbox(&self, width: usize, height: usize, fill: [u8; 3]) -> (Self, AffineTransform) {
+        let canvas = |image: &Self, left: i64, top: i64| {
+            let rect = Rectangle {
+                left: -left,
+                top: -top,
+                right: width as i64 - left,
+                bottom: height as i64 - top,
+            };
+            image.crop_padded(&rect, fill)
+        };
+
+        let (source_width, source_height) = (self.width(), self.height());
+        if source_width == 0 || source_height == 0 || width == 0 || height == 0 {
+            return (canvas(self, 0, 0), AffineTransform::identity());
+        }
+
+        let scale = (width as f64 / source_width as f64).min(height as f64 / source_height as f64);
+        let scaled_width = ((source_width as f64 * scale).round() as usize).clamp(1, width);
+        let scaled_height = ((source_height as f64 * scale).round() as usize).clamp(1, height);
+
+        let left = (width - scaled_width) / 2;
+        let top = (height - scaled_height) / 2;
+        let scaled = self.resize(scaled_width, scaled_height);
+
+        let scale_x = scaled_width as f64 / source_width as f64;
+        let scale_y = scaled_height as f64 / source_height as f64;
+        let transform = AffineTransform {
+            matrix: [[1.0 / scale_x, 0.0], [0.0, 1.0 / scale_y]],
+            offset: [-(left as f64) / scale_x, -(top as f64) / scale_y],
+        };
+
+        (canvas(&scaled, left as i64, top as i64), transform)
+    }
+
+    /// Rotate the image counter-clockwise by `degrees`, growing it so that nothing is cut off.
+    ///
+    /// Returns the rotated image along with the transform that maps points in this image to the rotated one.
+    pub fn rotated(&self, degrees: f64) -> (Self, AffineTransform) {
+        let radians = degrees.to_radians();
+        let mut rotated = Self::default();
+        let mut coefficients = [0f64; 6];
+
+        unsafe {
+            let out = &mut rotated;
+            let coefficients = coefficients.as_mut_ptr();
+
+            cpp!([self as "const dlib::matrix<dlib::rgb_pixel>*", radians as "double", out as "dlib::matrix<dlib::rgb_pixel>*", coefficients as "double*"] {
+                dlib::point_transform_affine transform = dlib::rotate_image(*self, *out, radians);
+
+                coefficients[0] = transform.get_m()(0, 0);
+                coefficients[1] = transform.get_m()(0, 1);
+                coefficients[2] = transform.get_m()(1, 0);
+                coefficients[3] = transform.get_m()(1, 1);
+                coefficients[4] = transform.get_b()(0);
+                coefficients[5] = transform.get_b()(1);
+            })
+        }
+
+        let transform = AffineTransform {
+            matrix: [
+                [coefficients[0], coefficients[1]],
+                [coefficients[2], coefficients[3]],
+            ],
+            offset: [coefficients[4], coefficients[5]],
+        };
+
+        (rotated, transform)
+    }
+
+    /// Rotate the image counter-clockwise by 90 degrees, the same direction as [`ImageMatrix::rotated`].
+    ///
+    /// Unlike rotating by an arbitrary angle, this moves pixels without interpolating them, so nothing is lost.
+    pub fn rotate90(&self) -> Self {
+        self.rotate_quarters(1)
+    }
+
+    /// Rotate the image by 180 degrees without interpolating it.
+    pub fn rotate180(&self) -> Self {
+        self.rotate_quarters(2)
+    }
+
+    /// Rotate the image counter-clockwise by 270 degrees (clockwise by 90) without interpolating it.
+    pub fn rotate270(&self) -> Self {
+        self.rotate_quarters(3)
+    }
+
+    fn rotate_quarters(&self, quarters: u32) -> Self {
+        unsafe {
+            cpp!([self as "const dlib::matrix<dlib::rgb_pixel>*", quarters as "uint32_t"] -> ImageMatrix as "dlib::matrix<dlib::rgb_pixel>" {
+                const long rows = self->nr();
+                const long columns = self->nc();
+
+                if (quarters == 2) {
+                    dlib::matrix<dlib::rgb_pixel> out(rows, columns);
+                    for (long y = 0; y < rows; y++) {
+                        for (long x = 0; x < columns; x++) {
+                            out(rows - 1 - y, columns - 1 - x) = (*self)(y, x);
+                        }
+                    }
+                    return out;
+                }
+
+                dlib::matrix<dlib::rgb_pixel> out(columns, rows);
+                for (long y = 0; y < rows; y++) {
+                    for (long x = 0; x < columns; x++) {
+                        if (quarters == 1) {
+                            out(columns - 1 - x, y) = (*self)(y, x);
+                        } else {
+                            out(x, rows - 1 - y) = (*self)(y, x);
+                        }
+                    }
+                }
+                return out;
+            })
+        }
+    }
+
+    /// Rotate and flip the image so that it's upright, given its EXIF orientation from 1 to 8.
+    ///
+    /// [`ImageMatrix::load`] already does this, but it's needed for images decoded some other way, as the
+    /// `image` crate ignores the orientation. Unknown orientations leave the image as it is.
+    pub fn oriented(&self, orientation: u16) -> Self {
+        match orientation {
+            2 => self.flip_horizontal(),
+            3 => self.rotate180(),
+            4 => self.flip_vertical(),
+            5 => self.flip_horizontal().rotate90(),
+            6 => self.rotate270(),
+            7 => self.flip_horizontal().rotate270(),
+            8 => self.rotate90(),
+            _ => self.clone(),
+        }
+    }
+
+    /// Mirror the image left to right, e.g. to undo a mirrored selfie camera.
+    pub fn flip_horizontal(&self) -> Self {
+        unsafe {
+            cpp!([self as "const dlib::matrix<dlib::rgb_pixel>*"] -> ImageMatrix as "dlib::matrix<dlib::rgb_pixel>" {
+                dlib::matrix<dlib::rgb_pixel> out;
+                dlib::flip_image_left_right(*self, out);
+                return out;
+            })
+        }
+    }
+
+    /// Mirror the image top to bottom.
+    pub fn flip_vertical(&self) -> Self {
+        unsafe {
+            cpp!([self as "const dlib::matrix<dlib::rgb_pixel>*"] -> ImageMatrix as "dlib::matrix<dlib::rgb_pixel>" {
+                dlib::matrix<dlib::rgb_pixel> out;
+                dlib::flip_image_up_down(*self, out);
+                return out;
+            })
+        }
+    }
+
+    /// Spread the brightness of the image over the full range of values, which brings out detail in under and
+    /// over exposed images, such as night-time security footage, before detecting faces.
+    ///
+    /// The brightness of each pixel is equalized, and the same change applied to each of its channels, so colours
+    /// are kept roughly the same.
+    pub fn equalize_histogram(&self) -> Self {
+        unsafe {
+            cpp!([self as "const dlib::matrix<dlib::rgb_pixel>*"] -> ImageMatrix as "dlib::matrix<dlib::rgb_pixel>" {
+                dlib::matrix<unsigned char> luma;
+                dlib::assign_image(luma, *self);
+
+                dlib::matrix<unsigned char> equalized;
+                dlib::equalize_histogram(luma, equalized);
+
+                dlib::matrix<dlib::rgb_pixel> out(self->nr(), self->nc());
+                for (long y = 0; y < self->nr(); y++) {
+                    for (long x = 0; x < self->nc(); x++) {
+                        const int change = (int)equalized(y, x) - (int)luma(y, x);
+                        const dlib::rgb_pixel& pixel = (*self)(y, x);
+
+                        auto shift = [change](unsigned char value) {
+                            return (unsigned char)std::min(255, std::max(0, value + change));
+                        };
+                        out(y, x) = dlib::rgb_pixel(shift(pixel.red), shift(pixel.green), shift(pixel.blue));
+                    }
+                }
+
+                return out;
+            })
+        }
+    }
+
+    /// Apply gamma correction to each channel, mapping values `v` in `0..=1` to `v.powf(gamma)`.
+    ///
+    /// A gamma below 1 brightens dark images, and a gamma above 1 darkens bright ones.
+    pub fn adjust_gamma(&self, gamma: f64) -> Self {
+        self.map_channels(&gamma_table(gamma))
+    }
+
+    /// Add `offset` to each channel, clamping the results to `0..=255`.
+    pub fn adjust_brightness(&self, offset: i32) -> Self {
+        self.map_channels(&brightness_table(offset))
+    }
+
+    /// Blur the image with a gaussian of standard deviation `sigma` pixels, which smooths out sensor noise in
+    /// low-light frames before detection.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `sigma` isn't positive.
+    pub fn gaussian_blur(&self, sigma: f64) -> Self {
+        assert!(
+            sigma > 0.0,
+            "The blur sigma must be positive, got {}",
+            sigma
+        );
+
+        unsafe {
+            cpp!([self as "const dlib::matrix<dlib::rgb_pixel>*", sigma as "double"] -> ImageMatrix as "dlib::matrix<dlib::rgb_pixel>" {
+                dlib::matrix<dlib::rgb_pixel> out;
+                dlib::gaussian_blur(*self, out, sigma);
+                return out;
+            })
+        }
+    }
+
+    /// Replace each channel of each pixel with the median of the pixels within `radius` of it, which removes
+    /// speckle noise while keeping edges sharper than a blur.
+    ///
+    /// Pixels near the border use the part of their neighbourhood inside the image. A radius of 0 leaves the
+    /// image as it is.
+    pub fn median_filter(&self, radius: usize) -> Self {
+        unsafe {
+            cpp!([self as "const dlib::matrix<dlib::rgb_pixel>*", radius as "size_t"] -> ImageMatrix as "dlib::matrix<dlib::rgb_pixel>" {
+                const long rows = self->nr();
+                const long columns = self->nc();
+                const long r = radius;
+
+                dlib::matrix<dlib::rgb_pixel> out(rows, columns);
+                std::vector<unsigned char> reds, greens, blues;
+
+                auto median = [](std::vector<unsigned char>& values) {
+                    auto middle = values.begin() + values.size() / 2;
+                    std::nth_element(values.begin(), middle, values.end());
+                    return *middle;
+                };
+
+                for (long y = 0; y < rows; y++) {
+                    for (long x = 0; x < columns; x++) {
+                        reds.clear();
+                        greens.clear();
+                        blues.clear();
+
+                        for (long v = std::max(0L, y - r); v <= std::min(rows - 1, y + r); v++) {
+                            for (long u = std::max(0L, x - r); u <= std::min(columns - 1, x + r); u++) {
+                                const dlib::rgb_pixel& pixel = (*self)(v, u);
+                                reds.push_back(pixel.red);
+                                greens.push_back(pixel.green);
+                                blues.push_back(pixel.blue);
+                            }
+                        }
+
+                        out(y, x) = dlib::rgb_pixel(median(reds), median(greens), median(blues));
+                    }
+                }
+
+                return out;
+            })
+        }
+    }
+
+    fn map_channels(&self, table: &[u8; 256]) -> Self {
+        let table = table.as_ptr();
+
+        unsafe {
+            cpp!([self as "const dlib::matrix<dlib::rgb_pixel>*", table as "const uint8_t*"] -> ImageMatrix as "dlib::matrix<dlib::rgb_pixel>" {
+                dlib::matrix<dlib::rgb_pixel> out(self->nr(), self->nc());
+                for (long y = 0; y < self->nr(); y++) {
+                    for (long x = 0; x < self->nc(); x++) {
+                        const dlib::rgb_pixel& pixel = (*self)(y, x);
+                        out(y, x) = dlib::rgb_pixel(table[pixel.red], table[pixel.green], table[pixel.blue]);
+                    }
+                }
+                return out;
+            })
+        }
+    }
+
+    /// Upsample the image to double its width and height.
+    pub fn pyramid_up(&self) -> Self {
+        unsafe {
+            cpp!([self as "const dlib::matrix<dlib::rgb_pixel>*"] -> ImageMatrix as "dlib::matrix<dlib::rgb_pixel>" {
+                dlib::matrix<dlib::rgb_pixel> out;
+                dlib::pyramid_up(*self, out);
+                return out;
+            })
+        }
+    }
+}
+
+/// A read-only rgb image over a borrowed buffer, which is used in place without being copied.
+///
+/// The HOG face detector, the landmark predictor and face chip extraction can all work on views directly, so
+/// large frames from a camera never need to be copied into an [`ImageMatrix`]. Only the small face chips are.
+#[derive(Copy, Clone, Debug)]
+#[repr(C)]
+pub struct ImageMatrixView<'a> {
+    data: *const u8,
+    height: i64,
+    width: i64,
+    stride: i64,
+    marker: PhantomData<&'a [u8]>,
+}
+
+// The view only ever reads from the buffer, which it borrows immutably.
+unsafe impl Send for ImageMatrixView<'_> {}
+unsafe impl Sync for ImageMatrixView<'_> {}
+
+impl<'a> ImageMatrixView<'a> {
+    /// View a buffer of rgb channel values (r, g, b, r, g, b), with rows `stride` bytes apart.
+    ///
+    /// Fails if the stride is too small for a row, or if the buffer is too small for the image.
+    pub fn new(width: usize, height: usize, stride: usize, data: &'a [u8]) -> Result<Self, String> {
+        check_buffer(width, height, stride, data, 3)?;
+
+        Ok(Self {
+            data: data.as_ptr(),
+            height: height as i64,
+            width: width as i64,
+            stride: stride as i64,
+            marker: PhantomData,
+        })
+    }
+
+    #[cfg(feature = "image")]
+    /// View an rgb image without copying it.
+    pub fn from_image<C: Deref<Target = [u8]>>(image: &'a ImageBuffer<Rgb<u8>, C>) -> Self {
+        let width = image.width() as usize;
+        let height = image.height() as usize;
+
+        Self::new(width, height, width * 3, image).expect("Image buffers are always large enough")
+    }
+
+    pub fn width(&self) -> usize {
+        self.width as usize
+    }
+
+    pub fn height(&self) -> usize {
+        self.height as usize
+    }
+
+    /// Copy the viewed image into a new matrix.
+    pub fn to_matrix(&self) -> ImageMatrix {
+        unsafe {
+            cpp!([self as "const rgb_image_view*"] -> ImageMatrix as "dlib::matrix<dlib::rgb_pixel>" {
+                dlib::matrix<dlib::rgb_pixel> image;
+                dlib::assign_image(image, *self);
+                return image;
+            })
+        }
+    }
+}
+
+cpp_class!(
+    /// A wrapper around a `matrix<unsigned char>`, a single channel grayscale dlib image.
+    pub unsafe struct GrayImageMatrix as "dlib::matrix<unsigned char>"
+);
+
+impl GrayImageMatrix {
+    /// # Safety
+    ///
+    /// Create a new matrix from grayscale values, one byte per pixel.
+    ///
+    /// Unsafe because we can't check that width * height <= number of values
+    pub unsafe fn new(width: usize, height: usize, ptr: *const u8) -> Self {
+        cpp!([width as "size_t", height as "size_t", ptr as "uint8_t*"] -> GrayImageMatrix as "dlib::matrix<unsigned char>" {
+            dlib::matrix<unsigned char> image = dlib::matrix<unsigned char>(height, width);
+
+            for (size_t y = 0; y < height; y++) {
+                for (size_t x = 0; x < width; x++) {
+                    image(y, x) = *(ptr + y * width + x);
+                }
+            }
+
+            return image;
+        })
+    }
+
+    /// Copy a matrix from a raw buffer of grayscale values, with rows `stride` bytes apart.
+    ///
+    /// Fails if the stride is too small for a row, or if the buffer is too small for the image.
+    pub fn from_gray(
+        width: usize,
+        height: usize,
+        stride: usize,
+        data: &[u8],
+    ) -> Result<Self, String> {
+        check_buffer(width, height, stride, data, 1)?;
+
+        let ptr = data.as_ptr();
+
+        Ok(unsafe {
+            cpp!([width as "size_t", height as "size_t", stride as "size_t", ptr as "const uint8_t*"] -> GrayImageMatrix as "dlib::matrix<unsigned char>" {
+                dlib::matrix<unsigned char> image(height, width);
+
+                for (size_t y = 0; y < height && width > 0; y++) {
+                    std::copy(ptr + y * stride, ptr + y * stride + width, &image(y, 0));
+                }
+
+                return image;
+            })
+        })
+    }
+
+    /// Load an image file with dlib's own image loaders, converting it to grayscale.
+    ///
+    /// As with [`ImageMatrix::load`], JPEGs are made upright according to their EXIF orientation.
+    pub fn load<P: AsRef<Path>>(filename: P) -> Result<Self, String> {
+        ImageMatrix::load(filename).map(|image| Self::from_rgb(&image))
+    }
+
+    #[cfg(feature = "image")]
+    /// Copy a matrix from a grayscale image
+    pub fn from_image<C: Deref<Target = [u8]>>(image: &ImageBuffer<Luma<u8>, C>) -> Self {
+        let width = image.width() as usize;
+        let height = image.height() as usize;
+        let ptr = image.as_ptr();
+
+        unsafe { Self::new(width, height, ptr) }
+    }
+
+    /// Convert a colour image to grayscale.
+    pub fn from_rgb(image: &ImageMatrix) -> Self {
+        unsafe {
+            cpp!([image as "const dlib::matrix<dlib::rgb_pixel>*"] -> GrayImageMatrix as "dlib::matrix<unsigned char>" {
+                dlib::matrix<unsigned char> gray;
+                dlib::assign_image(gray, *image);
+                return gray;
+            })
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        unsafe {
+            cpp!([self as "const dlib::matrix<unsigned char>*"] -> usize as "size_t" {
+                return self->nc();
+            })
+        }
+    }
+
+    pub fn height(&self) -> usize {
+        unsafe {
+            cpp!([self as "const dlib::matrix<unsigned char>*"] -> usize as "size_t" {
+                return self->nr();
+            })
+        }
+    }
+
+    /// Spread the brightness of the image over the full range of values, which brings out detail in under and
+    /// over exposed images before detecting faces.
+    pub fn equalize_histogram(&self) -> Self {
+        unsafe {
+            cpp!([self as "const dlib::matrix<unsigned char>*"] -> GrayImageMatrix as "dlib::matrix<unsigned char>" {
+                dlib::matrix<unsigned char> out;
+                dlib::equalize_histogram(*self, out);
+                return out;
+            })
+        }
+    }
+
+    /// Apply gamma correction, mapping values `v` in `0..=1` to `v.powf(gamma)`.
+    ///
+    /// A gamma below 1 brightens dark images, and a gamma above 1 darkens bright ones.
+    pub fn adjust_gamma(&self, gamma: f64) -> Self {
+        self.map_values(&gamma_table(gamma))
+    }
+
+    /// Add `offset` to each value, clamping the results to `0..=255`.
+    pub fn adjust_brightness(&self, offset: i32) -> Self {
+        self.map_values(&brightness_table(offset))
+    }
+
+    fn map_values(&self, table: &[u8; 256]) -> Self {
+        let table = table.as_ptr();
+
+        unsafe {
+            cpp!([self as "const dlib::matrix<unsigned char>*", table as "const uint8_t*"] -> GrayImageMatrix as "dlib::matrix<unsigned char>" {
+                dlib::matrix<unsigned char> out(self->nr(), self->nc());
+                std::transform(self->begin(), self->end(), out.begin(), [table](unsigned char value) { return table[value]; });
+                return out;
+            })
+        }
+    }
+
+    /// Copy the image out as grayscale values, one row after another.
+    pub fn to_raw(&self) -> Vec<u8> {
+        let mut data = vec![0u8; self.width() * self.height()];
+
+        unsafe {
+            let out = data.as_mut_ptr();
+
+            cpp!([self as "const dlib::matrix<unsigned char>*", out as "uint8_t*"] {
+                std::copy(self->begin(), self->end(), out);
+            })
+        }
+
+        data
+    }
+
+    #[cfg(feature = "image")]
+    /// Copy the matrix into a grayscale image, e.g. to save it.
+    pub fn to_image(&self) -> GrayImage {
+        GrayImage::from_raw(self.width() as u32, self.height() as u32, self.to_raw())
+            .expect("The buffer is always the size of the image")
+    }
+}
+
+#[cfg(feature = "compression")]
+fn compress_pixels(pixels: &[u8]) -> Result<Vec<u8>, String> {
+    use std::io::Write;
+
+    let mut encoder =
+        flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder
+        .write_all(pixels)
+        .map_err(|error| error.to_string())?;
+    encoder.finish().map_err(|error| error.to_string())
+}
+
+#[cfg(not(feature = "compression"))]
+fn compress_pixels(_pixels: &[u8]) -> Result<Vec<u8>, String> {
+    Err("Compressing an image requires the 'compression' feature".into())
+}
+
+#[cfg(feature = "compression")]
+fn decompress_pixels(payload: &[u8], expected: usize) -> Result<Vec<u8>, String> {
+    use std::io::Read;
+
+    // Read at most one byte past the expected size, so a corrupt or malicious payload can't exhaust memory.
+    let limit = (expected as u64).saturating_add(1);
+    let mut pixels = Vec::new();
+    flate2::read::DeflateDecoder::new(payload)
+        .take(limit)
+        .read_to_end(&mut pixels)
+        .map_err(|error| error.to_string())?;
+    if pixels.len() > expected {
+        return Err("The pixels of the serialized image are larger than its dimensions".into());
+    }
+    Ok(pixels)
+}
+
+#[cfg(not(feature = "compression"))]
+fn decompress_pixels(_payload: &[u8], _expected: usize) -> Result<Vec<u8>, String> {
+    Err("Decompressing an image requires the 'compression' feature".into())
+}
+
+// Copy the values of an image out of a buffer with padded rows, checking the buffer is large enough.
+fn strided_values<T>(
+    width: usize,
+    height: usize,
+    stride: usize,
+    data: &[T],
+    convert: impl Fn(&T) -> f64,
+) -> Result<Vec<f64>, String> {
+    if stride < width {
+        return Err(format!(
+            "A stride of {} values is too small for rows of {} values",
+            stride, width
+        ));
+    }
+
+    let needed = if height == 0 {
+        0
+    } else {
+        stride
+            .checked_mul(height - 1)
+            .and_then(|rows| rows.checked_add(width))
+            .ok_or_else(|| format!("A {}x{} image is too large", width, height))?
+    };
+    if data.len() < needed {
+        return Err(format!(
+            "A {}x{} image needs {} values, but the buffer only has {}",
+            width,
+            height,
+            needed,
+            data.len()
+        ));
+    }
+
+    Ok((0..height)
+        .flat_map(|y| data[y * stride..y * stride + width].iter())
+        .map(convert)
+        .collect())
+}
+
+fn scale_to_u8(values: &[f64], scaling: IntensityScaling) -> Vec<u8> {
+    let finite = || values.iter().copied().filter(|value| value.is_finite());
+
+    let (low, high) = match scaling {
+        IntensityScaling::Range { low, high } => (low, high),
+        IntensityScaling::MinMax => (
+            finite().fold(f64::INFINITY, f64::min),
+            finite().fold(f64::NEG_INFINITY, f64::max),
+        ),
+        IntensityScaling::Percentile { low, high } => {
+            let mut sorted: Vec<f64> = finite().collect();
+            sorted.sort_unstable_by(|a, b| a.partial_cmp(b).unwrap());
+
+            let percentile = |percent: f64| {
+                if sorted.is_empty() {
+                    return 0.0;
+                }
+                let index = (percent.clamp(0.0, 100.0) / 100.0 * (sorted.len() - 1) as f64).round();
+                sorted[index as usize]
+            };
+            (percentile(low), percentile(high))
+        }
+    };
+
+    let range = high - low;
+    values
+        .iter()
+        .map(|&value| {
+            if !value.is_finite() || range.abs() < f64::EPSILON {
+                0
+            } else {
+                ((value - low) / range * 255.0).round().clamp(0.0, 255.0) as u8
+            }
+        })
+        .collect()
+}
+
+fn gamma_table(gamma: f64) -> [u8; 256] {
+    let mut table = [0u8; 256];
+    for (value, entry) in table.iter_mut().enumerate() {
+        *entry = ((value as f64 / 255.0).powf(gamma) * 255.0).round() as u8;
+    }
+    table
+}
+
+fn brightness_table(offset: i32) -> [u8; 256] {
+    let mut table = [0u8; 256];
+    for (value, entry) in table.iter_mut().enumerate() {
+        *entry = (value as i32 + offset).clamp(0, 255) as u8;
+    }
+    table
+}
+
+fn check_buffer(
+    width: usize,
+    height: usize,
+    stride: usize,
+    data: &[u8],
+    channels: usize,
+) -> Result<(), String> {
+    let too_large = || format!("A {}x{} image is too large", width, height);
+
+    let row = width.checked_mul(channels).ok_or_else(too_large)?;
+    if stride < row {
+        return Err(format!(
+            "A stride of {} bytes is too small for rows of {} bytes",
+            stride, row
+        ));
+    }
+
+    let needed = if height == 0 {
+        0
+    } else {
+        stride
+            .checked_mul(height - 1)
+            .and_then(|rows| rows.checked_add(row))
+            .ok_or_else(too_large)?
+    };
+    if data.len() < needed {
+        return Err(format!(
+            "A {}x{} image needs {} bytes, but the buffer only has {}",
+            width,
+            height,
+            needed,
+            data.len()
+        ));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_check_buffer() {
+    assert!(check_buffer(4, 2, 12, &[0; 24], 3).is_ok());
+    // the last row doesn't need any padding
+    assert!(check_buffer(4, 2, 16, &[0; 28], 3).is_ok());
+    assert!(check_buffer(4, 2, 16, &[0; 27], 3).is_err());
+    assert!(check_buffer(4, 2, 11, &[0; 24], 3).is_err());
+    assert!(check_buffer(4, 0, 12, &[], 3).is_ok());
+    // sizes that would wrap around are errors rather than tiny buffers
+    assert!(check_buffer(usize::MAX / 2, 1, usize::MAX, &[0; 24], 3).is_err());
+    assert!(check_buffer(4, usize::MAX, usize::MAX / 2, &[0; 24], 3).is_err());
+    assert!(check_buffer(4, 3, usize::MAX / 2 + 1, &[0; 24], 3).is_err());
+    assert!(ImageMatrixView::new(4, usize::MAX, usize::MAX / 2, &[0; 24]).is_err());
+}
+
+#[test]
+fn test_from_raw() {
+    let rgba = [
+        1, 2, 3, 255, 4, 5, 6, 255, 0, 0, 7, 8, 9, 255, 10, 11, 12, 255, 0, 0,
+    ];
+    let bgr = [3, 2, 1, 6, 5, 4, 9, 8, 7, 12, 11, 10];
+
+    let a = ImageMatrix::from_rgba(2, 2, 10, &rgba).unwrap();
+    let b = ImageMatrix::from_bgr(2, 2, 6, &bgr).unwrap();
+    assert_eq!((a.width(), a.height()), (2, 2));
+
+    let same = unsafe {
+        let a = &a;
+        let b = &b;
+        cpp!([a as "const dlib::matrix<dlib::rgb_pixel>*", b as "const dlib::matrix<dlib::rgb_pixel>*"] -> bool as "bool" {
+            return *a == *b && (*a)(1, 1).blue == 12;
+        })
+    };
+    assert!(same);
+
+    assert!(ImageMatrix::from_rgb(2, 2, 6, &bgr[..11]).is_err());
+
+    let rgb = [1, 2, 3, 4, 5, 6, 0, 0, 7, 8, 9, 10, 11, 12];
+    let view = ImageMatrixView::new(2, 2, 8, &rgb).unwrap();
+    let c = view.to_matrix();
+    let same = unsafe {
+        let a = &a;
+        let c = &c;
+        cpp!([a as "const dlib::matrix<dlib::rgb_pixel>*", c as "const dlib::matrix<dlib::rgb_pixel>*"] -> bool as "bool" {
+            return *a == *c;
+        })
+    };
+    assert!(same);
+    assert_eq!(
+        GrayImageMatrix::from_gray(3, 2, 4, &[0; 7])
+            .unwrap()
+            .width(),
+        3
+    );
+}
+
+#[cfg(feature = "image")]
+#[test]
+fn test_to_image() {
+    let image = image::open("assets/obama_1.jpg").unwrap().to_rgb();
+
+    let matrix = ImageMatrix::from_image(&image);
+    assert_eq!(matrix.to_image(), image);
+
+    let crop = matrix.crop(&Rectangle {
+        left: 10,
+        top: 20,
+        right: 20,
+        bottom: 25,
+    });
+    let raw = crop.to_raw_rgb();
+    assert_eq!(raw.len(), 10 * 5 * 3);
+    assert_eq!(&raw[..3], &image.get_pixel(10, 20).0);
+
+    let gray = GrayImageMatrix::from_image(&image::DynamicImage::ImageRgb8(image).to_luma());
+    assert_eq!(gray.to_raw().len(), gray.width() * gray.height());
+    assert_eq!(gray.to_image().width() as usize, gray.width());
+}
+
+#[test]
+fn test_from_yuv() {
+    // white, black, red and blue in BT.601 limited range
+    let y = [235, 16, 81, 41];
+    let u = [128, 128, 90, 240];
+    let v = [128, 128, 240, 110];
+    let expected = [[255, 255, 255], [0, 0, 0], [255, 0, 0], [0, 0, 255]];
+
+    // a 2x2 image needs a single chroma sample, so use a 4x2 image with two
+    let y_plane = [y[0], y[0], y[2], y[2], y[0], y[0], y[2], y[2]];
+    let uv_plane = [u[0], v[0], u[2], v[2]];
+    let image = ImageMatrix::from_nv12(4, 2, 4, &y_plane, 4, &uv_plane)
+        .unwrap()
+        .to_raw_rgb();
+    assert_eq!(&image[..3], &expected[0]);
+    assert_eq!(&image[6..9], &expected[2]);
+    assert_eq!(&image[21..], &expected[2]);
+
+    let image = ImageMatrix::from_i420(4, 2, 4, &y_plane, 2, &[u[0], u[2]], &[v[0], v[2]])
+        .unwrap()
+        .to_raw_rgb();
+    assert_eq!(&image[..3], &expected[0]);
+    assert_eq!(&image[21..], &expected[2]);
+
+    let yuyv = [y[1], u[1], y[1], v[1], y[3], u[3], y[3], v[3]];
+    let image = ImageMatrix::from_yuyv(4, 1, 8, &yuyv).unwrap().to_raw_rgb();
+    assert_eq!(&image[..3], &expected[1]);
+    assert_eq!(&image[9..], &expected[3]);
+
+    assert!(ImageMatrix::from_nv12(4, 2, 4, &y_plane, 4, &uv_plane[..3]).is_err());
+    assert!(ImageMatrix::from_yuyv(usize::MAX, 1, usize::MAX, &[0; 8]).is_err());
+}
+
+#[test]
+fn test_load_and_save() {
+    let image = ImageMatrix::load("assets/obama_1.jpg").unwrap();
+    assert!(image.width() > 0 && image.height() > 0);
+
+    let gray = GrayImageMatrix::load("assets/obama_1.jpg").unwrap();
+    assert_eq!(
+        (gray.width(), gray.height()),
+        (image.width(), image.height())
+    );
+
+    assert!(ImageMatrix::load("assets/missing.jpg").is_err());
+
+    let path = std::env::temp_dir().join("dlib-face-recognition-saved.png");
+    let small = image.resize(32, 24);
+    small.save_png(&path).unwrap();
+    let loaded = ImageMatrix::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(loaded.to_raw_rgb(), small.to_raw_rgb());
+}
+
+#[test]
+fn test_oriented() {
+    // 1 2 3
+    // 4 5 6
+    let values = [1, 2, 3, 4, 5, 6];
+    let data: Vec<u8> = values.iter().flat_map(|&value| vec![value; 3]).collect();
+    let image = ImageMatrix::from_rgb(3, 2, 9, &data).unwrap();
+
+    let channel =
+        |image: &ImageMatrix| -> Vec<u8> { image.to_raw_rgb().into_iter().step_by(3).collect() };
+
+    assert_eq!(channel(&image.oriented(1)), values.to_vec());
+    // transposed
+    assert_eq!(channel(&image.oriented(5)), vec![1, 4, 2, 5, 3, 6]);
+    // transversed
+    assert_eq!(channel(&image.oriented(7)), vec![6, 3, 5, 2, 4, 1]);
+
+    // every orientation is undone by the matching stored image
+    let stored = [
+        (2, image.flip_horizontal()),
+        (3, image.rotate180()),
+        (4, image.flip_vertical()),
+        (6, image.rotate90()),
+        (8, image.rotate270()),
+    ];
+    for (orientation, stored) in stored.iter() {
+        assert_eq!(channel(&stored.oriented(*orientation)), values.to_vec());
+    }
+}
+
+#[test]
+fn test_resize_and_pyramid() {
+    let image = ImageMatrix::from_rgb(2, 1, 6, &[0, 0, 0, 255, 255, 255]).unwrap();
+
+    let nearest = image.resize_with(4, 1, Interpolation::Nearest).to_raw_rgb();
+    assert!(nearest.iter().all(|&value| value == 0 || value == 255));
+
+    let bilinear = image.resize_with(4, 1, Interpolation::Bilinear);
+    assert_eq!(bilinear.to_raw_rgb(), image.resize(4, 1).to_raw_rgb());
+    assert_eq!(
+        image.resize_with(4, 3, Interpolation::Quadratic).height(),
+        3
+    );
+
+    let large = ImageMatrix::blank(64, 48);
+    let down = large.pyramid_down();
+    // dlib trims a pixel or so from the borders of each pyramid level
+    assert!((30..=32).contains(&down.width()) && (22..=24).contains(&down.height()));
+    let up = down.pyramid_up();
+    assert!(up.width() >= 2 * down.width() - 2 && up.width() <= 2 * down.width());
+}
+
+#[test]
+fn test_crop_padded() {
+    let image = ImageMatrix::from_rgb(2, 2, 6, &[1, 1, 1, 2, 2, 2, 3, 3, 3, 4, 4, 4]).unwrap();
+
+    let crop = image.crop_padded(
+        &Rectangle {
+            left: 1,
+            top: -1,
+            right: 3,
+            bottom: 1,
+        },
+        [9, 9, 9],
+    );
+    assert_eq!(crop.to_raw_rgb(), vec![9, 9, 9, 9, 9, 9, 2, 2, 2, 9, 9, 9]);
+
+    let far = image.crop_padded(
+        &Rectangle {
+            left: 10,
+            top: 10,
+            right: 11,
+            bottom: 11,
+        },
+        [0, 0, 0],
+    );
+    assert_eq!(far.to_raw_rgb(), vec![0, 0, 0]);
+
+    let clamped = image.crop(&Rectangle {
+        left: 1,
+        top: -1,
+        right: 3,
+        bottom: 1,
+    });
+    assert_eq!(clamped.to_raw_rgb(), vec![2, 2, 2]);
+
+    // right and bottom are exclusive, so this is just the bottom right pixel
+    let pixel = Rectangle {
+        left: 1,
+        top: 1,
+        right: 2,
+        bottom: 2,
+    };
+    assert_eq!(image.crop(&pixel).to_raw_rgb(), vec![4, 4, 4]);
+    assert_eq!(
+        image.crop_padded(&pixel, [0, 0, 0]).to_raw_rgb(),
+        vec![4, 4, 4]
+    );
+}
+
+#[test]
+fn test_rotate_and_flip() {
+    // 1 2 3
+    // 4 5 6
+    let values = [1, 2, 3, 4, 5, 6];
+    let data: Vec<u8> = values.iter().flat_map(|&value| vec![value; 3]).collect();
+    let image = ImageMatrix::from_rgb(3, 2, 9, &data).unwrap();
+
+    let channel =
+        |image: &ImageMatrix| -> Vec<u8> { image.to_raw_rgb().into_iter().step_by(3).collect() };
+
+    let rotated = image.rotate90();
+    assert_eq!((rotated.width(), rotated.height()), (2, 3));
+    assert_eq!(channel(&rotated), vec![3, 6, 2, 5, 1, 4]);
+    assert_eq!(channel(&image.rotate180()), vec![6, 5, 4, 3, 2, 1]);
+    assert_eq!(channel(&image.rotate270()), vec![4, 1, 5, 2, 6, 3]);
+    assert_eq!(channel(&image.rotate90().rotate270()), values.to_vec());
+
+    assert_eq!(channel(&image.flip_horizontal()), vec![3, 2, 1, 6, 5, 4]);
+    assert_eq!(channel(&image.flip_vertical()), vec![4, 5, 6, 1, 2, 3]);
+}
+
+#[test]
+fn test_tables() {
+    let identity = gamma_table(1.0);
+    assert!(identity
+        .iter()
+        .enumerate()
+        .all(|(value, &entry)| entry as usize == value));
+
+    let brighter = gamma_table(0.5);
+    assert_eq!((brighter[0], brighter[64], brighter[255]), (0, 128, 255));
+    assert!(gamma_table(2.0)[128] < 128);
+
+    let offset = brightness_table(-20);
+    assert_eq!((offset[10], offset[100], offset[255]), (0, 80, 235));
+    assert_eq!(brightness_table(300)[0], 255);
+}
+
+#[test]
+fn test_equalize_histogram() {
+    let data: Vec<u8> = (0..64u8).map(|value| 100 + value / 4).collect();
+    let gray = GrayImageMatrix::from_gray(8, 8, 8, &data).unwrap();
+
+    let equalized = gray.equalize_histogram().to_raw();
+    assert!(*equalized.iter().max().unwrap() > 200);
+    assert!(*equalized.iter().min().unwrap() < 50);
+
+    let rgb: Vec<u8> = data.iter().flat_map(|&value| vec![value; 3]).collect();
+    let image = ImageMatrix::from_rgb(8, 8, 24, &rgb).unwrap();
+    let equalized = image.equalize_histogram().to_raw_rgb();
+    assert!(*equalized.iter().max().unwrap() > 200);
+}
+
+#[test]
+fn test_blur_and_median() {
+    // a single bright speck in the middle of a dark 5x5 image
+    let mut data = vec![10u8; 5 * 5 * 3];
+    data[12 * 3..13 * 3].copy_from_slice(&[250, 250, 250]);
+    let image = ImageMatrix::from_rgb(5, 5, 15, &data).unwrap();
+
+    let median = image.median_filter(1).to_raw_rgb();
+    assert!(median.iter().all(|&value| value == 10));
+    assert_eq!(image.median_filter(0).to_raw_rgb(), data);
+
+    let blurred = image.gaussian_blur(1.0).to_raw_rgb();
+    let centre = blurred[12 * 3];
+    assert!(centre > 10 && centre < 250);
+    assert!(blurred[11 * 3] > 10);
+}
+
+#[test]
+fn test_scale_to_u8() {
+    let values = [0.0, 500.0, 1000.0, f64::NAN, 2000.0];
+
+    let range = scale_to_u8(
+        &values,
+        IntensityScaling::Range {
+            low: 0.0,
+            high: 1000.0,
+        },
+    );
+    assert_eq!(range, vec![0, 128, 255, 0, 255]);
+
+    let min_max = scale_to_u8(&values, IntensityScaling::MinMax);
+    assert_eq!(min_max, vec![0, 64, 128, 0, 255]);
+
+    // the hot pixel is ignored by the percentiles
+    let mut hot = vec![100.0; 99];
+    hot.push(60000.0);
+    hot[0] = 0.0;
+    let scaled = scale_to_u8(
+        &hot,
+        IntensityScaling::Percentile {
+            low: 0.0,
+            high: 98.0,
+        },
+    );
+    assert_eq!((scaled[0], scaled[1], scaled[99]), (0, 255, 255));
+
+    assert_eq!(
+        scale_to_u8(&[5.0, 5.0], IntensityScaling::MinMax),
+        vec![0, 0]
+    );
+}
+
+#[test]
+fn test_strided_values() {
+    let data = [1u16, 2, 99, 3, 4];
+    let values = strided_values(2, 2, 3, &data, |&value| value as f64).unwrap();
+    assert_eq!(values, vec![1.0, 2.0, 3.0, 4.0]);
+
+    assert!(strided_values(2, 2, 3, &data[..4], |&value| value as f64).is_err());
+    assert!(strided_values(3, 1, 2, &data, |&value| value as f64).is_err());
+    assert!(strided_values(2, usize::MAX, usize::MAX / 2, &data, |&value| value as f64).is_err());
+
+    let image = ImageMatrix::from_gray16(2, 2, 3, &data, IntensityScaling::MinMax).unwrap();
+    assert_eq!(image.to_raw_rgb()[9..], [255, 255, 255]);
+}
+
+#[test]
+fn test_pixel_access() {
+    let mut image = ImageMatrix::blank(4, 3);
+
+    image.set_pixel(2, 1, [1, 2, 3]);
+    assert_eq!(image.get_pixel(2, 1), Some([1, 2, 3]));
+    assert_eq!(image.get_pixel(1, 2), Some([0, 0, 0]));
+    assert_eq!(image.get_pixel(4, 0), None);
+    assert_eq!(image.get_pixel(0, 3), None);
+
+    assert_eq!(
+        image.row(1).unwrap(),
+        &[0, 0, 0, 0, 0, 0, 1, 2, 3, 0, 0, 0][..]
+    );
+    assert!(image.row(3).is_none());
+
+    image.row_mut(2).unwrap()[0] = 255;
+    assert_eq!(image.get_pixel(0, 2), Some([255, 0, 0]));
+    assert_eq!(image.to_raw_rgb()[24..27], [255, 0, 0]);
+}
+
+#[test]
+fn test_bytes_round_trip() {
+    let mut image = ImageMatrix::blank(5, 3);
+    image.set_pixel(4, 2, [10, 20, 30]);
+
+    let bytes = image.to_bytes(false).unwrap();
+    assert_eq!(bytes.len(), HEADER_SIZE + 5 * 3 * 3);
+
+    let loaded = ImageMatrix::from_bytes(&bytes).unwrap();
+    assert_eq!((loaded.width(), loaded.height()), (5, 3));
+    assert_eq!(loaded.get_pixel(4, 2), Some([10, 20, 30]));
+
+    assert!(ImageMatrix::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    assert!(ImageMatrix::from_bytes(b"DFRDB").is_err());
+
+    #[cfg(feature = "compression")]
+    {
+        let compressed = image.to_bytes(true).unwrap();
+        assert!(compressed.len() < bytes.len());
+        let loaded = ImageMatrix::from_bytes(&compressed).unwrap();
+        assert_eq!(loaded.to_raw_rgb(), image.to_raw_rgb());
+
+        // A header claiming a smaller image than the payload decompresses to.
+        let mut oversized = compressed;
+        oversized[10..14].copy_from_slice(&1u32.to_le_bytes());
+        assert!(ImageMatrix::from_bytes(&oversized).is_err());
+    }
+}
+
+#[test]
+fn test_letterbox() {
+    let image = ImageMatrix::blank(200, 100).adjust_brightness(100);
+
+    let (letterboxed, transform) = image.letterbox(100, 100, [0, 0, 255]);
+    assert_eq!((letterboxed.width(), letterboxed.height()), (100, 100));
+    assert_eq!(letterboxed.get_pixel(50, 10), Some([0, 0, 255]));
+    assert_eq!(letterboxed.get_pixel(50, 50), Some([100, 100, 100]));
+    assert_eq!(letterboxed.get_pixel(50, 80), Some([0, 0, 255]));
+
+    assert_eq!(transform.apply([50.0, 25.0]), [100.0, 0.0]);
+    assert_eq!(transform.apply([100.0, 75.0]), [200.0, 100.0]);
+
+    let (tall, _) = image.letterbox(50, 200, [0, 0, 0]);
+    assert_eq!((tall.width(), tall.height()), (50, 200));
+    assert_eq!(tall.get_pixel(25, 100), Some([100, 100, 100]));
+    assert_eq!(tall.get_pixel(25, 80), Some([0, 0, 0]));
+}
+
+#[cfg(feature = "ndarray")]
+#[test]
+fn test_ndarray_round_trip() {
+    let mut image = ImageMatrix::blank(4, 2);
+    image.set_pixel(3, 1, [1, 2, 3]);
+
+    let array = image.to_array();
+    assert_eq!(array.dim(), (2, 4, 3));
+    assert_eq!(array[[1, 3, 2]], 3);
+
+    let copied = ImageMatrix::from_array(array.view()).unwrap();
+    assert_eq!(copied.to_raw_rgb(), image.to_raw_rgb());
+
+    // a transposed view isn't contiguous
+    let transposed = ImageMatrix::from_array(array.view().permuted_axes([1, 0, 2])).unwrap();
+    assert_eq!((transposed.width(), transposed.height()), (2, 4));
+    assert_eq!(transposed.get_pixel(1, 3), Some([1, 2, 3]));
+
+    assert!(ImageMatrix::from_array(ndarray::Array3::<u8>::zeros((2, 2, 4)).view()).is_err());
 }
@@ -0,0 +1,736 @@
+use std::path::{Path, PathBuf};
+
+use crate::face_detection::{FaceDetection, FaceDetector, FaceDetectorCnn, FaceDetectorTrait};
+use crate::face_encoding::{FaceEncoderNetwork, FaceEncoderTrait, FaceEncoding};
+use crate::geometry::Rectangle;
+use crate::landmark_prediction::{FaceLandmarks, LandmarkPredictor, LandmarkPredictorTrait};
+use crate::matrix::ImageMatrix;
+use crate::quality::{FaceQuality, FaceQualityOptions};
+
+#[cfg(test)]
+use crate::face_detection::{FaceDetections, FaceLocations};
+#[cfg(test)]
+use crate::face_encoding::FaceEncodings;
+#[cfg(test)]
+use crate::geometry::Point;
+
+/// A face found by a [`Pipeline`].
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Face {
+    /// Where the face was detected, in pixels of the processed image.
+    pub rect: Rectangle,
+    /// The detector's confidence in the face. The scale depends on the detector.
+    pub confidence: f64,
+    /// The face's landmarks in pixels of the processed image, with as many points as the predictor's model.
+    pub landmarks: FaceLandmarks,
+    /// The face's 128 dimensional encoding, compared with [`FaceEncoding::distance`].
+    pub encoding: FaceEncoding,
+    /// The quality of the face, if the pipeline assesses it and the landmarks have 68 points.
+    pub quality: Option<FaceQuality>,
+    /// The id of the face's track, when found by a [`VideoProcessor`](crate::video::VideoProcessor) that
+    /// tracks faces.
+    pub track_id: Option<u64>,
+    /// The label the face was identified as, when found by a
+    /// [`VideoProcessor`](crate::video::VideoProcessor) with a database of known faces.
+    pub identity: Option<String>,
+}
+
+/// Everything a [`Pipeline`] found in a single frame.
+///
+/// With the `serde` feature this can be serialized, giving services downstream of the pipeline a stable
+/// schema for its results.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FrameAnalysis {
+    /// The caller's identifier for the frame, e.g. its index in a stream.
+    pub frame_id: u64,
+    /// When the frame was captured, in seconds from an epoch of the caller's choosing.
+    pub timestamp: Option<f64>,
+    /// The size of the frame in pixels.
+    pub width: usize,
+    pub height: usize,
+    pub faces: Vec<Face>,
+}
+
+/// Detects, predicts the landmarks of and encodes every face in an image in one call.
+///
+/// Any detector, landmark predictor and encoder can be combined. With the `embed-lp` and `embed-fe-nn`
+/// features, [`Pipeline::default`] uses the HOG detector with the 68-point landmark predictor and dlib's face
+/// encoding network.
+pub struct Pipeline {
+    detector: Box<dyn FaceDetectorTrait + Send + Sync>,
+    predictor: Box<dyn LandmarkPredictorTrait + Send + Sync>,
+    encoder: Box<dyn FaceEncoderTrait + Send + Sync>,
+    num_jitters: u32,
+    num_upsamples: u32,
+    min_face_size: i64,
+    min_confidence: f64,
+    quality: Option<FaceQualityOptions>,
+}
+
+impl Pipeline {
+    pub fn new<D, P, E>(detector: D, predictor: P, encoder: E) -> Self
+    where
+        D: FaceDetectorTrait + Send + Sync + 'static,
+        P: LandmarkPredictorTrait + Send + Sync + 'static,
+        E: FaceEncoderTrait + Send + Sync + 'static,
+    {
+        Self {
+            detector: Box::new(detector),
+            predictor: Box::new(predictor),
+            encoder: Box::new(encoder),
+            num_jitters: 0,
+            num_upsamples: 0,
+            min_face_size: 0,
+            min_confidence: f64::NEG_INFINITY,
+            quality: None,
+        }
+    }
+
+    /// Set how many times each face is jittered when encoding it, see [`FaceEncoderTrait::get_face_encodings`].
+    pub fn set_num_jitters(&mut self, num_jitters: u32) {
+        self.num_jitters = num_jitters;
+    }
+
+    pub fn num_jitters(&self) -> u32 {
+        self.num_jitters
+    }
+
+    /// Set how many times the image is upsampled before detecting faces, see
+    /// [`FaceDetectorTrait::face_locations_upsampled_with_confidence`].
+    pub fn set_num_upsamples(&mut self, num_upsamples: u32) {
+        self.num_upsamples = num_upsamples;
+    }
+
+    pub fn num_upsamples(&self) -> u32 {
+        self.num_upsamples
+    }
+
+    /// Ignore faces smaller than `min_face_size` pixels, the larger of their width and height, whose landmarks
+    /// and encodings are unreliable.
+    pub fn set_min_face_size(&mut self, min_face_size: i64) {
+        self.min_face_size = min_face_size;
+    }
+
+    pub fn min_face_size(&self) -> i64 {
+        self.min_face_size
+    }
+
+    /// Ignore detections the detector is less confident in than `min_confidence`.
+    pub fn set_min_confidence(&mut self, min_confidence: f64) {
+        self.min_confidence = min_confidence;
+    }
+
+    pub fn min_confidence(&self) -> f64 {
+        self.min_confidence
+    }
+
+    /// Assess the [`FaceQuality`] of every face with these options, or not at all with `None`, the default.
+    pub fn set_quality_options(&mut self, options: Option<FaceQualityOptions>) {
+        self.quality = options;
+    }
+
+    pub fn quality_options(&self) -> Option<FaceQualityOptions> {
+        self.quality
+    }
+
+    pub fn detector(&self) -> &(dyn FaceDetectorTrait + Send + Sync) {
+        &*self.detector
+    }
+
+    pub fn predictor(&self) -> &(dyn LandmarkPredictorTrait + Send + Sync) {
+        &*self.predictor
+    }
+
+    pub fn encoder(&self) -> &(dyn FaceEncoderTrait + Send + Sync) {
+        &*self.encoder
+    }
+
+    /// Find the faces in an image, after upsampling it and without the faces that are too small or not
+    /// confident enough.
+    pub fn detect(&self, image: &ImageMatrix) -> Vec<FaceDetection> {
+        self.detect_with(&*self.detector, image, 1.0)
+    }
+
+    // Like `detect`, but with another detector and after scaling the image by `scale`, returning the faces in
+    // the coordinates of the unscaled image.
+    pub(crate) fn detect_with(
+        &self,
+        detector: &dyn FaceDetectorTrait,
+        image: &ImageMatrix,
+        scale: f64,
+    ) -> Vec<FaceDetection> {
+        let mut scaled: Option<ImageMatrix> = None;
+        if scale != 1.0 {
+            let width = ((image.width() as f64 * scale).round() as usize).max(1);
+            let height = ((image.height() as f64 * scale).round() as usize).max(1);
+            scaled = Some(image.resize(width, height));
+        }
+
+        let unscale = |value: i64| (value as f64 / scale).round() as i64;
+
+        detector
+            .face_locations_upsampled_with_confidence(
+                scaled.as_ref().unwrap_or(image),
+                self.num_upsamples,
+            )
+            .iter()
+            .filter(|detection| detection.confidence >= self.min_confidence)
+            .map(|detection| FaceDetection {
+                rect: Rectangle {
+                    left: unscale(detection.rect.left),
+                    top: unscale(detection.rect.top),
+                    right: unscale(detection.rect.right),
+                    bottom: unscale(detection.rect.bottom),
+                },
+                ..*detection
+            })
+            .filter(|detection| {
+                detection.rect.width().max(detection.rect.height()) >= self.min_face_size
+            })
+            .collect()
+    }
+
+    /// Find and encode every face in an image.
+    pub fn process(&self, image: &ImageMatrix) -> Vec<Face> {
+        let detections = self.detect(image);
+        self.process_detections(image, &detections)
+    }
+
+    /// Predict the landmarks of and encode faces that have already been found, e.g. by a tracker.
+    pub fn process_detections(
+        &self,
+        image: &ImageMatrix,
+        detections: &[FaceDetection],
+    ) -> Vec<Face> {
+        self.process_detections_with_encodings(image, detections, &vec![None; detections.len()])
+    }
+
+    // Like `process_detections`, but only encodes the faces without an encoding already.
+    pub(crate) fn process_detections_with_encodings(
+        &self,
+        image: &ImageMatrix,
+        detections: &[FaceDetection],
+        encodings: &[Option<FaceEncoding>],
+    ) -> Vec<Face> {
+        if detections.is_empty() {
+            return Vec::new();
+        }
+
+        let landmarks: Vec<FaceLandmarks> = detections
+            .iter()
+            .map(|detection| self.predictor.face_landmarks(image, &detection.rect))
+            .collect();
+
+        let unencoded: Vec<FaceLandmarks> = landmarks
+            .iter()
+            .zip(encodings.iter())
+            .filter(|(_, encoding)| encoding.is_none())
+            .map(|(landmarks, _)| landmarks.clone())
+            .collect();
+
+        let computed = if unencoded.is_empty() {
+            Vec::new()
+        } else {
+            self.encoder
+                .get_face_encodings(image, &unencoded, self.num_jitters)
+                .to_vec()
+        };
+        let mut computed = computed.into_iter();
+
+        detections
+            .iter()
+            .zip(landmarks)
+            .zip(encodings.iter())
+            .map(|((detection, landmarks), encoding)| Face {
+                rect: detection.rect,
+                confidence: detection.confidence,
+                quality: self
+                    .quality
+                    .and_then(|options| FaceQuality::assess(image, &landmarks, &options)),
+                landmarks,
+                encoding: match encoding {
+                    Some(encoding) => encoding.clone(),
+                    None => computed.next().unwrap(),
+                },
+                track_id: None,
+                identity: None,
+            })
+            .collect()
+    }
+
+    /// Find and encode every face in a frame, along with the frame's details.
+    pub fn analyze(
+        &self,
+        image: &ImageMatrix,
+        frame_id: u64,
+        timestamp: Option<f64>,
+    ) -> FrameAnalysis {
+        FrameAnalysis {
+            frame_id,
+            timestamp,
+            width: image.width(),
+            height: image.height(),
+            faces: self.process(image),
+        }
+    }
+
+    /// Warm up the detector, predictor and encoder, so that the first real image isn't slower than the rest.
+    pub fn warm_up(&self) {
+        self.detector.warm_up();
+        self.predictor.warm_up();
+        self.encoder.warm_up();
+    }
+}
+
+#[cfg(all(feature = "embed-lp", feature = "embed-fe-nn"))]
+impl Default for Pipeline {
+    fn default() -> Self {
+        Self::new(
+            crate::face_detection::FaceDetector::default(),
+            crate::landmark_prediction::LandmarkPredictor::default(),
+            crate::face_encoding::FaceEncoderNetwork::default(),
+        )
+    }
+}
+
+/// Which detector a [`PipelineBuilder`] uses.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DetectorKind {
+    /// [`FaceDetector`], which is fast and needs no model file, but only finds roughly frontal faces.
+    Hog,
+    /// [`FaceDetectorCnn`], which finds faces at more angles, but is much slower without a GPU.
+    Cnn,
+}
+
+/// Which landmark model a [`PipelineBuilder`] uses.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LandmarkKind {
+    /// The 68-point model, needed for [`crate::face_analysis`].
+    SixtyEight,
+    /// The smaller and faster 5-point model, which is enough for encoding faces.
+    Five,
+}
+
+impl LandmarkKind {
+    fn num_parts(self) -> usize {
+        match self {
+            LandmarkKind::SixtyEight => 68,
+            LandmarkKind::Five => 5,
+        }
+    }
+}
+
+/// Configures and loads the models of a [`Pipeline`].
+///
+/// Models are loaded from the paths given, or from the embedded models when the matching `embed-*` feature
+/// is enabled. [`PipelineBuilder::build`] loads every model up front and returns an error describing any
+/// that are missing or invalid, rather than failing on the first frame.
+#[derive(Clone, Debug)]
+pub struct PipelineBuilder {
+    detector: DetectorKind,
+    detector_model: Option<PathBuf>,
+    landmarks: LandmarkKind,
+    landmark_model: Option<PathBuf>,
+    encoder_model: Option<PathBuf>,
+    num_jitters: u32,
+    num_upsamples: u32,
+    min_face_size: i64,
+    adjust_threshold: f64,
+    min_confidence: f64,
+    quality: Option<FaceQualityOptions>,
+}
+
+impl Default for PipelineBuilder {
+    /// The HOG detector and the 68-point landmark predictor, with no upsampling or jittering.
+    fn default() -> Self {
+        Self {
+            detector: DetectorKind::Hog,
+            detector_model: None,
+            landmarks: LandmarkKind::SixtyEight,
+            landmark_model: None,
+            encoder_model: None,
+            num_jitters: 0,
+            num_upsamples: 0,
+            min_face_size: 0,
+            adjust_threshold: 0.0,
+            min_confidence: f64::NEG_INFINITY,
+            quality: None,
+        }
+    }
+}
+
+impl PipelineBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn detector(mut self, detector: DetectorKind) -> Self {
+        self.detector = detector;
+        self
+    }
+
+    /// Load the CNN detector from `path`. The HOG detector doesn't need a model.
+    pub fn detector_model<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.detector_model = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn landmarks(mut self, landmarks: LandmarkKind) -> Self {
+        self.landmarks = landmarks;
+        self
+    }
+
+    pub fn landmark_model<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.landmark_model = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn encoder_model<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.encoder_model = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// See [`Pipeline::set_num_jitters`].
+    pub fn num_jitters(mut self, num_jitters: u32) -> Self {
+        self.num_jitters = num_jitters;
+        self
+    }
+
+    /// See [`Pipeline::set_num_upsamples`].
+    pub fn num_upsamples(mut self, num_upsamples: u32) -> Self {
+        self.num_upsamples = num_upsamples;
+        self
+    }
+
+    /// See [`Pipeline::set_min_face_size`].
+    pub fn min_face_size(mut self, min_face_size: i64) -> Self {
+        self.min_face_size = min_face_size;
+        self
+    }
+
+    /// Set the detector's `adjust_threshold`, where lower values find more faces along with more false positives.
+    pub fn adjust_threshold(mut self, adjust_threshold: f64) -> Self {
+        self.adjust_threshold = adjust_threshold;
+        self
+    }
+
+    /// See [`Pipeline::set_min_confidence`].
+    pub fn min_confidence(mut self, min_confidence: f64) -> Self {
+        self.min_confidence = min_confidence;
+        self
+    }
+
+    /// See [`Pipeline::set_quality_options`].
+    pub fn assess_quality(mut self, options: FaceQualityOptions) -> Self {
+        self.quality = Some(options);
+        self
+    }
+
+    /// Load the models and build the pipeline.
+    pub fn build(self) -> Result<Pipeline, String> {
+        let detector_model = match self.detector {
+            DetectorKind::Hog => None,
+            DetectorKind::Cnn => Some(model_path(
+                "CNN face detector",
+                &self.detector_model,
+                embedded_detector(),
+            )?),
+        };
+
+        let predictor = self.load_predictor()?;
+        let encoder = FaceEncoderNetwork::new(model_path(
+            "face encoder",
+            &self.encoder_model,
+            embedded_encoder(),
+        )?)?;
+
+        let mut pipeline = match detector_model {
+            None => {
+                let mut detector = FaceDetector::new();
+                detector.set_adjust_threshold(self.adjust_threshold);
+                Pipeline::new(detector, predictor, encoder)
+            }
+            Some(path) => {
+                let mut detector = FaceDetectorCnn::new(path)?;
+                detector.set_adjust_threshold(self.adjust_threshold);
+                Pipeline::new(detector, predictor, encoder)
+            }
+        };
+
+        pipeline.set_num_jitters(self.num_jitters);
+        pipeline.set_num_upsamples(self.num_upsamples);
+        pipeline.set_min_face_size(self.min_face_size);
+        pipeline.set_min_confidence(self.min_confidence);
+        pipeline.set_quality_options(self.quality);
+
+        Ok(pipeline)
+    }
+
+    fn load_predictor(&self) -> Result<LandmarkPredictor, String> {
+        let embedded = match self.landmarks {
+            LandmarkKind::SixtyEight => embedded_landmarks_68(),
+            LandmarkKind::Five => embedded_landmarks_5(),
+        };
+        let path = model_path("landmark predictor", &self.landmark_model, embedded)?;
+        let predictor = LandmarkPredictor::new(&path)?;
+
+        let expected = self.landmarks.num_parts();
+        if predictor.num_parts() != expected {
+            return Err(format!(
+                "The landmark predictor '{}' predicts {} points, but {} were asked for",
+                path.display(),
+                predictor.num_parts(),
+                expected
+            ));
+        }
+
+        Ok(predictor)
+    }
+}
+
+// The path to load a model from, checking that it exists.
+fn model_path(
+    name: &str,
+    path: &Option<PathBuf>,
+    embedded: Option<PathBuf>,
+) -> Result<PathBuf, String> {
+    let path = path.clone().or(embedded).ok_or_else(|| {
+        format!(
+            "No model was given for the {}, and no model for it is embedded",
+            name
+        )
+    })?;
+
+    if !path.is_file() {
+        return Err(format!(
+            "The {} model '{}' doesn't exist",
+            name,
+            path.display()
+        ));
+    }
+
+    Ok(path)
+}
+
+#[cfg(feature = "embed-fd-nn")]
+fn embedded_detector() -> Option<PathBuf> {
+    Some(crate::embed::path_for_file("mmod_human_face_detector.dat"))
+}
+
+#[cfg(not(feature = "embed-fd-nn"))]
+fn embedded_detector() -> Option<PathBuf> {
+    None
+}
+
+#[cfg(feature = "embed-lp")]
+fn embedded_landmarks_68() -> Option<PathBuf> {
+    Some(crate::embed::path_for_file(
+        "shape_predictor_68_face_landmarks.dat",
+    ))
+}
+
+#[cfg(not(feature = "embed-lp"))]
+fn embedded_landmarks_68() -> Option<PathBuf> {
+    None
+}
+
+#[cfg(feature = "embed-lp5")]
+fn embedded_landmarks_5() -> Option<PathBuf> {
+    Some(crate::embed::path_for_file(
+        "shape_predictor_5_face_landmarks.dat",
+    ))
+}
+
+#[cfg(not(feature = "embed-lp5"))]
+fn embedded_landmarks_5() -> Option<PathBuf> {
+    None
+}
+
+#[cfg(feature = "embed-fe-nn")]
+fn embedded_encoder() -> Option<PathBuf> {
+    Some(crate::embed::path_for_file(
+        "dlib_face_recognition_resnet_model_v1.dat",
+    ))
+}
+
+#[cfg(not(feature = "embed-fe-nn"))]
+fn embedded_encoder() -> Option<PathBuf> {
+    None
+}
+
+// Finds a face in each 100x100 tile whose top left pixel isn't black, as confident as the pixel is red.
+#[cfg(test)]
+pub(crate) struct TileDetector;
+
+#[cfg(test)]
+impl FaceDetectorTrait for TileDetector {
+    fn face_locations(&self, image: &ImageMatrix) -> FaceLocations {
+        let rects: Vec<Rectangle> = self
+            .face_locations_with_confidence(image)
+            .iter()
+            .map(|detection| detection.rect)
+            .collect();
+        FaceLocations::from_rectangles(&rects)
+    }
+
+    fn face_locations_with_confidence(&self, image: &ImageMatrix) -> FaceDetections {
+        let mut detections = Vec::new();
+        for top in (0..image.height()).step_by(100) {
+            for left in (0..image.width()).step_by(100) {
+                let [red, green, blue] = image.get_pixel(left, top).unwrap();
+                if red as u32 + green as u32 + blue as u32 > 0 {
+                    detections.push(FaceDetection {
+                        rect: Rectangle {
+                            left: left as i64,
+                            top: top as i64,
+                            right: left as i64 + 100,
+                            bottom: top as i64 + 100,
+                        },
+                        confidence: red as f64 / 255.0,
+                        detector_index: 0,
+                    });
+                }
+            }
+        }
+
+        FaceDetections::from_detections(&detections)
+    }
+}
+
+// Predicts the top left and bottom right corners of the rectangle.
+#[cfg(test)]
+pub(crate) struct CornerPredictor;
+
+#[cfg(test)]
+impl LandmarkPredictorTrait for CornerPredictor {
+    fn face_landmarks(&self, _image: &ImageMatrix, rect: &Rectangle) -> FaceLandmarks {
+        let parts = [
+            Point::new(rect.left, rect.top),
+            Point::new(rect.right, rect.bottom),
+        ];
+        FaceLandmarks::new(rect, &parts)
+    }
+}
+
+// Encodes a face as the red value at its first landmark.
+#[cfg(test)]
+pub(crate) struct RedEncoder;
+
+#[cfg(test)]
+impl FaceEncoderTrait for RedEncoder {
+    fn get_face_encodings(
+        &self,
+        image: &ImageMatrix,
+        landmarks: &[FaceLandmarks],
+        _num_jitters: u32,
+    ) -> FaceEncodings {
+        let encodings: Vec<FaceEncoding> = landmarks
+            .iter()
+            .map(|landmarks| {
+                let corner = landmarks[0];
+                let [red, _, _] = image
+                    .get_pixel(corner.x() as usize, corner.y() as usize)
+                    .unwrap();
+                FaceEncoding::new_from_scalar(red as f64 / 255.0)
+            })
+            .collect();
+        FaceEncodings::from_encodings(&encodings)
+    }
+}
+
+// A 200x200 image with faces in the top left and bottom right tiles, with red values of 255 and 51.
+#[cfg(test)]
+pub(crate) fn two_faces() -> ImageMatrix {
+    let mut image = ImageMatrix::blank(200, 200);
+    image.set_pixel(0, 0, [255, 0, 0]);
+    image.set_pixel(100, 100, [51, 0, 0]);
+    image
+}
+
+#[test]
+fn test_process() {
+    let pipeline = Pipeline::new(TileDetector, CornerPredictor, RedEncoder);
+
+    let faces = pipeline.process(&two_faces());
+    assert_eq!(faces.len(), 2);
+
+    assert_eq!(faces[0].rect.left, 0);
+    assert_eq!(faces[0].confidence, 1.0);
+    assert_eq!(faces[0].landmarks.num_parts(), 2);
+    assert_eq!(faces[0].encoding, FaceEncoding::new_from_scalar(1.0));
+
+    assert_eq!(faces[1].rect.left, 100);
+    assert_eq!(faces[1].encoding, FaceEncoding::new_from_scalar(0.2));
+
+    assert!(pipeline.process(&ImageMatrix::blank(200, 200)).is_empty());
+}
+
+#[test]
+fn test_detection_options() {
+    let mut pipeline = Pipeline::new(TileDetector, CornerPredictor, RedEncoder);
+
+    pipeline.set_min_confidence(0.5);
+    let faces = pipeline.process(&two_faces());
+    assert_eq!(faces.len(), 1);
+    assert_eq!(faces[0].rect.left, 0);
+
+    pipeline.set_min_confidence(f64::NEG_INFINITY);
+    pipeline.set_min_face_size(101);
+    assert!(pipeline.detect(&two_faces()).is_empty());
+
+    // faces are found in tiles of the upsampled image, and returned at half the size
+    pipeline.set_min_face_size(0);
+    pipeline.set_num_upsamples(1);
+    let detections = pipeline.detect(&two_faces());
+    assert_eq!(detections.len(), 2);
+    assert_eq!(detections[1].rect.left, 100);
+    assert_eq!(detections[1].rect.width(), 50);
+
+    // and in tiles of the downscaled image, returned at twice the size
+    pipeline.set_num_upsamples(0);
+    let detections = pipeline.detect_with(&TileDetector, &two_faces(), 0.5);
+    assert_eq!(detections.len(), 1);
+    assert_eq!(detections[0].rect.right, 200);
+}
+
+#[test]
+fn test_builder_errors() {
+    let error = PipelineBuilder::new()
+        .landmark_model("missing/landmarks.dat")
+        .encoder_model("missing/encoder.dat")
+        .build()
+        .err()
+        .unwrap();
+    assert!(error.contains("missing/landmarks.dat"), "{}", error);
+
+    let error = PipelineBuilder::new()
+        .detector(DetectorKind::Cnn)
+        .detector_model("missing/detector.dat")
+        .build()
+        .err()
+        .unwrap();
+    assert!(error.contains("missing/detector.dat"), "{}", error);
+}
+
+#[test]
+fn test_analyze() {
+    let mut pipeline = Pipeline::new(TileDetector, CornerPredictor, RedEncoder);
+    pipeline.set_quality_options(Some(FaceQualityOptions::default()));
+
+    let analysis = pipeline.analyze(&two_faces(), 7, Some(0.25));
+    assert_eq!(analysis.frame_id, 7);
+    assert_eq!((analysis.width, analysis.height), (200, 200));
+    assert_eq!(analysis.faces.len(), 2);
+
+    // quality needs 68-point landmarks
+    assert!(analysis.faces[0].quality.is_none());
+
+    #[cfg(feature = "serde")]
+    {
+        let json = serde_json::to_string(&analysis).unwrap();
+        let decoded: FrameAnalysis = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, analysis);
+    }
+}
@@ -0,0 +1,406 @@
+//! Cheap measures of image quality, for rejecting unusable frames before running the face encoder.
+//!
+//! All measures are computed on the brightness of the image, using values in `0..=255`.
+
+use crate::face_analysis::{CameraIntrinsics, HeadPose};
+use crate::geometry::Rectangle;
+use crate::landmark_prediction::FaceLandmarks;
+use crate::matrix::{GrayImageMatrix, ImageMatrix};
+
+/// How sharp an image is, as the variance of its Laplacian.
+///
+/// Blurry images have few edges and so a low variance. The scale depends on the content and size of the image,
+/// but values below about 100 usually mean a blurred or out of focus face. Images smaller than 3x3 have a
+/// sharpness of 0.
+pub fn sharpness(image: &ImageMatrix) -> f64 {
+    let gray = GrayImageMatrix::from_rgb(image);
+    laplacian_variance(&gray.to_raw(), gray.width(), gray.height())
+}
+
+/// The mean brightness of an image, from 0 for black to 255 for white.
+pub fn brightness(image: &ImageMatrix) -> f64 {
+    mean_and_deviation(&GrayImageMatrix::from_rgb(image).to_raw()).0
+}
+
+/// The contrast of an image, as the standard deviation of its brightness, from 0 for a flat image to 127.5.
+pub fn contrast(image: &ImageMatrix) -> f64 {
+    mean_and_deviation(&GrayImageMatrix::from_rgb(image).to_raw()).1
+}
+
+/// The sharpness, brightness and contrast of an image, measured together.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ImageQuality {
+    /// See [`sharpness`].
+    pub sharpness: f64,
+    /// See [`brightness`].
+    pub brightness: f64,
+    /// See [`contrast`].
+    pub contrast: f64,
+}
+
+impl ImageQuality {
+    /// Measure the quality of a whole image.
+    pub fn measure(image: &ImageMatrix) -> Self {
+        let gray = GrayImageMatrix::from_rgb(image);
+        let values = gray.to_raw();
+        let (brightness, contrast) = mean_and_deviation(&values);
+
+        Self {
+            sharpness: laplacian_variance(&values, gray.width(), gray.height()),
+            brightness,
+            contrast,
+        }
+    }
+
+    /// Measure the quality of part of an image, such as a detected face.
+    ///
+    /// The rectangle is clamped to the bounds of the image.
+    pub fn measure_region(image: &ImageMatrix, rect: &Rectangle) -> Self {
+        Self::measure(&image.crop(rect))
+    }
+
+    /// Whether every measure is within the given thresholds.
+    pub fn is_usable(&self, thresholds: &QualityThresholds) -> bool {
+        self.sharpness >= thresholds.min_sharpness
+            && self.brightness >= thresholds.min_brightness
+            && self.brightness <= thresholds.max_brightness
+            && self.contrast >= thresholds.min_contrast
+    }
+}
+
+/// The limits an [`ImageQuality`] has to be within for [`ImageQuality::is_usable`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct QualityThresholds {
+    /// The lowest acceptable [`sharpness`], a Laplacian variance with no upper bound.
+    pub min_sharpness: f64,
+    /// Darker images are under exposed.
+    pub min_brightness: f64,
+    /// Brighter images are over exposed.
+    pub max_brightness: f64,
+    /// The lowest acceptable [`contrast`], a standard deviation of brightness from 0 to 127.5.
+    pub min_contrast: f64,
+}
+
+impl Default for QualityThresholds {
+    fn default() -> Self {
+        Self {
+            min_sharpness: 100.0,
+            min_brightness: 40.0,
+            max_brightness: 215.0,
+            min_contrast: 20.0,
+        }
+    }
+}
+
+/// One of the measures that make up a [`FaceQuality`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum QualityComponent {
+    /// The face is turned or tilted away from the camera.
+    Pose,
+    /// The face is blurred or out of focus.
+    Sharpness,
+    /// The face is too small, i.e. too far from the camera.
+    Size,
+    /// The face is under or over exposed, or too flat.
+    Exposure,
+}
+
+/// What a [`FaceQuality`] considers an ideal face.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FaceQualityOptions {
+    /// The yaw or pitch in degrees at which the pose scores 0.
+    pub max_angle: f64,
+    /// The sharpness of the face at and above which it scores 1.
+    pub ideal_sharpness: f64,
+    /// The width in pixels of the face at and above which it scores 1.
+    pub ideal_face_size: f64,
+    /// The range of mean brightness that scores 1.
+    pub ideal_brightness: [f64; 2],
+    /// The contrast at and above which the face scores 1.
+    pub ideal_contrast: f64,
+}
+
+impl Default for FaceQualityOptions {
+    fn default() -> Self {
+        Self {
+            max_angle: 45.0,
+            ideal_sharpness: 200.0,
+            // the size of the chips the face encoder runs on
+            ideal_face_size: 150.0,
+            ideal_brightness: [70.0, 185.0],
+            ideal_contrast: 40.0,
+        }
+    }
+}
+
+/// How suitable a photo of a face is for enrollment, combining its pose, sharpness, size and exposure.
+///
+/// Every component is between `0.0` and `1.0`, where `1.0` is ideal, so an enrollment UI can ask for a retake
+/// when the score is low and use [`FaceQuality::issues`] to say why.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FaceQuality {
+    /// How close the face is to looking straight at the camera.
+    pub pose: f64,
+    /// How sharp the face is, from `0.0` for no edges to `1.0` at or above the ideal sharpness.
+    pub sharpness: f64,
+    /// How large the face is, from `0.0` to `1.0` for faces at least the ideal width in pixels.
+    pub size: f64,
+    /// How well exposed the face is, from its brightness and contrast.
+    pub exposure: f64,
+    /// The lowest of the components.
+    pub score: f64,
+    /// The measures of the face region the sharpness and exposure are scored from.
+    pub image: ImageQuality,
+    /// The estimated head pose, if one could be found.
+    pub head_pose: Option<HeadPose>,
+}
+
+impl FaceQuality {
+    /// Assess a face from its 68-point landmarks and the image they were predicted on.
+    ///
+    /// Returns `None` if these aren't 68-point landmarks.
+    pub fn assess(
+        image: &ImageMatrix,
+        landmarks: &FaceLandmarks,
+        options: &FaceQualityOptions,
+    ) -> Option<Self> {
+        if landmarks.num_parts() != 68 {
+            return None;
+        }
+
+        let camera = CameraIntrinsics::from_image_size(image.width(), image.height());
+        let head_pose = HeadPose::estimate(landmarks, &camera);
+        let pose = head_pose.map_or(0.0, |pose| {
+            let angle = pose.yaw.abs().max(pose.pitch.abs());
+            (1.0 - angle / options.max_angle).max(0.0)
+        });
+
+        let rect = landmarks.rect();
+        let measures = ImageQuality::measure_region(image, &rect);
+
+        let sharpness = ratio(measures.sharpness, options.ideal_sharpness);
+        let size = ratio(rect.width() as f64, options.ideal_face_size);
+
+        let [low, high] = options.ideal_brightness;
+        let brightness = if measures.brightness < low {
+            ratio(measures.brightness, low)
+        } else if measures.brightness > high {
+            ratio(255.0 - measures.brightness, 255.0 - high)
+        } else {
+            1.0
+        };
+        let exposure = brightness.min(ratio(measures.contrast, options.ideal_contrast));
+
+        let score = pose.min(sharpness).min(size).min(exposure);
+
+        Some(Self {
+            pose,
+            sharpness,
+            size,
+            exposure,
+            score,
+            image: measures,
+            head_pose,
+        })
+    }
+
+    /// The score of a single component.
+    pub fn component(&self, component: QualityComponent) -> f64 {
+        match component {
+            QualityComponent::Pose => self.pose,
+            QualityComponent::Sharpness => self.sharpness,
+            QualityComponent::Size => self.size,
+            QualityComponent::Exposure => self.exposure,
+        }
+    }
+
+    /// The components scoring below `min_score`, worst first.
+    pub fn issues(&self, min_score: f64) -> Vec<QualityComponent> {
+        let mut issues: Vec<QualityComponent> = [
+            QualityComponent::Pose,
+            QualityComponent::Sharpness,
+            QualityComponent::Size,
+            QualityComponent::Exposure,
+        ]
+        .iter()
+        .copied()
+        .filter(|&component| self.component(component) < min_score)
+        .collect();
+
+        issues.sort_by(|&a, &b| {
+            self.component(a)
+                .partial_cmp(&self.component(b))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        issues
+    }
+
+    /// Whether every component scores at least `min_score`.
+    pub fn is_acceptable(&self, min_score: f64) -> bool {
+        self.score >= min_score
+    }
+}
+
+// `value / ideal`, capped at 1.
+fn ratio(value: f64, ideal: f64) -> f64 {
+    if ideal <= 0.0 {
+        1.0
+    } else {
+        (value / ideal).clamp(0.0, 1.0)
+    }
+}
+
+fn mean_and_deviation(values: &[u8]) -> (f64, f64) {
+    if values.is_empty() {
+        return (0.0, 0.0);
+    }
+
+    let count = values.len() as f64;
+    let mean = values.iter().map(|&value| value as f64).sum::<f64>() / count;
+    let variance = values
+        .iter()
+        .map(|&value| (value as f64 - mean).powi(2))
+        .sum::<f64>()
+        / count;
+
+    (mean, variance.sqrt())
+}
+
+// The variance of the 4-neighbour Laplacian over the interior of the image.
+fn laplacian_variance(values: &[u8], width: usize, height: usize) -> f64 {
+    if width < 3 || height < 3 {
+        return 0.0;
+    }
+
+    let at = |x: usize, y: usize| values[y * width + x] as f64;
+
+    let mut sum = 0.0;
+    let mut sum_squares = 0.0;
+    for y in 1..height - 1 {
+        for x in 1..width - 1 {
+            let laplacian =
+                at(x - 1, y) + at(x + 1, y) + at(x, y - 1) + at(x, y + 1) - 4.0 * at(x, y);
+            sum += laplacian;
+            sum_squares += laplacian * laplacian;
+        }
+    }
+
+    let count = ((width - 2) * (height - 2)) as f64;
+    let mean = sum / count;
+    sum_squares / count - mean * mean
+}
+
+#[test]
+fn test_measures() {
+    let flat = vec![100u8; 16];
+    assert_eq!(laplacian_variance(&flat, 4, 4), 0.0);
+    assert_eq!(mean_and_deviation(&flat), (100.0, 0.0));
+
+    let checkerboard: Vec<u8> = (0..16)
+        .map(|i| if (i % 4 + i / 4) % 2 == 0 { 0 } else { 255 })
+        .collect();
+    assert!(laplacian_variance(&checkerboard, 4, 4) > 1e5);
+    assert_eq!(mean_and_deviation(&checkerboard), (127.5, 127.5));
+
+    assert_eq!(laplacian_variance(&[0, 255, 0, 255], 2, 2), 0.0);
+    assert_eq!(mean_and_deviation(&[]), (0.0, 0.0));
+}
+
+#[test]
+fn test_image_quality() {
+    let stripes: Vec<u8> = (0..64 * 64)
+        .map(|i| if i % 2 == 0 { 30 } else { 200 })
+        .collect();
+    let image = ImageMatrix::from_gray(64, 64, 64, &stripes).unwrap();
+
+    let quality = ImageQuality::measure(&image);
+    assert!((quality.brightness - 115.0).abs() < 1.0);
+    assert!((quality.contrast - 85.0).abs() < 1.0);
+    assert!(quality.is_usable(&QualityThresholds::default()));
+
+    let blurred = ImageQuality::measure(&image.gaussian_blur(2.0));
+    assert!(blurred.sharpness < quality.sharpness / 10.0);
+    assert!(!blurred.is_usable(&QualityThresholds::default()));
+
+    let region = Rectangle {
+        left: 0,
+        top: 0,
+        right: 15,
+        bottom: 15,
+    };
+    let dark = ImageQuality::measure_region(&image.adjust_brightness(-150), &region);
+    assert!(dark.brightness < 40.0);
+    assert!(!dark.is_usable(&QualityThresholds::default()));
+}
+
+#[test]
+fn test_face_quality() {
+    use crate::geometry::Point;
+
+    // the face model looking straight at the camera
+    let mut parts = vec![Point::new(320, 240); 68];
+    let model = [
+        (8, 320, 375),
+        (36, 232, 173),
+        (45, 408, 173),
+        (48, 261, 299),
+        (54, 379, 299),
+    ];
+    for &(index, x, y) in &model {
+        parts[index] = Point::new(x, y);
+    }
+    let rect = Rectangle {
+        left: 200,
+        top: 120,
+        right: 439,
+        bottom: 399,
+    };
+    let landmarks = FaceLandmarks::new(&rect, &parts);
+
+    let stripes: Vec<u8> = (0..640 * 480)
+        .map(|i| if i % 2 == 0 { 60 } else { 190 })
+        .collect();
+    let image = ImageMatrix::from_gray(640, 480, 640, &stripes).unwrap();
+    let options = FaceQualityOptions::default();
+
+    let quality = FaceQuality::assess(&image, &landmarks, &options).unwrap();
+    assert!(quality.pose > 0.9, "{:?}", quality);
+    assert_eq!(quality.sharpness, 1.0);
+    assert_eq!(quality.size, 1.0);
+    assert_eq!(quality.exposure, 1.0);
+    assert!(quality.is_acceptable(0.9));
+    assert!(quality.issues(0.9).is_empty());
+
+    // a small and under exposed face
+    let small = Rectangle {
+        left: 300,
+        top: 220,
+        right: 374,
+        bottom: 294,
+    };
+    let dark = image.adjust_brightness(-80);
+    let quality =
+        FaceQuality::assess(&dark, &FaceLandmarks::new(&small, &parts), &options).unwrap();
+    assert!((quality.size - 0.5).abs() < 1e-9);
+    assert!(
+        quality.exposure > 0.7 && quality.exposure < 0.9,
+        "{:?}",
+        quality
+    );
+    assert!(!quality.is_acceptable(0.9));
+    assert_eq!(
+        quality.issues(0.9),
+        vec![QualityComponent::Size, QualityComponent::Exposure]
+    );
+
+    let blurred = FaceQuality::assess(&image.gaussian_blur(2.0), &landmarks, &options).unwrap();
+    assert!(blurred.sharpness < 0.1);
+    assert!(blurred.issues(0.9).contains(&QualityComponent::Sharpness));
+
+    assert!(
+        FaceQuality::assess(&image, &FaceLandmarks::new(&rect, &parts[..5]), &options).is_none()
+    );
+}
@@ -0,0 +1,238 @@
+/// The budget a [`VideoProcessor`](super::VideoProcessor) keeps to in adaptive mode.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AdaptiveOptions {
+    /// The frame rate to keep up with. Frames are processed in `1 / target_fps` seconds on average.
+    pub target_fps: f64,
+    /// The most frames between detections.
+    pub max_detect_every: u32,
+    /// The smallest factor frames are scaled by before detecting faces in them, at least `0.1`.
+    pub min_scale: f64,
+    /// How much each measured processing time counts towards the estimate used to choose the settings,
+    /// between `0.0` and `1.0`. Higher values react to changes in load more quickly but less steadily.
+    pub responsiveness: f64,
+}
+
+impl Default for AdaptiveOptions {
+    fn default() -> Self {
+        Self {
+            target_fps: 30.0,
+            max_detect_every: 15,
+            min_scale: 0.5,
+            responsiveness: 0.2,
+        }
+    }
+}
+
+/// The settings a [`VideoProcessor`](super::VideoProcessor) processed a frame with.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FrameSettings {
+    /// The factor the frame was scaled by before detecting faces in it.
+    pub scale: f64,
+    /// How many frames there are between detections.
+    pub detect_every: u32,
+    /// Whether faces were detected with the fast detector instead of the pipeline's.
+    pub fast_detector: bool,
+}
+
+const MIN_SCALE: f64 = 0.1;
+
+// Each step down in scale is this factor of the one before.
+const SCALE_STEP: f64 = 0.75;
+
+// Settings better than the current ones are only chosen when they are expected to take at most this fraction of
+// the budget, so that settings don't flip back and forth at its edge.
+const UPGRADE_MARGIN: f64 = 0.9;
+
+// Chooses the settings of each frame from the measured cost of the frames before it.
+#[derive(Clone, Debug)]
+pub(crate) struct AdaptiveController {
+    options: AdaptiveOptions,
+    has_fast_detector: bool,
+    settings: FrameSettings,
+    // the seconds a detection takes at full scale with the pipeline's detector and with the fast one
+    detection_costs: [Option<f64>; 2],
+    // the seconds a frame without a detection takes
+    tracking_cost: Option<f64>,
+}
+
+impl AdaptiveController {
+    pub(crate) fn new(options: AdaptiveOptions, has_fast_detector: bool) -> Self {
+        Self {
+            options,
+            has_fast_detector,
+            settings: FrameSettings {
+                scale: 1.0,
+                detect_every: 1,
+                fast_detector: false,
+            },
+            detection_costs: [None, None],
+            tracking_cost: None,
+        }
+    }
+
+    pub(crate) fn options(&self) -> AdaptiveOptions {
+        self.options
+    }
+
+    /// The settings to process the next frame with.
+    pub(crate) fn settings(&self) -> FrameSettings {
+        self.settings
+    }
+
+    /// Record how many seconds a frame processed with the current settings took.
+    pub(crate) fn record(&mut self, detected: bool, seconds: f64) {
+        let alpha = self.options.responsiveness.clamp(0.0, 1.0);
+        let average = |estimate: Option<f64>, value: f64| match estimate {
+            Some(estimate) => Some(estimate + alpha * (value - estimate)),
+            None => Some(value),
+        };
+
+        if detected {
+            let scale = self.settings.scale;
+            let index = self.settings.fast_detector as usize;
+            self.detection_costs[index] =
+                average(self.detection_costs[index], seconds / (scale * scale));
+        } else {
+            self.tracking_cost = average(self.tracking_cost, seconds);
+        }
+
+        self.settings = self.choose();
+    }
+
+    // The detectors and scales to choose from, from the best results to the cheapest.
+    fn candidates(&self) -> Vec<(bool, f64)> {
+        let min_scale = self.options.min_scale.clamp(MIN_SCALE, 1.0);
+        let detectors: &[bool] = if self.has_fast_detector {
+            &[false, true]
+        } else {
+            &[false]
+        };
+
+        let mut candidates = Vec::new();
+        for &fast_detector in detectors {
+            let mut scale = 1.0;
+            loop {
+                candidates.push((fast_detector, scale));
+                if scale <= min_scale {
+                    break;
+                }
+                scale = (scale * SCALE_STEP).max(min_scale);
+            }
+        }
+        candidates
+    }
+
+    fn choose(&self) -> FrameSettings {
+        let budget = 1.0 / self.options.target_fps;
+        let tracking_cost = self.tracking_cost.unwrap_or(0.0);
+        let max_detect_every = self.options.max_detect_every.max(1);
+
+        let candidates = self.candidates();
+        let current = candidates
+            .iter()
+            .position(|&(fast_detector, scale)| {
+                fast_detector == self.settings.fast_detector && scale == self.settings.scale
+            })
+            .unwrap_or(0);
+
+        for (index, &(fast_detector, scale)) in candidates.iter().enumerate() {
+            let detection_cost = match self.detection_costs[fast_detector as usize] {
+                Some(cost) => cost * scale * scale,
+                // try the detector to find out how long it takes
+                None => {
+                    return FrameSettings {
+                        scale,
+                        detect_every: self.settings.detect_every,
+                        fast_detector,
+                    }
+                }
+            };
+
+            let budget = if index < current {
+                budget * UPGRADE_MARGIN
+            } else {
+                budget
+            };
+
+            if let Some(detect_every) = detect_every(detection_cost, tracking_cost, budget) {
+                if detect_every <= max_detect_every {
+                    return FrameSettings {
+                        scale,
+                        detect_every,
+                        fast_detector,
+                    };
+                }
+            }
+        }
+
+        let (fast_detector, scale) = *candidates.last().unwrap();
+        FrameSettings {
+            scale,
+            detect_every: max_detect_every,
+            fast_detector,
+        }
+    }
+}
+
+// How often a detection costing `detection_cost` seconds can run, with `tracking_cost` seconds for the frames in
+// between, so that frames take `budget` seconds on average, or `None` if the frames in between alone take longer.
+fn detect_every(detection_cost: f64, tracking_cost: f64, budget: f64) -> Option<u32> {
+    if detection_cost <= budget {
+        return Some(1);
+    }
+    if tracking_cost >= budget {
+        return None;
+    }
+
+    let frames = ((detection_cost - tracking_cost) / (budget - tracking_cost)).ceil();
+    Some(frames.min(u32::MAX as f64) as u32)
+}
+
+#[test]
+fn test_detect_every() {
+    assert_eq!(detect_every(0.05, 0.0, 0.1), Some(1));
+    assert_eq!(detect_every(0.3, 0.0, 0.1), Some(3));
+    assert_eq!(detect_every(0.3, 0.02, 0.1), Some(4));
+    assert_eq!(detect_every(0.3, 0.1, 0.1), None);
+}
+
+#[test]
+fn test_adaptive_controller() {
+    let options = AdaptiveOptions {
+        target_fps: 10.0,
+        max_detect_every: 10,
+        min_scale: 0.5,
+        responsiveness: 1.0,
+    };
+    let mut controller = AdaptiveController::new(options, true);
+    let settings = |scale, detect_every, fast_detector| FrameSettings {
+        scale,
+        detect_every,
+        fast_detector,
+    };
+    assert_eq!(controller.settings(), settings(1.0, 1, false));
+
+    controller.record(true, 0.05);
+    assert_eq!(controller.settings(), settings(1.0, 1, false));
+
+    // slower detections are run less often
+    controller.record(true, 0.3);
+    assert_eq!(controller.settings(), settings(1.0, 3, false));
+    controller.record(false, 0.02);
+    assert_eq!(controller.settings(), settings(1.0, 4, false));
+
+    // and at a smaller scale, once they can't be run rarely enough
+    controller.record(true, 2.0);
+    assert_eq!(controller.settings(), settings(0.5625, 8, false));
+
+    // until the fast detector is tried
+    controller.record(true, 3.0);
+    assert_eq!(controller.settings(), settings(1.0, 8, true));
+    controller.record(true, 0.05);
+    assert_eq!(controller.settings(), settings(1.0, 1, true));
+
+    // without a fast detector the cheapest settings are used
+    let mut controller = AdaptiveController::new(options, false);
+    controller.record(true, 100.0);
+    assert_eq!(controller.settings(), settings(0.5, 10, false));
+}
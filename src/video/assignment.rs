@@ -0,0 +1,112 @@
+// The Hungarian algorithm, with the potentials of Jonker and Volgenant, for rectangular cost matrices.
+//
+// Returns the column assigned to each row, minimizing the total cost. When there are more rows than columns
+// some rows are left unassigned.
+pub(crate) fn assign(costs: &[Vec<f64>], columns: usize) -> Vec<Option<usize>> {
+    let rows = costs.len();
+    if rows == 0 || columns == 0 {
+        return vec![None; rows];
+    }
+
+    if rows > columns {
+        let transposed: Vec<Vec<f64>> = (0..columns)
+            .map(|column| costs.iter().map(|row| row[column]).collect())
+            .collect();
+
+        let mut assignment = vec![None; rows];
+        for (column, row) in assign(&transposed, rows).into_iter().enumerate() {
+            if let Some(row) = row {
+                assignment[row] = Some(column);
+            }
+        }
+        return assignment;
+    }
+
+    // 1-based, with row and column 0 as sentinels
+    let mut u = vec![0f64; rows + 1];
+    let mut v = vec![0f64; columns + 1];
+    let mut row_of = vec![0usize; columns + 1];
+    let mut way = vec![0usize; columns + 1];
+
+    for row in 1..=rows {
+        row_of[0] = row;
+        let mut column = 0;
+        let mut min_slack = vec![f64::INFINITY; columns + 1];
+        let mut used = vec![false; columns + 1];
+
+        loop {
+            used[column] = true;
+            let current = row_of[column];
+            let mut delta = f64::INFINITY;
+            let mut next = 0;
+
+            for candidate in 1..=columns {
+                if used[candidate] {
+                    continue;
+                }
+
+                let slack = costs[current - 1][candidate - 1] - u[current] - v[candidate];
+                if slack < min_slack[candidate] {
+                    min_slack[candidate] = slack;
+                    way[candidate] = column;
+                }
+                if min_slack[candidate] < delta {
+                    delta = min_slack[candidate];
+                    next = candidate;
+                }
+            }
+
+            for candidate in 0..=columns {
+                if used[candidate] {
+                    u[row_of[candidate]] += delta;
+                    v[candidate] -= delta;
+                } else {
+                    min_slack[candidate] -= delta;
+                }
+            }
+
+            column = next;
+            if row_of[column] == 0 {
+                break;
+            }
+        }
+
+        while column != 0 {
+            let previous = way[column];
+            row_of[column] = row_of[previous];
+            column = previous;
+        }
+    }
+
+    let mut assignment = vec![None; rows];
+    for column in 1..=columns {
+        if row_of[column] != 0 {
+            assignment[row_of[column] - 1] = Some(column - 1);
+        }
+    }
+    assignment
+}
+
+#[test]
+fn test_assign() {
+    let costs = vec![
+        vec![4.0, 1.0, 3.0],
+        vec![2.0, 0.0, 5.0],
+        vec![3.0, 2.0, 2.0],
+    ];
+    assert_eq!(assign(&costs, 3), [Some(1), Some(0), Some(2)]);
+
+    // greedily taking the cheapest pair first would cost 1 + 10
+    let costs = vec![vec![1.0, 2.0], vec![10.0, 1.5]];
+    assert_eq!(assign(&costs, 2), [Some(0), Some(1)]);
+    let costs = vec![vec![1.0, 2.0], vec![1.5, 10.0]];
+    assert_eq!(assign(&costs, 2), [Some(1), Some(0)]);
+
+    let costs = vec![vec![5.0], vec![1.0], vec![3.0]];
+    assert_eq!(assign(&costs, 1), [None, Some(0), None]);
+    let costs = vec![vec![5.0, 1.0, 3.0]];
+    assert_eq!(assign(&costs, 3), [Some(1)]);
+
+    assert!(assign(&[], 2).is_empty());
+    assert_eq!(assign(&[vec![]], 0), [None]);
+}
@@ -0,0 +1,191 @@
+use crate::geometry::Rectangle;
+
+/// The noise assumed by a [`BoxFilter`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct KalmanOptions {
+    /// The standard deviation of the change in velocity between frames, in pixels per frame. Higher values
+    /// follow changes in movement more quickly but smooth less.
+    pub process_noise: f64,
+    /// The standard deviation of the error in detected or tracked boxes, in pixels. Higher values smooth more
+    /// but lag further behind movement.
+    pub measurement_noise: f64,
+}
+
+impl Default for KalmanOptions {
+    fn default() -> Self {
+        Self {
+            process_noise: 1.0,
+            measurement_noise: 4.0,
+        }
+    }
+}
+
+// A constant velocity kalman filter of a single value.
+#[derive(Copy, Clone, Debug)]
+struct Axis {
+    position: f64,
+    velocity: f64,
+    covariance: [[f64; 2]; 2],
+}
+
+impl Axis {
+    fn new(position: f64, variance: f64) -> Self {
+        Self {
+            position,
+            velocity: 0.0,
+            covariance: [[variance, 0.0], [0.0, variance]],
+        }
+    }
+
+    fn predict(&mut self, dt: f64, process_variance: f64) {
+        self.position += self.velocity * dt;
+
+        let [[p00, p01], [p10, p11]] = self.covariance;
+        let (dt2, dt3, dt4) = (dt * dt, dt * dt * dt, dt * dt * dt * dt);
+        self.covariance = [
+            [
+                p00 + dt * (p10 + p01) + dt2 * p11 + process_variance * dt4 / 4.0,
+                p01 + dt * p11 + process_variance * dt3 / 2.0,
+            ],
+            [
+                p10 + dt * p11 + process_variance * dt3 / 2.0,
+                p11 + process_variance * dt2,
+            ],
+        ];
+    }
+
+    fn correct(&mut self, measurement: f64, measurement_variance: f64) {
+        let [[p00, p01], [p10, p11]] = self.covariance;
+        let total = p00 + measurement_variance;
+        let (gain_position, gain_velocity) = (p00 / total, p10 / total);
+
+        let innovation = measurement - self.position;
+        self.position += gain_position * innovation;
+        self.velocity += gain_velocity * innovation;
+
+        self.covariance = [
+            [(1.0 - gain_position) * p00, (1.0 - gain_position) * p01],
+            [p10 - gain_velocity * p00, p11 - gain_velocity * p01],
+        ];
+    }
+}
+
+/// Smooths the box of a tracked face with a kalman filter of the position and velocity of its center and
+/// size, so that overlays and crops don't jump around between detections.
+#[derive(Copy, Clone, Debug)]
+pub struct BoxFilter {
+    options: KalmanOptions,
+    // center x, center y, width and height
+    axes: [Axis; 4],
+}
+
+impl BoxFilter {
+    /// Start filtering at the first box of a face.
+    pub fn new(rect: &Rectangle, options: KalmanOptions) -> Self {
+        let variance = options.measurement_noise * options.measurement_noise;
+        let [x, y] = rect.center();
+        let (width, height) = (rect.width() as f64, rect.height() as f64);
+
+        Self {
+            options,
+            axes: [
+                Axis::new(x, variance),
+                Axis::new(y, variance),
+                Axis::new(width, variance),
+                Axis::new(height, variance),
+            ],
+        }
+    }
+
+    pub fn options(&self) -> KalmanOptions {
+        self.options
+    }
+
+    /// Move the box on by `dt` frames at its current velocity, e.g. for frames the face wasn't found on.
+    pub fn predict(&mut self, dt: f64) -> Rectangle {
+        let variance = self.options.process_noise * self.options.process_noise;
+        for axis in self.axes.iter_mut() {
+            axis.predict(dt, variance);
+        }
+        self.rect()
+    }
+
+    /// Move the box towards where the face was found.
+    pub fn correct(&mut self, rect: &Rectangle) -> Rectangle {
+        let variance = self.options.measurement_noise * self.options.measurement_noise;
+        let [x, y] = rect.center();
+        let measurements = [x, y, rect.width() as f64, rect.height() as f64];
+
+        for (axis, measurement) in self.axes.iter_mut().zip(measurements.iter()) {
+            axis.correct(*measurement, variance);
+        }
+        self.rect()
+    }
+
+    /// Predict the box `dt` frames on, then correct it with where the face was found.
+    pub fn update(&mut self, rect: &Rectangle, dt: f64) -> Rectangle {
+        self.predict(dt);
+        self.correct(rect)
+    }
+
+    /// The smoothed box.
+    pub fn rect(&self) -> Rectangle {
+        let [x, y, width, height] = [
+            self.axes[0].position,
+            self.axes[1].position,
+            self.axes[2].position.max(0.0),
+            self.axes[3].position.max(0.0),
+        ];
+
+        Rectangle {
+            left: (x - width / 2.0).round() as i64,
+            top: (y - height / 2.0).round() as i64,
+            right: (x + width / 2.0).round() as i64,
+            bottom: (y + height / 2.0).round() as i64,
+        }
+    }
+
+    /// The velocity of the center of the box in pixels per frame.
+    pub fn velocity(&self) -> [f64; 2] {
+        [self.axes[0].velocity, self.axes[1].velocity]
+    }
+}
+
+#[cfg(test)]
+fn square(left: i64) -> Rectangle {
+    Rectangle {
+        left,
+        top: 20,
+        right: left + 40,
+        bottom: 60,
+    }
+}
+
+#[test]
+fn test_box_filter() {
+    // a box moving steadily is followed, and its velocity learnt
+    let mut filter = BoxFilter::new(&square(0), KalmanOptions::default());
+    for frame in 1..40 {
+        filter.update(&square(2 * frame), 1.0);
+    }
+    let [vx, vy] = filter.velocity();
+    assert!((vx - 2.0).abs() < 0.1, "{}", vx);
+    assert!(vy.abs() < 1e-9);
+    assert!((filter.rect().left - 78).abs() <= 1, "{:?}", filter.rect());
+    // one frame ahead, the box has moved on by its velocity
+    let predicted = filter.predict(1.0);
+    assert!((predicted.left - 80).abs() <= 1, "{:?}", predicted);
+    assert_eq!(predicted.width(), 40);
+    assert_eq!(filter.rect(), predicted);
+
+    // a still box that jitters by 3 pixels either way is smoothed
+    let mut filter = BoxFilter::new(&square(100), KalmanOptions::default());
+    for frame in 0..40 {
+        let offset = if frame % 2 == 0 { 3 } else { -3 };
+        let rect = filter.update(&square(100 + offset), 1.0);
+        if frame >= 10 {
+            assert!((rect.left - 100).abs() <= 1, "{:?}", rect);
+        }
+    }
+    assert_eq!(filter.rect().width(), 40);
+}
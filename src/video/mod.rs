@@ -0,0 +1,16 @@
+//! Running a [`Pipeline`](crate::Pipeline) over the frames of a video.
+
+mod adaptive;
+mod assignment;
+mod kalman;
+mod multi;
+mod processor;
+mod scene;
+mod tracker;
+
+pub use self::adaptive::{AdaptiveOptions, FrameSettings};
+pub use self::kalman::{BoxFilter, KalmanOptions};
+pub use self::multi::{MultiTracker, MultiTrackerOptions, Track, TrackState};
+pub use self::processor::{Frames, VideoFrame, VideoProcessor};
+pub use self::scene::{SceneChangeDetector, SceneChangeOptions};
+pub use self::tracker::CorrelationTracker;
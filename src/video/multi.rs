@@ -0,0 +1,280 @@
+use super::assignment::assign;
+use super::tracker::CorrelationTracker;
+use crate::geometry::Rectangle;
+use crate::matrix::ImageMatrix;
+
+/// When a [`MultiTracker`] creates, confirms and removes tracks.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MultiTrackerOptions {
+    /// The smallest intersection over union between a detection and a track for them to be matched.
+    pub min_iou: f64,
+    /// How many times in a row a track has to be detected before it is confirmed.
+    pub min_hits: u32,
+    /// How many detection passes in a row a confirmed track can go undetected before it is removed.
+    pub max_misses: u32,
+    /// The correlation tracker confidence below which a track is considered lost between detections,
+    /// see [`CorrelationTracker::update`].
+    pub min_tracking_confidence: f64,
+}
+
+impl Default for MultiTrackerOptions {
+    fn default() -> Self {
+        Self {
+            min_iou: 0.3,
+            min_hits: 3,
+            max_misses: 5,
+            min_tracking_confidence: 7.0,
+        }
+    }
+}
+
+/// The lifecycle of a [`Track`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TrackState {
+    /// The track is new, and is removed the first time it goes undetected.
+    Tentative,
+    /// The track has been detected [`MultiTrackerOptions::min_hits`] times in a row and is being followed.
+    Confirmed,
+    /// The track was confirmed, but went undetected or the correlation tracker lost it. It is removed after
+    /// [`MultiTrackerOptions::max_misses`] detection passes unless it is detected again.
+    Lost,
+}
+
+/// A face followed by a [`MultiTracker`].
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Track {
+    /// Unique for the lifetime of the tracker, and never reused.
+    pub id: u64,
+    /// Where the face was last detected or tracked to.
+    pub rect: Rectangle,
+    pub state: TrackState,
+    /// How many frames the track has existed for.
+    pub age: u32,
+    /// How many detection passes in a row the track has been detected in.
+    pub hits: u32,
+    /// How many detection passes in a row the track has gone undetected in.
+    pub misses: u32,
+}
+
+#[derive(Clone)]
+struct Entry {
+    track: Track,
+    tracker: CorrelationTracker,
+}
+
+/// Follows many faces across video frames, giving each a stable id.
+///
+/// Call [`MultiTracker::update`] with the faces found on frames the detector runs on, which are matched to
+/// the existing tracks by their overlap, and [`MultiTracker::predict`] on the frames in between, which follows
+/// each track with a [`CorrelationTracker`].
+#[derive(Clone)]
+pub struct MultiTracker {
+    options: MultiTrackerOptions,
+    entries: Vec<Entry>,
+    next_id: u64,
+}
+
+impl MultiTracker {
+    pub fn new(options: MultiTrackerOptions) -> Self {
+        Self {
+            options,
+            entries: Vec::new(),
+            next_id: 0,
+        }
+    }
+
+    pub fn options(&self) -> MultiTrackerOptions {
+        self.options
+    }
+
+    /// Every current track, in the order they were created.
+    pub fn tracks(&self) -> impl Iterator<Item = &Track> {
+        self.entries.iter().map(|entry| &entry.track)
+    }
+
+    /// The confirmed tracks visible in the last frame.
+    pub fn confirmed(&self) -> impl Iterator<Item = &Track> {
+        self.tracks()
+            .filter(|track| track.state == TrackState::Confirmed)
+    }
+
+    pub fn track(&self, id: u64) -> Option<&Track> {
+        self.tracks().find(|track| track.id == id)
+    }
+
+    /// How many tracks have been created, e.g. to count the people seen.
+    pub fn num_created(&self) -> u64 {
+        self.next_id
+    }
+
+    /// Remove every track, e.g. after a cut in the video. Ids are not reused.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Match the faces detected in a frame to the tracks, returning the id of the track each detection now
+    /// belongs to.
+    ///
+    /// Detections are matched to tracks to maximize their total overlap. Unmatched detections start new
+    /// tentative tracks, and unmatched tracks are removed once they time out.
+    pub fn update(&mut self, image: &ImageMatrix, detections: &[Rectangle]) -> Vec<u64> {
+        let costs: Vec<Vec<f64>> = detections
+            .iter()
+            .map(|detection| {
+                self.entries
+                    .iter()
+                    .map(|entry| 1.0 - detection.iou(&entry.track.rect))
+                    .collect()
+            })
+            .collect();
+
+        let min_iou = self.options.min_iou;
+        let assignment: Vec<Option<usize>> = assign(&costs, self.entries.len())
+            .into_iter()
+            .zip(costs.iter())
+            .map(|(index, costs)| index.filter(|&index| 1.0 - costs[index] >= min_iou))
+            .collect();
+
+        let mut matched = vec![false; self.entries.len()];
+        for index in assignment.iter().flatten() {
+            matched[*index] = true;
+        }
+
+        let mut ids = Vec::with_capacity(detections.len());
+        for (detection, index) in detections.iter().zip(assignment.iter()) {
+            let entry = match index {
+                Some(index) => &mut self.entries[*index],
+                None => {
+                    self.entries.push(Entry {
+                        track: Track {
+                            id: self.next_id,
+                            rect: *detection,
+                            state: TrackState::Tentative,
+                            age: 0,
+                            hits: 0,
+                            misses: 0,
+                        },
+                        tracker: CorrelationTracker::new(),
+                    });
+                    self.next_id += 1;
+                    self.entries.last_mut().unwrap()
+                }
+            };
+
+            let track = &mut entry.track;
+            track.rect = *detection;
+            track.hits += 1;
+            track.misses = 0;
+            if track.state == TrackState::Lost || track.hits >= self.options.min_hits {
+                track.state = TrackState::Confirmed;
+            }
+            entry.tracker.start_track(image, detection);
+            ids.push(track.id);
+        }
+
+        for (entry, matched) in self.entries.iter_mut().zip(matched.iter()) {
+            if !matched {
+                let track = &mut entry.track;
+                track.hits = 0;
+                track.misses += 1;
+                if track.state == TrackState::Confirmed {
+                    track.state = TrackState::Lost;
+                }
+            }
+        }
+
+        let max_misses = self.options.max_misses;
+        self.entries.retain(|entry| match entry.track.state {
+            TrackState::Tentative => entry.track.misses == 0,
+            _ => entry.track.misses <= max_misses,
+        });
+
+        for entry in self.entries.iter_mut() {
+            entry.track.age += 1;
+        }
+
+        ids
+    }
+
+    /// Follow the tracks into a frame the detector didn't run on.
+    ///
+    /// Tracks the correlation tracker loses keep their last position until they are detected again.
+    pub fn predict(&mut self, image: &ImageMatrix) {
+        let min_confidence = self.options.min_tracking_confidence;
+
+        for entry in self.entries.iter_mut() {
+            let track = &mut entry.track;
+            track.age += 1;
+
+            if track.state == TrackState::Lost || !entry.tracker.is_tracking() {
+                continue;
+            }
+
+            let (rect, confidence) = entry.tracker.update(image);
+            if confidence >= min_confidence {
+                track.rect = rect;
+            } else if track.state == TrackState::Confirmed {
+                track.state = TrackState::Lost;
+            }
+        }
+    }
+}
+
+impl Default for MultiTracker {
+    fn default() -> Self {
+        Self::new(MultiTrackerOptions::default())
+    }
+}
+
+#[cfg(test)]
+fn square(left: i64, top: i64) -> Rectangle {
+    Rectangle {
+        left,
+        top,
+        right: left + 40,
+        bottom: top + 40,
+    }
+}
+
+#[test]
+fn test_multi_tracker() {
+    let mut tracker = MultiTracker::new(MultiTrackerOptions {
+        min_hits: 2,
+        max_misses: 1,
+        ..Default::default()
+    });
+    let image = ImageMatrix::blank(200, 200);
+
+    let ids = tracker.update(&image, &[square(0, 0), square(100, 100)]);
+    assert_eq!(ids, [0, 1]);
+    assert!(tracker
+        .tracks()
+        .all(|track| track.state == TrackState::Tentative));
+
+    // the faces move a little, and are listed the other way around
+    let ids = tracker.update(&image, &[square(104, 100), square(4, 0)]);
+    assert_eq!(ids, [1, 0]);
+    assert_eq!(tracker.confirmed().count(), 2);
+    assert_eq!(tracker.track(1).unwrap().rect, square(104, 100));
+
+    // a new face is tentative, and removed as soon as it isn't detected
+    let ids = tracker.update(&image, &[square(4, 0), square(104, 100), square(50, 150)]);
+    assert_eq!(ids, [0, 1, 2]);
+    assert_eq!(tracker.track(2).unwrap().state, TrackState::Tentative);
+
+    let ids = tracker.update(&image, &[square(4, 0)]);
+    assert_eq!(ids, [0]);
+    assert!(tracker.track(2).is_none());
+    assert_eq!(tracker.track(1).unwrap().state, TrackState::Lost);
+
+    // a lost track is confirmed again when it is detected before it times out
+    tracker.update(&image, &[square(4, 0), square(104, 100)]);
+    assert_eq!(tracker.track(1).unwrap().state, TrackState::Confirmed);
+
+    tracker.update(&image, &[square(4, 0)]);
+    tracker.update(&image, &[square(4, 0)]);
+    assert!(tracker.track(1).is_none());
+    assert_eq!(tracker.tracks().count(), 1);
+    assert_eq!(tracker.track(0).unwrap().age, 7);
+    assert_eq!(tracker.num_created(), 3);
+}
@@ -0,0 +1,601 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use super::adaptive::{AdaptiveController, AdaptiveOptions, FrameSettings};
+use super::kalman::{BoxFilter, KalmanOptions};
+use super::multi::{MultiTracker, MultiTrackerOptions, TrackState};
+use super::scene::{SceneChangeDetector, SceneChangeOptions};
+use crate::face_detection::{FaceDetection, FaceDetectorTrait};
+use crate::face_encoding::{FaceDatabase, FaceEncoding};
+use crate::geometry::Rectangle;
+use crate::matrix::ImageMatrix;
+use crate::pipeline::{Face, FrameAnalysis, Pipeline};
+
+#[cfg(test)]
+use crate::pipeline::{two_faces, CornerPredictor, RedEncoder, TileDetector};
+
+/// The results of a [`VideoProcessor`] for a single frame.
+#[derive(Clone)]
+pub struct VideoFrame {
+    pub image: ImageMatrix,
+    pub analysis: FrameAnalysis,
+    /// Whether the pipeline ran on this frame. Otherwise the faces were carried over or tracked from the last
+    /// frame it ran on.
+    pub detected: bool,
+    /// Whether the scene changed since the previous frame, which forgets every track and runs the pipeline.
+    pub scene_change: bool,
+    /// The settings the frame was processed with, which change from frame to frame in adaptive mode.
+    pub settings: FrameSettings,
+    /// How long processing the frame took.
+    pub elapsed: Duration,
+}
+
+// What is remembered about a track between frames.
+#[derive(Clone)]
+struct TrackedFace {
+    detection: FaceDetection,
+    encoding: FaceEncoding,
+    identity: Option<String>,
+    encoded_at: u64,
+}
+
+/// Runs a [`Pipeline`] over the frames of a video, only detecting faces every few frames.
+///
+/// Detecting and encoding faces is usually too slow to do on every frame of a live stream, while faces rarely
+/// move far between consecutive frames. By default the pipeline runs on every frame.
+///
+/// With [`VideoProcessor::set_tracking`] faces are followed with a [`MultiTracker`] between detections, and
+/// each track is only encoded once, and identified once against the [`FaceDatabase`] if there is one. The
+/// encoding and identity are then carried with the track, which saves most of the work of the encoder for
+/// faces that stay in view.
+pub struct VideoProcessor {
+    pipeline: Pipeline,
+    detect_every: u32,
+    frame_rate: Option<f64>,
+    next_frame_id: u64,
+    frames_since_detection: Option<u32>,
+    faces: Vec<Face>,
+    tracker: Option<MultiTracker>,
+    tracked: HashMap<u64, TrackedFace>,
+    reencode_every: Option<u64>,
+    database: Option<FaceDatabase>,
+    smoothing: Option<KalmanOptions>,
+    // the filter of each track, and the frame it was last updated on
+    filters: HashMap<u64, (BoxFilter, u64)>,
+    scene_detector: Option<SceneChangeDetector>,
+    fast_detector: Option<Box<dyn FaceDetectorTrait + Send + Sync>>,
+    adaptive: Option<AdaptiveController>,
+}
+
+impl VideoProcessor {
+    pub fn new(pipeline: Pipeline) -> Self {
+        Self {
+            pipeline,
+            detect_every: 1,
+            frame_rate: None,
+            next_frame_id: 0,
+            frames_since_detection: None,
+            faces: Vec::new(),
+            tracker: None,
+            tracked: HashMap::new(),
+            reencode_every: None,
+            database: None,
+            smoothing: None,
+            filters: HashMap::new(),
+            scene_detector: None,
+            fast_detector: None,
+            adaptive: None,
+        }
+    }
+
+    pub fn pipeline(&self) -> &Pipeline {
+        &self.pipeline
+    }
+
+    pub fn pipeline_mut(&mut self) -> &mut Pipeline {
+        &mut self.pipeline
+    }
+
+    /// Run the pipeline on every `detect_every`th frame, starting with the first. 0 is treated as 1.
+    ///
+    /// In adaptive mode this is chosen for each frame instead.
+    pub fn set_detect_every(&mut self, detect_every: u32) {
+        self.detect_every = detect_every.max(1);
+    }
+
+    pub fn detect_every(&self) -> u32 {
+        self.detect_every
+    }
+
+    /// Give frames from [`VideoProcessor::process_frame`] timestamps in seconds from the first frame, assuming
+    /// they arrive at `frame_rate` frames per second.
+    pub fn set_frame_rate(&mut self, frame_rate: Option<f64>) {
+        self.frame_rate = frame_rate;
+    }
+
+    pub fn frame_rate(&self) -> Option<f64> {
+        self.frame_rate
+    }
+
+    /// Track faces between detections with these options, or carry the faces from the last detection over
+    /// unchanged with `None`, the default. This forgets any current tracks.
+    pub fn set_tracking(&mut self, options: Option<MultiTrackerOptions>) {
+        self.tracker = options.map(MultiTracker::new);
+        self.tracked.clear();
+        self.filters.clear();
+    }
+
+    pub fn tracker(&self) -> Option<&MultiTracker> {
+        self.tracker.as_ref()
+    }
+
+    /// Encode tracked faces again when they are detected `reencode_every` frames or more after they were last
+    /// encoded, e.g. to catch up with changes in pose, or only once with `None`, the default.
+    pub fn set_reencode_every(&mut self, reencode_every: Option<u64>) {
+        self.reencode_every = reencode_every;
+    }
+
+    pub fn reencode_every(&self) -> Option<u64> {
+        self.reencode_every
+    }
+
+    /// Identify tracked faces against a database of known faces, setting [`Face::identity`].
+    pub fn set_database(&mut self, database: Option<FaceDatabase>) {
+        self.database = database;
+    }
+
+    pub fn database(&self) -> Option<&FaceDatabase> {
+        self.database.as_ref()
+    }
+
+    /// Smooth the boxes of tracked faces with a [`BoxFilter`] with these options, or not at all with `None`,
+    /// the default. Faces are only smoothed while they are tracked, see [`VideoProcessor::set_tracking`].
+    pub fn set_box_smoothing(&mut self, options: Option<KalmanOptions>) {
+        self.smoothing = options;
+        self.filters.clear();
+    }
+
+    pub fn box_smoothing(&self) -> Option<KalmanOptions> {
+        self.smoothing
+    }
+
+    /// Watch for changes of scene with a [`SceneChangeDetector`] with these options, or not at all with `None`,
+    /// the default. On a change of scene the faces of the previous frames are forgotten and the pipeline runs
+    /// immediately, so trackers don't latch onto whatever replaced the faces they were following.
+    pub fn set_scene_change_detection(&mut self, options: Option<SceneChangeOptions>) {
+        self.scene_detector = options.map(SceneChangeDetector::new);
+    }
+
+    pub fn scene_change_detection(&self) -> Option<SceneChangeOptions> {
+        self.scene_detector
+            .as_ref()
+            .map(|detector| detector.options())
+    }
+
+    /// Set a cheaper detector for adaptive mode to fall back on when the pipeline's detector is too slow, e.g.
+    /// [`FaceDetector`](crate::FaceDetector) for a pipeline with [`FaceDetectorCnn`](crate::FaceDetectorCnn).
+    ///
+    /// The pipeline's minimum confidence applies to both detectors, so it should suit the scores of both.
+    pub fn set_fast_detector<D>(&mut self, detector: D)
+    where
+        D: FaceDetectorTrait + Send + Sync + 'static,
+    {
+        self.fast_detector = Some(Box::new(detector));
+        if let Some(options) = self.adaptive_options() {
+            self.set_adaptive(Some(options));
+        }
+    }
+
+    pub fn fast_detector(&self) -> Option<&(dyn FaceDetectorTrait + Send + Sync)> {
+        self.fast_detector.as_deref()
+    }
+
+    /// Keep to a frame rate with these options, or run with fixed settings with `None`, the default.
+    ///
+    /// In adaptive mode the time taken by each frame is measured, and the settings of the next frame are
+    /// chosen to keep frames within the budget on average. Detections are first run less often, then on
+    /// downscaled frames, and then with the fast detector, if there is one, as they get slower, and the other
+    /// way around as they get faster. [`VideoFrame::settings`] reports the settings each frame used.
+    pub fn set_adaptive(&mut self, options: Option<AdaptiveOptions>) {
+        let has_fast_detector = self.fast_detector.is_some();
+        self.adaptive = options.map(|options| AdaptiveController::new(options, has_fast_detector));
+    }
+
+    pub fn adaptive_options(&self) -> Option<AdaptiveOptions> {
+        self.adaptive.as_ref().map(|adaptive| adaptive.options())
+    }
+
+    /// Drop every track and force detection on the next call to [`VideoProcessor::process_frame`] or
+    /// [`VideoProcessor::process_frame_at`], whatever the detection interval.
+    pub fn reset(&mut self) {
+        self.frames_since_detection = None;
+        self.faces.clear();
+        if let Some(tracker) = self.tracker.as_mut() {
+            tracker.clear();
+        }
+        self.tracked.clear();
+        self.filters.clear();
+    }
+
+    /// Process the next frame of the video.
+    pub fn process_frame(&mut self, image: ImageMatrix) -> VideoFrame {
+        let timestamp = self
+            .frame_rate
+            .map(|frame_rate| self.next_frame_id as f64 / frame_rate);
+        self.process(image, timestamp)
+    }
+
+    /// Process the next frame of the video, captured at `timestamp` seconds.
+    pub fn process_frame_at(&mut self, image: ImageMatrix, timestamp: f64) -> VideoFrame {
+        self.process(image, Some(timestamp))
+    }
+
+    /// Process every frame from a source, e.g. a decoder or camera, as it is iterated.
+    pub fn frames<I>(&mut self, source: I) -> Frames<'_, I::IntoIter>
+    where
+        I: IntoIterator<Item = ImageMatrix>,
+    {
+        Frames {
+            processor: self,
+            source: source.into_iter(),
+        }
+    }
+
+    fn process(&mut self, image: ImageMatrix, timestamp: Option<f64>) -> VideoFrame {
+        let start = Instant::now();
+
+        let scene_change = match self.scene_detector.as_mut() {
+            Some(detector) => detector.is_scene_change(&image),
+            None => false,
+        };
+        if scene_change {
+            self.reset();
+        }
+
+        let settings = match &self.adaptive {
+            Some(adaptive) => adaptive.settings(),
+            None => FrameSettings {
+                scale: 1.0,
+                detect_every: self.detect_every,
+                fast_detector: false,
+            },
+        };
+
+        let detected = match self.frames_since_detection {
+            Some(frames) => frames + 1 >= settings.detect_every,
+            None => true,
+        };
+
+        if self.tracker.is_some() {
+            self.faces = if detected {
+                self.detect_tracked(&image, &settings)
+            } else {
+                self.follow_tracked(&image)
+            };
+            self.smooth_boxes();
+        } else if detected {
+            let detections = self.detect(&image, &settings);
+            self.faces = self.pipeline.process_detections(&image, &detections);
+        }
+
+        if detected {
+            self.frames_since_detection = Some(0);
+        } else {
+            self.frames_since_detection = self.frames_since_detection.map(|frames| frames + 1);
+        }
+
+        let analysis = FrameAnalysis {
+            frame_id: self.next_frame_id,
+            timestamp,
+            width: image.width(),
+            height: image.height(),
+            faces: self.faces.clone(),
+        };
+        self.next_frame_id += 1;
+
+        let elapsed = start.elapsed();
+        if let Some(adaptive) = self.adaptive.as_mut() {
+            adaptive.record(detected, elapsed.as_secs_f64());
+        }
+
+        VideoFrame {
+            image,
+            analysis,
+            detected,
+            scene_change,
+            settings,
+            elapsed,
+        }
+    }
+
+    fn detect(&self, image: &ImageMatrix, settings: &FrameSettings) -> Vec<FaceDetection> {
+        let detector: &dyn FaceDetectorTrait = match &self.fast_detector {
+            Some(detector) if settings.fast_detector => &**detector,
+            _ => self.pipeline.detector(),
+        };
+
+        self.pipeline.detect_with(detector, image, settings.scale)
+    }
+
+    fn detect_tracked(&mut self, image: &ImageMatrix, settings: &FrameSettings) -> Vec<Face> {
+        let detections = self.detect(image, settings);
+        let rects: Vec<Rectangle> = detections.iter().map(|detection| detection.rect).collect();
+
+        let tracker = self.tracker.as_mut().unwrap();
+        let ids = tracker.update(image, &rects);
+        self.tracked.retain(|id, _| tracker.track(*id).is_some());
+
+        self.tracked_faces(image, &detections, &ids, true)
+    }
+
+    fn follow_tracked(&mut self, image: &ImageMatrix) -> Vec<Face> {
+        let tracker = self.tracker.as_mut().unwrap();
+        tracker.predict(image);
+
+        let mut detections = Vec::new();
+        let mut ids = Vec::new();
+        for track in tracker.tracks() {
+            if track.state == TrackState::Lost {
+                continue;
+            }
+
+            if let Some(tracked) = self.tracked.get(&track.id) {
+                detections.push(FaceDetection {
+                    rect: track.rect,
+                    ..tracked.detection
+                });
+                ids.push(track.id);
+            }
+        }
+
+        self.tracked_faces(image, &detections, &ids, false)
+    }
+
+    // The faces of tracks, only encoding those without an encoding or due to be encoded again when `detected`.
+    fn tracked_faces(
+        &mut self,
+        image: &ImageMatrix,
+        detections: &[FaceDetection],
+        ids: &[u64],
+        detected: bool,
+    ) -> Vec<Face> {
+        let frame_id = self.next_frame_id;
+        let reencode_every = self.reencode_every;
+
+        let encodings: Vec<Option<FaceEncoding>> = ids
+            .iter()
+            .map(|id| {
+                self.tracked
+                    .get(id)
+                    .filter(|tracked| match reencode_every {
+                        Some(every) if detected => frame_id - tracked.encoded_at < every,
+                        _ => true,
+                    })
+                    .map(|tracked| tracked.encoding.clone())
+            })
+            .collect();
+
+        let mut faces = self
+            .pipeline
+            .process_detections_with_encodings(image, detections, &encodings);
+
+        let entries = faces
+            .iter_mut()
+            .zip(detections.iter())
+            .zip(ids.iter())
+            .zip(encodings.iter());
+
+        for (((face, detection), id), encoding) in entries {
+            if encoding.is_none() {
+                let identity = self
+                    .database
+                    .as_ref()
+                    .and_then(|database| database.identify(&face.encoding))
+                    .map(|(label, _)| label.clone());
+
+                self.tracked.insert(
+                    *id,
+                    TrackedFace {
+                        detection: *detection,
+                        encoding: face.encoding.clone(),
+                        identity,
+                        encoded_at: frame_id,
+                    },
+                );
+            }
+
+            let tracked = self.tracked.get_mut(id).unwrap();
+            if detected {
+                tracked.detection = *detection;
+            }
+            face.track_id = Some(*id);
+            face.identity = tracked.identity.clone();
+        }
+
+        faces
+    }
+
+    fn smooth_boxes(&mut self) {
+        let options = match self.smoothing {
+            Some(options) => options,
+            None => return,
+        };
+
+        let tracker = self.tracker.as_ref().unwrap();
+        self.filters.retain(|id, _| tracker.track(*id).is_some());
+
+        let frame_id = self.next_frame_id;
+        for face in self.faces.iter_mut() {
+            let id = match face.track_id {
+                Some(id) => id,
+                None => continue,
+            };
+
+            let (filter, updated_at) = self
+                .filters
+                .entry(id)
+                .or_insert_with(|| (BoxFilter::new(&face.rect, options), frame_id));
+
+            let dt = (frame_id - *updated_at) as f64;
+            if dt > 0.0 {
+                face.rect = filter.update(&face.rect, dt);
+            }
+            *updated_at = frame_id;
+        }
+    }
+}
+
+/// An iterator over the processed frames of a source, see [`VideoProcessor::frames`].
+pub struct Frames<'a, S> {
+    processor: &'a mut VideoProcessor,
+    source: S,
+}
+
+impl<'a, S: Iterator<Item = ImageMatrix>> Iterator for Frames<'a, S> {
+    type Item = VideoFrame;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let image = self.source.next()?;
+        Some(self.processor.process_frame(image))
+    }
+}
+
+#[cfg(test)]
+pub(crate) fn test_processor() -> VideoProcessor {
+    VideoProcessor::new(Pipeline::new(TileDetector, CornerPredictor, RedEncoder))
+}
+
+#[test]
+fn test_detect_every() {
+    let mut processor = test_processor();
+    processor.set_detect_every(3);
+    processor.set_frame_rate(Some(10.0));
+
+    let frames: Vec<VideoFrame> = processor
+        .frames(std::iter::repeat(two_faces()).take(5))
+        .collect();
+
+    let detected: Vec<bool> = frames.iter().map(|frame| frame.detected).collect();
+    assert_eq!(detected, [true, false, false, true, false]);
+
+    assert_eq!(frames[4].analysis.frame_id, 4);
+    assert_eq!(frames[4].analysis.timestamp, Some(0.4));
+    assert!(frames.iter().all(|frame| frame.analysis.faces.len() == 2));
+
+    // faces from the last detection are carried over until the next
+    let frame = processor.process_frame(ImageMatrix::blank(200, 200));
+    assert!(!frame.detected);
+    assert_eq!(frame.analysis.faces.len(), 2);
+
+    processor.reset();
+    let frame = processor.process_frame_at(ImageMatrix::blank(200, 200), 1.5);
+    assert!(frame.detected);
+    assert!(frame.analysis.faces.is_empty());
+    assert_eq!(frame.analysis.timestamp, Some(1.5));
+}
+
+#[test]
+fn test_tracked_recognition() {
+    let mut processor = test_processor();
+    processor.set_tracking(Some(MultiTrackerOptions::default()));
+
+    let mut database = FaceDatabase::new();
+    database.enroll("first".to_string(), FaceEncoding::new_from_scalar(1.0));
+    processor.set_database(Some(database));
+
+    let frame = processor.process_frame(two_faces());
+    let faces = &frame.analysis.faces;
+    assert_eq!(faces[0].track_id, Some(0));
+    assert_eq!(faces[1].track_id, Some(1));
+    assert_eq!(faces[0].identity.as_deref(), Some("first"));
+    assert_eq!(faces[1].identity, None);
+
+    // the top left face changes, but keeps the encoding and identity of its track
+    let mut changed = two_faces();
+    changed.set_pixel(0, 0, [128, 0, 0]);
+    let frame = processor.process_frame(changed.clone());
+    let faces = &frame.analysis.faces;
+    assert_eq!(faces[0].track_id, Some(0));
+    assert_eq!(faces[0].encoding, FaceEncoding::new_from_scalar(1.0));
+    assert_eq!(faces[0].identity.as_deref(), Some("first"));
+
+    processor.set_reencode_every(Some(2));
+    let frame = processor.process_frame(changed);
+    let faces = &frame.analysis.faces;
+    assert_eq!(faces[0].track_id, Some(0));
+    assert_eq!(
+        faces[0].encoding,
+        FaceEncoding::new_from_scalar(128.0 / 255.0)
+    );
+    assert_eq!(faces[0].identity, None);
+}
+
+#[test]
+fn test_box_smoothing() {
+    let mut processor = test_processor();
+    processor.set_tracking(Some(MultiTrackerOptions::default()));
+    processor.set_box_smoothing(Some(KalmanOptions::default()));
+
+    let frame = processor.process_frame(two_faces());
+    assert_eq!(frame.analysis.faces[0].rect.right, 100);
+
+    // a face detected in the same place is left where it is
+    let frame = processor.process_frame(two_faces());
+    assert_eq!(frame.analysis.faces[0].rect.right, 100);
+    assert_eq!(frame.analysis.faces[1].rect.left, 100);
+}
+
+#[test]
+fn test_scene_change() {
+    let mut processor = test_processor();
+    processor.set_detect_every(100);
+    processor.set_tracking(Some(MultiTrackerOptions::default()));
+    processor.set_scene_change_detection(Some(SceneChangeOptions::default()));
+
+    assert!(processor.process_frame(two_faces()).detected);
+    let frame = processor.process_frame(two_faces());
+    assert!(!frame.detected && !frame.scene_change);
+
+    // every tile of a bright frame is a face, found straight away with new tracks
+    let frame = processor.process_frame(ImageMatrix::blank(200, 200).adjust_brightness(200));
+    assert!(frame.detected && frame.scene_change);
+    let ids: Vec<Option<u64>> = frame
+        .analysis
+        .faces
+        .iter()
+        .map(|face| face.track_id)
+        .collect();
+    assert_eq!(ids, [Some(2), Some(3), Some(4), Some(5)]);
+    assert_eq!(processor.tracker().unwrap().tracks().count(), 4);
+}
+
+#[test]
+fn test_adaptive() {
+    let mut processor = test_processor();
+    processor.set_detect_every(4);
+    let frame = processor.process_frame(two_faces());
+    assert_eq!(frame.settings.detect_every, 4);
+
+    // a budget this large is never exceeded, so the best settings are kept
+    processor.set_fast_detector(TileDetector);
+    processor.set_adaptive(Some(AdaptiveOptions {
+        target_fps: 1e-3,
+        ..Default::default()
+    }));
+    let frames: Vec<VideoFrame> = processor
+        .frames(std::iter::repeat(two_faces()).take(3))
+        .collect();
+
+    for frame in frames.iter() {
+        assert!(frame.detected);
+        assert_eq!(
+            frame.settings,
+            FrameSettings {
+                scale: 1.0,
+                detect_every: 1,
+                fast_detector: false,
+            }
+        );
+        assert_eq!(frame.analysis.faces.len(), 2);
+    }
+}
@@ -0,0 +1,187 @@
+use crate::matrix::ImageMatrix;
+
+const BINS: usize = 16;
+
+/// When a [`SceneChangeDetector`] considers the scene to have changed.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SceneChangeOptions {
+    /// The smallest distance between the color histograms of consecutive frames, between `0.0` and `1.0`,
+    /// that is a change of scene.
+    pub histogram_threshold: f64,
+    /// The smallest mean absolute difference between the gray levels of consecutive frames, between `0.0`
+    /// and `255.0`, that is a change of scene.
+    pub difference_threshold: f64,
+    /// How many pixels are sampled along each side of a frame. Larger values are more precise but slower.
+    pub sample_size: usize,
+}
+
+impl Default for SceneChangeOptions {
+    fn default() -> Self {
+        Self {
+            histogram_threshold: 0.5,
+            difference_threshold: 60.0,
+            sample_size: 64,
+        }
+    }
+}
+
+// The cheap summary of a frame that consecutive frames are compared by.
+#[derive(Clone, Debug)]
+struct Signature {
+    width: usize,
+    height: usize,
+    // the normalized histogram of each channel
+    histogram: Vec<f64>,
+    gray: Vec<f64>,
+}
+
+impl Signature {
+    fn new(image: &ImageMatrix, sample_size: usize) -> Self {
+        let (width, height) = (image.width(), image.height());
+        let columns = width.min(sample_size.max(1));
+        let rows = height.min(sample_size.max(1));
+
+        let mut histogram = vec![0f64; 3 * BINS];
+        let mut gray = Vec::with_capacity(columns * rows);
+
+        for row in 0..rows {
+            let pixels = image.row(row * height / rows).unwrap();
+            for column in 0..columns {
+                let x = column * width / columns;
+                let pixel = &pixels[3 * x..3 * x + 3];
+
+                for (channel, &value) in pixel.iter().enumerate() {
+                    histogram[channel * BINS + value as usize * BINS / 256] += 1.0;
+                }
+                gray.push(
+                    0.299 * pixel[0] as f64 + 0.587 * pixel[1] as f64 + 0.114 * pixel[2] as f64,
+                );
+            }
+        }
+
+        let total = gray.len().max(1) as f64;
+        for count in histogram.iter_mut() {
+            *count /= total;
+        }
+
+        Self {
+            width,
+            height,
+            histogram,
+            gray,
+        }
+    }
+
+    // One minus the intersection of the histograms, averaged over the channels.
+    fn histogram_distance(&self, other: &Self) -> f64 {
+        let intersection: f64 = self
+            .histogram
+            .iter()
+            .zip(other.histogram.iter())
+            .map(|(a, b)| a.min(*b))
+            .sum();
+        1.0 - intersection / 3.0
+    }
+
+    fn difference(&self, other: &Self) -> f64 {
+        if self.gray.is_empty() {
+            return 0.0;
+        }
+
+        let total: f64 = self
+            .gray
+            .iter()
+            .zip(other.gray.iter())
+            .map(|(a, b)| (a - b).abs())
+            .sum();
+        total / self.gray.len() as f64
+    }
+}
+
+/// Detects cuts between cameras, and changes so large that trackers can't follow them, by comparing the
+/// color histograms and gray levels of a sample of pixels of consecutive frames.
+#[derive(Clone, Debug)]
+pub struct SceneChangeDetector {
+    options: SceneChangeOptions,
+    previous: Option<Signature>,
+}
+
+impl SceneChangeDetector {
+    pub fn new(options: SceneChangeOptions) -> Self {
+        Self {
+            options,
+            previous: None,
+        }
+    }
+
+    pub fn options(&self) -> SceneChangeOptions {
+        self.options
+    }
+
+    /// Compare a frame to the one before it, returning whether the scene changed between them.
+    ///
+    /// The first frame, and the first after [`SceneChangeDetector::reset`], is never a change. A frame of a
+    /// different size to the one before it always is.
+    pub fn is_scene_change(&mut self, image: &ImageMatrix) -> bool {
+        let signature = Signature::new(image, self.options.sample_size);
+
+        let changed = match &self.previous {
+            None => false,
+            Some(previous)
+                if (previous.width, previous.height) != (signature.width, signature.height) =>
+            {
+                true
+            }
+            Some(previous) => {
+                signature.histogram_distance(previous) >= self.options.histogram_threshold
+                    || signature.difference(previous) >= self.options.difference_threshold
+            }
+        };
+
+        self.previous = Some(signature);
+        changed
+    }
+
+    /// Forget the previous frame.
+    pub fn reset(&mut self) {
+        self.previous = None;
+    }
+}
+
+impl Default for SceneChangeDetector {
+    fn default() -> Self {
+        Self::new(SceneChangeOptions::default())
+    }
+}
+
+#[test]
+fn test_scene_change_detector() {
+    let mut detector = SceneChangeDetector::default();
+    let dark = ImageMatrix::blank(120, 80).adjust_brightness(34);
+
+    assert!(!detector.is_scene_change(&dark));
+    assert!(!detector.is_scene_change(&dark));
+    assert!(!detector.is_scene_change(&dark.adjust_brightness(6)));
+
+    // the same colors, rearranged
+    let mut striped = ImageMatrix::blank(120, 80);
+    for y in 0..80 {
+        for x in 0..120 {
+            let value = if x < 60 { 10 } else { 240 };
+            striped.set_pixel(x, y, [value, value, value]);
+        }
+    }
+    let mut flipped = ImageMatrix::blank(120, 80);
+    for y in 0..80 {
+        for x in 0..120 {
+            flipped.set_pixel(x, y, striped.get_pixel(119 - x, y).unwrap());
+        }
+    }
+    assert!(detector.is_scene_change(&striped));
+    assert!(detector.is_scene_change(&flipped));
+
+    assert!(detector.is_scene_change(&ImageMatrix::blank(60, 40)));
+
+    detector.reset();
+    assert!(!detector.is_scene_change(&striped));
+}
@@ -0,0 +1,118 @@
+use crate::geometry::Rectangle;
+use crate::matrix::ImageMatrix;
+
+cpp_class!(unsafe struct CorrelationTrackerInner as "dlib::correlation_tracker");
+
+/// Follows a single object, such as a face, between video frames with dlib's `correlation_tracker`.
+///
+/// Tracking is much cheaper than detecting, so a face can be detected once and then tracked across the
+/// frames until the next detection. It follows changes in position and scale, but not rotation.
+#[derive(Clone, Default)]
+pub struct CorrelationTracker {
+    inner: CorrelationTrackerInner,
+    tracking: bool,
+}
+
+impl CorrelationTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start tracking the object inside `rect` of `image`, forgetting any object that was tracked before.
+    pub fn start_track(&mut self, image: &ImageMatrix, rect: &Rectangle) {
+        let inner = &mut self.inner;
+
+        unsafe {
+            cpp!([inner as "dlib::correlation_tracker*", image as "const dlib::matrix<dlib::rgb_pixel>*", rect as "const dlib::rectangle*"] {
+                inner->start_track(*image, to_dlib_rect(*rect));
+            })
+        }
+
+        self.tracking = true;
+    }
+
+    /// Whether [`CorrelationTracker::start_track`] has been called.
+    pub fn is_tracking(&self) -> bool {
+        self.tracking
+    }
+
+    /// Find the object in the next frame, returning its position and the tracker's confidence.
+    ///
+    /// The confidence is the peak to side lobe ratio of the correlation, which is usually above 10 while
+    /// the object is tracked well and drops below 5 or so once it is lost or occluded.
+    ///
+    /// # Panics
+    ///
+    /// If the tracker hasn't been started with [`CorrelationTracker::start_track`].
+    pub fn update(&mut self, image: &ImageMatrix) -> (Rectangle, f64) {
+        assert!(self.tracking, "The tracker hasn't been started");
+
+        let inner = &mut self.inner;
+        let mut rect = Rectangle::default();
+
+        let confidence = unsafe {
+            let rect = &mut rect;
+
+            cpp!([inner as "dlib::correlation_tracker*", image as "const dlib::matrix<dlib::rgb_pixel>*", rect as "dlib::rectangle*"] -> f64 as "double" {
+                double confidence = inner->update(*image);
+                *rect = from_dlib_rect(dlib::rectangle(inner->get_position()));
+                return confidence;
+            })
+        };
+
+        (rect, confidence)
+    }
+
+    /// The position of the object in the last frame, if the tracker has been started.
+    pub fn position(&self) -> Option<Rectangle> {
+        if !self.tracking {
+            return None;
+        }
+
+        let inner = &self.inner;
+        let mut rect = Rectangle::default();
+
+        unsafe {
+            let rect = &mut rect;
+
+            cpp!([inner as "const dlib::correlation_tracker*", rect as "dlib::rectangle*"] {
+                *rect = from_dlib_rect(dlib::rectangle(inner->get_position()));
+            })
+        }
+
+        Some(rect)
+    }
+}
+
+// A 200x200 image with a textured square between `left` and `left + 60` horizontally.
+#[cfg(test)]
+fn textured_square(left: usize) -> ImageMatrix {
+    let mut image = ImageMatrix::blank(200, 200);
+    for y in 70..130 {
+        for x in left..left + 60 {
+            let value = (((x - left) * 7 + y * 13) % 256) as u8;
+            image.set_pixel(x, y, [value, 255 - value, 128]);
+        }
+    }
+    image
+}
+
+#[test]
+fn test_correlation_tracker() {
+    let mut tracker = CorrelationTracker::new();
+    assert!(tracker.position().is_none());
+
+    let rect = Rectangle {
+        left: 40,
+        top: 70,
+        right: 100,
+        bottom: 130,
+    };
+    tracker.start_track(&textured_square(40), &rect);
+    assert!(tracker.is_tracking());
+
+    let (tracked, _) = tracker.update(&textured_square(44));
+    assert!((tracked.left - 44).abs() <= 3, "{:?}", tracked);
+    assert!((tracked.top - 70).abs() <= 3, "{:?}", tracked);
+    assert_eq!(tracker.position(), Some(tracked));
+}
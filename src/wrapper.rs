@@ -1,9 +1,139 @@
 cpp! {{
+    #include <dlib/clustering.h>
+    #include <dlib/data_io.h>
     #include <dlib/dnn.h>
+    #include <dlib/image_processing/correlation_tracker.h>
     #include <dlib/image_processing/frontal_face_detector.h>
     #include <dlib/image_processing/full_object_detection.h>
+    #include <dlib/image_processing/shape_predictor_trainer.h>
+    #include <dlib/image_io.h>
     #include <dlib/image_transforms.h>
     #include <dlib/matrix/matrix_math_functions_abstract.h>
+    #include <dlib/svm_threaded.h>
+    #include <dlib/threads.h>
+
+    #include <algorithm>
+    #include <cmath>
+    #include <map>
+    #include <numeric>
+    #include <sstream>
+    #include <string>
+
+    #if defined(DLIB_FACE_RECOGNITION_CUDA) && !defined(DLIB_USE_CUDA)
+    #error "The cuda feature needs dlib to be built with DLIB_USE_CUDA"
+    #endif
+
+    #ifdef DLIB_USE_CUDA
+    #include <dlib/cuda/cuda_dlib.h>
+    #endif
+
+    inline int cuda_device_count() {
+    #ifdef DLIB_USE_CUDA
+        try {
+            return dlib::cuda::get_num_devices();
+        } catch (const dlib::cuda_error& exception) {
+            return 0;
+        }
+    #else
+        return 0;
+    #endif
+    }
+
+    inline int cuda_device() {
+    #ifdef DLIB_USE_CUDA
+        try {
+            return dlib::cuda::get_device();
+        } catch (const dlib::cuda_error& exception) {
+            return -1;
+        }
+    #else
+        return -1;
+    #endif
+    }
+
+    inline bool set_cuda_device(int device) {
+    #ifdef DLIB_USE_CUDA
+        try {
+            dlib::cuda::set_device(device);
+            return true;
+        } catch (const dlib::cuda_error& exception) {
+            return false;
+        }
+    #else
+        return false;
+    #endif
+    }
+
+    inline bool save_png_image(const dlib::matrix<dlib::rgb_pixel>& image, const char* filename, std::string& error) {
+    #ifdef DLIB_PNG_SUPPORT
+        try {
+            dlib::save_png(image, filename);
+            return true;
+        } catch (const dlib::error& exception) {
+            error = exception.what();
+            return false;
+        }
+    #else
+        error = "dlib was built without PNG support";
+        return false;
+    #endif
+    }
+
+    // BT.601 limited range yuv, as output by most webcams and hardware decoders
+
+    inline dlib::rgb_pixel yuv_to_rgb(int y, int u, int v) {
+        const int c = y - 16;
+        const int d = u - 128;
+        const int e = v - 128;
+
+        auto clamp = [](int value) { return (unsigned char)std::min(255, std::max(0, value >> 8)); };
+
+        return dlib::rgb_pixel(
+            clamp(298 * c + 409 * e + 128),
+            clamp(298 * c - 100 * d - 208 * e + 128),
+            clamp(298 * c + 516 * d + 128)
+        );
+    }
+
+    // a read-only rgb image over memory owned by rust, usable anywhere dlib takes a generic image
+
+    struct rgb_image_view {
+        const unsigned char* data;
+        int64_t rows;
+        int64_t columns;
+        int64_t stride;
+    };
+
+    namespace dlib {
+        template <>
+        struct image_traits<rgb_image_view> {
+            typedef rgb_pixel pixel_type;
+        };
+    }
+
+    inline long num_rows(const rgb_image_view& image) { return image.rows; }
+    inline long num_columns(const rgb_image_view& image) { return image.columns; }
+    inline long width_step(const rgb_image_view& image) { return image.stride; }
+    inline const void* image_data(const rgb_image_view& image) { return image.data; }
+    inline void* image_data(rgb_image_view& image) { return const_cast<unsigned char*>(image.data); }
+
+    // rust's rectangles are right/bottom exclusive and dlib's are inclusive, so every rectangle is
+    // converted as it crosses between them
+
+    inline dlib::rectangle to_dlib_rect(const dlib::rectangle& rect) {
+        return dlib::rectangle(rect.left(), rect.top(), rect.right() - 1, rect.bottom() - 1);
+    }
+
+    inline dlib::rectangle from_dlib_rect(const dlib::rectangle& rect) {
+        return dlib::rectangle(rect.left(), rect.top(), rect.right() + 1, rect.bottom() + 1);
+    }
+
+    inline std::vector<dlib::rectangle> from_dlib_rects(std::vector<dlib::rectangle> rects) {
+        for (auto& rect: rects) {
+            rect = from_dlib_rect(rect);
+        }
+        return rects;
+    }
 
     // face encoding network definition from
     // https://github.com/davisking/dlib/blob/master/tools/python/src/face_recognition.cpp
@@ -36,6 +166,24 @@ cpp! {{
                                 dlib::input_rgb_image_sized<150>
                                 >>>>>>>>>>>>;
 
+    // age predictor network definition, the resnet-10 of dnn_age_predictor_v1.dat from
+    // https://github.com/davisking/dlib-models, which predicts one of 81 ages from 0 to 80
+
+    template <typename SUBNET> using age_level1 = ares<256,ares_down<256,SUBNET>>;
+    template <typename SUBNET> using age_level2 = ares<128,ares_down<128,SUBNET>>;
+    template <typename SUBNET> using age_level3 = ares<64,SUBNET>;
+
+    using age_predictor_nn = dlib::loss_multiclass_log<dlib::fc<81,dlib::avg_pool_everything<
+                                age_level1<
+                                age_level2<
+                                age_level3<
+                                dlib::max_pool<3,3,2,2,dlib::relu<dlib::affine<dlib::con<64,7,7,2,2,
+                                dlib::input_rgb_image
+                                >>>>>>>>>>;
+
+    // the same network with a softmax in place of the loss, so it outputs the probability of each age
+    using age_predictor_softmax = dlib::softmax<age_predictor_nn::subnet_type>;
+
     // cnn face detector definition from
     // https://github.com/davisking/dlib/blob/master/tools/python/src/cnn_face_detector.cpp#L121
 
@@ -47,18 +195,439 @@ cpp! {{
 
     using face_detection_cnn = dlib::loss_mmod<dlib::con<1,9,9,1,1,rcon5<rcon5<rcon5<downsampler<dlib::input_rgb_image_pyramid<dlib::pyramid_down<6>>>>>>>>;
 
+    // custom fhog object detectors, the same type as dlib::frontal_face_detector
+
+    using object_detector_fhog = dlib::object_detector<dlib::scan_fhog_pyramid<dlib::pyramid_down<6>>>;
+
+    // based on train_simple_object_detector from
+    // https://github.com/davisking/dlib/blob/master/tools/python/src/simple_object_detector.h
+
+    struct object_detector_training_options {
+        double c;
+        double epsilon;
+        bool symmetric;
+        size_t detection_window_size;
+        size_t num_threads;
+    };
+
+    inline object_detector_fhog train_object_detector(
+        std::vector<dlib::matrix<dlib::rgb_pixel>>& images,
+        std::vector<std::vector<dlib::rectangle>>& boxes,
+        const object_detector_training_options& options
+    ) {
+        // pick a detection window with the requested area and the average aspect ratio of the boxes
+        double aspect_ratio = 0;
+        size_t count = 0;
+        for (auto &image_boxes: boxes) {
+            for (auto &box: image_boxes) {
+                aspect_ratio += static_cast<double>(box.width()) / box.height();
+                count++;
+            }
+        }
+
+        if (count == 0) {
+            throw dlib::error("No boxes to train the object detector from.");
+        }
+        aspect_ratio /= count;
+
+        if (options.symmetric) {
+            dlib::add_image_left_right_flips(images, boxes);
+        }
+
+        dlib::scan_fhog_pyramid<dlib::pyramid_down<6>> scanner;
+        scanner.set_detection_window_size(
+            std::lround(std::sqrt(options.detection_window_size * aspect_ratio)),
+            std::lround(std::sqrt(options.detection_window_size / aspect_ratio))
+        );
+
+        dlib::structural_object_detection_trainer<dlib::scan_fhog_pyramid<dlib::pyramid_down<6>>> trainer(scanner);
+        trainer.set_num_threads(options.num_threads);
+        trainer.set_c(options.c);
+        trainer.set_epsilon(options.epsilon);
+
+        return trainer.train(images, boxes);
+    }
+
+    // shape predictor training
+
+    struct shape_predictor_training_options {
+        size_t cascade_depth;
+        size_t tree_depth;
+        double nu;
+        size_t oversampling_amount;
+        size_t num_threads;
+    };
+
+    inline dlib::shape_predictor train_shape_predictor(
+        const std::vector<dlib::matrix<dlib::rgb_pixel>>& images,
+        const std::vector<std::vector<dlib::full_object_detection>>& shapes,
+        const shape_predictor_training_options& options
+    ) {
+        dlib::shape_predictor_trainer trainer;
+        trainer.set_cascade_depth(options.cascade_depth);
+        trainer.set_tree_depth(options.tree_depth);
+        trainer.set_nu(options.nu);
+        trainer.set_oversampling_amount(options.oversampling_amount);
+        trainer.set_num_threads(options.num_threads);
+
+        return trainer.train(images, shapes);
+    }
+
+    struct metric_training_options {
+        double learning_rate;
+        double min_learning_rate;
+        double weight_decay;
+        double momentum;
+        size_t iterations_without_progress;
+        size_t max_iterations;
+        size_t identities_per_batch;
+        size_t images_per_identity;
+        bool final_layer_only;
+    };
+
+    // fine-tune the face encoding network in place, with mini-batches of several chips from each of several
+    // identities as in dlib's dnn_metric_learning_on_images_ex.cpp
+
+    inline void fine_tune_face_encoder(
+        face_encoding_nn& net,
+        const std::vector<dlib::matrix<dlib::rgb_pixel>>& chips,
+        const std::vector<unsigned long>& labels,
+        const metric_training_options& options
+    ) {
+        std::map<unsigned long, std::vector<size_t>> identities;
+        for (size_t i = 0; i < labels.size(); i++) {
+            identities[labels[i]].push_back(i);
+        }
+
+        std::vector<const std::vector<size_t>*> usable;
+        for (const auto& identity : identities) {
+            if (identity.second.size() >= 2) {
+                usable.push_back(&identity.second);
+            }
+        }
+
+        if (usable.size() < 2) {
+            throw dlib::error("fine-tuning needs at least two identities with two or more chips each");
+        }
+
+        if (options.final_layer_only) {
+            dlib::set_all_learning_rate_multipliers(net, 0);
+            dlib::layer<1>(net).layer_details().set_learning_rate_multiplier(1);
+        }
+
+        {
+            dlib::dnn_trainer<face_encoding_nn> trainer(net, dlib::sgd(options.weight_decay, options.momentum));
+            trainer.set_learning_rate(options.learning_rate);
+            trainer.set_min_learning_rate(options.min_learning_rate);
+            trainer.set_iterations_without_progress_threshold(options.iterations_without_progress);
+
+            dlib::rand rnd(0);
+            std::vector<size_t> order(usable.size());
+            std::iota(order.begin(), order.end(), 0);
+
+            const size_t identities_per_batch = std::min(options.identities_per_batch, usable.size());
+
+            std::vector<dlib::matrix<dlib::rgb_pixel>> batch_chips;
+            std::vector<unsigned long> batch_labels;
+
+            for (size_t iteration = 0; iteration < options.max_iterations && trainer.get_learning_rate() >= options.min_learning_rate; iteration++) {
+                batch_chips.clear();
+                batch_labels.clear();
+
+                for (size_t i = 0; i < identities_per_batch; i++) {
+                    std::swap(order[i], order[i + rnd.get_random_32bit_number() % (order.size() - i)]);
+
+                    const std::vector<size_t>& indices = *usable[order[i]];
+                    for (size_t j = 0; j < options.images_per_identity; j++) {
+                        const size_t index = indices[rnd.get_random_32bit_number() % indices.size()];
+                        batch_chips.push_back(chips[index]);
+                        batch_labels.push_back(labels[index]);
+                    }
+                }
+
+                trainer.train_one_step(batch_chips, batch_labels);
+            }
+
+            // wait for the training thread to finish with the network
+            trainer.get_net();
+        }
+
+        dlib::set_all_learning_rate_multipliers(net, 1);
+        net.clean();
+    }
+
+    // piecewise affine face warping, mapping each triangle of the destination landmarks back to the source image
+
+    inline dlib::matrix<dlib::rgb_pixel> warp_face_triangles(
+        const dlib::matrix<dlib::rgb_pixel>& src,
+        const dlib::matrix<dlib::rgb_pixel>& dst,
+        const double* src_points,
+        const double* dst_points,
+        const size_t* triangles,
+        size_t num_triangles,
+        int blending,
+        double parameter
+    ) {
+        dlib::matrix<dlib::rgb_pixel> warped = dst;
+        dlib::matrix<float> mask = dlib::zeros_matrix<float>(dst.nr(), dst.nc());
+        dlib::const_image_view<dlib::matrix<dlib::rgb_pixel>> view(src);
+        dlib::interpolate_bilinear interpolate;
+
+        for (size_t t = 0; t < num_triangles; t++) {
+            std::vector<dlib::dpoint> from, to;
+            for (size_t i = 0; i < 3; i++) {
+                size_t index = triangles[3 * t + i];
+                from.emplace_back(dst_points[2 * index], dst_points[2 * index + 1]);
+                to.emplace_back(src_points[2 * index], src_points[2 * index + 1]);
+            }
+
+            const dlib::dpoint a = from[0], b = from[1], c = from[2];
+            const double area = (b.x() - a.x()) * (c.y() - a.y()) - (c.x() - a.x()) * (b.y() - a.y());
+            if (std::abs(area) < 1e-9) {
+                continue;
+            }
+
+            const dlib::point_transform_affine map = dlib::find_affine_transform(from, to);
+
+            const long left = std::max<long>(0, std::floor(std::min({a.x(), b.x(), c.x()})));
+            const long right = std::min<long>(dst.nc() - 1, std::ceil(std::max({a.x(), b.x(), c.x()})));
+            const long top = std::max<long>(0, std::floor(std::min({a.y(), b.y(), c.y()})));
+            const long bottom = std::min<long>(dst.nr() - 1, std::ceil(std::max({a.y(), b.y(), c.y()})));
+
+            for (long y = top; y <= bottom; y++) {
+                for (long x = left; x <= right; x++) {
+                    // barycentric coordinates of the pixel within the triangle
+                    const double w0 = ((b.x() - x) * (c.y() - y) - (c.x() - x) * (b.y() - y)) / area;
+                    const double w1 = ((c.x() - x) * (a.y() - y) - (a.x() - x) * (c.y() - y)) / area;
+                    const double w2 = 1 - w0 - w1;
+                    if (w0 < -1e-9 || w1 < -1e-9 || w2 < -1e-9) {
+                        continue;
+                    }
+
+                    dlib::rgb_pixel pixel;
+                    if (interpolate(view, map(dlib::dpoint(x, y)), pixel)) {
+                        warped(y, x) = pixel;
+                        mask(y, x) = 1;
+                    }
+                }
+            }
+        }
+
+        if (blending == 0) {
+            return warped;
+        }
+
+        dlib::matrix<dlib::rgb_pixel> out = dst;
+
+        if (blending == 1) {
+            // feather the edge of the face inwards so that it fades into the destination image
+            dlib::matrix<float> blurred;
+            dlib::gaussian_blur(mask, blurred, std::max(parameter, 0.1));
+
+            for (long y = 0; y < out.nr(); y++) {
+                for (long x = 0; x < out.nc(); x++) {
+                    const float alpha = mask(y, x) * std::min(1.0f, std::max(0.0f, 2 * blurred(y, x) - 1));
+                    out(y, x).red = std::lround(alpha * warped(y, x).red + (1 - alpha) * dst(y, x).red);
+                    out(y, x).green = std::lround(alpha * warped(y, x).green + (1 - alpha) * dst(y, x).green);
+                    out(y, x).blue = std::lround(alpha * warped(y, x).blue + (1 - alpha) * dst(y, x).blue);
+                }
+            }
+
+            return out;
+        }
+
+        // poisson blending, keeping the gradients of the warped face while matching the destination at its edges
+        dlib::matrix<float> guide[3], solution[3], background[3];
+        for (int c = 0; c < 3; c++) {
+            guide[c].set_size(dst.nr(), dst.nc());
+            background[c].set_size(dst.nr(), dst.nc());
+        }
+        for (long y = 0; y < dst.nr(); y++) {
+            for (long x = 0; x < dst.nc(); x++) {
+                guide[0](y, x) = warped(y, x).red;
+                guide[1](y, x) = warped(y, x).green;
+                guide[2](y, x) = warped(y, x).blue;
+                background[0](y, x) = dst(y, x).red;
+                background[1](y, x) = dst(y, x).green;
+                background[2](y, x) = dst(y, x).blue;
+            }
+        }
+        for (int c = 0; c < 3; c++) {
+            solution[c] = guide[c];
+        }
+
+        // successive over-relaxation
+        const float omega = 1.9f;
+        const long iterations = std::lround(parameter);
+        const long offsets[4][2] = {{-1, 0}, {1, 0}, {0, -1}, {0, 1}};
+
+        for (long iteration = 0; iteration < iterations; iteration++) {
+            for (long y = 1; y + 1 < dst.nr(); y++) {
+                for (long x = 1; x + 1 < dst.nc(); x++) {
+                    if (mask(y, x) == 0) {
+                        continue;
+                    }
+
+                    for (int c = 0; c < 3; c++) {
+                        float sum = 0;
+                        for (auto &offset: offsets) {
+                            const long ny = y + offset[0];
+                            const long nx = x + offset[1];
+                            if (mask(ny, nx) != 0) {
+                                sum += solution[c](ny, nx) + guide[c](y, x) - guide[c](ny, nx);
+                            } else {
+                                sum += background[c](ny, nx);
+                            }
+                        }
+                        solution[c](y, x) += omega * (sum / 4 - solution[c](y, x));
+                    }
+                }
+            }
+        }
+
+        for (long y = 0; y < out.nr(); y++) {
+            for (long x = 0; x < out.nc(); x++) {
+                if (mask(y, x) != 0) {
+                    out(y, x).red = std::lround(std::min(255.0f, std::max(0.0f, solution[0](y, x))));
+                    out(y, x).green = std::lround(std::min(255.0f, std::max(0.0f, solution[1](y, x))));
+                    out(y, x).blue = std::lround(std::min(255.0f, std::max(0.0f, solution[2](y, x))));
+                }
+            }
+        }
+
+        return out;
+    }
+
+    // blurring or pixelating the inside of a convex polygon
+
+    inline void anonymize_polygon(
+        dlib::matrix<dlib::rgb_pixel>& image,
+        const double* points,
+        size_t num_points,
+        int method,
+        double parameter
+    ) {
+        if (num_points < 3) {
+            return;
+        }
+
+        double min_x = points[0], max_x = points[0], min_y = points[1], max_y = points[1];
+        for (size_t i = 1; i < num_points; i++) {
+            min_x = std::min(min_x, points[2 * i]);
+            max_x = std::max(max_x, points[2 * i]);
+            min_y = std::min(min_y, points[2 * i + 1]);
+            max_y = std::max(max_y, points[2 * i + 1]);
+        }
+
+        const dlib::rectangle area = dlib::rectangle(
+            std::floor(min_x), std::floor(min_y), std::ceil(max_x), std::ceil(max_y)
+        ).intersect(dlib::get_rect(image));
+        if (area.is_empty()) {
+            return;
+        }
+
+        const double size = std::max(max_x - min_x, max_y - min_y);
+        dlib::matrix<dlib::rgb_pixel> region = dlib::subm(image, area);
+        dlib::matrix<dlib::rgb_pixel> processed;
+
+        if (method == 0) {
+            dlib::gaussian_blur(region, processed, std::max(parameter * size, 0.1));
+        } else {
+            const long block = std::max<long>(1, std::ceil(size / std::max(parameter, 1.0)));
+            processed.set_size(region.nr(), region.nc());
+
+            for (long top = 0; top < region.nr(); top += block) {
+                for (long left = 0; left < region.nc(); left += block) {
+                    const long bottom = std::min(top + block, region.nr());
+                    const long right = std::min(left + block, region.nc());
+
+                    double red = 0, green = 0, blue = 0;
+                    for (long y = top; y < bottom; y++) {
+                        for (long x = left; x < right; x++) {
+                            red += region(y, x).red;
+                            green += region(y, x).green;
+                            blue += region(y, x).blue;
+                        }
+                    }
+
+                    const double count = (bottom - top) * (right - left);
+                    const dlib::rgb_pixel average(std::lround(red / count), std::lround(green / count), std::lround(blue / count));
+                    for (long y = top; y < bottom; y++) {
+                        for (long x = left; x < right; x++) {
+                            processed(y, x) = average;
+                        }
+                    }
+                }
+            }
+        }
+
+        for (long y = area.top(); y <= area.bottom(); y++) {
+            for (long x = area.left(); x <= area.right(); x++) {
+                // inside a convex polygon every edge turns the same way towards the point
+                bool positive = false, negative = false;
+                for (size_t i = 0; i < num_points; i++) {
+                    const size_t j = (i + 1) % num_points;
+                    const double cross = (points[2 * j] - points[2 * i]) * (y - points[2 * i + 1])
+                        - (points[2 * j + 1] - points[2 * i + 1]) * (x - points[2 * i]);
+                    positive |= cross > 0;
+                    negative |= cross < 0;
+                }
+
+                if (!(positive && negative)) {
+                    image(y, x) = processed(y - area.top(), x - area.left());
+                }
+            }
+        }
+    }
+
+    // face detections along with their scores
+
+    struct face_detection {
+        dlib::rectangle rect;
+        double confidence;
+        size_t detector_index;
+    };
+
+    // pca projection of face encodings
+
+    struct pca_projector {
+        dlib::matrix<double,0,1> mean;
+        dlib::matrix<double> projection;
+    };
+
+    inline void serialize(const pca_projector& item, std::ostream& out) {
+        int version = 1;
+        dlib::serialize(version, out);
+        dlib::serialize(item.mean, out);
+        dlib::serialize(item.projection, out);
+    }
+
+    inline void deserialize(pca_projector& item, std::istream& in) {
+        int version = 0;
+        dlib::deserialize(version, in);
+        if (version != 1) {
+            throw dlib::serialization_error("Unexpected version found while deserializing pca_projector.");
+        }
+        dlib::deserialize(item.mean, in);
+        dlib::deserialize(item.projection, in);
+    }
+
     // misc
 
-    // TODO: I am unsure if having rnd as a global here is thread safe.
+    // Each thread has its own random number generator, so that encoders can jitter images concurrently.
 
-    dlib::rand rnd;
+    thread_local dlib::rand rnd;
 
     // https://github.com/davisking/dlib/blob/master/tools/python/src/face_recognition.cpp#L131
-    std::vector<dlib::matrix<dlib::rgb_pixel>> jitter_image(const dlib::matrix<dlib::rgb_pixel>& img, const int num_jitters) {
+    std::vector<dlib::matrix<dlib::rgb_pixel>> jitter_image(const dlib::matrix<dlib::rgb_pixel>& img, const int num_jitters, dlib::rand& generator) {
         std::vector<dlib::matrix<dlib::rgb_pixel>> crops;
         for (int i = 0; i < num_jitters; ++i) {
-            crops.push_back(dlib::jitter_image(img, rnd));
+            crops.push_back(dlib::jitter_image(img, generator));
         }
         return crops;
     }
+
+    std::vector<dlib::matrix<dlib::rgb_pixel>> jitter_image(const dlib::matrix<dlib::rgb_pixel>& img, const int num_jitters) {
+        return jitter_image(img, num_jitters, rnd);
+    }
 }}
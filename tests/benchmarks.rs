@@ -1,3 +1,4 @@
+#![cfg(feature = "image")]
 #![allow(dead_code)]
 #![allow(unused_imports)]
 
@@ -115,3 +116,226 @@ fn encoding_distances() {
     let distance = a_encoding.distance(b_encoding);
     assert!(distance > 0.0 && distance < 0.6);
 }
+
+#[cfg(feature = "embed-all")]
+#[test]
+fn jittered_encodings() {
+    initialize();
+
+    let rect = DETECTOR.face_locations(&OBAMA_1_MATRIX)[0];
+    let landmarks = [PREDICTOR.face_landmarks(&OBAMA_1_MATRIX, &rect)];
+
+    let mut model = MODEL.clone();
+    model.set_jitter_seed(Some(42));
+
+    let plain = &model.get_face_encodings(&OBAMA_1_MATRIX, &landmarks, 0)[0];
+    let jittered = &model.get_face_encodings(&OBAMA_1_MATRIX, &landmarks, 10)[0];
+    let again = &model.get_face_encodings(&OBAMA_1_MATRIX, &landmarks, 10)[0];
+
+    assert_eq!(jittered, again);
+    assert!(plain.distance(jittered) < 0.3);
+}
+
+#[cfg(feature = "embed-all")]
+#[test]
+fn batched_encodings() {
+    initialize();
+
+    let a = &*OBAMA_1_MATRIX;
+    let b = &*OBAMA_2_MATRIX;
+
+    let a_landmarks = [PREDICTOR.face_landmarks(a, &DETECTOR.face_locations(a)[0])];
+    let b_landmarks = [PREDICTOR.face_landmarks(b, &DETECTOR.face_locations(b)[0])];
+
+    let batch =
+        MODEL.get_face_encodings_batch(&[(a, &a_landmarks[..]), (b, &[]), (b, &b_landmarks[..])]);
+    assert_eq!(batch.len(), 3);
+    assert!(batch[1].is_empty());
+
+    let a_encoding = &MODEL.get_face_encodings(a, &a_landmarks, 0)[0];
+    let b_encoding = &MODEL.get_face_encodings(b, &b_landmarks, 0)[0];
+    assert!(batch[0][0].distance(a_encoding) < 1e-4);
+    assert!(batch[2][0].distance(b_encoding) < 1e-4);
+
+    let chip = face_alignment::face_chip(a, &a_landmarks[0], &Default::default()).unwrap();
+    assert!(MODEL.encode_chips(&[chip]).unwrap()[0].distance(a_encoding) < 1e-4);
+    assert!(MODEL.encode_chips(&[ImageMatrix::blank(100, 100)]).is_err());
+}
+
+#[cfg(feature = "embed-all")]
+#[test]
+fn unsupported_landmarks() {
+    initialize();
+
+    let rect = DETECTOR.face_locations(&OBAMA_1_MATRIX)[0];
+    let landmarks = PREDICTOR.face_landmarks(&OBAMA_1_MATRIX, &rect);
+    let three = FaceLandmarks::new(&rect, &landmarks[..3]);
+
+    let encodings = MODEL
+        .try_get_face_encodings(&OBAMA_1_MATRIX, std::slice::from_ref(&landmarks), 0)
+        .unwrap();
+    assert_eq!(encodings.len(), 1);
+    assert!(MODEL
+        .try_get_face_encodings(&OBAMA_1_MATRIX, &[landmarks, three], 0)
+        .is_err());
+}
+
+#[cfg(feature = "embed-all")]
+#[test]
+fn encoding_chip_options() {
+    initialize();
+
+    let rect = DETECTOR.face_locations(&OBAMA_1_MATRIX)[0];
+    let landmarks = [PREDICTOR.face_landmarks(&OBAMA_1_MATRIX, &rect)];
+
+    let mut model = MODEL.clone();
+    assert_eq!(model.chip_options(), face_alignment::ChipOptions::default());
+
+    let tight = face_alignment::ChipOptions {
+        padding: 0.1,
+        ..Default::default()
+    };
+    model.set_chip_options(tight).unwrap();
+    assert!(model
+        .set_chip_options(face_alignment::ChipOptions {
+            size: 112,
+            ..Default::default()
+        })
+        .is_err());
+    assert_eq!(model.chip_options(), tight);
+
+    let default = &MODEL.get_face_encodings(&OBAMA_1_MATRIX, &landmarks, 0)[0];
+    let tighter = &model.get_face_encodings(&OBAMA_1_MATRIX, &landmarks, 0)[0];
+    assert!(default.distance(tighter) > 1e-4);
+
+    let chip = face_alignment::face_chip(&OBAMA_1_MATRIX, &landmarks[0], &tight).unwrap();
+    assert!(model.encode_chips(&[chip]).unwrap()[0].distance(tighter) < 1e-4);
+}
+
+#[cfg(feature = "embed-all")]
+#[test]
+fn models_from_bytes() {
+    initialize();
+
+    let read = |filename: &str| {
+        std::fs::read(
+            PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+                .join("files")
+                .join(filename),
+        )
+        .unwrap()
+    };
+
+    let detector = FaceDetectorCnn::from_bytes(&read("mmod_human_face_detector.dat")).unwrap();
+    let predictor =
+        LandmarkPredictor::from_bytes(&read("shape_predictor_68_face_landmarks.dat")).unwrap();
+    let model =
+        FaceEncoderNetwork::from_bytes(&read("dlib_face_recognition_resnet_model_v1.dat")).unwrap();
+
+    let rect = detector.face_locations(&OBAMA_1_MATRIX)[0];
+    assert_eq!(rect, DETECTOR_CNN.face_locations(&OBAMA_1_MATRIX)[0]);
+
+    let landmarks = [predictor.face_landmarks(&OBAMA_1_MATRIX, &rect)];
+    assert_eq!(
+        landmarks[0],
+        PREDICTOR.face_landmarks(&OBAMA_1_MATRIX, &rect)
+    );
+
+    let encoding = &model.get_face_encodings(&OBAMA_1_MATRIX, &landmarks, 0)[0];
+    let expected = &MODEL.get_face_encodings(&OBAMA_1_MATRIX, &landmarks, 0)[0];
+    assert!(encoding.distance(expected) < 1e-6);
+
+    assert!(LandmarkPredictor::from_bytes(b"not a model").is_err());
+}
+
+#[cfg(feature = "embed-all")]
+#[test]
+fn single_precision_encodings() {
+    initialize();
+
+    let rect = DETECTOR.face_locations(&OBAMA_1_MATRIX)[0];
+    let landmarks = [PREDICTOR.face_landmarks(&OBAMA_1_MATRIX, &rect)];
+
+    let doubles = MODEL.get_face_encodings(&OBAMA_1_MATRIX, &landmarks, 0);
+    let floats = MODEL.get_face_encodings_f32(&OBAMA_1_MATRIX, &landmarks);
+
+    assert_eq!(floats.len(), 1);
+    assert!(doubles[0].distance(&floats[0].to_f64()) < 1e-6);
+}
+
+#[cfg(feature = "embed-all")]
+#[test]
+fn fine_tune_encoder() {
+    initialize();
+
+    let options = face_alignment::ChipOptions::default();
+    let chip = |image: &ImageMatrix| {
+        let rect = DETECTOR.face_locations(image)[0];
+        face_alignment::face_chip(image, &PREDICTOR.face_landmarks(image, &rect), &options).unwrap()
+    };
+
+    let chips = [
+        chip(&OBAMA_1_MATRIX),
+        chip(&OBAMA_2_MATRIX),
+        OBAMA_1_MATRIX.resize(150, 150),
+        OBAMA_2_MATRIX.resize(150, 150),
+    ];
+    let labels = [0, 0, 1, 1];
+
+    let mut model = MODEL.clone();
+    let training = MetricTrainingOptions {
+        max_iterations: 2,
+        ..Default::default()
+    };
+
+    assert!(model.fine_tune(&chips, &labels[..3], &training).is_err());
+    assert!(model
+        .fine_tune(&chips[..3], &labels[..3], &training)
+        .is_err());
+    model.fine_tune(&chips, &labels, &training).unwrap();
+
+    let path = std::env::temp_dir().join("dlib-face-recognition-fine-tuned-encoder.dat");
+    model.save(&path).unwrap();
+    let loaded = FaceEncoderNetwork::new(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    let encodings = model.encode_chips(&chips).unwrap();
+    let loaded_encodings = loaded.encode_chips(&chips).unwrap();
+    assert!(encodings[0].distance(&loaded_encodings[0]) < 1e-4);
+}
+
+#[cfg(feature = "embed-all")]
+#[test]
+fn encodings_with_chips() {
+    initialize();
+
+    let rect = DETECTOR.face_locations(&OBAMA_1_MATRIX)[0];
+    let landmarks = [PREDICTOR.face_landmarks(&OBAMA_1_MATRIX, &rect)];
+
+    let (encodings, chips) = MODEL.get_face_encodings_with_chips(&OBAMA_1_MATRIX, &landmarks, 0);
+    assert_eq!(encodings.len(), 1);
+    assert_eq!(chips.len(), 1);
+    assert_eq!((chips[0].width(), chips[0].height()), (150, 150));
+    assert!(MODEL.encode_chips(&chips).unwrap()[0].distance(&encodings[0]) < 1e-4);
+}
+
+#[cfg(feature = "embed-all")]
+#[test]
+fn image_views() {
+    initialize();
+
+    let view = ImageMatrixView::from_image(&OBAMA_1);
+    let rects = DETECTOR.face_locations_view(&view);
+    assert_eq!(rects, DETECTOR.face_locations(&OBAMA_1_MATRIX));
+
+    let landmarks = [PREDICTOR.face_landmarks_view(&view, &rects[0])];
+    assert_eq!(
+        landmarks[0],
+        PREDICTOR.face_landmarks(&OBAMA_1_MATRIX, &rects[0])
+    );
+
+    let chips = face_alignment::face_chips_view(&view, &landmarks, &Default::default()).unwrap();
+    let encoding = &MODEL.encode_chips(&chips).unwrap()[0];
+    let expected = &MODEL.get_face_encodings(&OBAMA_1_MATRIX, &landmarks, 0)[0];
+    assert!(encoding.distance(expected) < 1e-4);
+}